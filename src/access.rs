@@ -0,0 +1,142 @@
+//! Who may use which list through `serve --users`, from a TOML file such as:
+//!
+//! ```toml
+//! [users]
+//! alice = "a-long-random-token"   # sent as `Authorization: Bearer a-long-random-token`
+//! bob = "another-long-token"
+//!
+//! [lists.family]
+//! alice = "owner"
+//! bob = "viewer"
+//! ```
+//!
+//! A `viewer` can read a list, an `editor` can also add, change and delete its tasks, and an
+//! `owner` can also change who has access to it, through the API. A list nobody has a role in
+//! can't be used at all. The file holds the tokens, so it should only be readable by the
+//! account running the server.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What a user may do with a list, each role allowing what the ones before it do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    /// Token of each user.
+    #[serde(default)]
+    users: BTreeMap<String, String>,
+    /// Role of each member of each list.
+    #[serde(default)]
+    lists: BTreeMap<String, BTreeMap<String, Role>>,
+}
+
+pub struct Access {
+    path: PathBuf,
+    file: File,
+}
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+impl Access {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let file: File = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some((user, _)) = file.users.iter().find(|(_, token)| token.len() < 16) {
+            return Err(format!(
+                "{}: the token of {} is too short; use at least 16 characters",
+                path.display(),
+                user
+            )
+            .into());
+        }
+        Ok(Access {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Re-reads the file, keeping what was loaded when it has become invalid.
+    pub fn reload(&mut self) -> Result<()> {
+        *self = Access::load(&self.path)?;
+        Ok(())
+    }
+
+    /// The user whose token `authorization`, an `Authorization` header, carries.
+    pub fn user(&self, authorization: Option<&str>) -> Option<&str> {
+        let token = authorization?.strip_prefix("Bearer ")?.trim();
+        // Compared by hash, so how long a comparison takes says nothing about the tokens.
+        let digest = Sha256::digest(token);
+        self.file
+            .users
+            .iter()
+            .find(|(_, known)| Sha256::digest(known.as_bytes()) == digest)
+            .map(|(user, _)| user.as_str())
+    }
+
+    pub fn role(&self, user: &str, list: &str) -> Option<Role> {
+        self.file.lists.get(list)?.get(user).copied()
+    }
+
+    /// The lists `user` has a role in.
+    pub fn lists(&self, user: &str) -> Vec<(&str, Role)> {
+        self.file
+            .lists
+            .iter()
+            .filter_map(|(list, members)| Some((list.as_str(), *members.get(user)?)))
+            .collect()
+    }
+
+    pub fn members(&self, list: &str) -> BTreeMap<String, Role> {
+        self.file.lists.get(list).cloned().unwrap_or_default()
+    }
+
+    /// Gives `member` `role` in `list`, or takes their role away with `None`, and saves. A
+    /// list keeps at least one owner.
+    pub fn set_role(
+        &mut self,
+        list: &str,
+        member: &str,
+        role: Option<Role>,
+    ) -> std::result::Result<(), String> {
+        if !self.file.users.contains_key(member) {
+            return Err(format!("there is no user {:?}", member));
+        }
+        let members = self.file.lists.entry(list.to_string()).or_default();
+        let previous = match role {
+            Some(role) => members.insert(member.to_string(), role),
+            None => members.remove(member),
+        };
+        if !members.values().any(|r| *r == Role::Owner) {
+            match previous {
+                Some(previous) => members.insert(member.to_string(), previous),
+                None => members.remove(member),
+            };
+            return Err(format!("{} needs an owner", list));
+        }
+        let text = toml::to_string(&self.file).map_err(|e| e.to_string())?;
+        fs::write(&self.path, text)
+            .map_err(|e| format!("could not write {}: {}", self.path.display(), e))
+    }
+}
@@ -0,0 +1,111 @@
+//! The agenda: pending tasks by the day they come up, for `agenda`.
+//!
+//! A task comes up on the day it is due. A repeating task also comes up on each later
+//! occurrence in the days shown, as it will once the one before is completed. A snoozed task
+//! comes up on the day it comes back instead, and on its due day as well when that is later.
+//! Tasks still pending after their due date are overdue rather than on a day, unless they are
+//! snoozed.
+
+use crate::Task;
+use chrono::{DateTime, Days, Local, NaiveDate};
+
+/// Why a task is on a day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Due,
+    /// A later occurrence of a repeating task.
+    Repeats,
+    /// The end of its snooze.
+    Unsnoozed,
+}
+
+/// A task on a day of the agenda.
+#[derive(Debug, Clone)]
+pub struct Entry<'a> {
+    pub task: &'a Task,
+    pub at: DateTime<Local>,
+    pub reason: Reason,
+}
+
+/// A day of the agenda and its tasks, by time.
+#[derive(Debug, Clone)]
+pub struct Day<'a> {
+    pub date: NaiveDate,
+    pub entries: Vec<Entry<'a>>,
+}
+
+/// The agenda for `days` days from the day of `now`.
+#[derive(Debug, Clone)]
+pub struct Agenda<'a> {
+    /// Tasks past their due date, most overdue first.
+    pub overdue: Vec<&'a Task>,
+    pub days: Vec<Day<'a>>,
+}
+
+/// The agenda of `tasks` for `days` days starting today, as of `now`. Completed tasks are
+/// left out.
+pub fn build<'a>(tasks: &[&'a Task], days: usize, now: DateTime<Local>) -> Agenda<'a> {
+    let today = now.date_naive();
+    let end = today + Days::new(days as u64);
+    let shown = |at: DateTime<Local>| (today..end).contains(&at.date_naive());
+    let mut overdue = vec![];
+    let mut entries: Vec<Entry> = vec![];
+    for &task in tasks.iter().filter(|t| !t.completed) {
+        let snoozed = task.snoozed_until.filter(|_| task.is_snoozed(now));
+        if let Some(until) = snoozed.filter(|until| shown(*until)) {
+            entries.push(Entry {
+                task,
+                at: until,
+                reason: Reason::Unsnoozed,
+            });
+        }
+        let Some(due) = task.due else {
+            continue;
+        };
+        // Until the day it comes back, a snoozed task is only on that day.
+        let hidden = |at: DateTime<Local>| {
+            snoozed.is_some_and(|until| at.date_naive() <= until.date_naive())
+        };
+        if due < now {
+            if snoozed.is_none() {
+                overdue.push(task);
+            }
+        } else if shown(due) && !hidden(due) {
+            entries.push(Entry {
+                task,
+                at: due,
+                reason: Reason::Due,
+            });
+        }
+        if let Some(rule) = &task.repeat {
+            // Occurrences past the last date there is are no longer shown.
+            let mut next = rule.step(due).ok();
+            while let Some(at) = next.filter(|at| at.date_naive() < end) {
+                if at >= now && !hidden(at) {
+                    entries.push(Entry {
+                        task,
+                        at,
+                        reason: Reason::Repeats,
+                    });
+                }
+                next = rule.step(at).ok();
+            }
+        }
+    }
+    overdue.sort_by_key(|t| (t.due, t.id));
+    entries.sort_by_key(|entry| (entry.at, entry.task.id));
+    let days = (0..days as u64)
+        .map(|offset| {
+            let date = today + Days::new(offset);
+            Day {
+                date,
+                entries: entries
+                    .iter()
+                    .filter(|entry| entry.at.date_naive() == date)
+                    .cloned()
+                    .collect(),
+            }
+        })
+        .collect();
+    Agenda { overdue, days }
+}
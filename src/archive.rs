@@ -0,0 +1,49 @@
+//! The archive: completed tasks moved out of the data file by `archive`, so that it stays
+//! small, and read back only by `list --archived` and `search --archived`.
+//!
+//! The archive is kept next to the data file as `<data file>.archive`, a [data
+//! file](crate::schema) of its own, oldest tasks first.
+
+use crate::{schema, storage, Task};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The archive kept next to `data_file`.
+pub fn path_for(data_file: &Path) -> PathBuf {
+    let mut name = data_file.file_name().unwrap_or_default().to_os_string();
+    name.push(".archive");
+    data_file.with_file_name(name)
+}
+
+/// The archived tasks at `path`.
+pub fn load(path: &Path) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let (tasks, _) = schema::parse(&storage::read_text(path)?)?;
+    Ok(tasks)
+}
+
+/// Drops the tasks that `forget` matches from the archive at `path`, returning how many
+/// there were.
+pub fn forget(
+    path: &Path,
+    forget: impl Fn(&Task) -> bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut archived = load(path)?;
+    let before = archived.len();
+    archived.retain(|t| !forget(t));
+    let removed = before - archived.len();
+    if removed > 0 {
+        storage::replace(path, schema::render(&archived)?.as_bytes())?;
+    }
+    Ok(removed)
+}
+
+/// Adds `tasks` to the archive at `path`.
+pub fn append(path: &Path, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archived = load(path)?;
+    archived.extend_from_slice(tasks);
+    fs::write(path, schema::render(&archived)?)?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! `attach` and `open`: files and web addresses kept on a task, and opening them with the
+//! platform's default application (`open` on macOS, `start` on Windows and `xdg-open`
+//! elsewhere).
+//!
+//! Web addresses are anything with a scheme, such as `https://...` or `mailto:...`, and are
+//! kept as given. Anything else is a file, which has to exist when attached and is kept as an
+//! absolute path, so it opens from any directory.
+
+use crate::error::Error;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Whether `target` is a web address rather than a file path. A single letter before the
+/// colon is a Windows drive.
+pub fn is_url(target: &str) -> bool {
+    target.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    })
+}
+
+/// What to keep for `target`: a web address as it is, or the absolute path of an existing file.
+pub fn reference(target: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if is_url(target) {
+        return Ok(target.to_string());
+    }
+    let path = std::path::absolute(target)?;
+    if !path.exists() {
+        return Err(Error::NotFound(format!("there is no file {}", path.display())).into());
+    }
+    Ok(path.display().to_string())
+}
+
+/// The address that opens `attachment`: the web address, or a `file://` one for a file.
+pub fn url(attachment: &str) -> String {
+    if is_url(attachment) {
+        attachment.to_string()
+    } else {
+        format!("file://{}", attachment)
+    }
+}
+
+/// Whether `attachment` is a file that no longer exists.
+pub fn missing(attachment: &str) -> bool {
+    !is_url(attachment) && !Path::new(attachment).exists()
+}
+
+/// Opens `attachment` with the default application for it, without waiting for it to close.
+pub fn open(attachment: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if missing(attachment) {
+        return Err(Error::NotFound(format!("{} no longer exists", attachment)).into());
+    }
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        // The empty title keeps `start` from taking a quoted path as the window title.
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .arg(attachment)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                format!(
+                    "{} isn't installed, so {} can't be opened",
+                    program, attachment
+                )
+            }
+            _ => format!("could not run {}: {}", program, e),
+        })?;
+    if !status.success() {
+        return Err(format!("{} could not open {} ({})", program, attachment, status).into());
+    }
+    Ok(())
+}
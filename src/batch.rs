@@ -0,0 +1,246 @@
+//! `add --stdin` and `apply`: many changes from a script in one save, so seeding a list
+//! doesn't take a process per task.
+//!
+//! `add --stdin` reads one task per line. Words starting with `#` are tags, `!high` (or any
+//! other priority) sets the priority, `@due:friday` the due date and `@home` (any other
+//! word starting with `@`) the context; the other words are the description:
+//!
+//! ```text
+//! Book flights #travel !high @due:friday
+//! Renew passport #travel @due:2026-11-30 @town
+//! ```
+//!
+//! `apply` reads a JSON array of operations, each naming its `op`:
+//!
+//! ```json
+//! [
+//!   {"op": "add", "description": "Plan the offsite", "tags": ["work"], "as": "offsite"},
+//!   {"op": "add", "description": "Book a venue", "parent": "offsite", "due": "next friday"},
+//!   {"op": "priority", "id": "offsite", "priority": "high"},
+//!   {"op": "complete", "id": 4}
+//! ]
+//! ```
+//!
+//! The operations are `add` (`description`, and optionally `tags`, `priority`, `due`,
+//! `parent` and `as`), `complete`, `delete`, `tag` and `untag` (`tags`), `priority`
+//! (`priority`, or null to clear it), `due` (`due`, or null) and `note` (`text`), all but
+//! `add` taking the task's `id`. A task added with `"as": "name"` can be referred to by that
+//! name instead of an ID in the operations after it.
+
+use crate::error::{self, Error};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::collections::HashMap;
+use todo_core::{dates, Completion, Priority, Task, TodoList};
+
+/// A task in an operation: its ID, or the name it was added `as`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TaskRef {
+    Id(usize),
+    Name(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+enum Op {
+    Add {
+        description: String,
+        #[serde(default)]
+        tags: Vec<String>,
+        priority: Option<Priority>,
+        due: Option<String>,
+        parent: Option<TaskRef>,
+        #[serde(rename = "as")]
+        name: Option<String>,
+    },
+    Complete {
+        id: TaskRef,
+    },
+    Delete {
+        id: TaskRef,
+    },
+    Tag {
+        id: TaskRef,
+        tags: Vec<String>,
+    },
+    Untag {
+        id: TaskRef,
+        tags: Vec<String>,
+    },
+    Priority {
+        id: TaskRef,
+        priority: Option<Priority>,
+    },
+    Due {
+        id: TaskRef,
+        due: Option<String>,
+    },
+    Note {
+        id: TaskRef,
+        text: String,
+    },
+}
+
+/// Reads a task from a line of `add --stdin`.
+pub fn parse_line(line: &str, now: DateTime<Local>) -> Result<Task, String> {
+    let (mut words, mut tags, mut priority, mut due, mut location) =
+        (vec![], vec![], None, None, None);
+    for word in line.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            tags.push(tag);
+        } else if let Some(level) = word.strip_prefix('!').filter(|level| !level.is_empty()) {
+            priority = Some(
+                <Priority as clap::ValueEnum>::from_str(level, true)
+                    .map_err(|_| format!("unknown priority {:?}", level))?,
+            );
+        } else if let Some(when) = word.strip_prefix("@due:") {
+            due = Some(dates::parse_due(when, now)?);
+        } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            location = Some(context.to_string());
+        } else {
+            words.push(word);
+        }
+    }
+    if words.is_empty() {
+        return Err("the task has no description".into());
+    }
+    let mut task = Task::builder()
+        .description(words.join(" "))
+        .priority(priority)
+        .due(due)
+        .location(location)
+        .build();
+    for tag in tags {
+        task.add_tag(tag);
+    }
+    Ok(task)
+}
+
+/// Parses `input`, a JSON array of operations, and applies them to `list` in one save.
+/// Nothing is saved when one of them fails. Returns how many were applied.
+pub fn apply(list: &mut TodoList, input: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let ops: Vec<Op> =
+        serde_json::from_str(input).map_err(|e| Error::Parse(format!("the batch: {}", e)))?;
+    let count = ops.len();
+    let now = dates::now();
+    list.batch(|list| {
+        let mut names: HashMap<String, usize> = HashMap::new();
+        for (n, op) in ops.into_iter().enumerate() {
+            apply_one(list, op, &mut names, now)
+                .map_err(|e| error::context(e, format_args!("operation {}", n + 1)))?;
+        }
+        Ok(())
+    })?;
+    Ok(count)
+}
+
+fn resolve(
+    list: &TodoList,
+    names: &HashMap<String, usize>,
+    task: &TaskRef,
+) -> Result<usize, Error> {
+    let id = match task {
+        TaskRef::Id(id) => *id,
+        TaskRef::Name(name) => *names.get(name).ok_or_else(|| {
+            Error::NotFound(format!("no task was added as {:?} before this", name))
+        })?,
+    };
+    match list.get(id) {
+        Some(_) => Ok(id),
+        None => Err(Error::task_not_found(id)),
+    }
+}
+
+fn apply_one(
+    list: &mut TodoList,
+    op: Op,
+    names: &mut HashMap<String, usize>,
+    now: DateTime<Local>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match op {
+        Op::Add {
+            description,
+            tags,
+            priority,
+            due,
+            parent,
+            name,
+        } => {
+            if description.trim().is_empty() {
+                return Err(Error::Parse("the description is empty".into()).into());
+            }
+            let parent = parent.map(|p| resolve(list, names, &p)).transpose()?;
+            if let Some(parent) = parent {
+                list.check_parent(None, parent).map_err(Error::Parse)?;
+            }
+            let due = due
+                .map(|d| dates::parse_due(&d, now))
+                .transpose()
+                .map_err(Error::Parse)?;
+            let mut task = Task::builder()
+                .description(description)
+                .priority(priority)
+                .due(due)
+                .parent(parent)
+                .build();
+            for tag in &tags {
+                task.add_tag(tag);
+            }
+            let id = list.insert(task);
+            if let Some(name) = name {
+                if names.insert(name.clone(), id).is_some() {
+                    return Err(
+                        Error::Parse(format!("a task was already added as {:?}", name)).into(),
+                    );
+                }
+            }
+        }
+        Op::Complete { id } => {
+            let id = resolve(list, names, &id)?;
+            if let Completion::NotFound = list.complete(id)? {
+                return Err(Error::task_not_found(id).into());
+            }
+        }
+        Op::Delete { id } => {
+            let id = resolve(list, names, &id)?;
+            list.delete(id)?;
+        }
+        Op::Tag { id, tags } => {
+            let id = resolve(list, names, &id)?;
+            list.update(id, |task| {
+                for tag in &tags {
+                    task.add_tag(tag);
+                }
+            })?;
+        }
+        Op::Untag { id, tags } => {
+            let id = resolve(list, names, &id)?;
+            list.update(id, |task| {
+                for tag in &tags {
+                    task.remove_tag(tag);
+                }
+            })?;
+        }
+        Op::Priority { id, priority } => {
+            let id = resolve(list, names, &id)?;
+            list.update(id, |task| task.priority = priority)?;
+        }
+        Op::Due { id, due } => {
+            let id = resolve(list, names, &id)?;
+            let due = due
+                .map(|d| dates::parse_due(&d, now))
+                .transpose()
+                .map_err(Error::Parse)?;
+            list.update(id, |task| task.due = due)?;
+        }
+        Op::Note { id, text } => {
+            let id = resolve(list, names, &id)?;
+            if text.trim().is_empty() {
+                return Err(Error::Parse("the note is empty".into()).into());
+            }
+            list.add_note(id, text.trim())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! `bench storage`: times the storage backends on synthetic lists.
+
+use chrono::{Days, Local};
+use clap::Subcommand;
+use std::fs;
+use std::time::{Duration, Instant};
+use todo_core::{JsonFile, Priority, Sqlite, Storage, Task, TaskLog};
+
+#[derive(Subcommand)]
+pub enum BenchTarget {
+    /// Compare the JSON and SQLite backends: saving a whole list, loading it, and saving
+    /// after one task changed
+    Storage {
+        /// List sizes to try
+        #[arg(long, value_delimiter = ',', default_values_t = [100, 1_000, 10_000])]
+        tasks: Vec<usize>,
+        /// Runs per measurement; the median is reported
+        #[arg(long, default_value_t = 5)]
+        runs: usize,
+    },
+}
+
+/// A list of `count` tasks resembling real ones: tags, some due dates and priorities, a
+/// third of them completed.
+fn synthetic(count: usize) -> Vec<Task> {
+    let now = Local::now();
+    let priorities = [None, Some(Priority::Low), Some(Priority::High)];
+    (1..=count)
+        .map(|id| {
+            let mut task = Task::builder()
+                .description(format!("Synthetic task {} with a few more words", id))
+                .completed(id % 3 == 0)
+                .tags(vec![format!("project-{}", id % 10)])
+                .priority(priorities[id % priorities.len()])
+                .due((id % 4 == 0).then(|| now + Days::new((id % 30) as u64)))
+                .build();
+            task.id = id;
+            task
+        })
+        .collect()
+}
+
+fn median(mut samples: Vec<Duration>) -> Duration {
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+fn measure(
+    runs: usize,
+    mut run: impl FnMut() -> Result<Duration, Box<dyn std::error::Error>>,
+) -> Result<Duration, Box<dyn std::error::Error>> {
+    let samples = (0..runs.max(1)).map(|_| run()).collect::<Result<_, _>>()?;
+    Ok(median(samples))
+}
+
+fn time(
+    f: impl FnOnce() -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<Duration, Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    f()?;
+    Ok(start.elapsed())
+}
+
+fn bench_storage(sizes: &[usize], runs: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("rust-todo-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    println!(
+        "{:<8} {:>7} {:>12} {:>12} {:>12}",
+        "backend", "tasks", "save all", "load", "save one"
+    );
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        for &size in sizes {
+            let tasks = synthetic(size);
+            let mut changed = tasks.clone();
+            if let Some(task) = changed.first_mut() {
+                task.description.push_str(" (edited)");
+            }
+            let stores: Vec<(&str, Box<dyn Storage>)> = vec![
+                (
+                    "json",
+                    Box::new(JsonFile::new(dir.join(format!("{}.json", size)))),
+                ),
+                (
+                    "sqlite",
+                    Box::new(Sqlite::open(dir.join(format!("{}.db", size)))?),
+                ),
+                (
+                    "log",
+                    Box::new(TaskLog::new(dir.join(format!("{}.jsonl", size)))),
+                ),
+            ];
+            for (name, store) in stores {
+                let save_all = measure(runs, || {
+                    store.save(&[])?;
+                    time(|| store.save(&tasks))
+                })?;
+                let load = measure(runs, || time(|| store.load().map(drop)))?;
+                let save_one = measure(runs, || {
+                    store.save(&tasks)?;
+                    time(|| store.save(&changed))
+                })?;
+                println!(
+                    "{:<8} {:>7} {:>12?} {:>12?} {:>12?}",
+                    name, size, save_all, load, save_one
+                );
+            }
+        }
+        Ok(())
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+pub fn run(target: BenchTarget) -> Result<(), Box<dyn std::error::Error>> {
+    match target {
+        BenchTarget::Storage { tasks, runs } => bench_storage(&tasks, runs),
+    }
+}
@@ -0,0 +1,243 @@
+//! Importing browser bookmarks as "read later" tasks.
+//!
+//! Accepts the Netscape bookmark HTML that every browser exports, Firefox's JSON backup and
+//! Chrome's `Bookmarks` JSON file. Folder names (and Firefox's own tags) become task tags.
+//! The URL is stored in the `url` metadata key and doubles as the external ID, so importing
+//! the same file twice does not duplicate anything.
+
+use crate::{normalize_tag, Task};
+use chrono::{Local, TimeZone};
+use regex::Regex;
+use serde_json::Value;
+
+/// Browser-managed root folders that say nothing about the bookmark itself.
+const ROOT_FOLDERS: &[&str] = &[
+    "bookmarks bar",
+    "bookmarks toolbar",
+    "bookmarks menu",
+    "other bookmarks",
+    "mobile bookmarks",
+    "toolbar",
+    "menu",
+    "unfiled",
+    "mobile",
+    "bookmark_bar",
+    "other",
+    "synced",
+];
+
+struct Bookmark {
+    title: String,
+    url: String,
+    tags: Vec<String>,
+    added: Option<i64>,
+}
+
+fn folder_tag(name: &str) -> Option<String> {
+    let tag = normalize_tag(name);
+    if tag.is_empty() || ROOT_FOLDERS.contains(&name.trim().to_lowercase().as_str()) {
+        None
+    } else {
+        Some(tag)
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+}
+
+fn parse_html(content: &str) -> Vec<Bookmark> {
+    let folder = Regex::new(r"(?i)<H3([^>]*)>(.*?)</H3>").unwrap();
+    let link = Regex::new(r"(?i)<A\s([^>]*)>(.*?)</A>").unwrap();
+    let attr = Regex::new(r#"(?i)([A-Z_]+)="([^"]*)""#).unwrap();
+    let open = Regex::new(r"(?i)<DL>").unwrap();
+    let close = Regex::new(r"(?i)</DL>").unwrap();
+
+    let mut stack: Vec<Option<String>> = Vec::new();
+    let mut pending: Option<Option<String>> = None;
+    let mut bookmarks = Vec::new();
+    for line in content.lines() {
+        if let Some(caps) = folder.captures(line) {
+            let special = caps[1].to_uppercase().contains("_FOLDER");
+            pending = Some(if special {
+                None
+            } else {
+                folder_tag(&unescape(&caps[2]))
+            });
+        } else if let Some(caps) = link.captures(line) {
+            let attrs: Vec<(String, String)> = attr
+                .captures_iter(&caps[1])
+                .map(|a| (a[1].to_uppercase(), unescape(&a[2])))
+                .collect();
+            let get = |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.clone())
+            };
+            let Some(url) = get("HREF") else {
+                continue;
+            };
+            let mut tags: Vec<String> = stack.iter().flatten().cloned().collect();
+            if let Some(extra) = get("TAGS") {
+                tags.extend(
+                    extra
+                        .split(',')
+                        .map(normalize_tag)
+                        .filter(|t| !t.is_empty()),
+                );
+            }
+            bookmarks.push(Bookmark {
+                title: unescape(&caps[2]),
+                url,
+                tags,
+                added: get("ADD_DATE").and_then(|d| d.parse().ok()),
+            });
+        }
+        if open.is_match(line) {
+            stack.push(pending.take().flatten());
+        }
+        if close.is_match(line) {
+            stack.pop();
+        }
+    }
+    bookmarks
+}
+
+fn walk_json(node: &Value, folders: &mut Vec<String>, bookmarks: &mut Vec<Bookmark>) {
+    let title = node
+        .get("title")
+        .or_else(|| node.get("name"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let url = node
+        .get("uri")
+        .or_else(|| node.get("url"))
+        .and_then(Value::as_str);
+    if let Some(url) = url {
+        if url.starts_with("place:") {
+            return;
+        }
+        let mut tags = folders.clone();
+        if let Some(extra) = node.get("tags").and_then(Value::as_str) {
+            tags.extend(
+                extra
+                    .split(',')
+                    .map(normalize_tag)
+                    .filter(|t| !t.is_empty()),
+            );
+        }
+        // Firefox stores microseconds, Chrome microseconds since 1601; only Firefox's is usable.
+        let added = node
+            .get("dateAdded")
+            .and_then(Value::as_i64)
+            .map(|us| us / 1_000_000);
+        bookmarks.push(Bookmark {
+            title: title.to_string(),
+            url: url.to_string(),
+            tags,
+            added,
+        });
+        return;
+    }
+    let children = node.get("children").and_then(Value::as_array);
+    if let Some(children) = children {
+        let tag = folder_tag(title);
+        if let Some(tag) = &tag {
+            folders.push(tag.clone());
+        }
+        for child in children {
+            walk_json(child, folders, bookmarks);
+        }
+        if tag.is_some() {
+            folders.pop();
+        }
+    } else if let Some(roots) = node.get("roots").and_then(Value::as_object) {
+        for root in roots.values() {
+            walk_json(root, folders, bookmarks);
+        }
+    }
+}
+
+pub fn parse(content: &str) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    let trimmed = content.trim_start();
+    let bookmarks = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        let root: Value = serde_json::from_str(trimmed)?;
+        let mut bookmarks = Vec::new();
+        match &root {
+            Value::Array(nodes) => nodes
+                .iter()
+                .for_each(|n| walk_json(n, &mut Vec::new(), &mut bookmarks)),
+            node => walk_json(node, &mut Vec::new(), &mut bookmarks),
+        }
+        bookmarks
+    } else {
+        parse_html(content)
+    };
+
+    Ok(bookmarks
+        .into_iter()
+        .map(|bookmark| {
+            let title = if bookmark.title.trim().is_empty() {
+                bookmark.url.clone()
+            } else {
+                bookmark.title.trim().to_string()
+            };
+            let mut tags = bookmark.tags;
+            tags.dedup();
+            let mut task = Task::builder()
+                .description(format!("Read: {}", title))
+                .tags(tags)
+                .metadata("url", bookmark.url.clone())
+                .metadata("external.id", format!("url:{}", bookmark.url))
+                .build();
+            if let Some(added) = bookmark
+                .added
+                .and_then(|s| Local.timestamp_opt(s, 0).single())
+            {
+                task.created_at = added.fixed_offset();
+            }
+            task
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_netscape_html_with_folders_as_tags() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3 PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+    <DL><p>
+        <DT><H3>Rust &amp; Go</H3>
+        <DL><p>
+            <DT><A HREF="https://doc.rust-lang.org/book/" ADD_DATE="1700000000">The Book</A>
+        </DL><p>
+        <DT><A HREF="https://example.com/">  </A>
+    </DL><p>
+</DL><p>
+"#;
+        let tasks = parse(html).unwrap();
+        let [book, untitled] = &tasks[..] else {
+            panic!("read {} tasks", tasks.len());
+        };
+        assert_eq!(book.description, "Read: The Book");
+        assert_eq!(book.tags, ["rust-&-go"]);
+        assert_eq!(book.metadata["url"], "https://doc.rust-lang.org/book/");
+        assert_eq!(book.created_at.timestamp(), 1_700_000_000);
+        assert_eq!(untitled.description, "Read: https://example.com/");
+        assert!(untitled.tags.is_empty());
+        // The same file again is recognised by the URLs.
+        assert_eq!(
+            parse(html).unwrap()[0].metadata["external.id"],
+            book.metadata["external.id"]
+        );
+    }
+}
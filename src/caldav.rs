@@ -0,0 +1,342 @@
+//! `sync caldav`: two-way sync with a task list on a CalDAV server such as Nextcloud or
+//! Fastmail.
+//!
+//! Pending tasks with a due date are uploaded as [iCalendar to-dos](todo_core::ical), one
+//! resource each, and to-dos added on the server become tasks. A synced task remembers its
+//! resource, the resource's ETag and a hash of what was synced in `caldav.*` metadata, so
+//! each side's changes since the last sync can be told apart. A change on one side is copied
+//! to the other; when both changed, a completion on either side wins and the rest of the
+//! task is kept as it is here. To-dos deleted on the server are unlinked from their task,
+//! which isn't uploaded again, and those whose
+//! task was deleted here, and is still in the trash, are deleted on the server.
+
+use crate::clipboard::base64;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use todo_core::{ical, Task, TodoList};
+use tracing::debug;
+
+/// Environment variable holding the CalDAV password (often an app password).
+pub const PASSWORD_ENV: &str = "CALDAV_PASSWORD";
+
+const HREF: &str = "caldav.href";
+const ETAG: &str = "caldav.etag";
+const SYNCED: &str = "caldav.synced";
+/// Set on tasks whose to-do was deleted on the server.
+const REMOVED: &str = "caldav.removed";
+
+const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#;
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub uploaded: usize,
+    pub updated_here: usize,
+    pub updated_there: usize,
+    pub added_here: usize,
+    pub deleted_there: usize,
+    pub unlinked: usize,
+}
+
+pub struct Client {
+    agent: ureq::Agent,
+    /// The task list's URL, ending in `/`.
+    collection: String,
+    auth: String,
+}
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+impl Client {
+    pub fn new(url: &str, user: &str, password: &str) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .allow_non_standard_methods(true)
+            .http_status_as_error(false)
+            .build()
+            .new_agent();
+        Client {
+            agent,
+            collection: format!("{}/", url.trim_end_matches('/')),
+            auth: format!(
+                "Basic {}",
+                base64(format!("{}:{}", user, password).as_bytes())
+            ),
+        }
+    }
+
+    /// The full URL of `href`, which servers usually give as an absolute path.
+    fn url(&self, href: &str) -> String {
+        if href.contains("://") {
+            return href.to_string();
+        }
+        let origin_end = self
+            .collection
+            .find("://")
+            .and_then(|scheme| {
+                self.collection[scheme + 3..]
+                    .find('/')
+                    .map(|i| scheme + 3 + i)
+            })
+            .unwrap_or(self.collection.len());
+        format!("{}{}", &self.collection[..origin_end], href)
+    }
+
+    fn check(response: &ureq::http::Response<ureq::Body>, what: &str) -> Result<()> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        Err(match status.as_u16() {
+            401 | 403 => format!(
+                "the CalDAV server refused the login ({}); check --user and {}",
+                status, PASSWORD_ENV
+            ),
+            412 => format!("{} changed on the server during the sync; sync again", what),
+            _ => format!("CalDAV {} failed: {}", what, status),
+        }
+        .into())
+    }
+
+    fn etag(response: &ureq::http::Response<ureq::Body>) -> Option<String> {
+        response
+            .headers()
+            .get("ETag")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    }
+
+    /// The resources in the collection and their ETags.
+    fn list(&self) -> Result<HashMap<String, String>> {
+        let request = ureq::http::Request::builder()
+            .method("PROPFIND")
+            .uri(&self.collection)
+            .header("Authorization", &self.auth)
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(PROPFIND)?;
+        let mut response = self.agent.run(request)?;
+        Self::check(&response, "listing the tasks")?;
+        let body = response.body_mut().read_to_string()?;
+        let element = |name: &str| {
+            Regex::new(&format!(
+                r"(?s)<(?:\w+:)?{0}[^>]*>(.*?)</(?:\w+:)?{0}>",
+                name
+            ))
+            .unwrap()
+        };
+        let (responses, href, etag) = (element("response"), element("href"), element("getetag"));
+        let collection_path = self.url(&self.collection);
+        Ok(responses
+            .captures_iter(&body)
+            .filter_map(|entry| {
+                let entry = &entry[1];
+                let href = href.captures(entry)?[1].trim().to_string();
+                let etag = etag.captures(entry)?[1].trim().replace("&quot;", "\"");
+                (href.ends_with(".ics") && self.url(&href) != collection_path)
+                    .then_some((href, etag))
+            })
+            .collect())
+    }
+
+    /// The to-do at `href` and its ETag.
+    fn get(&self, href: &str) -> Result<(ical::Todo, Option<String>)> {
+        let mut response = self
+            .agent
+            .get(&self.url(href))
+            .header("Authorization", &self.auth)
+            .call()?;
+        Self::check(&response, href)?;
+        let etag = Self::etag(&response);
+        let body = response.body_mut().read_to_string()?;
+        let todo = ical::parse_todos(&body)
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("{} holds no to-do", href))?;
+        Ok((todo, etag))
+    }
+
+    /// Uploads `body` to `href`, only over the version with `etag`, or only if there is none
+    /// yet when `etag` is `None`. Returns the new ETag.
+    fn put(&self, href: &str, body: &str, etag: Option<&str>) -> Result<String> {
+        let request = self
+            .agent
+            .put(&self.url(href))
+            .header("Authorization", &self.auth)
+            .header("Content-Type", "text/calendar; charset=utf-8");
+        let request = match etag {
+            Some(etag) => request.header("If-Match", etag),
+            None => request.header("If-None-Match", "*"),
+        };
+        let response = request.send(body)?;
+        Self::check(&response, href)?;
+        match Self::etag(&response) {
+            Some(etag) => Ok(etag),
+            // Servers may leave it out when they changed what was uploaded.
+            None => Ok(self.get(href)?.1.unwrap_or_default()),
+        }
+    }
+
+    fn delete(&self, href: &str, etag: &str) -> Result<()> {
+        let response = self
+            .agent
+            .delete(&self.url(href))
+            .header("Authorization", &self.auth)
+            .header("If-Match", etag)
+            .call()?;
+        Self::check(&response, href)
+    }
+
+    fn href_for(&self, uid: &str) -> String {
+        let name: String = uid
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}{}.ics", self.collection, name)
+    }
+}
+
+/// A fingerprint of what is synced of `task`, to tell whether it changed here since.
+fn synced_state(task: &Task) -> String {
+    let tags = task.tags.join(",");
+    let state = format!(
+        "{}\n{}\n{:?}\n{:?}\n{}",
+        task.description,
+        task.completed,
+        task.due.map(|d| d.timestamp()),
+        task.priority,
+        tags
+    );
+    Sha256::digest(state)[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn link(task: &mut Task, href: &str, etag: &str) {
+    task.metadata.insert(HREF.into(), href.to_string());
+    task.metadata.insert(ETAG.into(), etag.to_string());
+    let state = synced_state(task);
+    task.metadata.insert(SYNCED.into(), state);
+}
+
+fn unlink(task: &mut Task) {
+    for key in [HREF, ETAG, SYNCED] {
+        task.metadata.remove(key);
+    }
+    task.metadata.insert(REMOVED.into(), "true".into());
+}
+
+/// Applies the server's version of a task, completing it through the list so repeating
+/// tasks get their next occurrence.
+fn apply(list: &mut TodoList, id: usize, todo: &ical::Todo) -> Result<()> {
+    let Some(task) = list.get_mut(id) else {
+        return Ok(());
+    };
+    let (was_completed, status) = (task.completed, task.status);
+    todo.apply(task);
+    if todo.completed && !was_completed {
+        task.set_status(status);
+        list.complete(id)?;
+    }
+    Ok(())
+}
+
+fn calendar(list: &TodoList, id: usize) -> Option<String> {
+    let task = list.get(id)?;
+    Some(ical::render(std::slice::from_ref(task), list.tasks()))
+}
+
+pub fn sync(list: &mut TodoList, client: &Client) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    let remote = client.list()?;
+    // Resources whose task was deleted here.
+    let trashed: HashSet<String> = list
+        .trashed()?
+        .iter()
+        .filter_map(|t| t.task.metadata.get(HREF))
+        .map(|href| client.url(href))
+        .collect();
+    debug!(resources = remote.len(), "listed the CalDAV collection");
+    list.batch(|list| {
+        let mut linked: HashSet<String> = HashSet::new();
+        let ids: Vec<usize> = list.tasks().iter().map(|t| t.id).collect();
+        for id in ids {
+            let Some(task) = list.get(id) else { continue };
+            let Some(href) = task.metadata.get(HREF).cloned() else {
+                if task.due.is_none() || task.completed || task.metadata.contains_key(REMOVED) {
+                    continue;
+                }
+                let href = client.href_for(&ical::uid(task));
+                let etag = client.put(&href, &calendar(list, id).unwrap_or_default(), None)?;
+                if let Some(task) = list.get_mut(id) {
+                    link(task, &href, &etag);
+                }
+                linked.insert(href);
+                report.uploaded += 1;
+                continue;
+            };
+            // Servers may list the path where the task remembers the full URL.
+            let key = remote
+                .keys()
+                .find(|h| client.url(h) == client.url(&href))
+                .cloned();
+            let Some(key) = key else {
+                if let Some(task) = list.get_mut(id) {
+                    unlink(task);
+                }
+                report.unlinked += 1;
+                continue;
+            };
+            linked.insert(key.clone());
+            let changed_there = task.metadata.get(ETAG) != remote.get(&key);
+            let changed_here = task.metadata.get(SYNCED) != Some(&synced_state(task));
+            if !changed_there && !changed_here {
+                continue;
+            }
+            let mut etag = remote[&key].clone();
+            if changed_there {
+                let (todo, _) = client.get(&href)?;
+                if changed_here {
+                    // Keep the task as it is here, except for a completion over there.
+                    if todo.completed {
+                        list.complete(id)?;
+                    }
+                } else {
+                    apply(list, id, &todo)?;
+                    report.updated_here += 1;
+                }
+            }
+            if changed_here {
+                etag = client.put(&href, &calendar(list, id).unwrap_or_default(), Some(&etag))?;
+                report.updated_there += 1;
+            }
+            if let Some(task) = list.get_mut(id) {
+                link(task, &href, &etag);
+            }
+        }
+        for (href, etag) in &remote {
+            if linked.contains(href) {
+                continue;
+            }
+            if trashed.contains(&client.url(href)) {
+                client.delete(href, etag)?;
+                report.deleted_there += 1;
+                continue;
+            }
+            let (todo, _) = client.get(href)?;
+            let mut task = todo.to_task();
+            link(&mut task, &client.url(href), etag);
+            list.insert(task);
+            report.added_here += 1;
+        }
+        Ok(())
+    })?;
+    Ok(report)
+}
@@ -0,0 +1,44 @@
+//! Exporting tasks for classic Unix calendar tools.
+
+use crate::Task;
+
+/// Escapes characters that remind(1) would otherwise interpret inside `MSG` bodies.
+fn remind_escape(text: &str) -> String {
+    text.replace('%', "%%").replace('[', "[\"[\"]")
+}
+
+/// Renders a remind(1) script. Each undelivered reminder of a pending task becomes a dated
+/// `REM ... AT` entry. Pending tasks without reminders become undated `REM MSG` lines, which
+/// remind shows every day until the task is done and the file re-exported.
+pub fn render_remind(tasks: &[Task]) -> String {
+    let mut output = String::from("# Generated by rust-todo\n");
+    for task in tasks.iter().filter(|t| !t.completed) {
+        let message = remind_escape(&task.description);
+        let pending: Vec<_> = task.reminders.iter().filter(|r| !r.delivered).collect();
+        if pending.is_empty() {
+            output.push_str(&format!("REM MSG {} (#{})\n", message, task.id));
+        }
+        for reminder in pending {
+            output.push_str(&format!(
+                "REM {} AT {} MSG {} (#{})\n",
+                reminder.at.format("%-d %b %Y"),
+                reminder.at.format("%H:%M"),
+                message,
+                task.id
+            ));
+        }
+    }
+    output
+}
+
+/// Renders a calcurse todo file: `[0] text` for pending tasks and `[-0] text` for completed
+/// ones, which calcurse displays as done.
+pub fn render_calcurse(tasks: &[Task]) -> String {
+    tasks
+        .iter()
+        .map(|task| {
+            let priority = if task.completed { "-0" } else { "0" };
+            format!("[{}] {}\n", priority, task.description.replace('\n', " "))
+        })
+        .collect()
+}
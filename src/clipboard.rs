@@ -0,0 +1,147 @@
+//! Copying to and pasting from the system clipboard through the platform's clipboard tools.
+//!
+//! Over SSH, copying uses the OSC 52 escape sequence instead, so the text lands on the clipboard
+//! of the machine running the terminal rather than the remote one.
+
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use todo_core::Task;
+
+const COPY_TOOLS: &[&[&str]] = &[
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["clip.exe"],
+];
+
+const PASTE_TOOLS: &[&[&str]] = &[
+    &["pbpaste"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+    &["powershell.exe", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Runs the first tool in `tools` that is installed, feeding it `input` if given, and returns
+/// its output. `None` means none of them is installed.
+///
+/// When copying, the tool's output is discarded: `xclip` and `wl-copy` stay in the background
+/// to serve the clipboard, and would otherwise keep our pipes open.
+fn run_first(
+    tools: &[&[&str]],
+    input: Option<&str>,
+) -> Option<Result<String, Box<dyn std::error::Error>>> {
+    for tool in tools {
+        let mut command = Command::new(tool[0]);
+        command.args(&tool[1..]);
+        if input.is_some() {
+            command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+        } else {
+            command.stdin(Stdio::null()).stdout(Stdio::piped());
+        }
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let run = || -> Result<String, Box<dyn std::error::Error>> {
+            if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+                stdin.write_all(input.as_bytes())?;
+            }
+            let output = child.wait_with_output()?;
+            if !output.status.success() {
+                return Err(format!("{} failed with {}", tool[0], output.status).into());
+            }
+            Ok(String::from_utf8(output.stdout)?)
+        };
+        return Some(run());
+    }
+    None
+}
+
+/// Puts `text` on the clipboard.
+pub fn copy(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let over_ssh = std::env::var_os("SSH_TTY").is_some();
+    if !over_ssh {
+        if let Some(result) = run_first(COPY_TOOLS, Some(text)) {
+            return result.map(|_| ());
+        }
+    }
+    let mut stdout = std::io::stdout();
+    if !stdout.is_terminal() {
+        return Err("no clipboard tool found (install wl-clipboard, xclip or xsel)".into());
+    }
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Reads the clipboard's text.
+pub fn paste() -> Result<String, Box<dyn std::error::Error>> {
+    run_first(PASTE_TOOLS, None).unwrap_or_else(|| {
+        Err("no clipboard tool found (install wl-clipboard, xclip or xsel)".into())
+    })
+}
+
+/// Tasks from pasted text: a JSON export, or else one task per non-empty line. Markdown list
+/// markers are dropped, and `[x]` marks a line's task as completed.
+pub fn parse(text: &str) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    let trimmed = text.trim_start();
+    let checklist = ["[ ]", "[x]", "[X]"].iter().any(|b| trimmed.starts_with(b));
+    if (trimmed.starts_with('[') || trimmed.starts_with('{')) && !checklist {
+        return Ok(serde_json::from_str(trimmed)?);
+    }
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let line = ["- ", "* ", "+ "]
+                .iter()
+                .find_map(|marker| line.strip_prefix(marker))
+                .unwrap_or(line)
+                .trim_start();
+            let (completed, description) = if let Some(rest) = line.strip_prefix("[ ]") {
+                (false, rest)
+            } else if let Some(rest) = line
+                .strip_prefix("[x]")
+                .or_else(|| line.strip_prefix("[X]"))
+            {
+                (true, rest)
+            } else {
+                (false, line)
+            };
+            let description = description.trim();
+            (!description.is_empty()).then(|| {
+                Task::builder()
+                    .description(description)
+                    .completed(completed)
+                    .build()
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,89 @@
+//! Comparing text the way people read it rather than by code point, so "Émile" sorts with
+//! "emile" instead of after "zebra", and searching for "cafe" finds "Café".
+//!
+//! Texts are compared in three passes, like ICU's default collation: first by their letters
+//! alone, ignoring case and accents; then by accents; then by case, lower case first.
+//! Letters are folded through their canonical decomposition, plus a few letters that don't
+//! decompose: `ß` counts as `ss`, `æ` as `ae`, `œ` as `oe`, and `ø`, `ł`, `đ` and `ı` as their
+//! plain letter.
+
+use icu_normalizer::properties::CanonicalCombiningClassMapBorrowed;
+use icu_normalizer::DecomposingNormalizerBorrowed;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// Text folded for comparison, remembering where each byte came from.
+pub struct Folded {
+    pub text: String,
+    /// For each byte of `text`, the bytes of the original character it came from.
+    origin: Vec<Range<usize>>,
+}
+
+impl Folded {
+    /// The range of the original text that `range` of the folded text came from.
+    pub fn original(&self, range: &Range<usize>) -> Range<usize> {
+        if range.is_empty() {
+            let at = self.origin.get(range.start).map_or(usize::MAX, |r| r.start);
+            return at..at;
+        }
+        self.origin[range.start].start..self.origin[range.end - 1].end
+    }
+}
+
+/// What `c` folds to at the first level: lower-case base letters without accents.
+fn fold_char(c: char, out: &mut String) {
+    let special = match c {
+        'ß' | 'ẞ' => Some("ss"),
+        'æ' | 'Æ' => Some("ae"),
+        'œ' | 'Œ' => Some("oe"),
+        'ø' | 'Ø' => Some("o"),
+        'ł' | 'Ł' => Some("l"),
+        'đ' | 'Đ' => Some("d"),
+        'ı' => Some("i"),
+        _ => None,
+    };
+    if let Some(special) = special {
+        out.push_str(special);
+        return;
+    }
+    let marks = CanonicalCombiningClassMapBorrowed::new();
+    for base in DecomposingNormalizerBorrowed::new_nfd().normalize_iter(std::iter::once(c)) {
+        if marks.get_u8(base) == 0 {
+            out.extend(base.to_lowercase());
+        }
+    }
+}
+
+/// `text` folded for comparison and matching: lower case, without accents.
+pub fn fold(text: &str) -> Folded {
+    let mut folded = Folded {
+        text: String::with_capacity(text.len()),
+        origin: Vec::with_capacity(text.len()),
+    };
+    for (start, c) in text.char_indices() {
+        let before = folded.text.len();
+        fold_char(c, &mut folded.text);
+        let range = start..start + c.len_utf8();
+        folded
+            .origin
+            .extend(std::iter::repeat_n(range, folded.text.len() - before));
+    }
+    folded
+}
+
+/// `text` in lower case and decomposed, so accents compare apart from their letters.
+fn accents(text: &str) -> String {
+    DecomposingNormalizerBorrowed::new_nfd()
+        .normalize(&text.to_lowercase())
+        .into_owned()
+}
+
+/// Orders two texts as described in the module documentation.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    fold(a)
+        .text
+        .cmp(&fold(b).text)
+        .then_with(|| accents(a).cmp(&accents(b)))
+        // Lower case first, as in ICU's default.
+        .then_with(|| b.cmp(a))
+}
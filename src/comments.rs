@@ -0,0 +1,52 @@
+//! Recognising TODO-style comments in source code and in `grep`/`rg` output.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// A line of `grep -n`/`rg -n` output such as `src/main.rs:42: // TODO: fix X`.
+#[derive(Debug)]
+pub struct GrepLine {
+    pub file: String,
+    pub line: Option<usize>,
+    pub text: String,
+}
+
+fn grep_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(?P<file>[^:]+):(?:(?P<line>\d+):)?(?:\d+:)?(?P<text>.*)$").unwrap()
+    })
+}
+
+fn todo_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\b(?P<kind>TODO|FIXME|XXX|HACK)\b(?:\([^)]*\))?[:\s]\s*(?P<text>.*)$").unwrap()
+    })
+}
+
+/// Splits a grep output line into file, optional line number and matched text.
+pub fn parse_grep_line(line: &str) -> Option<GrepLine> {
+    let caps = grep_pattern().captures(line)?;
+    Some(GrepLine {
+        file: caps["file"].to_string(),
+        line: caps.name("line").and_then(|m| m.as_str().parse().ok()),
+        text: caps["text"].to_string(),
+    })
+}
+
+/// Extracts the text after a `TODO:`/`FIXME:`/`XXX:`/`HACK:` marker, without trailing comment
+/// closers, or `None` when the line holds no such marker.
+pub fn todo_text(line: &str) -> Option<String> {
+    let caps = todo_pattern().captures(line)?;
+    let text = caps["text"]
+        .trim()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
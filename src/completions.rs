@@ -0,0 +1,458 @@
+//! `completions`: shell completion scripts for bash, zsh and fish, generated from the
+//! command-line definition so they never fall behind it.
+//!
+//! Besides subcommands, options and their possible values, the scripts complete task IDs:
+//! wherever an ID is expected they run the hidden `__complete-ids` command, which prints the
+//! pending tasks (or, for `restore`, the tasks in the trash) as `ID<tab>description`.
+
+use clap::{Command, ValueEnum};
+use std::fmt::Write;
+use todo_core::{trash, TodoList};
+
+/// The hidden subcommand the scripts call for task IDs.
+pub const IDS_COMMAND: &str = "__complete-ids";
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// An option of one command, as far as completion cares.
+struct Opt {
+    /// `--name` and `-n`.
+    names: Vec<String>,
+    help: String,
+    takes_value: bool,
+    values: Vec<String>,
+    /// Takes a task ID, like `--parent`.
+    ids: bool,
+}
+
+/// What can follow one command or nested subcommand, e.g. `note add`.
+struct Spec {
+    /// Subcommand names from the top, e.g. `["note", "add"]`; empty for the program itself.
+    path: Vec<String>,
+    options: Vec<Opt>,
+    subcommands: Vec<(String, String)>,
+    /// Takes task IDs as arguments.
+    ids: bool,
+}
+
+impl Spec {
+    fn key(&self) -> String {
+        self.path.join(" ")
+    }
+}
+
+fn help(text: Option<&clap::builder::StyledStr>) -> String {
+    let text = text.map(|t| t.to_string()).unwrap_or_default();
+    text.lines().next().unwrap_or_default().to_string()
+}
+
+fn is_id(names: Option<&[clap::builder::Str]>) -> bool {
+    names.is_some_and(|names| names.iter().any(|n| n.starts_with("ID")))
+}
+
+fn specs(command: &Command, path: Vec<String>, out: &mut Vec<Spec>) {
+    let visible = |c: &&Command| !c.is_hide_set() && c.get_name() != "help";
+    let options = command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .map(|arg| {
+            let mut names: Vec<String> = arg
+                .get_long_and_visible_aliases()
+                .into_iter()
+                .flatten()
+                .map(|long| format!("--{}", long))
+                .collect();
+            names.extend(arg.get_short().map(|short| format!("-{}", short)));
+            Opt {
+                names,
+                help: help(arg.get_help()),
+                takes_value: arg.get_action().takes_values(),
+                values: arg
+                    .get_possible_values()
+                    .iter()
+                    .filter(|v| !v.is_hide_set())
+                    .map(|v| v.get_name().to_string())
+                    .collect(),
+                ids: is_id(arg.get_value_names()),
+            }
+        })
+        .collect();
+    let ids = command
+        .get_positionals()
+        .any(|arg| is_id(arg.get_value_names()));
+    out.push(Spec {
+        path: path.clone(),
+        options,
+        subcommands: command
+            .get_subcommands()
+            .filter(visible)
+            .map(|c| (c.get_name().to_string(), help(c.get_about())))
+            .collect(),
+        ids,
+    });
+    for sub in command.get_subcommands().filter(visible) {
+        let mut path = path.clone();
+        path.push(sub.get_name().to_string());
+        specs(sub, path, out);
+    }
+}
+
+/// Every option that takes a value, so the scripts can skip the value when looking for the
+/// subcommand.
+fn valued(specs: &[Spec]) -> String {
+    let mut names: Vec<&str> = specs
+        .iter()
+        .flat_map(|s| &s.options)
+        .filter(|o| o.takes_value)
+        .flat_map(|o| o.names.iter().map(String::as_str))
+        .collect();
+    names.sort();
+    names.dedup();
+    names.join(" ")
+}
+
+/// Quotes `text` for a POSIX shell.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// The completion script for `shell`, for the program described by `command`.
+pub fn script(shell: Shell, mut command: Command) -> String {
+    // Copies global options into every subcommand.
+    command.build();
+    let mut all = vec![];
+    specs(&command, vec![], &mut all);
+    match shell {
+        Shell::Bash => bash(&all),
+        Shell::Zsh => zsh(&all),
+        Shell::Fish => fish(&all),
+    }
+}
+
+/// The shell code walking the words typed so far, shared by bash and zsh: sets `typed` to the
+/// subcommands given and `list` to the `--list` argument.
+fn scan(specs: &[Spec], words: &str, first: usize, current: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "    local valued={}", quote(&valued(specs)));
+    out.push_str("    local typed=\"\" list=\"\" word i skip=\"\"\n");
+    let _ = writeln!(out, "    for ((i = {}; i < {}; i++)); do", first, current);
+    let _ = writeln!(out, "        word=${{{}[i]}}", words);
+    out.push_str(
+        r#"        if [[ -n $skip ]]; then
+            [[ $skip == --list ]] && list=$word
+            skip=""
+            continue
+        fi
+        case $word in
+            --list=*) list=${word#--list=} ;;
+            -*) [[ " $valued " == *" $word "* ]] && skip=$word ;;
+            *)
+                if [[ " $(_rust_todo_subcommands "$typed") " == *" $word "* ]]; then
+                    typed=${typed:+$typed }$word
+                fi
+                ;;
+        esac
+    done
+"#,
+    );
+    out
+}
+
+fn subcommands_function(specs: &[Spec]) -> String {
+    let mut out = String::from("_rust_todo_subcommands() {\n    case $1 in\n");
+    for spec in specs.iter().filter(|s| !s.subcommands.is_empty()) {
+        let names: Vec<&str> = spec.subcommands.iter().map(|(n, _)| n.as_str()).collect();
+        let _ = writeln!(
+            out,
+            "        {}) echo {} ;;",
+            quote(&spec.key()),
+            quote(&names.join(" "))
+        );
+    }
+    out.push_str("    esac\n}\n");
+    out
+}
+
+fn bash(specs: &[Spec]) -> String {
+    let mut out = String::from("# bash completion for rust-todo\n\n");
+    out.push_str(&subcommands_function(specs));
+    out.push_str(
+        r#"
+# Completes the task IDs for the command in $1, with descriptions when there is a choice.
+_rust_todo_ids() {
+    local id desc ids=() described=()
+    while IFS=$'\t' read -r id desc; do
+        if [[ $id == "$cur"* ]]; then
+            ids+=("$id")
+            described+=("$id  ($desc)")
+        fi
+    done < <(rust-todo ${list:+--list "$list"} __complete-ids $1 2>/dev/null)
+    if ((${#ids[@]} == 1)); then
+        COMPREPLY=("${ids[@]}")
+    else
+        COMPREPLY=("${described[@]}")
+    fi
+}
+
+_rust_todo() {
+    local cur=${COMP_WORDS[COMP_CWORD]} prev=${COMP_WORDS[COMP_CWORD-1]}
+"#,
+    );
+    out.push_str(&scan(specs, "COMP_WORDS", 1, "COMP_CWORD"));
+    out.push_str("    case $typed in\n");
+    for spec in specs {
+        let _ = writeln!(out, "        {})", quote(&spec.key()));
+        out.push_str("            case $prev in\n");
+        for opt in spec.options.iter().filter(|o| o.takes_value) {
+            let action = if opt.ids {
+                "_rust_todo_ids \"$typed\"; return".to_string()
+            } else if !opt.values.is_empty() {
+                format!(
+                    "COMPREPLY=($(compgen -W {} -- \"$cur\")); return",
+                    quote(&opt.values.join(" "))
+                )
+            } else {
+                "return".to_string()
+            };
+            let _ = writeln!(
+                out,
+                "                {}) {} ;;",
+                opt.names.join("|"),
+                action
+            );
+        }
+        out.push_str("            esac\n");
+        let options: Vec<&str> = spec
+            .options
+            .iter()
+            .flat_map(|o| o.names.iter().filter(|n| n.starts_with("--")))
+            .map(String::as_str)
+            .collect();
+        let _ = writeln!(
+            out,
+            "            if [[ $cur == -* ]]; then\n                COMPREPLY=($(compgen -W {} -- \"$cur\"))\n                return\n            fi",
+            quote(&options.join(" "))
+        );
+        if spec.ids {
+            out.push_str("            _rust_todo_ids \"$typed\"\n");
+        } else if !spec.subcommands.is_empty() {
+            out.push_str(
+                "            COMPREPLY=($(compgen -W \"$(_rust_todo_subcommands \"$typed\")\" -- \"$cur\"))\n",
+            );
+        }
+        out.push_str("            ;;\n");
+    }
+    out.push_str("    esac\n}\n\ncomplete -o default -F _rust_todo rust-todo\n");
+    out
+}
+
+/// Escapes `:` in a `_describe` entry's name.
+fn describe(name: &str, help: &str) -> String {
+    quote(&format!("{}:{}", name.replace(':', r"\:"), help))
+}
+
+fn zsh(specs: &[Spec]) -> String {
+    let mut out = String::from("#compdef rust-todo\n\n");
+    out.push_str(&subcommands_function(specs));
+    out.push_str(
+        r#"
+_rust_todo_ids() {
+    local -a tasks
+    local id desc
+    while IFS=$'\t' read -r id desc; do
+        tasks+=("$id:${desc//:/\\:}")
+    done < <(rust-todo ${list:+--list "$list"} __complete-ids ${=1} 2>/dev/null)
+    _describe -t tasks 'task' tasks
+}
+
+_rust_todo() {
+    local cur=${words[CURRENT]} prev=${words[CURRENT-1]}
+"#,
+    );
+    out.push_str(&scan(specs, "words", 2, "CURRENT"));
+    out.push_str("    local -a entries\n    case $typed in\n");
+    for spec in specs {
+        let _ = writeln!(out, "        {})", quote(&spec.key()));
+        out.push_str("            case $prev in\n");
+        for opt in spec.options.iter().filter(|o| o.takes_value) {
+            let action = if opt.ids {
+                "_rust_todo_ids \"$typed\"; return".to_string()
+            } else if !opt.values.is_empty() {
+                let values: Vec<String> = opt.values.iter().map(|v| quote(v)).collect();
+                format!("compadd -- {}; return", values.join(" "))
+            } else {
+                "_default; return".to_string()
+            };
+            let _ = writeln!(
+                out,
+                "                {}) {} ;;",
+                opt.names.join("|"),
+                action
+            );
+        }
+        out.push_str("            esac\n");
+        let options: Vec<String> = spec
+            .options
+            .iter()
+            .flat_map(|o| {
+                o.names
+                    .iter()
+                    .filter(|n| n.starts_with("--"))
+                    .map(|n| describe(n, &o.help))
+            })
+            .collect();
+        let _ = writeln!(
+            out,
+            "            if [[ $cur == -* ]]; then\n                entries=({})\n                _describe -t options 'option' entries\n                return\n            fi",
+            options.join(" ")
+        );
+        if spec.ids {
+            out.push_str("            _rust_todo_ids \"$typed\"\n");
+        } else if !spec.subcommands.is_empty() {
+            let commands: Vec<String> = spec
+                .subcommands
+                .iter()
+                .map(|(name, help)| describe(name, help))
+                .collect();
+            let _ = writeln!(
+                out,
+                "            entries=({})\n            _describe -t commands 'command' entries",
+                commands.join(" ")
+            );
+        } else {
+            out.push_str("            _default\n");
+        }
+        out.push_str("            ;;\n");
+    }
+    out.push_str(
+        r#"    esac
+}
+
+if [[ $zsh_eval_context[-1] == loadautofunc ]]; then
+    _rust_todo "$@"
+else
+    compdef _rust_todo rust-todo
+fi
+"#,
+    );
+    out
+}
+
+fn fish(specs: &[Spec]) -> String {
+    let mut out = String::from("# fish completion for rust-todo\n\n");
+    out.push_str("function __rust_todo_subcommands\n    switch $argv[1]\n");
+    for spec in specs.iter().filter(|s| !s.subcommands.is_empty()) {
+        let names: Vec<&str> = spec.subcommands.iter().map(|(n, _)| n.as_str()).collect();
+        let _ = writeln!(
+            out,
+            "        case {}\n            printf '%s\\n' {}",
+            quote(&spec.key()),
+            names.join(" ")
+        );
+    }
+    out.push_str("    end\nend\n\n");
+    let _ = writeln!(
+        out,
+        "# Prints the subcommands typed so far, then the --list argument if any.\nfunction __rust_todo_scan\n    set -l valued {}",
+        valued(specs)
+    );
+    out.push_str(
+        r#"    set -l typed
+    set -l list
+    set -l skip
+    for word in (commandline -opc)[2..-1]
+        if test -n "$skip"
+            test "$skip" = --list; and set list $word
+            set skip
+            continue
+        end
+        switch $word
+            case '--list=*'
+                set list (string replace -- --list= '' $word)
+            case '-*'
+                contains -- $word $valued; and set skip $word
+            case '*'
+                contains -- $word (__rust_todo_subcommands "$typed"); and set typed $typed $word
+        end
+    end
+    echo "$typed"
+    echo "$list"
+end
+
+function __rust_todo_at
+    test (__rust_todo_scan)[1] = "$argv[1]"
+end
+
+function __rust_todo_ids
+    set -l scan (__rust_todo_scan)
+    set -l list
+    test -n "$scan[2]"; and set list --list $scan[2]
+    rust-todo $list __complete-ids (string split ' ' -- $scan[1]) 2>/dev/null
+end
+
+"#,
+    );
+    for spec in specs {
+        let at = format!("-n \"__rust_todo_at '{}'\"", spec.key());
+        for (name, help) in &spec.subcommands {
+            let _ = writeln!(
+                out,
+                "complete -c rust-todo {} -f -a {} -d {}",
+                at,
+                quote(name),
+                quote(help)
+            );
+        }
+        if spec.ids {
+            let _ = writeln!(
+                out,
+                "complete -c rust-todo {} -f -a '(__rust_todo_ids)'",
+                at
+            );
+        }
+        for opt in &spec.options {
+            let mut line = format!("complete -c rust-todo {}", at);
+            for name in &opt.names {
+                match name.strip_prefix("--") {
+                    Some(long) => line.push_str(&format!(" -l {}", long)),
+                    None => line.push_str(&format!(" -s {}", &name[1..])),
+                }
+            }
+            if opt.ids {
+                line.push_str(" -x -a '(__rust_todo_ids)'");
+            } else if !opt.values.is_empty() {
+                line.push_str(&format!(" -x -a {}", quote(&opt.values.join(" "))));
+            } else if opt.takes_value {
+                line.push_str(" -r");
+            }
+            let _ = writeln!(out, "{} -d {}", line, quote(&opt.help));
+        }
+    }
+    out
+}
+
+/// Prints what `__complete-ids` prints for `command`, the subcommands typed so far.
+pub fn print_ids(list: &TodoList, command: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let one_line = |text: &str| text.replace(['\t', '\n'], " ");
+    if command.first().map(String::as_str) == Some("restore") {
+        for trashed in trash::load(&trash::path_for(list.path()))? {
+            println!(
+                "{}\t{}",
+                trashed.task.id,
+                one_line(&trashed.task.description)
+            );
+        }
+        return Ok(());
+    }
+    // `reopen` only makes sense for closed tasks, every other command for open ones.
+    let reopening = command.first().map(String::as_str) == Some("reopen");
+    for task in list.tasks().iter().filter(|t| t.completed == reopening) {
+        println!("{}\t{}", task.id, one_line(&task.description));
+    }
+    Ok(())
+}
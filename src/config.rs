@@ -0,0 +1,464 @@
+//! User settings from `config.toml` in the configuration directory (see [`dirs`]), or the
+//! file named by `RUST_TODO_CONFIG`. Every setting is optional:
+//!
+//! ```toml
+//! data_file = "~/Dropbox/todo.json"  # where the default list is stored
+//! backend = "sqlite"                 # json, sqlite or log, as with --backend
+//! list = "work"                      # list used without --list
+//! date_format = "%d.%m.%Y"           # how listings show dates (strftime)
+//! color = "auto"                     # auto, always or never
+//! hyperlinks = "auto"                # clickable links in list and show: auto, always or never
+//! sort = "due"                       # list order without --sort: id, priority, due, created or description
+//! confirm = false                    # go ahead without asking, as with --yes
+//! trash_days = 30                    # empty deleted tasks from the trash after this long
+//! ids = "random"                     # IDs for new tasks: sequential or random
+//! context = "work"                   # the context until `context set` changes it
+//! user = "sam"                       # who "me" is in assign and list --assignee
+//!
+//! [stale]                            # escalate tasks pending too long, in remind-check
+//! after_days = 14
+//! tag = "stale"
+//! bump_priority = true
+//!
+//! [notify]                           # for notify-daemon
+//! before = ["1d", "30m"]             # notify this long before tasks are due
+//!
+//! [hooks]                            # commands run for each task; see hooks
+//! on-complete = "~/bin/track-done"
+//!
+//! [theme]                            # how `list` looks; see Theme
+//! overdue = "bold red"
+//! completed = ""                     # no style
+//! emoji = false                      # words instead of 📅, ⚑, 🔁...
+//! ```
+
+use crate::{dirs, hooks::Hooks, storage, Backend, SortBy};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Environment variable naming a configuration file to use instead of the default one.
+pub const CONFIG_ENV: &str = "RUST_TODO_CONFIG";
+
+/// When listings use colour.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    /// When writing to a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    /// Even when piped, e.g. into `less -R`
+    Always,
+    Never,
+}
+
+/// When `list` and `show` make links clickable, with OSC 8 escape sequences.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hyperlinks {
+    /// On terminals known to support them
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How new tasks get their IDs; see [`ids`](crate::ids).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ids {
+    /// One more than the highest ID in use
+    #[default]
+    Sequential,
+    /// Short random numbers, e.g. 407
+    Random,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where the default list is stored, instead of the platform location. A leading `~/` is
+    /// the home directory.
+    pub data_file: Option<PathBuf>,
+    /// What lists are kept in when `--backend` isn't given.
+    pub backend: Option<Backend>,
+    /// The list commands use when `--list` isn't given.
+    pub list: Option<String>,
+    /// strftime format for dates in listings; times are added as `%H:%M` when set.
+    pub date_format: Option<String>,
+    pub color: Color,
+    pub hyperlinks: Hyperlinks,
+    /// Order of `list` when `--sort` isn't given.
+    pub sort: Option<SortBy>,
+    /// Whether to ask before deleting tasks or changing several at once, and to only act
+    /// with `--yes` for `clear`, `purge` and `delete-list` without a terminal.
+    pub confirm: bool,
+    /// How many days deleted tasks stay in the [trash](crate::trash); forever when unset.
+    pub trash_days: Option<u32>,
+    pub ids: Ids,
+    /// The context `list` and `next` are limited to until `context set` or `context clear`
+    /// changes it; see [`context`].
+    pub context: Option<String>,
+    /// Who `me` is when assigning tasks on a shared list; see [`user`].
+    pub user: Option<String>,
+    pub stale: Option<Stale>,
+    pub notify: Notify,
+    pub hooks: Hooks,
+    pub theme: Theme,
+}
+
+/// How `list` looks. Styles are words separated by spaces: `bold`, `dim`, `italic`,
+/// `underline`, `reverse` and the colours `black`, `red`, `green`, `yellow`, `blue`,
+/// `magenta`, `cyan` and `white`, also as `bright-red` and so on; an empty style leaves the
+/// text as it is. See [`sgr`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Style of pending tasks past their due date.
+    pub overdue: String,
+    /// Style of critical-priority tasks.
+    pub critical: String,
+    /// Style of high-priority tasks.
+    pub high: String,
+    /// Style of completed tasks.
+    pub completed: String,
+    /// Style of group headings, as with `--group-by`.
+    pub heading: String,
+    /// Shown before pending tasks.
+    pub checkbox_pending: String,
+    /// Shown before completed tasks.
+    pub checkbox_done: String,
+    /// Whether to mark due dates, priorities and the like with emoji, or with words, e.g.
+    /// `due 2024-05-01` for `📅 2024-05-01`, for plain-text logs and terminals without them.
+    pub emoji: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            overdue: "red".into(),
+            critical: "bold magenta".into(),
+            high: "bold yellow".into(),
+            completed: "dim".into(),
+            heading: "bold".into(),
+            checkbox_pending: "[ ]".into(),
+            checkbox_done: "[x]".into(),
+            emoji: true,
+        }
+    }
+}
+
+/// The ANSI SGR parameters for `style`, a [`Theme`] style such as `bold red`, e.g. `1;31`.
+/// Empty for an empty style.
+pub fn sgr(style: &str) -> Result<String, String> {
+    const COLOURS: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    let codes: Vec<String> = style
+        .split_whitespace()
+        .map(|word| {
+            let colour = |name: &str| COLOURS.iter().position(|c| *c == name);
+            let code = match word {
+                "bold" => 1,
+                "dim" => 2,
+                "italic" => 3,
+                "underline" => 4,
+                "reverse" => 7,
+                _ => match word.strip_prefix("bright-") {
+                    Some(name) => 90 + colour(name).ok_or(word)?,
+                    None => 30 + colour(word).ok_or(word)?,
+                },
+            };
+            Ok(code.to_string())
+        })
+        .collect::<Result<_, &str>>()
+        .map_err(|word| format!("unknown style {:?} in {:?}", word, style))?;
+    Ok(codes.join(";"))
+}
+/// Desktop notifications from `notify-daemon`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Notify {
+    /// How long before a task is due to notify, each once, e.g. `["1d", "30m"]`; see
+    /// [`dates::parse_duration`](crate::dates::parse_duration).
+    pub before: Vec<String>,
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Notify {
+            before: vec!["30m".into(), "0".into()],
+        }
+    }
+}
+
+impl Notify {
+    /// The lead times in [`before`](Self::before).
+    pub fn lead_times(&self) -> Result<Vec<chrono::Duration>, String> {
+        self.before
+            .iter()
+            .map(|lead| crate::dates::parse_duration(lead))
+            .collect()
+    }
+}
+
+/// How `remind-check` escalates tasks left pending too long; see
+/// [`TodoList::escalate_stale`](crate::TodoList::escalate_stale).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stale {
+    /// How many days after being added a pending task is stale.
+    pub after_days: u32,
+    /// Tag given to stale tasks; none when empty.
+    #[serde(default = "default_stale_tag")]
+    pub tag: String,
+    /// Whether stale tasks also go up one priority.
+    #[serde(default)]
+    pub bump_priority: bool,
+}
+
+fn default_stale_tag() -> String {
+    "stale".to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_file: None,
+            backend: None,
+            list: None,
+            date_format: None,
+            color: Color::Auto,
+            hyperlinks: Hyperlinks::Auto,
+            sort: None,
+            confirm: true,
+            trash_days: None,
+            ids: Ids::Sequential,
+            context: None,
+            user: None,
+            stale: None,
+            notify: Notify::default(),
+            hooks: Hooks::default(),
+            theme: Theme::default(),
+        }
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The configuration file, whether or not it exists.
+pub fn path() -> Option<PathBuf> {
+    std::env::var_os(CONFIG_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(dirs::config_dir()?.join("config.toml")))
+}
+
+fn read() -> Result<Config, String> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+    let text = match storage::read_text(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("could not read {}: {}", path.display(), e)),
+    };
+    let mut config: Config =
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    config.data_file = config.data_file.map(dirs::expand_home);
+    check(&config).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(config)
+}
+
+/// Checks what parsing can't.
+fn check(config: &Config) -> Result<(), String> {
+    if let Some(format) = &config.date_format {
+        // chrono reports an invalid format when it is used, not when it is parsed.
+        let mut probe = String::new();
+        if write!(probe, "{}", chrono::Local::now().format(format)).is_err() {
+            return Err(format!("invalid date_format {:?}", format));
+        }
+    }
+    config
+        .notify
+        .lead_times()
+        .map_err(|e| format!("notify.before: {}", e))?;
+    let theme = &config.theme;
+    for (name, style) in [
+        ("overdue", &theme.overdue),
+        ("critical", &theme.critical),
+        ("high", &theme.high),
+        ("completed", &theme.completed),
+        ("heading", &theme.heading),
+    ] {
+        sgr(style).map_err(|e| format!("theme.{}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+/// The settings in the configuration file as written, without defaults; empty when there
+/// is no file.
+fn settings() -> Result<toml::Table, String> {
+    let Some(path) = path().filter(|path| path.exists()) else {
+        return Ok(toml::Table::new());
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    text.parse()
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Changes one top-level setting in the configuration file, or removes it when `value` is
+/// `None`, keeping the others. Returns the file written.
+pub fn set(key: &str, value: Option<toml::Value>) -> Result<PathBuf, String> {
+    let mut settings = settings()?;
+    match value {
+        Some(value) => settings.insert(key.into(), value),
+        None => settings.remove(key),
+    };
+    save(&settings)
+}
+
+/// File in the data directory holding the context chosen with `context set`. It is state
+/// rather than a setting, so changing it leaves `config.toml` as the user wrote it.
+const CONTEXT_FILE: &str = "context";
+
+/// The context `list` and `next` are limited to: tasks whose
+/// [location](crate::Task::location) is this, ignoring case. The one chosen with
+/// [`set_context`] wins; until there is one, it is the `context` setting.
+pub fn context() -> Option<String> {
+    let chosen = dirs::data_dir().and_then(|dir| fs::read_to_string(dir.join(CONTEXT_FILE)).ok());
+    match chosen {
+        Some(name) => Some(name.trim().to_string()).filter(|name| !name.is_empty()),
+        None => current().context.clone(),
+    }
+}
+
+/// Makes `name` the [context], or clears it with `None`, also over a `context` setting.
+/// Returns the file written.
+pub fn set_context(name: Option<&str>) -> Result<PathBuf, String> {
+    let dir = dirs::data_dir().ok_or("no data directory on this system")?;
+    let path = dir.join(CONTEXT_FILE);
+    let write = || -> std::io::Result<()> {
+        fs::create_dir_all(&dir)?;
+        storage::replace(&path, format!("{}\n", name.unwrap_or_default()).as_bytes())
+    };
+    write().map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Version of the bundles written by [`export`].
+const BUNDLE_VERSION: i64 = 1;
+
+/// The settings as a bundle to set up another machine with [`import`]: a TOML document with
+/// a `version` and the configuration file's settings under `[config]`.
+pub fn export() -> Result<String, String> {
+    let settings = settings()?;
+    let mut bundle = toml::Table::new();
+    bundle.insert("version".into(), BUNDLE_VERSION.into());
+    bundle.insert("config".into(), settings.into());
+    let text = toml::to_string(&bundle).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "# rust-todo settings, for `config import`\n{}",
+        text
+    ))
+}
+
+/// Checks a bundle written by [`export`] and makes its settings the configuration file,
+/// keeping the file it replaces as `config.toml.bak`. Returns the file written.
+pub fn import(bundle: &str) -> Result<PathBuf, String> {
+    let bundle: toml::Table = bundle
+        .parse()
+        .map_err(|e| format!("not a settings bundle: {}", e))?;
+    match bundle.get("version").and_then(toml::Value::as_integer) {
+        Some(BUNDLE_VERSION) => {}
+        Some(version) => {
+            return Err(format!(
+                "unsupported settings bundle version {} (this rust-todo reads {})",
+                version, BUNDLE_VERSION
+            ))
+        }
+        None => return Err("not a settings bundle (no version)".into()),
+    }
+    let settings = match bundle.get("config") {
+        Some(toml::Value::Table(settings)) => settings.clone(),
+        None => toml::Table::new(),
+        Some(_) => return Err("[config] in the settings bundle is not a table".into()),
+    };
+    save(&settings)
+}
+
+/// Checks `settings` and makes them the configuration file, keeping the file they replace as
+/// `config.toml.bak`. Returns the file written.
+pub fn save(settings: &toml::Table) -> Result<PathBuf, String> {
+    let config: Config = toml::Value::Table(settings.clone())
+        .try_into()
+        .map_err(|e| format!("invalid settings: {}", e))?;
+    check(&config)?;
+
+    let path = path().ok_or("no configuration directory on this system")?;
+    let write = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if path.exists() {
+            let mut backup = path.clone().into_os_string();
+            backup.push(".bak");
+            fs::copy(&path, backup)?;
+        }
+        let text = toml::to_string(settings).map_err(std::io::Error::other)?;
+        fs::write(&path, text)
+    };
+    write().map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Reads the configuration file on first use and returns it; an invalid file is an error.
+pub fn load() -> Result<&'static Config, String> {
+    if let Some(config) = CONFIG.get() {
+        return Ok(config);
+    }
+    let config = read()?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// Who is running rust-todo, for assigning tasks to `me`: `user` from the configuration,
+/// or else the login name in `USER` (`USERNAME` on Windows).
+pub fn user() -> Option<String> {
+    current()
+        .user
+        .clone()
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .map(|user| user.trim().to_string())
+        .filter(|user| !user.is_empty())
+}
+
+/// `name` as given for an assignee, with `me` standing for the [`user`].
+pub fn assignee(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("the assignee has no name".into());
+    }
+    if !name.eq_ignore_ascii_case("me") {
+        return Ok(name.to_string());
+    }
+    user().ok_or_else(|| {
+        "don't know who \"me\" is: set user in the configuration file, or USER".to_string()
+    })
+}
+
+/// The configuration, read on first use. An invalid file is ignored with a warning; call
+/// [`load`] first to report it instead.
+pub fn current() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        read().unwrap_or_else(|e| {
+            warn!(error = %e, "ignoring the configuration file");
+            Config::default()
+        })
+    })
+}
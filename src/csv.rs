@@ -0,0 +1,66 @@
+//! Just enough RFC 4180 CSV for the CSV formats: quoting fields and splitting records, with
+//! quoted commas, quotes and line breaks.
+
+/// `value` as a CSV field, quoted when it contains a separator, quote or line break.
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One CSV record, ending in a newline.
+pub fn record<S: AsRef<str>>(values: &[S]) -> String {
+    let fields: Vec<String> = values.iter().map(|v| field(v.as_ref())).collect();
+    fields.join(",") + "\n"
+}
+
+/// Splits CSV text into records of fields. Blank lines are skipped, and a leading byte order
+/// mark is ignored.
+pub fn parse(content: &str) -> Vec<Vec<String>> {
+    parse_lines(content)
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect()
+}
+
+/// Like [`parse`], with the line each record starts on, counting from 1.
+pub fn parse_lines(content: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' || c == '\r' && chars.peek() != Some(&'\n') {
+            line += 1;
+        }
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push((start, std::mem::take(&mut record)));
+                }
+                record.clear();
+                start = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.is_empty()) {
+        records.push((start, record));
+    }
+    records
+}
@@ -0,0 +1,413 @@
+//! Parsing user-supplied dates and times, and the clock everything else reads.
+
+use chrono::{
+    DateTime, Datelike, Days, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Weekday,
+};
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Environment variable that fixes the time [`now`] returns, for scripts and tests that need
+/// the same output every run.
+pub const NOW_ENV: &str = "RUST_TODO_NOW";
+
+static FIXED_NOW: OnceLock<Option<DateTime<Local>>> = OnceLock::new();
+
+/// The current time: the one given to [`set_now`] or in [`NOW_ENV`], otherwise the system
+/// clock. Due dates, overdue checks, repeats and relative dates all use it; the operation log
+/// and file names keep the real time.
+pub fn now() -> DateTime<Local> {
+    FIXED_NOW
+        .get_or_init(|| {
+            let value = std::env::var(NOW_ENV).ok().filter(|v| !v.is_empty())?;
+            parse_now(&value, Local::now())
+                .inspect_err(|e| warn!(error = %e, "ignoring {}", NOW_ENV))
+                .ok()
+        })
+        .unwrap_or_else(Local::now)
+}
+
+/// Pretends it is `now` from here on. Returns false, changing nothing, when [`now`] was
+/// already used.
+pub fn set_now(now: DateTime<Local>) -> bool {
+    FIXED_NOW.set(Some(now)).is_ok()
+}
+
+/// Time of day used when only a date is given.
+const DEFAULT_TIME: (u32, u32) = (9, 0);
+
+/// Time of day a due date without a time falls on: the task is due by the end of that day.
+const END_OF_DAY: (u32, u32) = (23, 59);
+
+fn local(naive: NaiveDateTime) -> Result<DateTime<Local>, String> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("{} does not exist in the local time zone", naive))
+}
+
+/// Parses an absolute date/time: RFC 3339, `YYYY-MM-DD HH:MM`, `YYYY-MM-DDTHH:MM`,
+/// `YYYY-MM-DD` (at 09:00) or `HH:MM` (today).
+pub fn parse_datetime(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let input = input.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(&Local));
+    }
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, format) {
+            return local(naive);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        let (hour, minute) = DEFAULT_TIME;
+        return local(date.and_hms_opt(hour, minute, 0).unwrap());
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(input, "%H:%M") {
+        return local(now.date_naive().and_time(time));
+    }
+    Err(format!(
+        "could not understand date {:?} (try 2024-05-01 14:30 or 14:30)",
+        input
+    ))
+}
+
+/// Reads a timestamp kept in a file, such as a task's `created_at`: RFC 3339, or as written
+/// by hand, with a space for the `T`, without seconds, without an offset (local time) or as
+/// only a date (midnight).
+pub fn parse_timestamp(input: &str) -> Option<DateTime<FixedOffset>> {
+    let input = input.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(input) {
+        return Some(at);
+    }
+    if let Ok(at) = DateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S%.f%:z") {
+        return Some(at);
+    }
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)
+    })?;
+    Some(local(naive).ok()?.fixed_offset())
+}
+
+/// Parses a time of day such as `17:00`, `5pm`, `5:30pm` or `noon`.
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    if word == "noon" {
+        return NaiveTime::from_hms_opt(12, 0, 0);
+    }
+    if word == "midnight" {
+        return NaiveTime::from_hms_opt(0, 0, 0);
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(word, "%H:%M") {
+        return Some(time);
+    }
+    let (clock, pm) = match (word.strip_suffix("am"), word.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, false),
+        (_, Some(clock)) => (clock, true),
+        _ => return None,
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    NaiveTime::from_hms_opt(hour % 12 + if pm { 12 } else { 0 }, minute, 0)
+}
+
+/// Parses a bare hour on the 24-hour clock, such as the `10` in `monday at 10`.
+fn parse_hour(word: &str) -> Option<NaiveTime> {
+    NaiveTime::from_hms_opt(word.parse().ok()?, 0, 0)
+}
+
+/// Resolves the day part of a natural-language date: `today`, `tomorrow`, `yesterday`, a weekday
+/// (optionally preceded by `next`, meaning the next one after today), `next week` (Monday)
+/// or `YYYY-MM-DD`.
+fn parse_day(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    let weekday_after = |day: Weekday| {
+        let ahead = (7 + day.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        today + Days::new(if ahead == 0 { 7 } else { ahead } as u64)
+    };
+    match words {
+        [] | ["today"] | ["tonight"] => Some(today),
+        ["tomorrow"] => Some(today + Days::new(1)),
+        ["yesterday"] => Some(today - Days::new(1)),
+        ["next", "week"] => Some(weekday_after(Weekday::Mon)),
+        ["next", day] | [day] => match day.parse::<Weekday>() {
+            Ok(weekday) => Some(weekday_after(weekday)),
+            Err(_) => NaiveDate::parse_from_str(day, "%Y-%m-%d").ok(),
+        },
+        _ => None,
+    }
+}
+
+/// Parses a due date: anything [`parse_datetime`] accepts, or natural language such as
+/// `tomorrow 5pm`, `next friday`, `friday at noon`, `monday at 10` or `in 3 days`. After `at`, a
+/// bare number is an hour on the 24-hour clock. A day without a time means the end of that day.
+pub fn parse_due(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    parse_natural(input, now, end_of_day).map_err(|_| {
+        format!(
+            "could not understand due date {:?} (try \"tomorrow 5pm\", \"next friday\", \"in 3 days\" or 2024-05-01)",
+            input
+        )
+    })
+}
+
+/// Parses a time to pretend it is (see [`set_now`]): what [`parse_due`] accepts, except that
+/// a day without a time means 09:00, e.g. `monday` or `2024-05-06 08:30`.
+pub fn parse_now(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    parse_start(input, now)
+}
+
+/// Parses when something starts, such as the end of a snooze: what [`parse_due`] accepts,
+/// except that a day without a time means the start of the working day, 09:00.
+pub fn parse_start(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let (hour, minute) = DEFAULT_TIME;
+    parse_natural(input, now, |date| {
+        local(date.and_hms_opt(hour, minute, 0).unwrap())
+    })
+    .map_err(|_| format!("could not understand the time {:?}", input))
+}
+
+fn parse_natural(
+    input: &str,
+    now: DateTime<Local>,
+    day_only: impl Fn(NaiveDate) -> Result<DateTime<Local>, String>,
+) -> Result<DateTime<Local>, String> {
+    let lower = input.trim().to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    if let ["in", count, unit] = words.as_slice() {
+        if let Some(offset) = count.parse().ok().and_then(|count| span(count, unit)) {
+            return now
+                .checked_add_signed(offset)
+                .ok_or_else(|| format!("{:?} is too far away", input));
+        }
+    }
+    // `at` only goes right before the time, which there can be a bare hour, as in `monday at
+    // 10`; anything else with `at` in it, such as `monday at`, isn't a date.
+    let (time, day_words) = match words.as_slice() {
+        [rest @ .., "at", last] => match parse_time(last).or_else(|| parse_hour(last)) {
+            Some(time) => (Some(time), rest),
+            None => (None, words.as_slice()),
+        },
+        [rest @ .., last] => match parse_time(last) {
+            Some(time) => (Some(time), rest),
+            None => (None, words.as_slice()),
+        },
+        [] => (None, words.as_slice()),
+    };
+    if !words.is_empty() {
+        if let Some(date) = parse_day(day_words, now.date_naive()) {
+            return match time {
+                Some(time) => local(date.and_time(time)),
+                None => day_only(date),
+            };
+        }
+    }
+    parse_datetime(input, now)
+}
+
+/// The moment a due date without a time falls on.
+pub fn end_of_day(date: NaiveDate) -> Result<DateTime<Local>, String> {
+    let (hour, minute) = END_OF_DAY;
+    local(date.and_hms_opt(hour, minute, 0).unwrap())
+}
+
+/// Whether `due` is at the end of its day, as a due date given without a time is.
+pub fn is_end_of_day(due: DateTime<Local>) -> bool {
+    let (hour, minute) = END_OF_DAY;
+    due.time() == NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// The due date `offset` after `start`; an offset in whole days falls due at the end of the
+/// day.
+pub fn due_after(start: DateTime<Local>, offset: Duration) -> DateTime<Local> {
+    let Some(due) = start.checked_add_signed(offset) else {
+        return start;
+    };
+    if offset.num_seconds() % Duration::days(1).num_seconds() == 0 {
+        end_of_day(due.date_naive()).unwrap_or(due)
+    } else {
+        due
+    }
+}
+
+/// Formats a due date, leaving out the time when it is the default end of day.
+pub fn format_due(due: DateTime<Local>) -> String {
+    format_due_as(due, "%Y-%m-%d")
+}
+
+/// Like [`format_due`], with the date in the strftime format `date_format`.
+pub fn format_due_as(due: DateTime<Local>, date_format: &str) -> String {
+    let date = due.format(date_format).to_string();
+    if is_end_of_day(due) {
+        date
+    } else {
+        format!("{} {}", date, due.format("%H:%M"))
+    }
+}
+
+/// The longest length of time [`span`] accepts, so that adding it to or taking it from any
+/// date in use stays within the dates chrono can represent.
+const MAX_SPAN_DAYS: i64 = 1000 * 366;
+
+/// `count` of the time unit named `unit`, e.g. `minutes`, `hr` or `d`. `None` for an unknown
+/// unit, or a length of time longer than [`MAX_SPAN_DAYS`].
+fn span(count: i64, unit: &str) -> Option<Duration> {
+    let span = match unit.trim_end_matches('s') {
+        "m" | "minute" | "min" => Duration::try_minutes(count),
+        "h" | "hour" | "hr" => Duration::try_hours(count),
+        "d" | "day" => Duration::try_days(count),
+        "w" | "week" => Duration::try_weeks(count),
+        _ => None,
+    }?;
+    (span.num_days().abs() <= MAX_SPAN_DAYS).then_some(span)
+}
+
+/// Parses a length of time such as `30m`, `2h`, `1d`, `1w` or `30 minutes`; `0` is none. At
+/// most about a thousand years are accepted.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim().to_lowercase();
+    if input == "0" {
+        return Ok(Duration::zero());
+    }
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (count, unit) = input.split_at(digits);
+    count
+        .parse()
+        .ok()
+        .and_then(|count| span(count, unit.trim()))
+        .ok_or_else(|| {
+            format!(
+                "could not understand the length of time {:?} (try 30m, 2h, 1d or \"30 minutes\")",
+                input
+            )
+        })
+}
+
+/// Formats a duration compactly in its largest whole unit, e.g. `3d`, `5h` or `12m`.
+pub fn format_age(age: Duration) -> String {
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+/// Formats time spent in hours and minutes, e.g. `2h 05m` or `35m`.
+pub fn format_duration(spent: Duration) -> String {
+    let minutes = spent.num_minutes().max(0);
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Local> {
+        local(NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn timestamps_are_read_as_written_by_hand() {
+        let exact = parse_timestamp("2026-10-15T10:00:00.5+02:00").unwrap();
+        assert_eq!(exact.to_rfc3339(), "2026-10-15T10:00:00.500+02:00");
+        assert_eq!(
+            parse_timestamp("2026-10-15 10:00:00+02:00"),
+            Some(exact - Duration::milliseconds(500))
+        );
+        for input in [
+            "2026-10-15 10:00",
+            "2026-10-15T10:00:00",
+            " 2026-10-15 10:00:00 ",
+        ] {
+            assert_eq!(
+                parse_timestamp(input),
+                Some(at("2026-10-15 10:00").fixed_offset())
+            );
+        }
+        assert_eq!(
+            parse_timestamp("2026-10-15"),
+            Some(at("2026-10-15 00:00").fixed_offset())
+        );
+        assert_eq!(parse_timestamp("last tuesday"), None);
+    }
+
+    #[test]
+    fn due_dates_are_read_in_words_or_numbers() {
+        // A Thursday.
+        let now = at("2026-10-15 10:00");
+        for (input, due) in [
+            ("today", "2026-10-15 23:59"),
+            ("tomorrow", "2026-10-16 23:59"),
+            ("friday", "2026-10-16 23:59"),
+            ("next friday", "2026-10-16 23:59"),
+            ("thursday", "2026-10-22 23:59"),
+            ("next week", "2026-10-19 23:59"),
+            ("in 3 days", "2026-10-18 10:00"),
+            ("2026-10-20", "2026-10-20 23:59"),
+            ("2026-10-20 15:00", "2026-10-20 15:00"),
+            ("friday at noon", "2026-10-16 12:00"),
+            ("monday at 10", "2026-10-19 10:00"),
+            ("at 5pm", "2026-10-15 17:00"),
+        ] {
+            let parsed = parse_due(input, now).unwrap();
+            assert_eq!(
+                parsed.format("%Y-%m-%d %H:%M").to_string(),
+                due,
+                "{}",
+                input
+            );
+        }
+        assert!(is_end_of_day(parse_due("today", now).unwrap()));
+        assert!(parse_due("someday", now).is_err());
+        assert!(parse_due("monday at", now).is_err());
+        assert!(parse_due("monday at 25", now).is_err());
+    }
+
+    #[test]
+    fn durations_are_read_and_shown() {
+        assert_eq!(parse_duration("90m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::days(2));
+        assert!(parse_duration("soon").is_err());
+        assert_eq!(format_duration(Duration::minutes(45)), "45m");
+        assert_eq!(format_duration(Duration::minutes(125)), "2h 05m");
+    }
+
+    #[test]
+    fn huge_offsets_are_errors_not_panics() {
+        let now = at("2026-10-15 10:00");
+        for input in [
+            "in 99999999999999 days",
+            "in 9223372036854775807 weeks",
+            "in 9999999 weeks",
+        ] {
+            assert!(parse_due(input, now).is_err(), "{}", input);
+            assert!(parse_start(input, now).is_err(), "{}", input);
+        }
+        assert!(parse_duration("99999999999999d").is_err());
+        assert!(parse_duration("9223372036854775807m").is_err());
+        assert_eq!(parse_duration("1000w").unwrap(), Duration::weeks(1000));
+    }
+
+    #[test]
+    fn due_after_stays_in_range() {
+        let now = at("2026-10-15 10:00");
+        assert_eq!(due_after(now, Duration::MAX), now);
+    }
+}
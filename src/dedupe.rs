@@ -0,0 +1,148 @@
+//! `dedupe`: finds pending tasks with the same or nearly the same description (see
+//! [`duplicates`](todo_core::duplicates)), shows each group side by side and merges it into
+//! its oldest task, or the one picked, when asked to or with `--auto`. Merged tasks go to the
+//! trash.
+
+use std::io::{BufRead, Write};
+use todo_core::{Task, TodoList};
+
+const HELP: &str =
+    "Enter or `y` merges the group into its first task, a task's ID merges it into that one,
+`n` skips the group and `q` stops.";
+
+enum Answer {
+    Merge(usize),
+    Skip,
+    Quit,
+}
+
+/// Reads a `--threshold`, a similarity from 0 to 1.
+pub fn parse_threshold(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(format!("{:?} is not a number from 0 to 1", text)),
+    }
+}
+
+fn parse(line: &str, group: &[&Task]) -> Result<Answer, String> {
+    match line.trim() {
+        "" | "y" | "yes" => Ok(Answer::Merge(group[0].id)),
+        "n" | "no" => Ok(Answer::Skip),
+        "q" | "quit" => Ok(Answer::Quit),
+        other => match other.parse::<usize>() {
+            Ok(id) if group.iter().any(|t| t.id == id) => Ok(Answer::Merge(id)),
+            _ => Err(format!("{:?} isn't y, n, q or an ID in this group", other)),
+        },
+    }
+}
+
+/// The lines showing `group` in columns, one per task, as wide as the terminal allows.
+fn side_by_side(group: &[&Task]) -> Vec<String> {
+    let terminal = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+        .unwrap_or(100);
+    let width = (terminal.saturating_sub(3 * (group.len() - 1)) / group.len()).clamp(12, 40);
+    let fit = |text: &str| -> String {
+        let chars = text.chars().count();
+        if chars > width {
+            let mut cut: String = text.chars().take(width - 1).collect();
+            cut.push('…');
+            cut
+        } else {
+            format!("{}{}", text, " ".repeat(width - chars))
+        }
+    };
+    let rows: [fn(&Task) -> String; 6] = [
+        |t| format!("Task {}", t.id),
+        |t| t.description.clone(),
+        |t| format!("added {}", t.created_at.format("%Y-%m-%d")),
+        |t| {
+            let mut details: Vec<String> = t.tags.iter().map(|tag| format!("#{}", tag)).collect();
+            details.extend(t.priority.map(|p| format!("⚑ {}", p)));
+            details.extend(t.location.as_ref().map(|l| format!("@{}", l)));
+            details.join(" ")
+        },
+        |t| match t.due {
+            Some(due) => format!("📅 {}", crate::show_due(due)),
+            None => String::new(),
+        },
+        |t| match t.notes.len() {
+            0 => String::new(),
+            n => format!("{} note(s)", n),
+        },
+    ];
+    let mut lines = vec![];
+    for (n, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = group.iter().map(|t| fit(&row(t))).collect();
+        if n > 0 && cells.iter().all(|c| c.trim().is_empty()) {
+            continue;
+        }
+        lines.push(format!("  {}", cells.join(" │ ").trim_end()));
+        if n == 0 {
+            lines.push(format!(
+                "  {}",
+                vec!["─".repeat(width); group.len()].join("─┼─")
+            ));
+        }
+    }
+    lines
+}
+
+pub fn run(
+    list: &mut TodoList,
+    threshold: f64,
+    auto: bool,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let groups = list.duplicate_groups(threshold);
+    if groups.is_empty() {
+        writeln!(out, "No duplicates found.")?;
+        return Ok(());
+    }
+    writeln!(out, "🔍 {} group(s) of duplicates.", groups.len())?;
+    if auto {
+        list.merge_duplicate_groups(&groups)?;
+        for ids in &groups {
+            let others: Vec<String> = ids[1..].iter().map(|id| id.to_string()).collect();
+            writeln!(out, "✓ Merged {} into task {}.", others.join(", "), ids[0])?;
+        }
+        return Ok(());
+    }
+    writeln!(out, "{}", HELP)?;
+    let (mut merges, mut skipped) = (0, 0);
+    'groups: for (n, ids) in groups.iter().enumerate() {
+        let group: Vec<&Task> = ids.iter().filter_map(|id| list.get(*id)).collect();
+        if group.len() < 2 {
+            continue;
+        }
+        writeln!(out, "\n[{}/{}]", n + 1, groups.len())?;
+        for line in side_by_side(&group) {
+            writeln!(out, "{}", line)?;
+        }
+        let keep = loop {
+            write!(out, "Merge into task {}? [Y/n/ID/q] ", group[0].id)?;
+            out.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(out)?;
+                break 'groups;
+            }
+            match parse(&line, &group) {
+                Ok(Answer::Merge(id)) => break id,
+                Ok(Answer::Skip) => {
+                    skipped += 1;
+                    continue 'groups;
+                }
+                Ok(Answer::Quit) => break 'groups,
+                Err(e) => writeln!(out, "  {}", e)?,
+            }
+        };
+        list.merge_duplicates(keep, ids)?;
+        writeln!(out, "  ✓ Merged into task {}.", keep)?;
+        merges += 1;
+    }
+    writeln!(out, "\n✓ Merged {} group(s), skipped {}.", merges, skipped)?;
+    Ok(())
+}
@@ -0,0 +1,256 @@
+//! Where rust-todo keeps its files on each platform.
+//!
+//! | | Linux and other Unix | macOS | Windows |
+//! |---|---|---|---|
+//! | data | `$XDG_DATA_HOME/rust-todo` (`~/.local/share/rust-todo`) | `~/Library/Application Support/rust-todo` | `%APPDATA%\rust-todo` |
+//! | configuration | `$XDG_CONFIG_HOME/rust-todo` (`~/.config/rust-todo`) | `~/Library/Application Support/rust-todo` | `%APPDATA%\rust-todo` |
+//!
+//! The default list is `tasks.json` in the data directory and named lists are in its `lists`
+//! directory. Older versions kept them in the home directory as `.rust-todo.json` and
+//! `.rust-todo-lists`; [`migrate`] moves them. The configuration file can move the tasks
+//! elsewhere; named lists then go in a `lists` directory next to them. So do
+//! `RUST_TODO_DATA` ([`DATA_ENV`]) and `--data-file` ([`set_data_file`]), which win over it,
+//! for a list carried around on a USB stick or kept in a synced folder.
+//!
+//! A project can carry its own list as a `.todo.json` beside its source. [`find_local`] finds
+//! it the way git finds a repository, and [`use_data_file`] makes it the default list; named
+//! lists stay where they are.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const APP: &str = "rust-todo";
+
+/// File name of the default list in the data directory.
+pub const DATA_FILE: &str = "tasks.json";
+
+/// Extensions of the other stores a list can be kept in, beside its JSON file: the SQLite
+/// database and the [task log](crate::tasklog).
+pub const OTHER_STORES: [&str; 2] = ["db", "jsonl"];
+
+/// File name of a project's own list.
+pub const LOCAL_FILE: &str = ".todo.json";
+
+/// The default list's file, when [`use_data_file`] has replaced the usual one.
+static DATA_FILE_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Environment variable naming the file (or directory) the tasks are kept in.
+pub const DATA_ENV: &str = "RUST_TODO_DATA";
+
+/// The file given to [`set_data_file`], which wins over [`DATA_ENV`].
+static DATA_FILE_GIVEN: OnceLock<PathBuf> = OnceLock::new();
+
+/// Directory of the named lists, next to the default list.
+const LISTS_DIR: &str = "lists";
+
+/// Where older versions kept the default list and the named lists, in the home directory.
+const HOME_DATA_FILE: &str = ".rust-todo.json";
+const LEGACY_LISTS_DIR: &str = ".rust-todo-lists";
+
+fn var(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The user's home directory: `$HOME`, or on Windows the profile folder.
+pub fn home_dir() -> Option<PathBuf> {
+    var("HOME").or_else(|| {
+        if !cfg!(windows) {
+            return None;
+        }
+        var("USERPROFILE").or_else(|| {
+            let mut path = var("HOMEDRIVE")?;
+            path.push(var("HOMEPATH")?);
+            Some(path)
+        })
+    })
+}
+
+/// `path` with a leading `~` replaced by the [home directory](home_dir).
+pub fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path,
+    }
+}
+
+/// The per-user directory for application data; on Windows the roaming `%APPDATA%`, so
+/// tasks follow the user between machines on a domain.
+pub fn data_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return Some(var("APPDATA")?.join(APP));
+    }
+    if cfg!(target_os = "macos") {
+        return Some(home_dir()?.join("Library/Application Support").join(APP));
+    }
+    Some(
+        var("XDG_DATA_HOME")
+            .or_else(|| Some(home_dir()?.join(".local/share")))?
+            .join(APP),
+    )
+}
+
+/// The per-user directory for configuration files.
+pub fn config_dir() -> Option<PathBuf> {
+    if cfg!(windows) || cfg!(target_os = "macos") {
+        return data_dir();
+    }
+    Some(
+        var("XDG_CONFIG_HOME")
+            .or_else(|| Some(home_dir()?.join(".config")))?
+            .join(APP),
+    )
+}
+
+/// The file holding the default list: the one given to [`use_data_file`], the
+/// [given](given_data_file) one, `data_file` from the [configuration](crate::config), or else
+/// `tasks.json` in [`data_dir`]. A
+/// `~/.rust-todo.json` from older versions is used until [`migrate`] has moved it. Without a
+/// home directory there is nowhere to keep it.
+pub fn data_file() -> Result<PathBuf, String> {
+    match DATA_FILE_OVERRIDE.get() {
+        Some(path) => Ok(path.clone()),
+        None => usual_data_file(),
+    }
+}
+
+/// Makes `path` the default list's file for the rest of the process, e.g. a project's
+/// `.todo.json`. The named lists stay next to the usual one.
+pub fn use_data_file(path: PathBuf) {
+    let _ = DATA_FILE_OVERRIDE.set(path);
+}
+
+/// Keeps the tasks in `path` for the rest of the process, as `--data-file` does: unlike
+/// [`use_data_file`], the named lists move next to it.
+pub fn set_data_file(path: PathBuf) {
+    let _ = DATA_FILE_GIVEN.set(path);
+}
+
+/// The file given to [`set_data_file`] or in [`DATA_ENV`], with `~` expanded and made absolute,
+/// so hooks and `sync git` find it from any directory. A directory stands for `tasks.json` in
+/// it.
+pub fn given_data_file() -> Option<PathBuf> {
+    let path = expand_home(DATA_FILE_GIVEN.get().cloned().or_else(|| var(DATA_ENV))?);
+    let path = std::path::absolute(&path).unwrap_or(path);
+    if path.is_dir() {
+        return Some(path.join(DATA_FILE));
+    }
+    Some(path)
+}
+
+/// The `.todo.json` of the project `dir` is in: the one in `dir` or the nearest directory
+/// above it, stored as JSON or in one of the other stores.
+pub fn find_local(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(LOCAL_FILE))
+        .find(|file| stored(file))
+}
+
+/// Whether `path`, the JSON file of a list or one of its other stores, is a project's list.
+pub fn is_local(path: &Path) -> bool {
+    path.with_extension("json").file_name() == Some(LOCAL_FILE.as_ref())
+}
+
+/// [`data_file`] without [`use_data_file`].
+fn usual_data_file() -> Result<PathBuf, String> {
+    if let Some(path) = given_data_file() {
+        return Ok(path);
+    }
+    if let Some(path) = &crate::config::current().data_file {
+        return Ok(path.clone());
+    }
+    let file = data_dir()
+        .ok_or(
+            "no home directory to keep tasks in; set HOME, or RUST_TODO_DATA, or data_file in the configuration file",
+        )?
+        .join(DATA_FILE);
+    match home_dir().map(|home| home.join(HOME_DATA_FILE)) {
+        Some(legacy) if !stored(&file) && stored(&legacy) => Ok(legacy),
+        _ => Ok(file),
+    }
+}
+
+/// The directory holding the named lists, next to the default list's usual file.
+pub fn lists_dir() -> Result<PathBuf, String> {
+    let data_file = usual_data_file()?;
+    let name = if data_file.ends_with(HOME_DATA_FILE) {
+        LEGACY_LISTS_DIR
+    } else {
+        LISTS_DIR
+    };
+    Ok(data_file.with_file_name(name))
+}
+
+/// The files a list with its JSON file at `json` can be stored in, whichever exist.
+pub fn stores(json: &Path) -> Vec<PathBuf> {
+    let mut stores = vec![json.to_path_buf()];
+    stores.extend(OTHER_STORES.iter().map(|ext| json.with_extension(ext)));
+    stores
+}
+
+/// Whether a list is stored at `json`, as JSON or in one of the other stores beside it.
+pub fn stored(json: &Path) -> bool {
+    stores(json).iter().any(|store| store.exists())
+}
+
+/// `file` with `suffix` appended to its name, as for the operation log.
+fn with_suffix(file: &Path, suffix: &str) -> PathBuf {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    file.with_file_name(name)
+}
+
+/// Renames `from` to `to`, copying instead when they are on different filesystems.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            move_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir(from)
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+/// Moves the files kept in the home directory by older versions — `~/.rust-todo.json`, the
+/// SQLite database, their undo logs and snapshots, and `~/.rust-todo-lists` — into
+/// [`data_dir`]. Does nothing when a data file is given or named in the configuration, or
+/// the data directory already holds tasks. Returns the moves made.
+pub fn migrate() -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let (Some(home), Some(dir)) = (home_dir(), data_dir()) else {
+        return Ok(vec![]);
+    };
+    let (legacy, file) = (home.join(HOME_DATA_FILE), dir.join(DATA_FILE));
+    let elsewhere = given_data_file().is_some() || crate::config::current().data_file.is_some();
+    if elsewhere || stored(&file) || !stored(&legacy) {
+        return Ok(vec![]);
+    }
+    fs::create_dir_all(&dir)?;
+    let mut moves = vec![];
+    // The tasks go first: until they have moved, the old files stay in use.
+    for (from, to) in stores(&legacy).into_iter().zip(stores(&file)) {
+        for suffix in ["", ".oplog", ".snapshots"] {
+            moves.push((with_suffix(&from, suffix), with_suffix(&to, suffix)));
+        }
+    }
+    moves.push((home.join(LEGACY_LISTS_DIR), dir.join(LISTS_DIR)));
+    let mut moved = vec![];
+    for (from, to) in moves {
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        move_path(&from, &to)?;
+        moved.push((from, to));
+    }
+    Ok(moved)
+}
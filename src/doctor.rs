@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use todo_core::{config, dirs, parent_cycles, Storage, Task};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Ok,
+    Warning,
+    Problem,
+}
+
+struct Finding {
+    level: Level,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Finding {
+    fn ok(message: impl Into<String>) -> Self {
+        Finding {
+            level: Level::Ok,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warning(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Finding {
+            level: Level::Warning,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn problem(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Finding {
+            level: Level::Problem,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+fn check_home(findings: &mut Vec<Finding>) {
+    match dirs::home_dir() {
+        Some(home) => findings.push(Finding::ok(format!("home directory is {}", home.display()))),
+        None if cfg!(windows) => findings.push(Finding::warning(
+            "neither HOME nor USERPROFILE is set",
+            "set USERPROFILE or RUST_TODO_DATA, or data_file in the configuration file",
+        )),
+        None => findings.push(Finding::warning(
+            "HOME is not set, so there is nowhere to store tasks",
+            "set HOME or RUST_TODO_DATA, or data_file in the configuration file",
+        )),
+    }
+    if let Some(path) = config::path() {
+        let state = if path.exists() { "" } else { " (not present)" };
+        findings.push(Finding::ok(format!(
+            "configuration file is {}{}",
+            path.display(),
+            state
+        )));
+    }
+    if let Some(path) = dirs::given_data_file() {
+        findings.push(Finding::ok(format!(
+            "tasks are kept in {}, as given by --data-file or {}",
+            path.display(),
+            dirs::DATA_ENV
+        )));
+    }
+}
+
+fn check_permissions(path: &Path, findings: &mut Vec<Finding>) {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            findings.push(Finding::problem(
+                format!("cannot stat {}: {}", path.display(), e),
+                "check that the file and its directory are accessible",
+            ));
+            return;
+        }
+    };
+    if metadata.permissions().readonly() {
+        findings.push(Finding::problem(
+            "data file is read-only; changes cannot be saved",
+            format!("make it writable, e.g. `chmod u+w {}`", path.display()),
+        ));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        if mode & 0o077 != 0 {
+            findings.push(Finding::warning(
+                format!(
+                    "data file is accessible by other users (mode {:o})",
+                    mode & 0o777
+                ),
+                format!("restrict it with `chmod 600 {}`", path.display()),
+            ));
+        }
+    }
+    if let Some(dir) = path.parent() {
+        if fs::metadata(dir)
+            .map(|m| m.permissions().readonly())
+            .unwrap_or(false)
+        {
+            findings.push(Finding::problem(
+                format!("directory {} is read-only", dir.display()),
+                "make the directory writable so the data file can be replaced",
+            ));
+        }
+    }
+}
+
+fn check_tasks(tasks: &[Task], findings: &mut Vec<Finding>) {
+    let mut seen = HashSet::new();
+    let duplicates: Vec<usize> = tasks
+        .iter()
+        .map(|t| t.id)
+        .filter(|id| !seen.insert(*id))
+        .collect();
+    if !duplicates.is_empty() {
+        findings.push(Finding::problem(
+            format!("duplicate task IDs: {:?}", duplicates),
+            "edit the data file so every task has a unique id; `complete` only acts on the first match",
+        ));
+    }
+    let cycles = parent_cycles(tasks);
+    if !cycles.is_empty() {
+        findings.push(Finding::problem(
+            format!("tasks in a cycle of subtasks: {:?}", cycles),
+            "edit the data file so one of them has no parent; `list` shows them as top-level tasks",
+        ));
+    }
+    let empty = tasks
+        .iter()
+        .filter(|t| t.description.trim().is_empty())
+        .count();
+    if empty > 0 {
+        findings.push(Finding::warning(
+            format!("{} task(s) have an empty description", empty),
+            "delete them with `rust-todo delete <id>`",
+        ));
+    }
+    let bad_dates: Vec<usize> = tasks
+        .iter()
+        .filter(|t| t.created_at == chrono::DateTime::<chrono::FixedOffset>::default())
+        .map(|t| t.id)
+        .collect();
+    if !bad_dates.is_empty() {
+        findings.push(Finding::warning(
+            format!(
+                "tasks with an unreadable created_at timestamp, read as 1970-01-01: {:?}",
+                bad_dates
+            ),
+            "use RFC 3339 timestamps such as 2024-01-31T09:00:00+00:00",
+        ));
+    }
+}
+
+fn check_data_file(storage: &dyn Storage, findings: &mut Vec<Finding>) {
+    let path = storage.path();
+    if !path.exists() {
+        findings.push(Finding::ok(format!(
+            "no data file at {} yet; it is created on the first add",
+            path.display()
+        )));
+        return;
+    }
+    check_permissions(path, findings);
+    match storage.load() {
+        Ok(tasks) => {
+            findings.push(Finding::ok(format!(
+                "data file {} is valid ({} tasks)",
+                path.display(),
+                tasks.len()
+            )));
+            check_tasks(&tasks, findings);
+        }
+        Err(e) => findings.push(Finding::problem(
+            format!("cannot load tasks from {}: {}", path.display(), e),
+            "fix or move the file before running other commands; they will start from an empty list and overwrite it",
+        )),
+    }
+}
+
+/// Runs all diagnostics against `storage`, printing findings, and fails if any problem was found.
+pub fn run(storage: &dyn Storage) -> Result<(), Box<dyn std::error::Error>> {
+    let mut findings = Vec::new();
+    check_home(&mut findings);
+    check_data_file(storage, &mut findings);
+
+    println!("\n🩺 rust-todo doctor:\n");
+    for finding in &findings {
+        let marker = match finding.level {
+            Level::Ok => "✓",
+            Level::Warning => "⚠️ ",
+            Level::Problem => "✗",
+        };
+        println!("{} {}", marker, finding.message);
+        if let Some(hint) = &finding.hint {
+            println!("    → {}", hint);
+        }
+    }
+    println!();
+
+    let problems = findings
+        .iter()
+        .filter(|f| f.level == Level::Problem)
+        .count();
+    let warnings = findings
+        .iter()
+        .filter(|f| f.level == Level::Warning)
+        .count();
+    if problems > 0 {
+        return Err(format!(
+            "doctor found {} problem(s) and {} warning(s)",
+            problems, warnings
+        )
+        .into());
+    }
+    println!("No problems found ({} warning(s)).", warnings);
+    Ok(())
+}
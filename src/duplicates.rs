@@ -0,0 +1,177 @@
+//! Finding tasks added twice, for `dedupe`, and merging them into one.
+//!
+//! Descriptions are compared [folded](crate::collate::fold), with punctuation and runs of
+//! spaces ignored, so "Buy milk" and "buy milk!" are identical. Near-identical ones score by
+//! how few characters would have to change to turn one into the other (the Levenshtein
+//! distance), relative to the longer one: "Renew passport" and "Renew pasport" score 0.93.
+//! Only tasks under the same parent are compared, so a "Write tests" subtask in each of two
+//! projects isn't a duplicate.
+
+use crate::collate;
+use crate::Task;
+use chrono::DateTime;
+use std::collections::BTreeMap;
+
+/// How similar two descriptions have to be for [`groups`] when no threshold is given.
+pub const DEFAULT_THRESHOLD: f64 = 0.85;
+
+/// `text` as compared: folded, with every run of punctuation and spaces as one space.
+fn normalize(text: &str) -> Vec<char> {
+    let folded = collate::fold(text).text;
+    let words: Vec<&str> = folded
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    words.join(" ").chars().collect()
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+fn score(a: &[char], b: &[char]) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+/// How similar descriptions `a` and `b` are, from 0 (nothing alike) to 1 (the same once
+/// folded).
+pub fn similarity(a: &str, b: &str) -> f64 {
+    score(&normalize(a), &normalize(b))
+}
+
+/// The representative of `i`'s set, for grouping.
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// When `task` was added, for ordering.
+fn created(task: &Task) -> (DateTime<chrono::FixedOffset>, usize) {
+    (task.created_at, task.id)
+}
+
+/// Groups of tasks whose descriptions are at least `threshold` similar, directly or through
+/// other tasks in the group. Each group is ordered oldest first, and the groups by the ID
+/// of their oldest task.
+pub fn groups<'a>(tasks: &[&'a Task], threshold: f64) -> Vec<Vec<&'a Task>> {
+    let texts: Vec<Vec<char>> = tasks.iter().map(|t| normalize(&t.description)).collect();
+    let mut parents: Vec<usize> = (0..tasks.len()).collect();
+    // Comparing in order of length, a longer text can only be close enough while the
+    // shorter one is at least `threshold` of its length.
+    let mut order: Vec<usize> = (0..tasks.len()).collect();
+    order.sort_by_key(|&i| texts[i].len());
+    for (n, &i) in order.iter().enumerate() {
+        for &j in &order[n + 1..] {
+            if (texts[i].len() as f64) < threshold * texts[j].len() as f64 {
+                break;
+            }
+            if tasks[i].parent != tasks[j].parent || root(&mut parents, i) == root(&mut parents, j)
+            {
+                continue;
+            }
+            if score(&texts[i], &texts[j]) >= threshold {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a] = b;
+            }
+        }
+    }
+    let mut grouped: BTreeMap<usize, Vec<&Task>> = BTreeMap::new();
+    for (i, task) in tasks.iter().enumerate() {
+        grouped.entry(root(&mut parents, i)).or_default().push(task);
+    }
+    let mut groups: Vec<Vec<&Task>> = grouped
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_by_key(|t| created(t));
+            group
+        })
+        .collect();
+    groups.sort_by_key(|group| group[0].id);
+    groups
+}
+
+/// Merges `other` into `keeper`: its tags, notes, attachments, reminders, tracked time,
+/// dependencies and links are added, the earlier creation date and due date and the higher
+/// priority kept, and anything `keeper` doesn't have taken from it. The description stays
+/// `keeper`'s.
+pub fn absorb(keeper: &mut Task, other: &Task) {
+    if created(other) < created(keeper) {
+        keeper.created_at = other.created_at;
+    }
+    for tag in &other.tags {
+        keeper.add_tag(tag);
+    }
+    keeper.notes.extend(other.notes.iter().cloned());
+    keeper.notes.sort_by_key(|note| note.at);
+    for attachment in &other.attachments {
+        if !keeper.attachments.contains(attachment) {
+            keeper.attachments.push(attachment.clone());
+        }
+    }
+    keeper.reminders.extend(other.reminders.iter().cloned());
+    keeper.time.extend(other.time.iter().cloned());
+    keeper.time.sort_by_key(|interval| interval.start);
+    for id in &other.depends_on {
+        if *id != keeper.id && !keeper.depends_on.contains(id) {
+            keeper.depends_on.push(*id);
+        }
+    }
+    for id in &other.related {
+        if *id != keeper.id && !keeper.related.contains(id) {
+            keeper.related.push(*id);
+        }
+    }
+    keeper.priority = keeper.priority.max(other.priority);
+    keeper.due = match (keeper.due, other.due) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    for (key, value) in &other.metadata {
+        keeper
+            .metadata
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+    if keeper.alias.is_none() {
+        keeper.alias = other.alias.clone();
+    }
+    if keeper.location.is_none() {
+        keeper.location = other.location.clone();
+    }
+    if keeper.assignee.is_none() {
+        keeper.assignee = other.assignee.clone();
+    }
+    if keeper.waiting_on.is_none() {
+        keeper.waiting_on = other.waiting_on.clone();
+    }
+    if keeper.repeat.is_none() {
+        keeper.repeat = other.repeat.clone();
+    }
+    if keeper.follow_up.is_none() {
+        keeper.follow_up = other.follow_up.clone();
+    }
+    if keeper.icon.is_none() {
+        keeper.icon = other.icon.clone();
+    }
+    if keeper.color.is_none() {
+        keeper.color = other.color;
+    }
+}
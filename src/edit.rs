@@ -0,0 +1,241 @@
+//! Changing an existing task, either with flags or by editing it as TOML in `$EDITOR`.
+
+use crate::error::Error;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::process::Command;
+use todo_core::{dates, recurrence::Rule, Priority, Task, TaskColor, TodoList};
+
+#[derive(Args, Default)]
+pub struct EditArgs {
+    /// New description
+    #[arg(short, long, value_name = "TEXT")]
+    description: Option<String>,
+    /// New due date ("tomorrow 5pm", "next friday", 2024-05-01, ...)
+    #[arg(long, value_name = "WHEN", conflicts_with = "no_due")]
+    due: Option<String>,
+    /// Remove the due date
+    #[arg(long)]
+    no_due: bool,
+    /// New priority
+    #[arg(short, long, value_enum, conflicts_with = "no_priority")]
+    priority: Option<Priority>,
+    /// Remove the priority
+    #[arg(long)]
+    no_priority: bool,
+    /// Add a tag (repeatable)
+    #[arg(short, long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+    /// Remove a tag (repeatable)
+    #[arg(long = "untag", value_name = "TAG")]
+    untags: Vec<String>,
+    /// New repeat rule ("daily", "every monday", an RRULE, ...)
+    #[arg(long, value_name = "RULE", value_parser = Rule::parse, conflicts_with = "no_repeat")]
+    repeat: Option<Rule>,
+    /// Stop the task repeating
+    #[arg(long)]
+    no_repeat: bool,
+    /// New alias, to name the task by instead of its ID
+    #[arg(long, value_name = "NAME", value_parser = parse_alias, conflicts_with = "no_alias")]
+    alias: Option<String>,
+    /// Remove the alias
+    #[arg(long)]
+    no_alias: bool,
+    /// Show this before the description in listings, e.g. an emoji
+    #[arg(long, value_name = "ICON", conflicts_with = "no_icon")]
+    icon: Option<String>,
+    /// Remove the icon
+    #[arg(long)]
+    no_icon: bool,
+    /// Show the task in this colour, whatever its priority or due date
+    #[arg(long, value_enum, conflicts_with = "no_color")]
+    color: Option<TaskColor>,
+    /// Remove the colour
+    #[arg(long)]
+    no_color: bool,
+    /// Open the task in $VISUAL or $EDITOR instead of using flags
+    #[arg(short, long, conflicts_with_all = ["description", "due", "no_due", "priority", "no_priority", "tags", "untags", "repeat", "no_repeat", "alias", "no_alias", "icon", "no_icon", "color", "no_color"])]
+    editor: bool,
+}
+
+impl EditArgs {
+    /// Arguments for editing in `$EDITOR`, like `edit --editor`.
+    pub fn in_editor() -> EditArgs {
+        EditArgs {
+            editor: true,
+            ..EditArgs::default()
+        }
+    }
+}
+
+/// The editable fields of a task, as shown in the editor.
+#[derive(Serialize, Deserialize)]
+struct Editable {
+    description: String,
+    /// Empty for no due date.
+    #[serde(default)]
+    due: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    location: String,
+    /// Empty for a task that doesn't repeat.
+    #[serde(default)]
+    repeat: String,
+    /// Empty for no alias.
+    #[serde(default)]
+    alias: String,
+    #[serde(default)]
+    icon: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<TaskColor>,
+}
+
+const EDITOR_HELP: &str = "\
+# Edit the task, save and quit. Leave `due`, `location`, `repeat`, `alias` or `icon` empty
+# to clear them; priority is one of low, medium, high, critical and color one of red, green, yellow,
+# blue, magenta, cyan (delete the line to clear either).
+";
+
+impl Editable {
+    fn of(task: &Task) -> Self {
+        Editable {
+            description: task.description.clone(),
+            due: task.due.map(dates::format_due).unwrap_or_default(),
+            priority: task.priority,
+            tags: task.tags.clone(),
+            location: task.location.clone().unwrap_or_default(),
+            repeat: task
+                .repeat
+                .as_ref()
+                .map(Rule::to_string)
+                .unwrap_or_default(),
+            alias: task.alias.clone().unwrap_or_default(),
+            icon: task.icon.clone().unwrap_or_default(),
+            color: task.color,
+        }
+    }
+
+    fn apply(self, task: &mut Task) -> Result<(), Box<dyn std::error::Error>> {
+        if self.description.trim().is_empty() {
+            return Err("the description cannot be empty".into());
+        }
+        task.description = self.description.trim().to_string();
+        task.due = match self.due.trim() {
+            "" => None,
+            due => Some(dates::parse_due(due, dates::now()).map_err(Error::Parse)?),
+        };
+        task.priority = self.priority;
+        task.tags.clear();
+        for tag in &self.tags {
+            task.add_tag(tag);
+        }
+        task.location = Some(self.location.trim().to_string()).filter(|l| !l.is_empty());
+        task.repeat = match self.repeat.trim() {
+            "" => None,
+            rule => Some(Rule::parse(rule).map_err(Error::Parse)?),
+        };
+        task.alias = match self.alias.trim() {
+            "" => None,
+            alias => Some(parse_alias(alias).map_err(Error::Parse)?),
+        };
+        task.icon = Some(self.icon.trim().to_string()).filter(|i| !i.is_empty());
+        task.color = self.color;
+        Ok(())
+    }
+}
+
+/// Parses an alias given on the command line, as [`todo_core::check_alias`] allows.
+pub fn parse_alias(text: &str) -> Result<String, String> {
+    todo_core::check_alias(text)?;
+    Ok(text.to_string())
+}
+
+/// Runs `$VISUAL`/`$EDITOR` (falling back to `vi`) on the task rendered as TOML and returns
+/// the edited fields.
+fn edit_in_editor(task: &Task) -> Result<Editable, Box<dyn std::error::Error>> {
+    let path =
+        std::env::temp_dir().join(format!("rust-todo-{}-{}.toml", task.id, std::process::id()));
+    fs::write(
+        &path,
+        format!("{}{}", EDITOR_HELP, toml::to_string(&Editable::of(task))?),
+    )?;
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("$EDITOR is empty")?;
+    let status = Command::new(program).args(words).arg(&path).status();
+    let content = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    let status = status.map_err(|e| format!("could not run {:?}: {}", editor, e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}; task left unchanged", editor, status).into());
+    }
+    Ok(toml::from_str(&content?)?)
+}
+
+pub fn run(
+    list: &mut TodoList,
+    id: usize,
+    args: EditArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    let mut edited = task.clone();
+    if args.editor {
+        edit_in_editor(task)?.apply(&mut edited)?;
+    } else {
+        if let Some(description) = args.description {
+            if description.trim().is_empty() {
+                return Err("the description cannot be empty".into());
+            }
+            edited.description = description.trim().to_string();
+        }
+        if let Some(due) = args.due {
+            edited.due = Some(dates::parse_due(&due, dates::now()).map_err(Error::Parse)?);
+        }
+        if args.no_due {
+            edited.due = None;
+        }
+        if args.priority.is_some() || args.no_priority {
+            edited.priority = args.priority;
+        }
+        if args.repeat.is_some() || args.no_repeat {
+            edited.repeat = args.repeat;
+        }
+        if args.alias.is_some() || args.no_alias {
+            edited.alias = args.alias;
+        }
+        for tag in &args.tags {
+            edited.add_tag(tag);
+        }
+        for tag in &args.untags {
+            edited.remove_tag(tag);
+        }
+        if let Some(icon) = args.icon {
+            edited.icon = Some(icon.trim().to_string()).filter(|i| !i.is_empty());
+        }
+        if args.no_icon {
+            edited.icon = None;
+        }
+        if args.color.is_some() || args.no_color {
+            edited.color = args.color;
+        }
+    }
+    if let Some(alias) = &edited.alias {
+        crate::alias_free(list, alias, Some(id)).map_err(Error::Parse)?;
+    }
+    let unchanged = serde_json::to_value(&edited)? == serde_json::to_value(task)?;
+    if unchanged {
+        println!("No changes to task {}.", id);
+        return Ok(());
+    }
+    list.update(id, |task| *task = edited)?;
+    println!("✓ Task {} updated.", id);
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Errors that end a command, and the exit status each kind gives, so scripts can tell a
+//! missing task from a broken file without reading the message.
+//!
+//! Most errors come up from the library as `Box<dyn Error>`; [`exit_code`] sorts those by
+//! their type. Commands return an [`Error`] where the type alone doesn't say what went
+//! wrong, such as a task that doesn't exist or a date that can't be read.
+
+use std::fmt;
+
+/// Something else went wrong.
+pub const FAILURE: i32 = 1;
+/// A task, list or other named thing doesn't exist.
+pub const NOT_FOUND: i32 = 2;
+/// A file couldn't be read or written.
+pub const IO: i32 = 3;
+/// Input couldn't be understood: arguments, dates, or files being imported.
+pub const PARSE: i32 = 4;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The whole message, e.g. "task 7 not found".
+    NotFound(String),
+    Parse(String),
+}
+
+impl Error {
+    pub fn task_not_found(id: usize) -> Error {
+        Error::NotFound(format!("task {} not found", id))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(message) | Error::Parse(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Treats an error from reading input, such as a file being imported, as a [`Parse`] error
+/// unless its type says it is something else, such as a file that couldn't be read.
+///
+/// [`Parse`]: Error::Parse
+pub fn as_parse(error: Box<dyn std::error::Error>) -> Box<dyn std::error::Error> {
+    if exit_code(&*error) == FAILURE {
+        Box::new(Error::Parse(error.to_string()))
+    } else {
+        error
+    }
+}
+
+/// `error` with `context` in front of its message, e.g. which operation of a batch failed,
+/// keeping the exit status it gives.
+pub fn context(
+    error: Box<dyn std::error::Error>,
+    context: impl fmt::Display,
+) -> Box<dyn std::error::Error> {
+    let message = format!("{}: {}", context, error);
+    match exit_code(&*error) {
+        NOT_FOUND => Box::new(Error::NotFound(message)),
+        PARSE => Box::new(Error::Parse(message)),
+        IO => Box::new(std::io::Error::other(message)),
+        _ => message.into(),
+    }
+}
+
+/// The exit status for `error`: one of the constants in this module.
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> i32 {
+    if let Some(error) = error.downcast_ref::<Error>() {
+        return match error {
+            Error::NotFound(_) => NOT_FOUND,
+            Error::Parse(_) => PARSE,
+        };
+    }
+    if error.is::<std::io::Error>() {
+        return IO;
+    }
+    if let Some(error) = error.downcast_ref::<serde_json::Error>() {
+        return if error.is_io() { IO } else { PARSE };
+    }
+    if error.is::<toml::de::Error>()
+        || error.is::<regex::Error>()
+        || error.is::<chrono::ParseError>()
+        || error.is::<std::num::ParseIntError>()
+    {
+        return PARSE;
+    }
+    if error.is::<rusqlite::Error>() || error.is::<ureq::Error>() {
+        return IO;
+    }
+    FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_keeps_the_exit_status() {
+        let cases: [(Box<dyn std::error::Error>, i32); 4] = [
+            (Box::new(Error::task_not_found(3)), NOT_FOUND),
+            (Box::new(Error::Parse("bad date".into())), PARSE),
+            (Box::new(std::io::Error::other("disk full")), IO),
+            ("something else".into(), FAILURE),
+        ];
+        for (error, code) in cases {
+            let error = context(error, "operation 2");
+            assert_eq!(exit_code(&*error), code);
+            assert!(error.to_string().starts_with("operation 2: "));
+        }
+    }
+}
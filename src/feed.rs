@@ -0,0 +1,182 @@
+//! Read-only feeds of a list for static sites and feed readers: RSS 2.0 and JSON Feed 1.1.
+//!
+//! A feed has one item per pending task and per task completed since a cutoff, newest first.
+//! Completed tasks are dated by when they were completed, read from the
+//! [operation log](crate::oplog) as in [`stats`](crate::stats); completions the log doesn't
+//! know about are left out. An item's ID changes when its task is completed, so readers show
+//! the completion as a new item.
+
+use crate::oplog::History;
+use crate::Task;
+use chrono::{DateTime, Local};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// A task in a feed.
+pub struct Item<'a> {
+    pub task: &'a Task,
+    /// When the task was completed; `None` while it is pending.
+    pub completed_at: Option<DateTime<Local>>,
+}
+
+impl Item<'_> {
+    /// When the item was published: when the task was completed, or else added.
+    pub fn date(&self) -> Option<DateTime<Local>> {
+        self.completed_at
+            .or_else(|| Some(self.task.created_at.with_timezone(&Local)))
+    }
+
+    fn id(&self, base: &str) -> String {
+        match self.completed_at {
+            Some(at) => format!("{}#{}-done-{}", base, self.task.id, at.timestamp()),
+            None => format!("{}#{}", base, self.task.id),
+        }
+    }
+
+    fn summary(&self) -> String {
+        let mut parts = vec![if self.task.completed {
+            "Done".to_string()
+        } else {
+            "Open".to_string()
+        }];
+        if let Some(due) = self.task.due {
+            parts.push(format!("due {}", due.format("%Y-%m-%d %H:%M")));
+        }
+        if let Some(priority) = self.task.priority {
+            parts.push(format!("{} priority", priority));
+        }
+        if !self.task.tags.is_empty() {
+            let tags: Vec<String> = self.task.tags.iter().map(|t| format!("#{}", t)).collect();
+            parts.push(tags.join(" "));
+        }
+        parts.join(" · ")
+    }
+}
+
+/// The pending tasks in `tasks` and those completed since `since` according to `history`,
+/// newest first.
+pub fn items<'a>(tasks: &'a [Task], history: &History, since: DateTime<Local>) -> Vec<Item<'a>> {
+    let mut completed: HashMap<usize, DateTime<Local>> = HashMap::new();
+    for (at, task) in crate::stats::completions(history) {
+        completed.insert(task.id, at);
+    }
+    let mut items: Vec<Item> = tasks
+        .iter()
+        .filter_map(|task| {
+            if !task.completed {
+                return Some(Item {
+                    task,
+                    completed_at: None,
+                });
+            }
+            let at = completed.get(&task.id).copied().filter(|at| *at >= since)?;
+            Some(Item {
+                task,
+                completed_at: Some(at),
+            })
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.date()));
+    items
+}
+
+/// Describes the feed as a whole.
+pub struct Channel<'a> {
+    pub title: &'a str,
+    /// Web page the feed belongs to, if any.
+    pub link: Option<&'a str>,
+    /// Where the feed itself will be published, if it is a URL.
+    pub feed_url: Option<&'a str>,
+}
+
+impl Channel<'_> {
+    /// Prefix of item IDs, so they stay unique across feeds.
+    fn base(&self) -> &str {
+        self.feed_url.or(self.link).unwrap_or(self.title)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders an RSS 2.0 document.
+pub fn render_rss(channel: &Channel, items: &[Item], now: DateTime<Local>) -> String {
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    output.push_str("<rss version=\"2.0\">\n<channel>\n");
+    output.push_str(&format!("  <title>{}</title>\n", escape(channel.title)));
+    if let Some(link) = channel.link.or(channel.feed_url) {
+        output.push_str(&format!("  <link>{}</link>\n", escape(link)));
+    }
+    output.push_str("  <description>Open and recently completed tasks</description>\n");
+    output.push_str("  <generator>rust-todo</generator>\n");
+    output.push_str(&format!(
+        "  <lastBuildDate>{}</lastBuildDate>\n",
+        now.to_rfc2822()
+    ));
+    for item in items {
+        output.push_str("  <item>\n");
+        let title = if item.task.completed {
+            format!("✓ {}", item.task.description)
+        } else {
+            item.task.description.clone()
+        };
+        output.push_str(&format!("    <title>{}</title>\n", escape(&title)));
+        output.push_str(&format!(
+            "    <description>{}</description>\n",
+            escape(&item.summary())
+        ));
+        for tag in &item.task.tags {
+            output.push_str(&format!("    <category>{}</category>\n", escape(tag)));
+        }
+        output.push_str(&format!(
+            "    <guid isPermaLink=\"false\">{}</guid>\n",
+            escape(&item.id(channel.base()))
+        ));
+        if let Some(date) = item.date() {
+            output.push_str(&format!("    <pubDate>{}</pubDate>\n", date.to_rfc2822()));
+        }
+        output.push_str("  </item>\n");
+    }
+    output.push_str("</channel>\n</rss>\n");
+    output
+}
+
+/// Renders a JSON Feed 1.1 document.
+pub fn render_json(channel: &Channel, items: &[Item]) -> serde_json::Result<String> {
+    let mut feed = json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": channel.title,
+        "items": items.iter().map(|item| json_item(channel, item)).collect::<Vec<Value>>(),
+    });
+    if let Some(link) = channel.link {
+        feed["home_page_url"] = json!(link);
+    }
+    if let Some(url) = channel.feed_url {
+        feed["feed_url"] = json!(url);
+    }
+    Ok(serde_json::to_string_pretty(&feed)? + "\n")
+}
+
+fn json_item(channel: &Channel, item: &Item) -> Value {
+    let mut value = json!({
+        "id": item.id(channel.base()),
+        "title": item.task.description,
+        "content_text": item.summary(),
+    });
+    if !item.task.tags.is_empty() {
+        value["tags"] = json!(item.task.tags);
+    }
+    if let Some(date) = item.date() {
+        value["date_published"] = json!(date.to_rfc3339());
+    }
+    value["_rust_todo"] = json!({
+        "id": item.task.id,
+        "completed": item.task.completed,
+        "due": item.task.due.map(|d| d.to_rfc3339()),
+    });
+    value
+}
@@ -0,0 +1,127 @@
+//! Filing inbox tasks: what `triage` takes for each task, such as `#home !high due:friday`,
+//! and applying it.
+
+use crate::{dates, Priority, Task};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+
+/// Tags, a priority and a due date to give a task; what isn't given is left as it is.
+#[derive(Debug, Default, PartialEq)]
+pub struct Filing {
+    pub tags: Vec<String>,
+    pub priority: Option<Priority>,
+    pub due: Option<DateTime<Local>>,
+}
+
+/// What was typed for a task.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    File(Filing),
+    Skip,
+    Done,
+    Delete,
+    Quit,
+}
+
+/// Reads a line typed for a task: a mix of `#tags`, a `!priority` and a `due:date`, or
+/// nothing to skip it, `done`, `delete` or `quit`. Due dates are taken from `now`.
+pub fn parse(line: &str, now: DateTime<Local>) -> Result<Action, String> {
+    match line.trim() {
+        "" => return Ok(Action::Skip),
+        "done" => return Ok(Action::Done),
+        "delete" => return Ok(Action::Delete),
+        "quit" | "q" => return Ok(Action::Quit),
+        _ => {}
+    }
+    let mut filing = Filing::default();
+    let mut words = line.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        if let Some(tag) = word.strip_prefix('#') {
+            filing.tags.push(tag.to_string());
+        } else if let Some(level) = word.strip_prefix('!') {
+            filing.priority = Some(
+                Priority::from_str(level, true)
+                    .map_err(|_| format!("unknown priority {:?}", level))?,
+            );
+        } else if let Some(first) = word.strip_prefix("due:") {
+            // The date runs until the next tag or priority, so "due:next friday" works.
+            let mut due = vec![first];
+            while let Some(next) = words.next_if(|w| !w.starts_with(['#', '!'])) {
+                due.push(next);
+            }
+            filing.due = Some(dates::parse_due(due.join(" ").trim(), now)?);
+        } else {
+            return Err(format!(
+                "didn't understand {:?} (tags start with #, priorities with !, dates with due:)",
+                word
+            ));
+        }
+    }
+    Ok(Action::File(filing))
+}
+
+impl Filing {
+    /// Gives `task` the tags, priority and due date filed.
+    pub fn apply(&self, task: &mut Task) {
+        for tag in &self.tags {
+            task.add_tag(tag);
+        }
+        if self.priority.is_some() {
+            task.priority = self.priority;
+        }
+        if self.due.is_some() {
+            task.due = self.due;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn reads_tags_a_priority_and_a_due_date_in_any_order() {
+        let Ok(Action::File(filing)) = parse("!high due:next friday #home #errand", now()) else {
+            panic!("not filed");
+        };
+        assert_eq!(filing.tags, ["home", "errand"]);
+        assert_eq!(filing.priority, Some(Priority::High));
+        assert_eq!(
+            filing.due,
+            Some(dates::parse_due("next friday", now()).unwrap())
+        );
+    }
+
+    #[test]
+    fn keeps_what_isnt_filed() {
+        let mut task = Task::builder().description("Buy milk").build();
+        task.priority = Some(Priority::Low);
+        task.add_tag("shop");
+        let Ok(Action::File(filing)) = parse("#errand", now()) else {
+            panic!("not filed");
+        };
+        filing.apply(&mut task);
+        assert_eq!(task.tags, ["shop", "errand"]);
+        assert_eq!(task.priority, Some(Priority::Low));
+    }
+
+    #[test]
+    fn reads_the_words_for_other_actions() {
+        assert_eq!(parse("  \n", now()), Ok(Action::Skip));
+        assert_eq!(parse("done", now()), Ok(Action::Done));
+        assert_eq!(parse("delete", now()), Ok(Action::Delete));
+        assert_eq!(parse("q", now()), Ok(Action::Quit));
+    }
+
+    #[test]
+    fn rejects_what_it_doesnt_understand() {
+        assert!(parse("!urgent", now()).is_err());
+        assert!(parse("due:someday", now()).is_err());
+        assert!(parse("home", now()).is_err());
+    }
+}
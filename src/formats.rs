@@ -0,0 +1,165 @@
+//! The file formats `import` reads and `export` writes, and reading or writing tasks in any of
+//! them. Where the text comes from or goes to, a file, the clipboard or the terminal, is up to
+//! the caller; a Google Keep takeout is a directory, read with
+//! [`keep::parse_dir`](crate::keep::parse_dir).
+
+use crate::import::Problem;
+use crate::{
+    bookmarks, calendar, habitica, ical, omnifocus, pdf, planner, rtm, schema, taskpaper,
+    taskwarrior, todoist, todotxt, Task,
+};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// rust-todo's own JSON format
+    Json,
+    /// TaskPaper plain text
+    Taskpaper,
+    /// Habitica data export or API response (import only)
+    Habitica,
+    /// Browser bookmarks: Netscape HTML, Firefox or Chrome JSON (import only)
+    Bookmarks,
+    /// Google Keep takeout directory (Takeout/Keep) or a single note (import only)
+    Keep,
+    /// Remember The Milk JSON account export (import only)
+    Rtm,
+    /// OmniFocus TaskPaper export (import only)
+    Omnifocus,
+    /// Todoist CSV, as exported from a project or used as a template
+    Todoist,
+    /// Taskwarrior JSON (`task export` / `task import`)
+    Taskwarrior,
+    /// todo.txt plain text, one task per line
+    Todotxt,
+    /// iCalendar to-dos (VTODO), for calendar apps
+    Ical,
+    /// remind(1) script (export only)
+    Remind,
+    /// calcurse todo file (export only)
+    Calcurse,
+    /// Printable A4 checklist (export only)
+    Pdf,
+    /// Printable HTML week planner, see --week (export only)
+    Planner,
+}
+
+/// What [`parse`] read.
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub tasks: Vec<Task>,
+    /// Records of the input skipped or only partly read.
+    pub problems: Vec<Problem>,
+    /// Items with no task equivalent that were left out, such as Habitica's habits.
+    pub unsupported: usize,
+}
+
+impl Parsed {
+    fn with_problems((tasks, problems): (Vec<Task>, Vec<Problem>)) -> Self {
+        Parsed {
+            tasks,
+            problems,
+            unsupported: 0,
+        }
+    }
+
+    fn tasks(tasks: Vec<Task>) -> Self {
+        Parsed {
+            tasks,
+            ..Parsed::default()
+        }
+    }
+}
+
+/// Reads the tasks in `content`, written in `format`.
+pub fn parse(format: Format, content: &str) -> Result<Parsed, Box<dyn std::error::Error>> {
+    Ok(match format {
+        Format::Json => Parsed::with_problems(schema::parse_lenient(content)?),
+        Format::Taskpaper => Parsed::tasks(taskpaper::parse(content)),
+        Format::Habitica => {
+            let result = habitica::parse(content)?;
+            Parsed {
+                tasks: result.tasks,
+                problems: vec![],
+                unsupported: result.skipped,
+            }
+        }
+        Format::Bookmarks => Parsed::tasks(bookmarks::parse(content)?),
+        Format::Keep => {
+            return Err("Keep takeouts are directories; read them with keep::parse_dir".into())
+        }
+        Format::Rtm => Parsed::with_problems(rtm::parse(content)?),
+        Format::Omnifocus => Parsed::with_problems(omnifocus::parse(content)),
+        Format::Todoist => Parsed::with_problems(todoist::parse(content)?),
+        Format::Taskwarrior => Parsed::with_problems(taskwarrior::parse(content)?),
+        Format::Ical => Parsed::with_problems(ical::parse(content)?),
+        Format::Todotxt => Parsed::with_problems(todotxt::parse(content)),
+        Format::Remind | Format::Calcurse | Format::Pdf | Format::Planner => {
+            return Err("remind, calcurse, pdf and planner are export-only formats".into())
+        }
+    })
+}
+
+/// Writes `tasks` in `format`. The planner shows the week `day` is in.
+pub fn render(
+    format: Format,
+    tasks: &[Task],
+    day: NaiveDate,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Ok(match format {
+        Format::Json => (serde_json::to_string_pretty(tasks)? + "\n").into_bytes(),
+        Format::Taskpaper => taskpaper::render(tasks).into_bytes(),
+        Format::Todoist => todoist::render(tasks).into_bytes(),
+        Format::Taskwarrior => taskwarrior::render(tasks)?.into_bytes(),
+        Format::Todotxt => todotxt::render(tasks).into_bytes(),
+        Format::Ical => ical::render(tasks, tasks).into_bytes(),
+        Format::Remind => calendar::render_remind(tasks).into_bytes(),
+        Format::Calcurse => calendar::render_calcurse(tasks).into_bytes(),
+        Format::Pdf => pdf::render(tasks),
+        Format::Planner => planner::render_week(tasks, planner::week_start(day)).into_bytes(),
+        Format::Habitica | Format::Bookmarks | Format::Keep | Format::Rtm | Format::Omnifocus => {
+            return Err("this format can only be imported".into())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, sample_tasks};
+
+    #[test]
+    fn formats_that_go_both_ways_read_back_what_they_write() {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        for format in [
+            Format::Json,
+            Format::Taskpaper,
+            Format::Todoist,
+            Format::Taskwarrior,
+            Format::Todotxt,
+            Format::Ical,
+        ] {
+            let written = render(format, &sample_tasks(), day).unwrap();
+            let read = parse(format, &String::from_utf8(written).unwrap()).unwrap();
+            // Todoist only writes pending tasks; the formats' own tests cover the rest.
+            find(&read.tasks, "Plan the trip");
+            find(&read.tasks, "Book the flights");
+            assert!(
+                read.problems.is_empty(),
+                "{:?}: {:?}",
+                format,
+                read.problems
+            );
+        }
+    }
+
+    #[test]
+    fn one_way_formats_are_refused_the_other_way() {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        assert!(render(Format::Habitica, &sample_tasks(), day).is_err());
+        assert!(render(Format::Keep, &sample_tasks(), day).is_err());
+        assert!(parse(Format::Pdf, "").is_err());
+        assert!(parse(Format::Keep, "").is_err());
+    }
+}
@@ -0,0 +1,583 @@
+//! Keeping the data directory in a git repository, to share lists between machines.
+//!
+//! `sync git` turns the directory holding the list into a repository the first time, then
+//! commits, pulls and pushes. Only the JSON lists are tracked, the named lists in `lists/`
+//! included; the undo log, trash, snapshots and lock files stay local. Once the directory is a
+//! repository, every command that changes a list commits it.
+//!
+//! Lists changed on two machines are merged task by task, and field by field within a task,
+//! against the version both started from: a field changed on one side only takes that
+//! change, and a field changed differently on both sides keeps the local value. Tasks are
+//! matched by UUID rather than by ID, since both machines may have given the same ID to
+//! different new tasks; the remote one then gets a new ID. Tasks from before UUIDs were
+//! given out are matched by when they were created. Parents changed on both sides so that
+//! tasks would be each other's subtasks keep their local values. Git runs this merge
+//! through a merge driver, `rust-todo sync merge-driver`, set up in the repository's
+//! configuration.
+
+use crate::{dirs, ids, schema, Lock, Task};
+use chrono::{DateTime, FixedOffset};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Name of the merge driver in `.gitattributes` and the git configuration.
+pub const DRIVER: &str = "rust-todo";
+
+const GITIGNORE: &str = "# Only the lists are shared; the undo log, trash and snapshots stay local.\n*\n!lists/\n!*.json\n!.gitignore\n!.gitattributes\n";
+
+/// The `.gitignore` written before the named lists were tracked, replaced when found.
+const OLD_GITIGNORE: &str = "# Only the lists are shared; the undo log, trash and snapshots stay local.\n*\n!*.json\n!.gitignore\n!.gitattributes\n";
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output, Box<dyn std::error::Error>> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("could not run git: {}", e).into())
+}
+
+/// Runs git in `dir`, failing with its error output unless it succeeds.
+fn run(dir: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = git(dir, args)?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The repository set up by [`init`] that tracks the list stored in `data_file`: the one in
+/// its directory, or for a named list, the one in the directory above `lists/`.
+pub fn repository_for(data_file: &Path) -> Option<&Path> {
+    data_file
+        .ancestors()
+        .skip(1)
+        .take(2)
+        .find(|dir| is_enabled(dir))
+}
+
+/// Whether `dir` was set up by [`init`]: a repository whose lists use the merge driver.
+pub fn is_enabled(dir: &Path) -> bool {
+    dir.join(".git").exists()
+        && fs::read_to_string(dir.join(".gitattributes"))
+            .is_ok_and(|a| a.contains(&format!("merge={}", DRIVER)))
+}
+
+/// Makes `dir` a repository tracking the lists, if it isn't one yet, and points the merge
+/// driver at `program`. Returns whether the repository was created.
+pub fn init(dir: &Path, program: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let created = !dir.join(".git").exists();
+    if created {
+        fs::create_dir_all(dir)?;
+        run(dir, &["init", "--quiet"])?;
+    }
+    let ignore = dir.join(".gitignore");
+    if fs::read_to_string(&ignore).map_or(true, |content| content == OLD_GITIGNORE) {
+        fs::write(&ignore, GITIGNORE)?;
+    }
+    if !is_enabled(dir) {
+        fs::write(
+            dir.join(".gitattributes"),
+            format!("*.json merge={}\n", DRIVER),
+        )?;
+    }
+    // Set on every sync, so the driver follows the binary when it moves.
+    let driver = format!(
+        "'{}' sync merge-driver %O %A %B",
+        program.display().to_string().replace('\'', r"'\''")
+    );
+    run(
+        dir,
+        &[
+            "config",
+            &format!("merge.{}.name", DRIVER),
+            "rust-todo task lists",
+        ],
+    )?;
+    run(
+        dir,
+        &["config", &format!("merge.{}.driver", DRIVER), &driver],
+    )?;
+    Ok(created)
+}
+
+/// Commits every change to the lists in `dir` with `message`. Returns whether there was
+/// anything to commit.
+pub fn commit(dir: &Path, message: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    run(dir, &["add", "--all"])?;
+    if git(dir, &["diff", "--cached", "--quiet"])?.status.success() {
+        return Ok(false);
+    }
+    run(dir, &["commit", "--quiet", "--no-verify", "-m", message])?;
+    Ok(true)
+}
+
+/// Sets the `origin` remote to `url`.
+pub fn set_remote(dir: &Path, url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if remote_url(dir).is_ok() {
+        run(dir, &["remote", "set-url", "origin", url])?;
+    } else {
+        run(dir, &["remote", "add", "origin", url])?;
+    }
+    Ok(())
+}
+
+/// The URL of the `origin` remote.
+pub fn remote_url(dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    run(dir, &["remote", "get-url", "origin"])
+}
+
+/// What [`pull_and_push`] did.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Whether there is a remote at all.
+    pub remote: bool,
+    pub pulled: bool,
+    pub pushed: bool,
+}
+
+/// Merges the branch of the same name from `origin`, if it has one, then pushes.
+pub fn pull_and_push(dir: &Path) -> Result<SyncReport, Box<dyn std::error::Error>> {
+    let mut report = SyncReport::default();
+    if remote_url(dir).is_err() {
+        return Ok(report);
+    }
+    report.remote = true;
+    let branch = run(dir, &["symbolic-ref", "--short", "HEAD"])?;
+    run(dir, &["fetch", "--quiet", "origin"])?;
+    let upstream = format!("refs/remotes/origin/{}", branch);
+    if git(dir, &["rev-parse", "--verify", "--quiet", &upstream])?
+        .status
+        .success()
+    {
+        let before = run(dir, &["rev-parse", "HEAD"])?;
+        let merged = git(
+            dir,
+            &[
+                "merge",
+                "--quiet",
+                "--no-edit",
+                "--allow-unrelated-histories",
+                &upstream,
+            ],
+        )?;
+        if !merged.status.success() {
+            let _ = git(dir, &["merge", "--abort"]);
+            return Err(format!(
+                "could not merge the remote lists: {}",
+                String::from_utf8_lossy(&merged.stderr).trim()
+            )
+            .into());
+        }
+        report.pulled = run(dir, &["rev-parse", "HEAD"])? != before;
+    }
+    let ahead = git(
+        dir,
+        &["rev-list", "--count", &format!("{}..HEAD", upstream)],
+    )?;
+    if !ahead.status.success() || String::from_utf8_lossy(&ahead.stdout).trim() != "0" {
+        run(
+            dir,
+            &["push", "--quiet", "--set-upstream", "origin", &branch],
+        )?;
+        report.pushed = true;
+    }
+    Ok(report)
+}
+
+/// What [`sync`] did.
+#[derive(Debug)]
+pub struct Synced {
+    /// The repository the lists are in.
+    pub dir: PathBuf,
+    /// Whether the repository was created for this sync.
+    pub created: bool,
+    pub report: SyncReport,
+}
+
+/// `sync git` for the list stored in JSON in `data_file`: sets up the repository tracking it
+/// and the lists beside it if need be, with `program` as the merge driver, points `origin` at
+/// `remote` when given, commits, pulls and pushes. A named list is synced with the others, in
+/// the repository above `lists/`. The list is locked throughout, since git rewrites it while
+/// merging; it has to be reloaded afterwards.
+pub fn sync(
+    data_file: &Path,
+    remote: Option<&str>,
+    program: &Path,
+) -> Result<Synced, Box<dyn std::error::Error>> {
+    if data_file.extension().is_some_and(|ext| ext != "json") {
+        return Err("git sync only works with the JSON backend".into());
+    }
+    let parent = data_file.parent().ok_or("the list has no data directory")?;
+    let dir = match repository_for(data_file) {
+        Some(dir) => dir,
+        None if dirs::lists_dir().is_ok_and(|lists| lists == parent) => {
+            parent.parent().unwrap_or(parent)
+        }
+        None => parent,
+    };
+    let _lock = Lock::acquire(data_file)?;
+    let created = init(dir, program)?;
+    if let Some(url) = remote {
+        set_remote(dir, url)?;
+    }
+    commit(dir, "rust-todo sync")?;
+    let report = pull_and_push(dir)?;
+    Ok(Synced {
+        dir: dir.to_path_buf(),
+        created,
+        report,
+    })
+}
+
+fn to_map(task: &Task) -> Map<String, Value> {
+    match serde_json::to_value(task) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Merges one task changed on both sides, field by field. Returns the merged task and
+/// whether any field was changed differently on both sides.
+pub(crate) fn merge_task(base: Option<&Task>, ours: &Task, theirs: &Task) -> (Task, bool) {
+    let base = base.map(to_map).unwrap_or_default();
+    let (ours_map, theirs_map) = (to_map(ours), to_map(theirs));
+    let keys: BTreeSet<&String> = base
+        .keys()
+        .chain(ours_map.keys())
+        .chain(theirs_map.keys())
+        .collect();
+    let mut merged = Map::new();
+    let mut conflict = false;
+    for key in keys {
+        let (b, o, t) = (base.get(key), ours_map.get(key), theirs_map.get(key));
+        let value = if o == t || t == b {
+            o
+        } else if o == b {
+            t
+        } else {
+            conflict = true;
+            o
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    match serde_json::from_value(Value::Object(merged)) {
+        Ok(task) => (task, conflict),
+        Err(_) => (ours.clone(), true),
+    }
+}
+
+/// The result of [`merge`].
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub tasks: Vec<Task>,
+    /// Tasks with fields changed differently on both sides, where the local value was kept.
+    pub conflicts: usize,
+    /// Tasks added remotely under an ID already used here, given a new one.
+    pub renumbered: usize,
+}
+
+/// What identifies a task on every machine, unlike its ID, which another machine may have
+/// given to a different task.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Uuid(String),
+    /// For a task without a UUID: when it was created, and which of the tasks created at
+    /// that time it is.
+    Created(DateTime<FixedOffset>, usize),
+}
+
+fn keyed(tasks: &[Task]) -> Vec<(Key, &Task)> {
+    let mut sorted: Vec<&Task> = tasks.iter().collect();
+    sorted.sort_by_key(|t| t.id);
+    let mut seen: HashMap<DateTime<FixedOffset>, usize> = HashMap::new();
+    sorted
+        .into_iter()
+        .map(|task| match &task.uuid {
+            Some(uuid) => (Key::Uuid(uuid.clone()), task),
+            None => {
+                let n = seen.entry(task.created_at).or_default();
+                *n += 1;
+                (Key::Created(task.created_at, *n), task)
+            }
+        })
+        .collect()
+}
+
+/// Three-way merge of `ours` and `theirs`, two versions of a list that both started from
+/// `base`. Local tasks keep their IDs.
+pub fn merge(base: &[Task], ours: &[Task], theirs: &[Task]) -> Merged {
+    let base: HashMap<Key, &Task> = keyed(base).into_iter().collect();
+    let ours = keyed(ours);
+    let mut merged = Merged::default();
+
+    // Bring the remote tasks over to local IDs first.
+    let local: HashMap<&Key, usize> = ours.iter().map(|(key, task)| (key, task.id)).collect();
+    let mut used: BTreeSet<usize> = local.values().copied().collect();
+    // IDs that were ever used on either side, which a renumbered task doesn't take.
+    let mut seen: BTreeSet<usize> = ours
+        .iter()
+        .map(|(_, t)| t.id)
+        .chain(theirs.iter().map(|t| t.id))
+        .chain(base.values().map(|t| t.id))
+        .collect();
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    let theirs: Vec<(Key, Task)> = keyed(theirs)
+        .into_iter()
+        .map(|(key, task)| {
+            let id = match local.get(&key) {
+                Some(&id) => id,
+                None => {
+                    let wanted = base.get(&key).map_or(task.id, |b| b.id);
+                    let id = if used.contains(&wanted) {
+                        merged.renumbered += usize::from(!base.contains_key(&key));
+                        let id = ids::next(&seen);
+                        seen.insert(id);
+                        id
+                    } else {
+                        wanted
+                    };
+                    used.insert(id);
+                    id
+                }
+            };
+            ids.insert(task.id, id);
+            (key, task.clone())
+        })
+        .collect();
+    let mut theirs: HashMap<Key, Task> = theirs
+        .into_iter()
+        .map(|(key, mut task)| {
+            task.id = ids[&task.id];
+            task.parent = task.parent.map(|p| ids.get(&p).copied().unwrap_or(p));
+            for dependency in &mut task.depends_on {
+                *dependency = ids.get(dependency).copied().unwrap_or(*dependency);
+            }
+            for other in &mut task.related {
+                *other = ids.get(other).copied().unwrap_or(*other);
+            }
+            (key, task)
+        })
+        .collect();
+
+    let local_parents: HashMap<usize, Option<usize>> =
+        ours.iter().map(|(_, t)| (t.id, t.parent)).collect();
+    let mut tasks: Vec<Task> = vec![];
+    for (key, o) in ours {
+        let (b, t) = (base.get(&key).copied(), theirs.remove(&key));
+        match t {
+            Some(t) if *o == t || b == Some(&t) => tasks.push(o.clone()),
+            Some(t) if b == Some(o) => tasks.push(t),
+            Some(t) => {
+                let (task, conflict) = merge_task(b, o, &t);
+                merged.conflicts += usize::from(conflict);
+                tasks.push(task);
+            }
+            // Deleted remotely: kept only if it was changed or added here.
+            None if b != Some(o) => tasks.push(o.clone()),
+            None => {}
+        }
+    }
+    for (key, t) in theirs {
+        // Deleted here: kept only if it was changed or added remotely.
+        if base.get(&key).is_none_or(|b| **b != t) {
+            tasks.push(t);
+        }
+    }
+    tasks.sort_by_key(|t| t.id);
+    // Each side may have made a task a subtask of the other: the local parents win, and
+    // tasks still in a cycle, added remotely, go to the top level.
+    for local in [true, false] {
+        let cycles = crate::parent_cycles(&tasks);
+        for task in tasks.iter_mut().filter(|t| cycles.contains(&t.id)) {
+            let parent = match local {
+                true => local_parents.get(&task.id).copied().flatten(),
+                false => None,
+            };
+            if task.parent != parent {
+                task.parent = parent;
+                merged.conflicts += 1;
+            }
+        }
+    }
+    merged.tasks = tasks;
+    merged
+}
+
+/// Reads a list for the merge driver; git passes an empty file for a list added on both
+/// sides.
+fn read(path: &Path) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(schema::parse(&content)?.0)
+}
+
+/// The merge driver: merges the lists in `ours` and `theirs` against `base`, writing the
+/// result to `ours` as git expects.
+pub fn merge_files(
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+) -> Result<Merged, Box<dyn std::error::Error>> {
+    let merged = merge(&read(base)?, &read(ours)?, &read(theirs)?);
+    fs::write(ours, schema::render(&merged.tasks)?)?;
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn task(id: usize, description: &str, uuid: Option<&str>) -> Task {
+        let mut task = Task::builder().description(description).build();
+        task.id = id;
+        task.created_at = DateTime::parse_from_rfc3339("2026-10-15T10:00:00+00:00").unwrap();
+        task.uuid = uuid.map(str::to_string);
+        task
+    }
+
+    #[test]
+    fn named_lists_are_committed() {
+        let dir = TempDir::new();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("lists")).unwrap();
+        fs::create_dir_all(dir.join("tasks.json.snapshots")).unwrap();
+        for file in [
+            "tasks.json",
+            "lists/work.json",
+            "tasks.json.oplog",
+            "lists/work.json.trash",
+            "tasks.json.snapshots/20261015-100000-clear.json",
+        ] {
+            fs::write(dir.join(file), "[]").unwrap();
+        }
+        init(dir, Path::new("rust-todo")).unwrap();
+        run(dir, &["config", "user.name", "Test"]).unwrap();
+        run(dir, &["config", "user.email", "test@example.com"]).unwrap();
+        assert!(commit(dir, "test").unwrap());
+
+        let tracked = run(dir, &["ls-files"]).unwrap();
+        let tracked: Vec<&str> = tracked.lines().collect();
+        assert_eq!(
+            tracked,
+            [
+                ".gitattributes",
+                ".gitignore",
+                "lists/work.json",
+                "tasks.json"
+            ]
+        );
+        assert_eq!(
+            repository_for(&dir.join("lists/work.json")),
+            Some(dir),
+            "the named list's repository"
+        );
+    }
+
+    fn descriptions(merged: &Merged) -> Vec<&str> {
+        merged
+            .tasks
+            .iter()
+            .map(|t| t.description.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn fields_changed_on_one_side_take_the_change() {
+        let base = vec![task(1, "Buy milk", Some("a"))];
+        let mut ours = base.clone();
+        ours[0].add_tag("shop");
+        let mut theirs = base.clone();
+        theirs[0].description = "Buy oat milk".into();
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(descriptions(&merged), ["Buy oat milk"]);
+        assert_eq!(merged.tasks[0].tags, ["shop"]);
+        assert_eq!(merged.conflicts, 0);
+    }
+
+    #[test]
+    fn fields_changed_on_both_sides_keep_the_local_value() {
+        let base = vec![task(1, "Buy milk", Some("a"))];
+        let mut ours = base.clone();
+        ours[0].description = "Buy milk today".into();
+        let mut theirs = base.clone();
+        theirs[0].description = "Buy oat milk".into();
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(descriptions(&merged), ["Buy milk today"]);
+        assert_eq!(merged.conflicts, 1);
+    }
+
+    #[test]
+    fn deletions_win_over_tasks_left_alone() {
+        let base = vec![
+            task(1, "Deleted there", Some("a")),
+            task(2, "Deleted there, changed here", Some("b")),
+            task(3, "Deleted here", Some("c")),
+        ];
+        let mut ours = vec![base[0].clone(), base[1].clone()];
+        ours[1].add_tag("kept");
+        let theirs = vec![base[2].clone()];
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(descriptions(&merged), ["Deleted there, changed here"]);
+    }
+
+    #[test]
+    fn parents_changed_into_a_cycle_keep_the_local_ones() {
+        let base = vec![task(1, "Trip", Some("a")), task(2, "Flights", Some("b"))];
+        let mut ours = base.clone();
+        ours[1].parent = Some(1);
+        let mut theirs = base.clone();
+        theirs[0].parent = Some(2);
+
+        let merged = merge(&base, &ours, &theirs);
+        assert!(crate::parent_cycles(&merged.tasks).is_empty());
+        assert_eq!(merged.tasks[0].parent, None);
+        assert_eq!(merged.tasks[1].parent, Some(1));
+        assert_eq!(merged.conflicts, 1);
+    }
+
+    #[test]
+    fn a_cycle_among_remote_tasks_goes_to_the_top_level() {
+        let base = vec![];
+        let ours = vec![];
+        let mut theirs = vec![task(1, "A", Some("a")), task(2, "B", Some("b"))];
+        theirs[0].parent = Some(2);
+        theirs[1].parent = Some(1);
+
+        let merged = merge(&base, &ours, &theirs);
+        assert!(merged.tasks.iter().all(|t| t.parent.is_none()));
+    }
+
+    #[test]
+    fn tasks_created_in_the_same_second_stay_apart() {
+        let base = vec![task(1, "Shared", Some("a"))];
+        let mut ours = base.clone();
+        ours.push(task(2, "Added here", Some("b")));
+        let mut theirs = base.clone();
+        theirs.push(task(2, "Added there", Some("c")));
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(
+            descriptions(&merged),
+            ["Shared", "Added here", "Added there"]
+        );
+        assert_eq!(merged.renumbered, 1);
+        assert_eq!(merged.tasks[2].id, 3);
+    }
+}
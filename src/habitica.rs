@@ -0,0 +1,115 @@
+//! Importing tasks from Habitica's data export (`habitica-user-data.json`) or from the
+//! `GET /api/v3/tasks/user` API response.
+//!
+//! To-dos and dailies become tasks. Habits and rewards have no equivalent and are skipped.
+//! Every imported task records its Habitica ID as `external.id`, so importing the same
+//! export again does not create duplicates.
+
+use crate::{dates, Task};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Deserialize)]
+struct HabiticaTask {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(rename = "_id", default)]
+    legacy_id: Option<String>,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    text: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    completed: bool,
+    #[serde(rename = "createdAt", default)]
+    created_at: Option<String>,
+}
+
+pub struct HabiticaImport {
+    pub tasks: Vec<Task>,
+    pub skipped: usize,
+}
+
+/// Collects task objects from either the grouped export (`tasks.todos`, `tasks.dailys`, …),
+/// the API envelope (`data: [...]`) or a bare array, tagging grouped ones with their type.
+/// Anything else isn't a Habitica export.
+fn raw_tasks(root: Value) -> Result<Vec<Value>, String> {
+    if let Some(groups) = root.get("tasks").and_then(Value::as_object) {
+        let mut tasks = Vec::new();
+        for (group, kind) in [
+            ("todos", "todo"),
+            ("dailys", "daily"),
+            ("habits", "habit"),
+            ("rewards", "reward"),
+        ] {
+            for mut task in groups
+                .get(group)
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default()
+            {
+                if let Some(object) = task.as_object_mut() {
+                    object.entry("type").or_insert_with(|| Value::from(kind));
+                }
+                tasks.push(task);
+            }
+        }
+        return Ok(tasks);
+    }
+    match root {
+        Value::Object(mut object) => match object.remove("data") {
+            Some(Value::Array(tasks)) => Ok(tasks),
+            _ => Err("not a Habitica export: expected \"tasks\" or a \"data\" array".into()),
+        },
+        Value::Array(tasks) => Ok(tasks),
+        _ => Err("not a Habitica export: expected an object or an array of tasks".into()),
+    }
+}
+
+pub fn parse(content: &str) -> Result<HabiticaImport, Box<dyn std::error::Error>> {
+    let root: Value = serde_json::from_str(content)?;
+    let mut result = HabiticaImport {
+        tasks: Vec::new(),
+        skipped: 0,
+    };
+    for raw in raw_tasks(root)? {
+        let item: HabiticaTask = serde_json::from_value(raw)?;
+        let kind = item.kind.as_deref().unwrap_or("todo");
+        if !matches!(kind, "todo" | "daily") {
+            result.skipped += 1;
+            continue;
+        }
+        let mut builder = Task::builder()
+            .description(item.text)
+            .completed(kind == "todo" && item.completed)
+            .metadata("habitica.type", kind);
+        if let Some(id) = item.id.or(item.legacy_id) {
+            builder = builder.metadata("external.id", format!("habitica:{}", id));
+        }
+        if !item.notes.trim().is_empty() {
+            builder = builder.metadata("habitica.notes", item.notes.trim());
+        }
+        let mut task = builder.build();
+        if let Some(created_at) = item.created_at.as_deref().and_then(dates::parse_timestamp) {
+            task.created_at = created_at;
+        }
+        result.tasks.push(task);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_documents_are_errors() {
+        for content in [r#"{"todos": []}"#, r#"{"data": {}}"#, "3"] {
+            assert!(parse(content).is_err(), "{}", content);
+        }
+        let result = parse(r#"{"data": [{"text": "Stretch", "type": "daily"}]}"#).unwrap();
+        assert_eq!(result.tasks.len(), 1);
+        assert!(parse("[]").unwrap().tasks.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Hooks: commands run when tasks are added, completed or deleted, so other tools can follow
+//! the list, set in the `[hooks]` table of the [configuration file](crate::config):
+//!
+//! ```toml
+//! [hooks]
+//! on-add = "~/bin/log-task added"
+//! on-complete = "curl -s -X POST -H 'Content-Type: application/json' -d @- $SLACK_WEBHOOK"
+//! on-delete = "jq -r .description >> ~/deleted.txt"
+//! ```
+//!
+//! A hook runs through the shell (`sh -c`, or `cmd /C` on Windows) once for each task, with
+//! the task as JSON on stdin and `RUST_TODO_HOOK` and `RUST_TODO_FILE` set to the hook's name
+//! and the list's data file; what it prints goes to stderr. Hooks run after the change is
+//! saved and the list is unlocked, so a hook can run rust-todo itself, and one that fails is
+//! reported without undoing the change. Changes from every command, `serve` and `rpc` set
+//! them off, except `undo` and `redo`, which take back earlier changes, and `archive`: an
+//! archived task isn't deleted.
+
+use crate::oplog::Change;
+use crate::{Status, Task};
+use serde::Deserialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+
+/// The commands to run, by event.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Hooks {
+    /// Run for each task added, including subtasks, imported and restored tasks.
+    pub on_add: Option<String>,
+    /// Run for each task marked done; cancelling a task isn't completing it.
+    pub on_complete: Option<String>,
+    /// Run for each task deleted or moved to another list.
+    pub on_delete: Option<String>,
+}
+
+/// A hook to run for a task.
+pub struct Event {
+    pub hook: &'static str,
+    pub task: Task,
+}
+
+/// The hooks that `changes`, saved by `operation`, set off, in the order of the changes.
+pub fn events(hooks: &Hooks, operation: &str, changes: &[Change]) -> Vec<Event> {
+    if operation == "archive" {
+        return vec![];
+    }
+    let done = |task: &Task| task.status() == Status::Done;
+    changes
+        .iter()
+        .filter_map(|change| match (&change.before, &change.after) {
+            (None, Some(after)) if hooks.on_add.is_some() => Some(("on-add", after)),
+            (Some(before), Some(after)) if hooks.on_complete.is_some() => {
+                (done(after) && !done(before)).then_some(("on-complete", after))
+            }
+            (Some(before), None) if hooks.on_delete.is_some() => Some(("on-delete", before)),
+            _ => None,
+        })
+        .map(|(hook, task)| Event {
+            hook,
+            task: task.clone(),
+        })
+        .collect()
+}
+
+/// Runs the hooks for `events` on the list stored at `path`, waiting for each to finish.
+pub fn run(hooks: &Hooks, path: &Path, events: &[Event]) {
+    for event in events {
+        let command = match event.hook {
+            "on-add" => &hooks.on_add,
+            "on-complete" => &hooks.on_complete,
+            _ => &hooks.on_delete,
+        };
+        let Some(command) = command else {
+            continue;
+        };
+        if let Err(e) = run_one(command, event, path) {
+            warn!(hook = event.hook, task = event.task.id, error = %e, "hook failed");
+        }
+    }
+}
+
+fn run_one(command: &str, event: &Event, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    debug!(
+        hook = event.hook,
+        task = event.task.id,
+        command,
+        "running hook"
+    );
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .env("RUST_TODO_HOOK", event.hook)
+        .env("RUST_TODO_FILE", path)
+        .stdin(Stdio::piped())
+        // Off stdout, which `rpc` speaks its protocol on.
+        .stdout(std::io::stderr())
+        .spawn()?;
+    let json = serde_json::to_string(&event.task)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its input closes the pipe early, which is fine.
+        match stdin.write_all(json.as_bytes()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("`{}` exited with {}", command, status).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,443 @@
+//! iCalendar (RFC 5545) to-dos, the format of calendar apps and CalDAV servers.
+//!
+//! Each task becomes a `VTODO` with its description as `SUMMARY`, its due date, its tags as
+//! `CATEGORIES`, its priority (critical 1, high 3, medium 5, low 9) and a `STATUS` of
+//! `NEEDS-ACTION` or `COMPLETED`. Subtasks point at their parent with `RELATED-TO`. Tasks
+//! imported from iCalendar keep their `UID` as `external.id`, and others get one derived from
+//! their ID and creation time, so exporting twice gives the same UIDs.
+//!
+//! Importing reads the same properties back. A due date without a time means 09:00, and a
+//! time with a `TZID` is taken as local time. To-dos without a `SUMMARY` or an `END`, and
+//! dates that can't be read, are reported as [problems](crate::import::Problem).
+//!
+//! Calendars that are subscribed to, rather than synced, mostly ignore to-dos, so
+//! [`render_events`] gives each due date as a `VEVENT` instead.
+
+use crate::import::Problem;
+use crate::{dates, normalize_tag, Priority, Task};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Prefix of `external.id` for tasks imported from iCalendar.
+const EXTERNAL: &str = "ical:";
+
+/// A `VTODO`, as far as rust-todo reads one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Todo {
+    pub uid: String,
+    pub summary: String,
+    pub due: Option<DateTime<Local>>,
+    pub completed: bool,
+    pub priority: Option<Priority>,
+    pub categories: Vec<String>,
+    /// `UID` of the parent to-do.
+    pub related_to: Option<String>,
+    pub created: Option<DateTime<Local>>,
+}
+
+/// The `UID` of `task` in calendars.
+pub fn uid(task: &Task) -> String {
+    if let Some(uid) = task
+        .metadata
+        .get("external.id")
+        .and_then(|id| id.strip_prefix(EXTERNAL))
+    {
+        return uid.to_string();
+    }
+    let digest = Sha256::digest(format!(
+        "rust-todo:{}:{}",
+        task.id,
+        task.created_at.to_rfc3339()
+    ));
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}@rust-todo", hex)
+}
+
+fn format_date(at: DateTime<impl TimeZone>) -> String {
+    at.with_timezone(&Utc).format(DATE_FORMAT).to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Splits `text` on commas that aren't escaped.
+fn split_list(text: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            ',' if !escaped => items.push(String::new()),
+            _ => {
+                items.last_mut().unwrap().push(c);
+                escaped = c == '\\' && !escaped;
+                continue;
+            }
+        }
+        escaped = false;
+    }
+    items.iter().map(|item| unescape(item)).collect()
+}
+
+/// Appends a content line, folded at 75 bytes as RFC 5545 requires.
+fn line(out: &mut String, content: &str) {
+    let mut width = 0;
+    for c in content.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn ical_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::Critical => 1,
+        Priority::High => 3,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
+
+fn priority(value: &str) -> Option<Priority> {
+    match value.trim().parse::<u8>().ok()? {
+        1 | 2 => Some(Priority::Critical),
+        3 | 4 => Some(Priority::High),
+        5 => Some(Priority::Medium),
+        6..=9 => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+fn render_todo(out: &mut String, task: &Task, parent: Option<&str>, now: DateTime<Local>) {
+    line(out, "BEGIN:VTODO");
+    line(out, &format!("UID:{}", uid(task)));
+    line(out, &format!("DTSTAMP:{}", format_date(now)));
+    line(out, &format!("CREATED:{}", format_date(task.created_at)));
+    line(out, &format!("SUMMARY:{}", escape(&task.description)));
+    if let Some(due) = task.due {
+        line(out, &format!("DUE:{}", format_date(due)));
+    }
+    if let Some(priority) = task.priority {
+        line(out, &format!("PRIORITY:{}", ical_priority(priority)));
+    }
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task.tags.iter().map(|t| escape(t)).collect();
+        line(out, &format!("CATEGORIES:{}", tags.join(",")));
+    }
+    if let Some(parent) = parent {
+        line(out, &format!("RELATED-TO:{}", parent));
+    }
+    if task.completed {
+        line(out, "STATUS:COMPLETED");
+        line(out, "PERCENT-COMPLETE:100");
+    } else {
+        line(out, "STATUS:NEEDS-ACTION");
+    }
+    line(out, "END:VTODO");
+}
+
+/// Renders `tasks` as one calendar. `all` is the whole list, for the `UID`s of parents.
+pub fn render(tasks: &[Task], all: &[Task]) -> String {
+    let uids: HashMap<usize, String> = all.iter().map(|t| (t.id, uid(t))).collect();
+    let now = dates::now();
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(&mut out, "PRODID:-//rust-todo//rust-todo//EN");
+    for task in tasks {
+        let parent = task.parent.and_then(|p| uids.get(&p)).map(String::as_str);
+        render_todo(&mut out, task, parent, now);
+    }
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Renders the due dates of `tasks` as events in a calendar called `name`, for calendar apps
+/// to subscribe to. A due date without a time is an all-day event, and one with a time is a
+/// 15-minute event starting then. Tasks without a due date are left out.
+pub fn render_events(tasks: &[&Task], name: &str) -> String {
+    let now = dates::now();
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(&mut out, "PRODID:-//rust-todo//rust-todo//EN");
+    line(&mut out, "METHOD:PUBLISH");
+    line(&mut out, &format!("X-WR-CALNAME:{}", escape(name)));
+    // How often subscribers should fetch it again.
+    line(&mut out, "REFRESH-INTERVAL;VALUE=DURATION:PT15M");
+    line(&mut out, "X-PUBLISHED-TTL:PT15M");
+    for task in tasks {
+        let Some(due) = task.due else { continue };
+        line(&mut out, "BEGIN:VEVENT");
+        line(&mut out, &format!("UID:{}", uid(task)));
+        line(&mut out, &format!("DTSTAMP:{}", format_date(now)));
+        if dates::end_of_day(due.date_naive()) == Ok(due) {
+            let day = due.date_naive();
+            line(
+                &mut out,
+                &format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+            );
+            if let Some(next) = day.succ_opt() {
+                line(
+                    &mut out,
+                    &format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")),
+                );
+            }
+        } else {
+            line(&mut out, &format!("DTSTART:{}", format_date(due)));
+            line(&mut out, "DURATION:PT15M");
+        }
+        line(&mut out, &format!("SUMMARY:{}", escape(&task.description)));
+        line(
+            &mut out,
+            &format!(
+                "DESCRIPTION:{}",
+                escape(&format!("rust-todo task {}", task.id))
+            ),
+        );
+        if let Some(priority) = task.priority {
+            line(&mut out, &format!("PRIORITY:{}", ical_priority(priority)));
+        }
+        if !task.tags.is_empty() {
+            let tags: Vec<String> = task.tags.iter().map(|t| escape(t)).collect();
+            line(&mut out, &format!("CATEGORIES:{}", tags.join(",")));
+        }
+        line(&mut out, "TRANSP:TRANSPARENT");
+        line(&mut out, "END:VEVENT");
+    }
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Joins folded lines back together, each with the line it starts on.
+fn unfold(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = vec![];
+    for (number, raw) in content.lines().enumerate() {
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, line))) => line.push_str(rest),
+            _ => lines.push((number + 1, raw.to_string())),
+        }
+    }
+    lines
+}
+
+/// Reads a `DUE` or `CREATED` value; `params` are the property's parameters, such as
+/// `VALUE=DATE`.
+fn parse_date(params: &str, value: &str) -> Option<DateTime<Local>> {
+    let value = value.trim();
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Local
+            .from_local_datetime(&date.and_hms_opt(9, 0, 0)?)
+            .earliest();
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(naive.and_utc().with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+/// The to-dos in an iCalendar document. Events and other components are skipped.
+pub fn parse_todos(content: &str) -> Vec<Todo> {
+    read_todos(content)
+        .0
+        .into_iter()
+        .map(|(_, todo)| todo)
+        .collect()
+}
+
+/// The to-dos in an iCalendar document with the line each begins on, and what couldn't be
+/// read of them.
+fn read_todos(content: &str) -> (Vec<(usize, Todo)>, Vec<Problem>) {
+    let mut todos = vec![];
+    let mut problems = vec![];
+    let mut current: Option<(usize, Todo)> = None;
+    // Nested components, such as alarms, have properties of their own.
+    let mut depth = 0;
+    for (number, line) in unfold(content) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        let name = name.to_ascii_uppercase();
+        match (name.as_str(), value.trim()) {
+            ("BEGIN", "VTODO") => {
+                if let Some((begin, _)) = current.replace((number, Todo::default())) {
+                    problems.push(Problem::at(begin, "BEGIN:VTODO without END:VTODO"));
+                }
+                depth = 0;
+            }
+            ("END", "VTODO") if depth == 0 => todos.extend(current.take()),
+            ("BEGIN", _) if current.is_some() => depth += 1,
+            ("END", _) if current.is_some() => depth -= 1,
+            _ => {}
+        }
+        let Some((_, todo)) = current.as_mut().filter(|_| depth == 0) else {
+            continue;
+        };
+        let mut date = |property: &str| {
+            let date = parse_date(params, value);
+            if date.is_none() {
+                problems.push(Problem::at(
+                    number,
+                    format!(
+                        "{} {:?} isn't a date; imported without it",
+                        property,
+                        value.trim()
+                    ),
+                ));
+            }
+            date
+        };
+        match name.as_str() {
+            "UID" => todo.uid = value.trim().to_string(),
+            "SUMMARY" => todo.summary = unescape(value),
+            "DUE" => todo.due = date("DUE"),
+            "CREATED" => todo.created = date("CREATED"),
+            "STATUS" => todo.completed = value.trim().eq_ignore_ascii_case("COMPLETED"),
+            "COMPLETED" => todo.completed = true,
+            "PRIORITY" => todo.priority = priority(value),
+            "CATEGORIES" => todo.categories.extend(
+                split_list(value)
+                    .iter()
+                    .map(|c| normalize_tag(c))
+                    .filter(|c| !c.is_empty()),
+            ),
+            "RELATED-TO" if !params.contains("RELTYPE=") || params.contains("RELTYPE=PARENT") => {
+                todo.related_to = Some(value.trim().to_string())
+            }
+            _ => {}
+        }
+    }
+    if let Some((begin, _)) = current {
+        problems.push(Problem::at(begin, "BEGIN:VTODO without END:VTODO"));
+    }
+    (todos, problems)
+}
+
+impl Todo {
+    /// Copies what the to-do says onto `task`.
+    pub fn apply(&self, task: &mut Task) {
+        task.description = self.summary.trim().to_string();
+        task.set_completed(self.completed);
+        task.due = self.due;
+        task.priority = self.priority;
+        task.tags = self.categories.clone();
+    }
+
+    /// A new task from the to-do, remembering its `UID`.
+    pub fn to_task(&self) -> Task {
+        let mut task = Task::builder()
+            .description(self.summary.trim())
+            .metadata("external.id", format!("{}{}", EXTERNAL, self.uid))
+            .build();
+        self.apply(&mut task);
+        if let Some(created) = self.created {
+            task.created_at = created.fixed_offset();
+        }
+        task
+    }
+}
+
+/// Reads the to-dos of an iCalendar document as tasks, with what had to be skipped.
+pub fn parse(content: &str) -> Result<(Vec<Task>, Vec<Problem>), Box<dyn std::error::Error>> {
+    if !content.trim_start().starts_with("BEGIN:VCALENDAR") {
+        return Err("not an iCalendar file (expected BEGIN:VCALENDAR)".into());
+    }
+    let (todos, mut problems) = read_todos(content);
+    let todos: Vec<Todo> = todos
+        .into_iter()
+        .filter_map(|(line, todo)| {
+            if todo.summary.trim().is_empty() {
+                problems.push(Problem::at(line, "to-do without a SUMMARY"));
+                return None;
+            }
+            Some(todo)
+        })
+        .collect();
+    problems.sort_by_key(|p| p.line);
+    // Provisional IDs, so subtasks can point at their parent until the list assigns real ones.
+    let ids: HashMap<&str, usize> = todos
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.uid.as_str(), i + 1))
+        .collect();
+    let tasks = todos
+        .iter()
+        .enumerate()
+        .map(|(i, todo)| {
+            let mut task = todo.to_task();
+            task.id = i + 1;
+            task.parent = todo
+                .related_to
+                .as_deref()
+                .and_then(|uid| ids.get(uid).copied())
+                .filter(|&parent| parent != task.id);
+            task
+        })
+        .collect();
+    Ok((tasks, problems))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, sample_tasks};
+
+    #[test]
+    fn round_trips_to_dos_and_subtasks() {
+        let sample = sample_tasks();
+        let (tasks, problems) = parse(&render(&sample, &sample)).unwrap();
+        assert!(problems.is_empty());
+        let trip = find(&tasks, "Plan the trip");
+        assert_eq!(trip.priority, Some(Priority::High));
+        assert_eq!(trip.tags, ["travel"]);
+        assert_eq!(trip.due, sample[0].due);
+        assert_eq!(find(&tasks, "Book the flights").parent, Some(trip.id));
+        assert!(find(&tasks, "Renew the passport").completed);
+    }
+
+    #[test]
+    fn reads_folded_lines_escapes_and_all_day_dates() {
+        let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:1\r\nSUMMARY:Milk\\, eggs and a\r\n  very long list\r\nDUE;VALUE=DATE:20261020\r\nBEGIN:VALARM\r\nSUMMARY:Not the to-do\r\nEND:VALARM\r\nEND:VTODO\r\nBEGIN:VTODO\r\nUID:2\r\nDUE:tomorrow\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        let (tasks, problems) = parse(calendar).unwrap();
+        let milk = find(&tasks, "Milk, eggs and a very long list");
+        assert_eq!(
+            milk.due.map(|d| d.format("%Y-%m-%d %H:%M").to_string()),
+            Some("2026-10-20 09:00".to_string())
+        );
+        assert_eq!(tasks.len(), 1);
+        let lines: Vec<Option<usize>> = problems.iter().map(|p| p.line).collect();
+        // The second has no SUMMARY, and its DUE isn't a date.
+        assert_eq!(lines, [Some(11), Some(13)]);
+    }
+}
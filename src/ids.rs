@@ -0,0 +1,75 @@
+//! How new tasks get their IDs, set with `ids` in the [configuration](crate::config):
+//! `sequential`, one more than the highest ID in use, or `random`, short random numbers.
+//! Random IDs don't give away how many tasks a list has had when it is shared or exported,
+//! and two copies of a list that both added tasks rarely pick the same ones, so fewer tasks
+//! are renumbered when they are merged (see [`git::merge`](crate::git::merge)).
+//!
+//! Every task added also gets a random [`uuid`], which stays the same when it is renumbered
+//! or moved to another list.
+
+use crate::config::{self, Ids};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Random IDs have at least this many digits, and a digit more each time half of the IDs
+/// that long are taken.
+const MIN_DIGITS: u32 = 3;
+
+fn random() -> u64 {
+    // Each `RandomState` is keyed differently, which is random enough for IDs.
+    let mut hasher = RandomState::new().build_hasher();
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(since_epoch.as_nanos());
+    hasher.finish()
+}
+
+/// An ID for a new task that isn't one of `used`, by the configured strategy.
+pub fn next(used: &BTreeSet<usize>) -> usize {
+    next_with(config::current().ids, used)
+}
+
+/// An ID for a new task that isn't one of `used`, by `strategy`.
+pub fn next_with(strategy: Ids, used: &BTreeSet<usize>) -> usize {
+    match strategy {
+        Ids::Sequential => used.last().map_or(1, |max| max + 1),
+        Ids::Random => {
+            let mut digits = MIN_DIGITS;
+            loop {
+                let low = 10usize.pow(digits - 1);
+                let high = low * 10;
+                let taken = used.range(low..high).count();
+                if taken < (high - low) / 2 {
+                    loop {
+                        let id = low + (random() % (high - low) as u64) as usize;
+                        if !used.contains(&id) {
+                            return id;
+                        }
+                    }
+                }
+                digits += 1;
+            }
+        }
+    }
+}
+
+/// A random (version 4) UUID, such as `3f2b8c1e-9a4d-4c7e-8b1a-2d5e6f708192`.
+pub fn uuid() -> String {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random().to_be_bytes());
+    bytes[8..].copy_from_slice(&random().to_be_bytes());
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
@@ -0,0 +1,115 @@
+//! What the import parsers share. Rather than failing on the first malformed record, a
+//! parser reads what it can and reports each record it skipped, or only partly read, as a
+//! [`Problem`] saying where and why, so an import isn't all or nothing. Input that isn't in
+//! the format at all, such as a CSV file without the expected columns, still fails as a whole.
+//!
+//! Imported tasks described like one already in the list are dealt with by a [`Duplicates`]
+//! policy before they are merged into it.
+
+use crate::{Task, TodoList};
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A record of the input that was skipped or only partly read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// Line of the input the record starts on, counting from 1, when known.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn at(line: usize, message: impl Into<String>) -> Self {
+        Problem {
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// What to do with an imported task whose description is already in the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Duplicates {
+    /// Import it anyway
+    Keep,
+    /// Leave it out
+    Skip,
+    /// Import it with " (imported)" added to its description
+    Rename,
+}
+
+/// Applies the `policy` to imported tasks whose description is already in `list`, ignoring
+/// case. Returns the tasks to merge and how many had such a description.
+///
+/// A skipped task's subtasks are still imported, as top-level tasks.
+pub fn resolve_duplicates(
+    list: &TodoList,
+    imported: Vec<Task>,
+    policy: Duplicates,
+) -> (Vec<Task>, usize) {
+    let existing: HashSet<String> = list
+        .tasks()
+        .iter()
+        .map(|t| t.description.trim().to_lowercase())
+        .collect();
+    let mut conflicts = 0;
+    let mut kept = vec![];
+    for mut task in imported {
+        // Tasks already imported are skipped by `merge` and aren't conflicts.
+        if list.has_external_id(&task)
+            || !existing.contains(&task.description.trim().to_lowercase())
+        {
+            kept.push(task);
+            continue;
+        }
+        conflicts += 1;
+        match policy {
+            Duplicates::Keep => kept.push(task),
+            Duplicates::Skip => {}
+            Duplicates::Rename => {
+                task.description.push_str(" (imported)");
+                kept.push(task);
+            }
+        }
+    }
+    (kept, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn duplicates_are_kept_skipped_or_renamed() {
+        let dir = TempDir::new();
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        list.add(Task::builder().description("Buy milk").build())
+            .unwrap();
+        let imported = || {
+            vec![
+                Task::builder().description("buy MILK ").build(),
+                Task::builder().description("Walk the dog").build(),
+            ]
+        };
+        let descriptions = |tasks: Vec<Task>| -> Vec<String> {
+            tasks.into_iter().map(|t| t.description).collect()
+        };
+        let (kept, conflicts) = resolve_duplicates(&list, imported(), Duplicates::Keep);
+        assert_eq!((descriptions(kept).len(), conflicts), (2, 1));
+        let (kept, _) = resolve_duplicates(&list, imported(), Duplicates::Skip);
+        assert_eq!(descriptions(kept), ["Walk the dog"]);
+        let (kept, _) = resolve_duplicates(&list, imported(), Duplicates::Rename);
+        assert_eq!(descriptions(kept), ["buy MILK  (imported)", "Walk the dog"]);
+    }
+}
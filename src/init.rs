@@ -0,0 +1,181 @@
+//! `init`: a first-run wizard that asks where to keep tasks, which lists to create and how
+//! listings should look, and writes the answers to the configuration file.
+//!
+//! Every question has a default, taken by pressing Enter, and only answers that differ from
+//! the defaults are written, so the file stays short. Without a terminal, e.g. with stdin
+//! from `/dev/null`, every question takes its default.
+
+use clap::ValueEnum;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use todo_core::{
+    config::{self, Color},
+    dirs, SortBy, DEFAULT_LIST,
+};
+
+/// What `init` set up, for the caller to finish.
+pub struct Setup {
+    /// The configuration file written.
+    pub path: PathBuf,
+    /// Named lists to create.
+    pub lists: Vec<String>,
+}
+
+/// Asks `question` and returns the answer, or `default` for an empty answer or at the end of
+/// the input.
+fn ask(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> std::io::Result<String> {
+    if default.is_empty() {
+        write!(out, "{}: ", question)?;
+    } else {
+        write!(out, "{} [{}]: ", question, default)?;
+    }
+    out.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        writeln!(out)?;
+    }
+    match line.trim() {
+        "" => Ok(default.to_string()),
+        answer => Ok(answer.to_string()),
+    }
+}
+
+/// Asks `question` until the answer is one of `T`'s values.
+fn choose<T: ValueEnum>(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> std::io::Result<T> {
+    let names: Vec<String> = T::value_variants()
+        .iter()
+        .filter_map(|v| Some(v.to_possible_value()?.get_name().to_string()))
+        .collect();
+    let question = format!("{} ({})", question, names.join(", "));
+    loop {
+        let answer = ask(input, out, &question, default)?;
+        match T::from_str(&answer, true) {
+            Ok(value) => return Ok(value),
+            Err(_) => writeln!(out, "  Please answer one of: {}.", names.join(", "))?,
+        }
+    }
+}
+
+fn yes(answer: &str) -> bool {
+    matches!(answer.to_lowercase().as_str(), "y" | "yes")
+}
+
+fn valid_list_name(name: &str) -> bool {
+    name.chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn run(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<Option<Setup>, Box<dyn std::error::Error>> {
+    let path = config::path().ok_or("no configuration directory on this system")?;
+    writeln!(
+        out,
+        "Setting up rust-todo. Press Enter to keep the suggestion in brackets.\n"
+    )?;
+    if path.exists() {
+        let answer = ask(
+            input,
+            out,
+            &format!(
+                "{} already exists. Replace it (the old one is kept as config.toml.bak)?",
+                path.display()
+            ),
+            "no",
+        )?;
+        if !yes(&answer) {
+            writeln!(out, "Nothing changed.")?;
+            return Ok(None);
+        }
+    }
+    let mut settings = toml::Table::new();
+
+    let default_file = dirs::data_dir()
+        .map(|dir| dir.join(dirs::DATA_FILE).display().to_string())
+        .unwrap_or_default();
+    let file = ask(
+        input,
+        out,
+        "Where should tasks be kept? Named lists go in a lists/ directory next to this file",
+        &default_file,
+    )?;
+    if file != default_file {
+        settings.insert("data_file".into(), file.into());
+    }
+
+    let lists = loop {
+        let answer = ask(
+            input,
+            out,
+            "Lists to create besides the default one, separated by spaces (e.g. work home)",
+            "",
+        )?;
+        let names: Vec<String> = answer.split_whitespace().map(str::to_string).collect();
+        match names.iter().find(|name| !valid_list_name(name)) {
+            Some(name) => writeln!(
+                out,
+                "  {:?} can't be a list name; use letters, digits, '-' and '_'.",
+                name
+            )?,
+            None => break names,
+        }
+    };
+    if !lists.is_empty() {
+        let mut choices = vec![DEFAULT_LIST.to_string()];
+        choices.extend(lists.iter().cloned());
+        let list = loop {
+            let answer = ask(
+                input,
+                out,
+                &format!(
+                    "Which list should commands use without --list ({})",
+                    choices.join(", ")
+                ),
+                DEFAULT_LIST,
+            )?;
+            if choices.contains(&answer) {
+                break answer;
+            }
+            writeln!(out, "  Please answer one of: {}.", choices.join(", "))?;
+        };
+        if list != DEFAULT_LIST {
+            settings.insert("list".into(), list.into());
+        }
+    }
+
+    let color: Color = choose(input, out, "When should listings use colour", "auto")?;
+    if color != Color::Auto {
+        let name = color.to_possible_value().map(|v| v.get_name().to_string());
+        settings.insert("color".into(), name.unwrap_or_default().into());
+    }
+    let emoji = ask(
+        input,
+        out,
+        "Show emoji such as 📅 and ⚑ in listings?",
+        "yes",
+    )?;
+    if !yes(&emoji) {
+        let mut theme = toml::Table::new();
+        theme.insert("emoji".into(), false.into());
+        settings.insert("theme".into(), theme.into());
+    }
+    let sort: SortBy = choose(input, out, "How should `list` be sorted", "id")?;
+    if sort != SortBy::Id {
+        let name = sort.to_possible_value().map(|v| v.get_name().to_string());
+        settings.insert("sort".into(), name.unwrap_or_default().into());
+    }
+
+    let path = config::save(&settings)?;
+    Ok(Some(Setup { path, lists }))
+}
@@ -0,0 +1,117 @@
+use crate::Task;
+use chrono::{DateTime, Local};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Environment variable naming the directory that receives one Markdown file per day.
+pub const JOURNAL_DIR_ENV: &str = "RUST_TODO_JOURNAL_DIR";
+
+/// Returns the configured journal directory, if the integration is enabled.
+fn journal_dir() -> Option<PathBuf> {
+    std::env::var_os(JOURNAL_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Appends `task` to `<dir>/YYYY-MM-DD.md`, creating the file with a heading on first use.
+fn append_completed(dir: &Path, task: &Task, at: DateTime<Local>) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.md", at.format("%Y-%m-%d")));
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    if is_new {
+        writeln!(file, "# Done — {}\n", at.format("%A, %B %-d, %Y"))?;
+    }
+    writeln!(
+        file,
+        "- [x] {} {} (#{})",
+        at.format("%H:%M"),
+        task.description,
+        task.id
+    )?;
+    Ok(path)
+}
+
+/// Appends `task` to today's journal when the integration is enabled, warning on stderr if the
+/// file cannot be written (the completion itself has already been saved).
+pub fn record(task: &Task) {
+    let Some(dir) = journal_dir() else {
+        return;
+    };
+    match append_completed(&dir, task, crate::dates::now()) {
+        Ok(path) => debug!(path = %path.display(), "appended to journal"),
+        Err(e) => eprintln!(
+            "⚠️  Could not append to journal in {}: {}",
+            dir.display(),
+            e
+        ),
+    }
+}
+
+/// Removes the journal entries `forget` picks, given each entry's description and task ID, when
+/// the integration is enabled. Returns how many were removed.
+pub fn forget(forget: impl Fn(&str, usize) -> bool) -> std::io::Result<usize> {
+    match journal_dir() {
+        Some(dir) if dir.is_dir() => forget_in(&dir, forget),
+        _ => Ok(0),
+    }
+}
+
+fn forget_in(dir: &Path, forget: impl Fn(&str, usize) -> bool) -> std::io::Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|e| e != "md") {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        let kept: Vec<&str> = content
+            .lines()
+            .filter(|line| !parse_entry(line).is_some_and(|(d, id)| forget(d, id)))
+            .collect();
+        let dropped = content.lines().count() - kept.len();
+        if dropped > 0 {
+            fs::write(&path, kept.join("\n") + "\n")?;
+            removed += dropped;
+        }
+    }
+    Ok(removed)
+}
+
+/// The description and task ID of a line written by [`append_completed`].
+fn parse_entry(line: &str) -> Option<(&str, usize)> {
+    let (_time, rest) = line.strip_prefix("- [x] ")?.split_once(' ')?;
+    let (description, id) = rest.strip_suffix(')')?.rsplit_once(" (#")?;
+    Some((description, id.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use chrono::TimeZone;
+
+    #[test]
+    fn forgets_the_entries_picked() {
+        let dir = TempDir::new();
+        let at = Local.with_ymd_and_hms(2024, 3, 4, 9, 30, 0).unwrap();
+        for (id, description) in [(1, "Buy milk"), (2, "Rotate key hunter2"), (3, "Old name")] {
+            let task = Task {
+                id,
+                ..Task::builder().description(description).build()
+            };
+            append_completed(dir.path(), &task, at).unwrap();
+        }
+        let removed = forget_in(dir.path(), |description, id| {
+            description.contains("hunter2") || id == 3
+        })
+        .unwrap();
+        assert_eq!(removed, 2);
+        let journal = fs::read_to_string(dir.path().join("2024-03-04.md")).unwrap();
+        assert!(journal.starts_with("# Done"));
+        assert!(journal.contains("09:30 Buy milk (#1)"));
+        assert!(!journal.contains("hunter2") && !journal.contains("Old name"));
+    }
+}
@@ -0,0 +1,150 @@
+//! The JSON Schema of the task format, in `task.schema.json` at the root of the repository,
+//! and checking documents against it for `export --validate` and `import --validate`.
+//!
+//! The check understands the keywords the schema uses, not all of JSON Schema: `$ref` to
+//! `#/$defs/...`, `anyOf`, `type`, `enum`, `minimum`, `format: date-time`, `properties`,
+//! `required`, `additionalProperties` and `items`.
+
+use chrono::DateTime;
+use serde_json::Value;
+
+/// The schema, as published.
+pub const SCHEMA: &str = include_str!("../task.schema.json");
+
+/// Checks `document` against [`SCHEMA`]. Fails with every violation found, each prefixed
+/// with the JSON Pointer of the offending value, or `document` for the document itself.
+pub fn validate(document: &Value) -> Result<(), Vec<String>> {
+    let schema: Value = serde_json::from_str(SCHEMA).expect("task.schema.json is valid JSON");
+    let mut errors = vec![];
+    check(&schema, &schema, document, "", &mut errors);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(errors
+        .into_iter()
+        .map(|e| match e.strip_prefix(':') {
+            Some(rest) => format!("document:{}", rest),
+            None => e,
+        })
+        .collect())
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// `schema`, or what its `$ref` points to.
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .unwrap_or_else(|| panic!("task.schema.json: unknown $ref {}", reference)),
+        None => schema,
+    }
+}
+
+fn check(root: &Value, schema: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+    if schema.get("$ref").is_some() {
+        check(root, resolve(root, schema), value, at, errors);
+    }
+    if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+        // Report why the closest branch failed rather than every branch: one for this type
+        // of value if there is one, with the fewest errors.
+        let mut closest: Option<(bool, Vec<String>)> = None;
+        for branch in branches {
+            let mut found = vec![];
+            check(root, branch, value, at, &mut found);
+            if found.is_empty() {
+                closest = None;
+                break;
+            }
+            let other_type = resolve(root, branch)
+                .get("type")
+                .and_then(Value::as_str)
+                .is_some_and(|expected| expected != type_name(value));
+            if closest
+                .as_ref()
+                .is_none_or(|(o, c)| (other_type, found.len()) < (*o, c.len()))
+            {
+                closest = Some((other_type, found));
+            }
+        }
+        errors.extend(closest.map(|(_, found)| found).unwrap_or_default());
+    }
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        let actual = type_name(value);
+        if actual != expected && !(expected == "number" && actual == "integer") {
+            errors.push(format!("{}: expected {}, found {}", at, expected, actual));
+            return;
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let names: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            errors.push(format!(
+                "{}: {} is not one of {}",
+                at,
+                value,
+                names.join(", ")
+            ));
+        }
+    }
+    if let (Some(minimum), Some(n)) = (
+        schema.get("minimum").and_then(Value::as_f64),
+        value.as_f64(),
+    ) {
+        if n < minimum {
+            errors.push(format!("{}: {} is less than {}", at, n, minimum));
+        }
+    }
+    if let (Some("date-time"), Some(text)) =
+        (schema.get("format").and_then(Value::as_str), value.as_str())
+    {
+        if DateTime::parse_from_rfc3339(text).is_err() {
+            errors.push(format!("{}: {:?} is not an RFC 3339 date-time", at, text));
+        }
+    }
+    if let Value::Object(fields) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !fields.contains_key(name) {
+                errors.push(format!("{}: missing required field {:?}", at, name));
+            }
+        }
+        for (name, field) in fields {
+            let path = format!("{}/{}", at, name.replace('~', "~0").replace('/', "~1"));
+            match properties.and_then(|p| p.get(name)) {
+                Some(property) => check(root, property, field, &path, errors),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{}: unknown field {:?}", at, name))
+                    }
+                    Some(additional @ Value::Object(_)) => {
+                        check(root, additional, field, &path, errors)
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+    if let (Some(items), Value::Array(elements)) = (schema.get("items"), value) {
+        for (index, element) in elements.iter().enumerate() {
+            check(root, items, element, &format!("{}/{}", at, index), errors);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Importing notes from a Google Keep takeout (`Takeout/Keep/*.json`).
+//!
+//! A checklist note becomes one task per item, checked items completed; a text note becomes a
+//! single task titled after the note, with its text kept as `keep.text`. Labels become tags.
+//! Trashed notes are skipped and archived notes are imported as completed. Items record
+//! `external.id` so re-importing the same takeout does not create duplicates.
+
+use crate::{dates, normalize_tag, Task};
+use chrono::{DateTime, FixedOffset, Local};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Note {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    list_content: Vec<ListItem>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    is_trashed: bool,
+    #[serde(default)]
+    is_archived: bool,
+    #[serde(default)]
+    created_timestamp_usec: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListItem {
+    text: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+#[derive(Deserialize)]
+struct Label {
+    name: String,
+}
+
+/// The JSON files of a takeout: `dir` itself if it is a file, otherwise every `*.json` in it.
+fn note_files(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn created_at(note: &Note) -> DateTime<FixedOffset> {
+    note.created_timestamp_usec
+        .and_then(DateTime::from_timestamp_micros)
+        .map(|at| at.with_timezone(&Local))
+        .unwrap_or_else(dates::now)
+        .fixed_offset()
+}
+
+fn convert(note: Note, key: &str) -> Vec<Task> {
+    let tags: Vec<String> = note
+        .labels
+        .iter()
+        .map(|l| normalize_tag(&l.name))
+        .filter(|t| !t.is_empty())
+        .collect();
+    let title = note.title.trim().to_string();
+    let created_at = created_at(&note);
+    let mut tasks = vec![];
+    if note.list_content.is_empty() {
+        let text = note.text_content.trim();
+        let description = if title.is_empty() {
+            text.lines().next().unwrap_or("").trim().to_string()
+        } else {
+            title
+        };
+        if description.is_empty() {
+            return tasks;
+        }
+        let mut builder = Task::builder()
+            .description(description)
+            .completed(note.is_archived)
+            .tags(tags)
+            .metadata("external.id", format!("keep:{}", key));
+        if !text.is_empty() {
+            builder = builder.metadata("keep.text", text);
+        }
+        tasks.push(builder.build());
+    } else {
+        for (index, item) in note.list_content.iter().enumerate() {
+            if item.text.trim().is_empty() {
+                continue;
+            }
+            let mut builder = Task::builder()
+                .description(item.text.trim())
+                .completed(item.is_checked || note.is_archived)
+                .tags(tags.clone())
+                .metadata("external.id", format!("keep:{}#{}", key, index));
+            if !title.is_empty() {
+                builder = builder.metadata("keep.list", title.as_str());
+            }
+            tasks.push(builder.build());
+        }
+    }
+    for task in tasks.iter_mut() {
+        task.created_at = created_at;
+    }
+    tasks
+}
+
+/// Reads every note in the takeout directory (or a single note file) at `path`.
+pub fn parse_dir(path: &Path) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    let mut tasks = vec![];
+    for file in note_files(path)? {
+        let note: Note = serde_json::from_str(&fs::read_to_string(&file)?)
+            .map_err(|e| format!("{}: {}", file.display(), e))?;
+        if note.is_trashed {
+            continue;
+        }
+        let key = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        tasks.extend(convert(note, &key));
+    }
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn reads_checklists_and_text_notes_but_not_trashed_ones() {
+        let dir = TempDir::new();
+        let notes = [
+            (
+                "groceries.json",
+                r#"{"title": "Groceries", "labels": [{"name": "Home"}],
+                    "listContent": [{"text": "Milk", "isChecked": true}, {"text": "Eggs"}]}"#,
+            ),
+            (
+                "idea.json",
+                r#"{"title": "", "textContent": "Write a blog post\nabout Rust", "isArchived": true}"#,
+            ),
+            ("old.json", r#"{"title": "Gone", "isTrashed": true}"#),
+        ];
+        for (name, json) in notes {
+            fs::write(dir.path().join(name), json).unwrap();
+        }
+
+        let tasks = parse_dir(dir.path()).unwrap();
+        let read: Vec<(&str, bool, &str)> = tasks
+            .iter()
+            .map(|t| {
+                let external = t.metadata["external.id"].as_str();
+                (t.description.as_str(), t.completed, external)
+            })
+            .collect();
+        assert_eq!(
+            read,
+            [
+                ("Milk", true, "keep:groceries#0"),
+                ("Eggs", false, "keep:groceries#1"),
+                ("Write a blog post", true, "keep:idea"),
+            ]
+        );
+        assert_eq!(tasks[0].tags, ["home"]);
+        assert_eq!(
+            tasks[2].metadata["keep.text"],
+            "Write a blog post\nabout Rust"
+        );
+    }
+}
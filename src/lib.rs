@@ -0,0 +1,83 @@
+//! The rust-todo task model and list, usable without the command line.
+//!
+//! ```no_run
+//! use todo_core::{Task, TodoList};
+//!
+//! let mut list = TodoList::new();
+//! let id = list.add(Task::builder().description("Buy milk").build())?;
+//! list.complete(id)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`TodoList`] keeps its tasks in a [`Storage`]; [`TodoList::open`] uses the default JSON
+//! file. The format and sync modules work on a `TodoList` as well.
+
+mod list;
+mod storage;
+mod task;
+#[cfg(test)]
+mod testing;
+
+pub mod agenda;
+pub mod archive;
+pub mod bookmarks;
+pub mod calendar;
+pub mod collate;
+pub mod comments;
+pub mod config;
+pub mod csv;
+pub mod dates;
+pub mod dirs;
+pub mod duplicates;
+pub mod feed;
+pub mod filing;
+pub mod formats;
+pub mod git;
+pub mod habitica;
+pub mod hooks;
+pub mod ical;
+pub mod ids;
+pub mod import;
+pub mod journal;
+pub mod json_schema;
+pub mod keep;
+pub mod maildir;
+pub mod markdown;
+pub mod obsidian;
+pub mod omnifocus;
+pub mod oplog;
+pub mod outline;
+pub mod pdf;
+pub mod planner;
+pub mod queue;
+pub mod recurrence;
+pub mod report;
+pub mod rtm;
+pub mod scan;
+pub mod schema;
+pub mod search;
+pub mod select;
+pub mod snapshot;
+pub mod sqlite;
+pub mod stats;
+pub mod summary;
+pub mod tasklog;
+pub mod taskpaper;
+pub mod taskwarrior;
+pub mod templates;
+pub mod timelog;
+pub mod todoist;
+pub mod todotxt;
+pub mod trash;
+
+pub use list::{
+    parent_cycles, Completion, DueAlert, DueReminder, Filter, MergeReport, Move, SortBy, StaleTask,
+    Timings, TodoList, DEFAULT_LIST,
+};
+pub use sqlite::Sqlite;
+pub use storage::{Backend, JsonFile, Lock, Storage};
+pub use task::{
+    check_alias, normalize_tag, FollowUp, HasDescription, Interval, NoDescription, Note, Priority,
+    Reminder, Status, Task, TaskBuilder, TaskColor, WaitingOn,
+};
+pub use tasklog::TaskLog;
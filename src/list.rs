@@ -0,0 +1,2092 @@
+use crate::config::Stale;
+use crate::oplog::{self, Operation};
+use crate::storage::{JsonFile, Lock, Storage};
+use crate::templates::Template;
+use crate::trash::{self, Trashed};
+use crate::{
+    archive, collate, comments, config, dates, dirs, duplicates, git, hooks, ical, ids, journal,
+    normalize_tag, queue, snapshot, summary, Interval, Note, Priority, Reminder, Status, Task,
+};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, instrument, warn};
+
+/// Order of the tasks returned by [`TodoList::filter`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Order of creation.
+    #[default]
+    Id,
+    /// Highest priority first; tasks without a priority last.
+    Priority,
+    /// Earliest due date first; tasks without a due date last.
+    Due,
+    /// Oldest first by `created_at`, which imported tasks keep from their source.
+    Created,
+    /// Alphabetical by description, ignoring case and accents (see [`collate`](crate::collate)).
+    #[value(alias = "alpha")]
+    #[serde(alias = "alpha")]
+    Description,
+}
+
+/// Which tasks [`TodoList::filter`] returns, and in what order.
+#[derive(Debug, Default, Clone)]
+pub struct Filter {
+    /// `Some(true)` for completed tasks only, `Some(false)` for pending ones only.
+    pub completed: Option<bool>,
+    /// Only tasks that can be done at this place (case-insensitive).
+    pub near: Option<String>,
+    /// Only pending tasks waiting on someone, longest-waiting first.
+    pub waiting: bool,
+    /// Only tasks assigned to this person (case-insensitive).
+    pub assignee: Option<String>,
+    /// Only tasks in one of these [statuses](Task::status); any when empty.
+    pub status: Vec<Status>,
+    /// Only tasks due at or before this time.
+    pub due_before: Option<DateTime<Local>>,
+    /// Only pending tasks whose due date has passed.
+    pub overdue: bool,
+    /// Only tasks carrying every one of these tags.
+    pub tags: Vec<String>,
+    /// Only tasks in the inbox; see [`Task::in_inbox`].
+    pub inbox: bool,
+    /// Include the [archived](TodoList::archived) tasks.
+    pub archived: bool,
+    /// Include the tasks [snoozed](Task::is_snoozed) until later.
+    pub snoozed: bool,
+    pub sort: SortBy,
+    /// Reverse the whole order, after sorting.
+    pub reverse: bool,
+}
+
+/// Outcome of [`TodoList::complete`].
+#[derive(Debug)]
+pub enum Completion {
+    Completed(Box<Task>),
+    /// A repeating task was completed and its next occurrence added to the list.
+    Repeated {
+        completed: Box<Task>,
+        next: Box<Task>,
+    },
+    AlreadyCompleted,
+    NotFound,
+}
+
+/// Outcome of [`TodoList::move_to`].
+#[derive(Debug)]
+pub enum Move {
+    /// The task and `subtasks` of its own were moved, and the task is `id` in the other list.
+    Moved {
+        id: usize,
+        subtasks: usize,
+    },
+    /// Task `id` of those moving is in the other list already, so nothing was moved.
+    AlreadyThere(usize),
+    NotFound,
+}
+
+/// Counts returned by [`TodoList::merge`].
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    pub added: usize,
+    /// Tasks skipped because their `external.id` is already in the list.
+    pub duplicates: usize,
+}
+
+/// A reminder reported by [`TodoList::fire_reminders`].
+#[derive(Debug)]
+pub struct DueReminder {
+    pub id: usize,
+    pub description: String,
+    pub at: DateTime<Local>,
+}
+
+/// A notification that a task is coming due; see [`TodoList::due_alerts`].
+#[derive(Debug, Clone)]
+pub struct DueAlert {
+    pub id: usize,
+    pub description: String,
+    pub due: DateTime<Local>,
+    /// How long before the due date the alert is for; zero when the task is due.
+    pub lead: chrono::Duration,
+}
+
+/// Metadata keys recording which alert was last given for a task, and for which due date.
+const ALERTED_DUE: &str = "notify.due";
+const ALERTED_LEAD: &str = "notify.lead";
+
+/// A task [escalated](TodoList::escalate_stale) for being pending too long.
+#[derive(Debug, Clone)]
+pub struct StaleTask {
+    pub id: usize,
+    pub description: String,
+    /// How many days ago it was added.
+    pub days: i64,
+    /// Its priority after escalation.
+    pub priority: Option<Priority>,
+}
+
+/// Metadata key marking when a task was escalated for being stale.
+const STALE_KEY: &str = "stale.escalated";
+
+/// How much of a UUID, and of a description, [`TodoList::lookup`] needs to find a task by.
+const LOOKUP_UUID: usize = 8;
+const LOOKUP_PREFIX: usize = 3;
+
+/// Time spent in storage and filtering since the list was opened; see [`TodoList::timings`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timings {
+    pub load: Duration,
+    pub filter: Duration,
+    pub save: Duration,
+    /// Number of saves, which `save` adds up.
+    pub saves: u32,
+}
+
+/// Name of the list stored in the data file, used when no `--list` is given.
+pub const DEFAULT_LIST: &str = "default";
+
+pub struct TodoList {
+    pub(crate) tasks: Vec<Task>,
+    storage: Box<dyn Storage>,
+    timings: Cell<Timings>,
+    /// Why changes can't be saved, when the store turned out to be read-only on opening.
+    read_only: Option<String>,
+    /// The tasks as last loaded or saved, to find what a save changes for the operation log.
+    saved: RefCell<Vec<Task>>,
+    /// The store's [version](Storage::version) when the tasks were last loaded or saved.
+    version: Option<i64>,
+    /// New tasks that took another ID in the last save, because another process had used
+    /// theirs in the meantime.
+    renumbered: HashMap<usize, usize>,
+    /// Name recorded in the operation log for the changes saved from now on.
+    operation: String,
+    /// The archived tasks, read on first use.
+    archived: OnceCell<Vec<Task>>,
+    /// Set during [`batch`](Self::batch), when saving waits until the end.
+    deferred: bool,
+    /// Set by [`set_dry_run`](Self::set_dry_run), when nothing is written at all.
+    dry_run: bool,
+}
+
+impl TodoList {
+    /// Loads the default list.
+    ///
+    /// # Panics
+    ///
+    /// When there is no home directory to keep it in; use [`open`](Self::open) then.
+    pub fn new() -> Self {
+        Self::open(Self::get_data_path().expect("no location for the default list"))
+    }
+
+    /// Loads the task list stored at `path`; a missing file yields an empty list.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self::with_storage(Box::new(JsonFile::new(path)))
+    }
+
+    /// Loads the task list from any [`Storage`]. A store that cannot be read, e.g. one written
+    /// by a newer version, yields an empty list that is [read-only](Self::read_only), so the
+    /// stored tasks aren't overwritten.
+    pub fn with_storage(storage: Box<dyn Storage>) -> Self {
+        info!(path = %storage.path().display(), "using data file");
+        let read_only = storage.read_only();
+        if let Some(reason) = &read_only {
+            info!(reason = %reason, "task store is read-only");
+        }
+        let mut list = TodoList {
+            tasks: vec![],
+            storage,
+            timings: Cell::default(),
+            read_only,
+            saved: RefCell::default(),
+            version: None,
+            renumbered: HashMap::new(),
+            operation: "change".to_string(),
+            archived: OnceCell::new(),
+            deferred: false,
+            dry_run: false,
+        };
+        list.reload();
+        list
+    }
+
+    /// The default list's file for this platform; see [`dirs`](crate::dirs).
+    pub fn get_data_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(crate::dirs::data_file()?)
+    }
+
+    /// Directory holding the named lists, one `<name>.json` file each.
+    pub fn lists_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(crate::dirs::lists_dir()?)
+    }
+
+    /// Path of the JSON file for the list called `name`; [`DEFAULT_LIST`] is
+    /// [`get_data_path`](Self::get_data_path). Names are limited to letters, digits, `-` and `_`.
+    pub fn named_data_path(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if name == DEFAULT_LIST {
+            return Self::get_data_path();
+        }
+        let valid = name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        if name.is_empty() || !valid {
+            return Err(format!(
+                "invalid list name {:?}: use letters, digits, '-' and '_'",
+                name
+            )
+            .into());
+        }
+        Ok(Self::lists_dir()?.join(format!("{}.json", name)))
+    }
+
+    /// Names of the existing lists (stored as JSON or SQLite), [`DEFAULT_LIST`] first.
+    pub fn list_names() -> Vec<String> {
+        let mut names: Vec<String> = Self::lists_dir()
+            .ok()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().is_some_and(|ext| {
+                    ext == "json" || dirs::OTHER_STORES.iter().any(|o| ext == *o)
+                })
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        names.sort_by(|a, b| collate::compare(a, b));
+        names.dedup();
+        names.insert(0, DEFAULT_LIST.to_string());
+        names
+    }
+
+    /// Re-reads the tasks from storage, dropping unsaved changes.
+    #[instrument(skip_all, fields(path = %self.storage.path().display()))]
+    pub fn reload(&mut self) {
+        let start = Instant::now();
+        self.tasks = self.storage.load().unwrap_or_else(|e| {
+            warn!(error = %e, "failed to load task file, opening it read-only");
+            self.read_only = Some(format!("it could not be read: {}", e));
+            vec![]
+        });
+        let elapsed = start.elapsed();
+        self.record(|t| t.load += elapsed);
+        *self.saved.borrow_mut() = self.tasks.clone();
+        self.version = self.storage.version();
+        self.archived.take();
+        info!(count = self.tasks.len(), elapsed = ?elapsed, "tasks loaded");
+    }
+
+    /// Names the changes saved from now on in the operation log, e.g. after the command
+    /// making them.
+    pub fn set_operation(&mut self, name: impl Into<String>) {
+        self.operation = name.into();
+    }
+
+    /// From now on, changes stay in memory instead of being written, for `--dry-run`: saving,
+    /// undo and redo, the trash and the archive write nothing, and queued tasks stay queued.
+    /// [`unsaved`](Self::unsaved) tells what would have been saved.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Whether changes are kept from being written; see [`set_dry_run`](Self::set_dry_run).
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// What changed since the tasks were last loaded or saved.
+    pub fn unsaved(&self) -> Vec<oplog::Change> {
+        oplog::diff(&self.saved.borrow(), &self.tasks)
+    }
+
+    /// Writes the tasks to storage and appends what changed since the last load or save to
+    /// the operation log, so it can be undone.
+    ///
+    /// If another process saved the list in the meantime, these changes are applied on top
+    /// of what it saved rather than overwriting it: a task changed by both ends up as changed
+    /// here, and new tasks whose ID was taken in the meantime get the next free one (see
+    /// [`current_id`](Self::current_id)).
+    pub fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.deferred || self.dry_run {
+            return Ok(());
+        }
+        let events = {
+            let _lock = self.lock()?;
+            let changes = self.write()?;
+            self.log(changes)
+        };
+        self.run_hooks(&events);
+        Ok(())
+    }
+
+    /// Adds the tasks queued by `enqueue` (see [`queue`]) and saves. Returns how many there
+    /// were.
+    pub fn add_queued(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
+        if self.read_only.is_some() || self.dry_run || !queue::dir_for(self.path()).exists() {
+            return Ok(0);
+        }
+        // Under one lock, so two processes can't both add the same queued task.
+        let _lock = self.lock()?;
+        let queued = queue::pending(self.path());
+        if queued.is_empty() {
+            return Ok(0);
+        }
+        let mut files = vec![];
+        for (file, task) in queued {
+            self.insert(task);
+            files.push(file);
+        }
+        let changes = self.write()?;
+        let events = self.log(changes);
+        drop(_lock);
+        self.run_hooks(&events);
+        for file in &files {
+            if let Err(e) = std::fs::remove_file(file) {
+                warn!(error = %e, path = %file.display(), "could not remove a queued task");
+            }
+        }
+        Ok(files.len())
+    }
+
+    /// Appends `changes` to the operation log, so they can be undone, and returns the
+    /// [hooks](hooks) they set off.
+    fn log(&self, changes: Vec<oplog::Change>) -> Vec<hooks::Event> {
+        if changes.is_empty() {
+            return vec![];
+        }
+        let events = hooks::events(&config::current().hooks, &self.operation, &changes);
+        let log = oplog::path_for(self.path());
+        if let Err(e) = oplog::record(&log, &self.operation, changes) {
+            warn!(error = %e, path = %log.display(), "could not record the change for undo");
+        }
+        events
+    }
+
+    /// Runs the hooks for `events`, once the list is unlocked, so they can use it.
+    fn run_hooks(&self, events: &[hooks::Event]) {
+        if !events.is_empty() {
+            hooks::run(&config::current().hooks, self.path(), events);
+        }
+    }
+
+    /// Runs `change`, which may make many changes, and saves once at the end, so they are
+    /// written together and undone as one. If `change` fails, nothing is saved and its
+    /// changes are discarded.
+    pub fn batch<R>(
+        &mut self,
+        change: impl FnOnce(&mut Self) -> Result<R, Box<dyn std::error::Error>>,
+    ) -> Result<R, Box<dyn std::error::Error>> {
+        // IDs in the batch are only renumbered by the save at the end.
+        self.renumbered.clear();
+        self.deferred = true;
+        let result = change(self);
+        self.deferred = false;
+        match result {
+            Ok(result) => {
+                self.save()?;
+                Ok(result)
+            }
+            Err(e) => {
+                self.reload();
+                Err(e)
+            }
+        }
+    }
+
+    /// The ID task `id` has since the last save, which differs when it was added while
+    /// another process used the same ID.
+    pub fn current_id(&self, id: usize) -> usize {
+        self.renumbered.get(&id).copied().unwrap_or(id)
+    }
+
+    /// Takes the store's lock, after checking that it can be written at all.
+    fn lock(&self) -> Result<Lock, Box<dyn std::error::Error>> {
+        if let Some(reason) = &self.read_only {
+            return Err(format!(
+                "{} is read-only ({}), so changes can't be saved",
+                self.path().display(),
+                reason
+            )
+            .into());
+        }
+        Ok(Lock::acquire(self.path())?)
+    }
+
+    /// Saves the tasks, merged with anything saved by another process since they were
+    /// loaded, and returns what changed. The caller holds the lock.
+    #[instrument(skip_all, fields(path = %self.storage.path().display(), tasks = self.tasks.len()))]
+    fn write(&mut self) -> Result<Vec<oplog::Change>, Box<dyn std::error::Error>> {
+        self.renumbered.clear();
+        let version = self.storage.version();
+        if version.is_none() || version != self.version {
+            let start = Instant::now();
+            let stored = self.storage.load()?;
+            self.record(|t| t.load += start.elapsed());
+            if stored != *self.saved.borrow() {
+                info!("the list was saved by another process; merging");
+                self.rebase(stored);
+            }
+        }
+        let changes = oplog::diff(&self.saved.borrow(), &self.tasks);
+        let start = Instant::now();
+        self.storage.save(&self.tasks)?;
+        if let Err(e) = summary::write(self.path(), &self.tasks) {
+            warn!(error = %e, "could not write the summary");
+        }
+        let elapsed = start.elapsed();
+        self.record(|t| {
+            t.save += elapsed;
+            t.saves += 1;
+        });
+        *self.saved.borrow_mut() = self.tasks.clone();
+        self.version = self.storage.version();
+        debug!(elapsed = ?elapsed, "tasks saved");
+        Ok(changes)
+    }
+
+    /// Re-applies the unsaved changes on top of `stored`, the tasks another process saved.
+    fn rebase(&mut self, stored: Vec<Task>) {
+        let changes = oplog::diff(&self.saved.borrow(), &self.tasks);
+        let mut used: BTreeSet<usize> = stored.iter().chain(&self.tasks).map(|t| t.id).collect();
+        for change in &changes {
+            if change.before.is_none() && stored.iter().any(|t| t.id == change.id) {
+                let id = ids::next(&used);
+                used.insert(id);
+                self.renumbered.insert(change.id, id);
+            }
+        }
+        self.tasks = stored.clone();
+        *self.saved.borrow_mut() = stored;
+        for change in changes {
+            let base = change.before.as_ref();
+            let id = self.current_id(change.id);
+            let after = change.after.map(|mut task| {
+                task.id = id;
+                task.parent = task.parent.map(|parent| self.current_id(parent));
+                for dependency in &mut task.depends_on {
+                    *dependency = self.current_id(*dependency);
+                }
+                for other in &mut task.related {
+                    *other = self.current_id(*other);
+                }
+                // Changed there too: keep the fields only changed there.
+                match (base, self.get(id)) {
+                    (Some(base), Some(theirs)) if theirs != base => {
+                        git::merge_task(Some(base), &task, theirs).0
+                    }
+                    _ => task,
+                }
+            });
+            self.put(id, after);
+        }
+    }
+
+    /// The recorded changes to `task`, oldest first, from when it was last created: a deleted
+    /// task's ID can be given to a new one.
+    pub fn history_of(&self, task: &Task) -> Result<Vec<oplog::Event>, Box<dyn std::error::Error>> {
+        let history = oplog::history(&oplog::path_for(self.path()))?;
+        let mut events: Vec<oplog::Event> = oplog::events(&history)
+            .into_iter()
+            .filter(|event| event.id == task.id)
+            .collect();
+        if let Some(created) = events
+            .iter()
+            .rposition(|event| event.kind == oplog::EventKind::Created)
+        {
+            events.drain(..created);
+        }
+        Ok(events)
+    }
+
+    /// Reverts the last `count` operations in the log, most recent first, and returns them.
+    pub fn undo(&mut self, count: usize) -> Result<Vec<Operation>, Box<dyn std::error::Error>> {
+        let log = oplog::path_for(self.path());
+        let mut history = oplog::history(&log)?;
+        let mut undone = vec![];
+        while let Some(operation) = history.done.pop().filter(|_| undone.len() < count) {
+            for change in operation.changes.iter().rev() {
+                self.put(change.id, change.before.clone());
+            }
+            undone.push(operation);
+        }
+        if !undone.is_empty() && !self.dry_run {
+            let _lock = self.lock()?;
+            self.write()?;
+            for operation in &undone {
+                oplog::mark_undone(&log, operation.seq)?;
+            }
+            // The deleted tasks are back, so `trash restore` mustn't bring them back again.
+            let path = trash::path_for(self.path());
+            let mut trash = trash::load(&path)?;
+            let before = trash.len();
+            for task in undone.iter().flat_map(removed) {
+                if let Some(index) = trash.iter().rposition(|t| t.task == *task) {
+                    trash.remove(index);
+                }
+            }
+            if trash.len() != before {
+                trash::save(&path, &trash)?;
+            }
+        }
+        Ok(undone)
+    }
+
+    /// Re-applies the last `count` undone operations, oldest first, and returns them.
+    pub fn redo(&mut self, count: usize) -> Result<Vec<Operation>, Box<dyn std::error::Error>> {
+        let log = oplog::path_for(self.path());
+        let mut history = oplog::history(&log)?;
+        let mut redone = vec![];
+        while let Some(operation) = history.undone.pop().filter(|_| redone.len() < count) {
+            for change in &operation.changes {
+                self.put(change.id, change.after.clone());
+            }
+            redone.push(operation);
+        }
+        if !redone.is_empty() && !self.dry_run {
+            {
+                let _lock = self.lock()?;
+                self.write()?;
+                for operation in &redone {
+                    oplog::mark_redone(&log, operation.seq)?;
+                }
+            }
+            // Undo took them out of the trash; deleting them again puts them back.
+            let trashed = redone
+                .iter()
+                .filter(|operation| TRASHING_OPERATIONS.contains(&operation.name.as_str()))
+                .flat_map(removed)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !trashed.is_empty() {
+                self.move_to_trash(trashed)?;
+            }
+        }
+        Ok(redone)
+    }
+
+    /// Replaces task `id` with `task`, or removes it for `None`, keeping the list in ID order.
+    fn put(&mut self, id: usize, task: Option<Task>) {
+        self.tasks.retain(|t| t.id != id);
+        if let Some(task) = task {
+            let index = self.tasks.partition_point(|t| t.id < id);
+            self.tasks.insert(index, task);
+        }
+    }
+
+    /// How long loading, filtering and saving have taken so far.
+    pub fn timings(&self) -> Timings {
+        self.timings.get()
+    }
+
+    fn record(&self, change: impl FnOnce(&mut Timings)) {
+        let mut timings = self.timings.get();
+        change(&mut timings);
+        self.timings.set(timings);
+    }
+
+    /// Why the list can't be saved, if its store was read-only when it was opened. Tasks can
+    /// still be read and filtered.
+    pub fn read_only(&self) -> Option<&str> {
+        self.read_only.as_deref()
+    }
+
+    /// The file backing the list.
+    pub fn path(&self) -> &Path {
+        self.storage.path()
+    }
+
+    /// [Compacts](Storage::compact) the store, under the lock.
+    pub fn compact(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
+        // Only still current if no other process saved since the tasks were loaded.
+        let current = self.version.is_some() && self.version == self.storage.version();
+        self.storage.compact()?;
+        if current {
+            self.version = self.storage.version();
+        }
+        Ok(())
+    }
+
+    pub fn storage(&self) -> &dyn Storage {
+        self.storage.as_ref()
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().filter(|t| !t.completed)
+    }
+
+    /// Tasks waiting to be triaged; see [`Task::in_inbox`].
+    pub fn inbox(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().filter(|t| t.in_inbox())
+    }
+
+    pub fn completed(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().filter(|t| t.completed)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| t.id == id)
+    }
+
+    /// Whether a task with the same `external.id` (the source system's ID) already exists.
+    pub fn has_external_id(&self, task: &Task) -> bool {
+        task.metadata.get("external.id").is_some_and(|external| {
+            self.tasks
+                .iter()
+                .any(|t| t.metadata.get("external.id") == Some(external))
+        })
+    }
+
+    /// Appends `task` under a new [ID](crate::ids), and a UUID if it has none, without saving
+    /// and returns that ID.
+    pub fn insert(&mut self, mut task: Task) -> usize {
+        task.id = ids::next(&self.tasks.iter().map(|t| t.id).collect());
+        if task.uuid.is_none() {
+            task.uuid = Some(ids::uuid());
+        }
+        let id = task.id;
+        self.tasks.push(task);
+        id
+    }
+
+    /// The tasks `name` could mean, as given on the command line instead of an ID:
+    ///
+    /// 1. the task with that alias, ignoring case: the pending one, or else the one completed
+    ///    last;
+    /// 2. the tasks whose UUID starts with it, for 8 characters or more;
+    /// 3. the tasks with that description, compared [folded](collate::fold);
+    /// 4. the tasks whose description starts with it, for 3 characters or more.
+    ///
+    /// Pending tasks are looked for before completed ones at each step, and the first step
+    /// to find any decides.
+    pub fn lookup(&self, name: &str) -> Vec<usize> {
+        let find = |matches: &dyn Fn(&Task) -> bool| -> Vec<usize> {
+            let pending: Vec<usize> = self
+                .pending()
+                .filter(|t| matches(t))
+                .map(|t| t.id)
+                .collect();
+            if !pending.is_empty() {
+                return pending;
+            }
+            self.tasks
+                .iter()
+                .filter(|t| matches(t))
+                .map(|t| t.id)
+                .collect()
+        };
+        let aliased = find(&|t| {
+            t.alias
+                .as_deref()
+                .is_some_and(|alias| alias.eq_ignore_ascii_case(name))
+        });
+        if let [.., last] = aliased.as_slice() {
+            let pending = self.get(*last).is_some_and(|t| !t.completed);
+            return if pending { aliased } else { vec![*last] };
+        }
+        let uuid = name.to_lowercase();
+        if uuid.len() >= LOOKUP_UUID && uuid.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            let found = find(&|t| t.uuid.as_deref().is_some_and(|u| u.starts_with(&uuid)));
+            if !found.is_empty() {
+                return found;
+            }
+        }
+        let folded = collate::fold(name.trim()).text;
+        let described = |t: &Task| collate::fold(&t.description).text;
+        let exact = find(&|t| described(t) == folded);
+        if !exact.is_empty() || folded.chars().count() < LOOKUP_PREFIX {
+            return exact;
+        }
+        find(&|t| described(t).starts_with(&folded))
+    }
+
+    /// Direct subtasks of task `id`, in ID order.
+    pub fn children(&self, id: usize) -> impl DoubleEndedIterator<Item = &Task> {
+        self.tasks.iter().filter(move |t| t.parent == Some(id))
+    }
+
+    /// Checks that task `id`, or a task not added yet when `None`, can be made a subtask of
+    /// `parent`: that `parent` exists, and isn't `id` itself or below it, which would make a
+    /// cycle.
+    pub fn check_parent(&self, id: Option<usize>, parent: usize) -> Result<(), String> {
+        if id == Some(parent) {
+            return Err(format!("task {} can't be its own parent", parent));
+        }
+        let mut seen = BTreeSet::new();
+        let mut next = Some(parent);
+        while let Some(ancestor) = next {
+            if let Some(id) = id.filter(|id| *id == ancestor) {
+                return Err(format!(
+                    "task {} is a subtask of task {}, so it can't be its parent",
+                    parent, id
+                ));
+            }
+            if !seen.insert(ancestor) {
+                return Err(format!("task {} is in a cycle of subtasks", parent));
+            }
+            match self.get(ancestor) {
+                Some(task) => next = task.parent,
+                None if ancestor == parent => {
+                    return Err(format!("there is no task {} to add a subtask to", parent))
+                }
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// IDs of the pending tasks `task` depends on. Dependencies that were completed, archived
+    /// or deleted don't hold it up.
+    pub fn blockers(&self, task: &Task) -> Vec<usize> {
+        task.depends_on
+            .iter()
+            .copied()
+            .filter(|id| self.get(*id).is_some_and(|t| !t.completed))
+            .collect()
+    }
+
+    /// IDs of the tasks that depend on task `id`.
+    pub fn dependents(&self, id: usize) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .filter(|t| t.depends_on.contains(&id))
+            .map(|t| t.id)
+            .collect()
+    }
+
+    /// Makes task `id` depend on the tasks in `on`, or stop depending on them with `remove`,
+    /// and saves. Fails when a task doesn't exist or a dependency would go round in a circle.
+    pub fn depend(
+        &mut self,
+        id: usize,
+        on: &[usize],
+        remove: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(missing) = on.iter().find(|other| self.get(**other).is_none()) {
+            return Err(format!("task {} not found", missing).into());
+        }
+        if !remove {
+            if let Some(other) = on
+                .iter()
+                .find(|other| **other == id || self.depends_on(**other, id))
+            {
+                return Err(format!(
+                    "task {} can't depend on task {}, which already waits for it",
+                    id, other
+                )
+                .into());
+            }
+        }
+        let Some(task) = self.get_mut(id) else {
+            return Err(format!("task {} not found", id).into());
+        };
+        if remove {
+            task.depends_on.retain(|other| !on.contains(other));
+        } else {
+            for other in on {
+                if !task.depends_on.contains(other) {
+                    task.depends_on.push(*other);
+                }
+            }
+            task.depends_on.sort_unstable();
+        }
+        self.save()
+    }
+
+    /// Links task `id` to task `other` as related, or unlinks them with `remove`, whichever
+    /// of the two the link was made from. Returns `false` when there was nothing to change.
+    pub fn link(
+        &mut self,
+        id: usize,
+        other: usize,
+        remove: bool,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        if let Some(missing) = [id, other].into_iter().find(|id| self.get(*id).is_none()) {
+            return Err(format!("task {} not found", missing).into());
+        }
+        if id == other {
+            return Err("a task can't be linked to itself".into());
+        }
+        let linked = |list: &Self, from: usize, to: usize| {
+            list.get(from).is_some_and(|t| t.related.contains(&to))
+        };
+        if remove {
+            if !linked(self, id, other) && !linked(self, other, id) {
+                return Ok(false);
+            }
+            for (from, to) in [(id, other), (other, id)] {
+                if let Some(task) = self.get_mut(from) {
+                    task.related.retain(|related| *related != to);
+                }
+            }
+        } else {
+            if linked(self, id, other) || linked(self, other, id) {
+                return Ok(false);
+            }
+            if let Some(task) = self.get_mut(id) {
+                task.related.push(other);
+                task.related.sort_unstable();
+            }
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// IDs of the tasks that link to task `id`, in ID order.
+    pub fn backlinks(&self, id: usize) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .filter(|t| t.related.contains(&id))
+            .map(|t| t.id)
+            .collect()
+    }
+
+    /// Whether task `id` depends on task `other`, directly or through other tasks.
+    fn depends_on(&self, id: usize, other: usize) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(next) = stack.pop() {
+            if !seen.insert(next) {
+                continue;
+            }
+            let Some(task) = self.get(next) else { continue };
+            if task.depends_on.contains(&other) {
+                return true;
+            }
+            stack.extend(&task.depends_on);
+        }
+        false
+    }
+
+    /// The tasks that can be worked on now, most important first: pending, not waiting on
+    /// anyone, and with no pending dependencies or subtasks. They are ordered by priority,
+    /// then due date, then ID.
+    pub fn actionable(&self) -> Vec<&Task> {
+        let now = dates::now();
+        let mut tasks: Vec<&Task> = self
+            .tasks
+            .iter()
+            .filter(|t| !t.completed && t.waiting_on.is_none())
+            .filter(|t| !t.is_snoozed(now))
+            .filter(|t| self.blockers(t).is_empty())
+            .filter(|t| self.children(t.id).all(|child| child.completed))
+            .collect();
+        tasks.sort_by_key(|t| (std::cmp::Reverse(t.priority), t.due.is_none(), t.due, t.id));
+        tasks
+    }
+
+    /// The tasks with `status`, and `tag` if given, in the order a board shows them: done
+    /// tasks most recent first, the others by priority, then due date, then ID.
+    pub fn column(&self, status: Status, tag: Option<&str>) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self
+            .tasks
+            .iter()
+            .filter(|t| t.status() == status)
+            .filter(|t| tag.is_none_or(|tag| t.tags.iter().any(|t| t == tag)))
+            .collect();
+        if status == Status::Done {
+            tasks.sort_by_key(|t| std::cmp::Reverse(t.id));
+        } else {
+            tasks.sort_by_key(|t| (std::cmp::Reverse(t.priority), t.due.is_none(), t.due, t.id));
+        }
+        tasks
+    }
+
+    /// The places pending tasks are at, each under the first spelling seen and in order
+    /// without case, with how many pending tasks are there.
+    pub fn contexts(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<String, (String, usize)> = BTreeMap::new();
+        for task in self.pending() {
+            if let Some(place) = &task.location {
+                counts
+                    .entry(place.to_lowercase())
+                    .or_insert_with(|| (place.clone(), 0))
+                    .1 += 1;
+            }
+        }
+        counts.into_values().collect()
+    }
+
+    /// IDs of every task below `id`, depth first.
+    pub fn descendants(&self, id: usize) -> Vec<usize> {
+        let mut ids = vec![];
+        let mut stack: Vec<usize> = self.children(id).map(|t| t.id).rev().collect();
+        while let Some(next) = stack.pop() {
+            if ids.contains(&next) {
+                continue; // a cycle in hand-edited data
+            }
+            ids.push(next);
+            stack.extend(self.children(next).map(|t| t.id).rev());
+        }
+        ids
+    }
+
+    /// Points the subtasks of a removed task at its own parent, so they stay in the tree, and
+    /// drops it from what other tasks depend on and link to.
+    fn adopt_children(&mut self, removed: &Task) {
+        for task in &mut self.tasks {
+            if task.parent == Some(removed.id) {
+                task.parent = removed.parent;
+            }
+            task.depends_on.retain(|id| *id != removed.id);
+            task.related.retain(|id| *id != removed.id);
+        }
+    }
+
+    /// Inserts `task`, saves, and returns its ID.
+    pub fn add(&mut self, task: Task) -> Result<usize, Box<dyn std::error::Error>> {
+        let id = self.insert(task);
+        self.save()?;
+        Ok(self.current_id(id))
+    }
+
+    /// Adds the tasks of `template`, due relative to `start`, under each other and depending
+    /// on each other as the template says, and saves them together. Returns their IDs, in the
+    /// template's order.
+    pub fn add_template(
+        &mut self,
+        template: &Template,
+        start: DateTime<Local>,
+    ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+        template.check()?;
+        let ids = self.batch(|list| {
+            let ids: Vec<usize> = template
+                .tasks(start)
+                .into_iter()
+                .map(|task| list.insert(task))
+                .collect();
+            for (item, id) in template.tasks.iter().zip(&ids) {
+                if let Some(task) = list.get_mut(*id) {
+                    task.parent = item.parent.map(|position| ids[position - 1]);
+                }
+                let on: Vec<usize> = item.depends_on.iter().map(|p| ids[p - 1]).collect();
+                list.depend(*id, &on, false)?;
+            }
+            Ok(ids)
+        })?;
+        Ok(ids.into_iter().map(|id| self.current_id(id)).collect())
+    }
+
+    /// Applies `change` to task `id` and saves. Returns `None` when there is no such task.
+    pub fn update<R>(
+        &mut self,
+        id: usize,
+        change: impl FnOnce(&mut Task) -> R,
+    ) -> Result<Option<R>, Box<dyn std::error::Error>> {
+        let Some(task) = self.get_mut(id) else {
+            return Ok(None);
+        };
+        let result = change(task);
+        self.save()?;
+        Ok(Some(result))
+    }
+
+    /// Inserts the tasks that are not already present (by `external.id`) and saves.
+    pub fn merge(
+        &mut self,
+        tasks: impl IntoIterator<Item = Task>,
+    ) -> Result<MergeReport, Box<dyn std::error::Error>> {
+        let mut report = MergeReport::default();
+        // Imported tasks get new IDs, so subtasks are re-linked to their parent's new ID.
+        let mut new_ids = HashMap::new();
+        let mut merged = vec![];
+        for task in tasks {
+            if self.has_external_id(&task) {
+                report.duplicates += 1;
+                continue;
+            }
+            let old = task.id;
+            let id = self.insert(task);
+            new_ids.insert(old, id);
+            merged.push(id);
+            debug!(id, "merged task");
+            report.added += 1;
+        }
+        for id in merged {
+            if let Some(task) = self.get_mut(id) {
+                task.parent = task.parent.and_then(|old| new_ids.get(&old).copied());
+                task.depends_on = task
+                    .depends_on
+                    .iter()
+                    .filter_map(|old| new_ids.get(old).copied())
+                    .collect();
+                task.related = task
+                    .related
+                    .iter()
+                    .filter_map(|old| new_ids.get(old).copied())
+                    .collect();
+            }
+        }
+        if report.added > 0 {
+            self.save()?;
+        }
+        Ok(report)
+    }
+
+    /// Creates one task per TODO found in grep output. TODOs that already have a task for the
+    /// same file and text are skipped, only refreshing their line number. Returns the number
+    /// of tasks added and skipped.
+    pub fn add_from_grep(
+        &mut self,
+        input: &str,
+    ) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        let (mut added, mut skipped) = (0, 0);
+        for line in input.lines() {
+            let Some(hit) = comments::parse_grep_line(line) else {
+                continue;
+            };
+            let description =
+                comments::todo_text(&hit.text).unwrap_or_else(|| hit.text.trim().to_string());
+            if description.is_empty() {
+                continue;
+            }
+            let existing = self.tasks.iter_mut().find(|t| {
+                t.description == description && t.metadata.get("source.file") == Some(&hit.file)
+            });
+            if let Some(task) = existing {
+                if let Some(line) = hit.line {
+                    task.metadata
+                        .insert("source.line".to_string(), line.to_string());
+                }
+                skipped += 1;
+                continue;
+            }
+            let mut builder = Task::builder()
+                .description(description)
+                .metadata("source.file", hit.file);
+            if let Some(line) = hit.line {
+                builder = builder.metadata("source.line", line.to_string());
+            }
+            self.insert(builder.build());
+            added += 1;
+        }
+        if added + skipped > 0 {
+            self.save()?;
+        }
+        Ok((added, skipped))
+    }
+
+    pub fn filter(&self, filter: &Filter) -> Vec<&Task> {
+        let start = Instant::now();
+        let mut tasks: Vec<&Task> = match filter.completed {
+            Some(true) => self.completed().collect(),
+            Some(false) => self.pending().collect(),
+            None => self.tasks.iter().collect(),
+        };
+        if filter.archived && filter.completed != Some(false) {
+            tasks.extend(self.archived());
+        }
+        if let Some(place) = &filter.near {
+            tasks.retain(|t| {
+                t.location
+                    .as_deref()
+                    .is_some_and(|l| l.eq_ignore_ascii_case(place))
+            });
+        }
+        if let Some(person) = &filter.assignee {
+            tasks.retain(|t| {
+                t.assignee
+                    .as_deref()
+                    .is_some_and(|a| a.eq_ignore_ascii_case(person))
+            });
+        }
+        if !filter.tags.is_empty() {
+            let wanted: Vec<String> = filter.tags.iter().map(|t| normalize_tag(t)).collect();
+            tasks.retain(|t| wanted.iter().all(|tag| t.tags.contains(tag)));
+        }
+        if let Some(limit) = filter.due_before {
+            tasks.retain(|t| t.due.is_some_and(|due| due <= limit));
+        }
+        if filter.inbox {
+            tasks.retain(|t| t.in_inbox());
+        }
+        if filter.overdue {
+            let now = dates::now();
+            tasks.retain(|t| t.is_overdue(now));
+        }
+        if !filter.snoozed {
+            let now = dates::now();
+            tasks.retain(|t| !t.is_snoozed(now));
+        }
+        if !filter.status.is_empty() {
+            tasks.retain(|t| filter.status.contains(&t.status()));
+        }
+        if filter.waiting {
+            tasks.retain(|t| !t.completed && t.waiting_on.is_some());
+            tasks.sort_by_key(|t| t.waiting_on.as_ref().map(|w| w.since));
+        }
+        // Sorts are stable, so ties keep their ID (or waiting) order.
+        match filter.sort {
+            SortBy::Id => {}
+            SortBy::Priority => tasks.sort_by_key(|t| std::cmp::Reverse(t.priority)),
+            SortBy::Due => tasks.sort_by_key(|t| (t.due.is_none(), t.due)),
+            SortBy::Created => tasks.sort_by_key(|t| t.created_at),
+            SortBy::Description => {
+                tasks.sort_by(|a, b| collate::compare(&a.description, &b.description))
+            }
+        }
+        if filter.reverse {
+            tasks.reverse();
+        }
+        let elapsed = start.elapsed();
+        self.record(|t| t.filter += elapsed);
+        info!(
+            matched = tasks.len(),
+            total = self.tasks.len(),
+            "filter applied"
+        );
+        tasks
+    }
+
+    /// Marks task `id` as completed, saves, and records it in the journal. A repeating task
+    /// gets its next occurrence added as a new task, due on the rule's next date, and a task
+    /// with a [follow-up](crate::FollowUp) gets it added, linked back to it.
+    pub fn complete(&mut self, id: usize) -> Result<Completion, Box<dyn std::error::Error>> {
+        let Some(task) = self.get_mut(id) else {
+            return Ok(Completion::NotFound);
+        };
+        if task.completed {
+            return Ok(Completion::AlreadyCompleted);
+        }
+        let now = dates::now();
+        // Worked out first, so a rule that can't step any further leaves the task as it was.
+        let next_due = match &task.repeat {
+            Some(rule) => {
+                let due = task
+                    .due
+                    .unwrap_or_else(|| dates::end_of_day(now.date_naive()).unwrap_or(now));
+                Some(rule.next_due(due, now)?)
+            }
+            None => None,
+        };
+        task.set_status(Status::Done);
+        if let Some(running) = task.time.last_mut().filter(|i| i.end.is_none()) {
+            running.end = Some(now);
+        }
+        // The rule moves on to the next occurrence, so the completed one no longer repeats.
+        let repeat = task.repeat.take();
+        let task = task.clone();
+        let next = repeat.map(|rule| {
+            let mut next = task.clone();
+            next.uuid = None;
+            next.set_status(Status::Todo);
+            next.created_at = now.fixed_offset();
+            next.due = next_due;
+            next.repeat = Some(rule);
+            next.reminders.clear();
+            next.waiting_on = None;
+            next.snoozed_until = None;
+            next.notes.clear();
+            next.time.clear();
+            // The next occurrence is a new task, not the one an importer or a sync created.
+            next.metadata
+                .retain(|key, _| key != "external.id" && !key.starts_with("caldav."));
+            next
+        });
+        let next = next.map(|next| self.insert(next));
+        if let Some(follow_up) = &task.follow_up {
+            self.insert(follow_up.task(&task, now));
+        }
+        self.save()?;
+        let result = match next {
+            Some(next) => Completion::Repeated {
+                completed: Box::new(task.clone()),
+                next: Box::new(
+                    self.get(self.current_id(next))
+                        .cloned()
+                        .expect("just inserted"),
+                ),
+            },
+            None => Completion::Completed(Box::new(task.clone())),
+        };
+        journal::record(&task);
+        Ok(result)
+    }
+
+    /// Adds a reminder to task `id`. Returns `false` when there is no such task.
+    pub fn remind_at(
+        &mut self,
+        id: usize,
+        at: DateTime<Local>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let added = self.update(id, |task| {
+            task.reminders.push(Reminder {
+                at,
+                delivered: false,
+            });
+            task.reminders.sort_by_key(|r| r.at);
+        })?;
+        Ok(added.is_some())
+    }
+
+    /// The task whose timer is running; only one runs at a time.
+    pub fn running(&self) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.is_running())
+    }
+
+    /// Stops the running timer at `now` and saves. Returns the task it ran on and how long
+    /// it ran, or `None` when no timer was running.
+    pub fn stop_timer(
+        &mut self,
+        now: DateTime<Local>,
+    ) -> Result<Option<(Task, chrono::Duration)>, Box<dyn std::error::Error>> {
+        let Some(id) = self.running().map(|t| t.id) else {
+            return Ok(None);
+        };
+        self.update(id, |task| {
+            let interval = task.time.last_mut().expect("the timer is running");
+            interval.end = Some(now);
+            let ran = now - interval.start;
+            (task.clone(), ran)
+        })
+    }
+
+    /// Starts a timer on task `id` at `now` and saves, stopping the one running on another
+    /// task and moving a task that wasn't started to in progress. Returns the stopped timer as [`stop_timer`](Self::stop_timer) does.
+    pub fn start_timer(
+        &mut self,
+        id: usize,
+        now: DateTime<Local>,
+    ) -> Result<Option<(Task, chrono::Duration)>, Box<dyn std::error::Error>> {
+        self.batch(|list| {
+            let stopped = list.stop_timer(now)?;
+            list.update(id, |task| {
+                task.time.push(Interval {
+                    start: now,
+                    end: None,
+                });
+                if task.status() == Status::Todo {
+                    task.set_status(Status::InProgress);
+                }
+            })?;
+            Ok(stopped)
+        })
+    }
+
+    /// Adds a note to task `id`, timestamped now. Returns `false` when there is no such task.
+    pub fn add_note(&mut self, id: usize, text: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let added = self.update(id, |task| {
+            task.notes.push(Note {
+                at: dates::now(),
+                text: text.to_string(),
+            })
+        })?;
+        Ok(added.is_some())
+    }
+
+    /// Returns every undelivered reminder whose time has passed, then marks it delivered so
+    /// each reminder fires once.
+    pub fn fire_reminders(
+        &mut self,
+        now: DateTime<Local>,
+    ) -> Result<Vec<DueReminder>, Box<dyn std::error::Error>> {
+        let mut fired = vec![];
+        for task in self.tasks.iter_mut().filter(|t| !t.completed) {
+            for reminder in task.reminders.iter_mut() {
+                if !reminder.delivered && reminder.at <= now {
+                    fired.push(DueReminder {
+                        id: task.id,
+                        description: task.description.clone(),
+                        at: reminder.at,
+                    });
+                    reminder.delivered = true;
+                }
+            }
+        }
+        if !fired.is_empty() {
+            self.save()?;
+        }
+        Ok(fired)
+    }
+
+    /// Returns an alert for each pending task that has come within one of the `leads` of its
+    /// due date since it was last alerted, and saves that it was. When several lead times
+    /// have passed, as when the daemon wasn't running, only the shortest is alerted. Moving
+    /// the due date starts over.
+    pub fn due_alerts(
+        &mut self,
+        leads: &[chrono::Duration],
+        now: DateTime<Local>,
+    ) -> Result<Vec<DueAlert>, Box<dyn std::error::Error>> {
+        let mut alerts = vec![];
+        for task in self.tasks.iter_mut().filter(|t| !t.completed) {
+            let Some(due) = task.due else { continue };
+            let Some(lead) = leads.iter().filter(|lead| due - **lead <= now).min() else {
+                continue;
+            };
+            let stamp = due.timestamp().to_string();
+            let alerted = task
+                .metadata
+                .get(ALERTED_LEAD)
+                .and_then(|minutes| minutes.parse::<i64>().ok())
+                .filter(|_| task.metadata.get(ALERTED_DUE) == Some(&stamp));
+            if alerted.is_some_and(|minutes| minutes <= lead.num_minutes()) {
+                continue;
+            }
+            task.metadata.insert(ALERTED_DUE.into(), stamp);
+            task.metadata
+                .insert(ALERTED_LEAD.into(), lead.num_minutes().to_string());
+            alerts.push(DueAlert {
+                id: task.id,
+                description: task.description.clone(),
+                due,
+                lead: *lead,
+            });
+        }
+        if !alerts.is_empty() {
+            self.save()?;
+        }
+        Ok(alerts)
+    }
+
+    /// Escalates pending tasks added more than `rule.after_days` days before `now`: each gets
+    /// the rule's tag and, if it asks for it, the next higher priority (medium when it had
+    /// none). A task is escalated once, so removing the tag or lowering the priority again
+    /// sticks. Saves and returns the tasks escalated.
+    pub fn escalate_stale(
+        &mut self,
+        rule: &Stale,
+        now: DateTime<Local>,
+    ) -> Result<Vec<StaleTask>, Box<dyn std::error::Error>> {
+        let Some(cutoff) =
+            now.checked_sub_signed(chrono::Duration::days(i64::from(rule.after_days)))
+        else {
+            return Ok(vec![]);
+        };
+        let mut escalated = vec![];
+        for task in self.tasks.iter_mut().filter(|t| !t.completed) {
+            if task.created_at > cutoff || task.metadata.contains_key(STALE_KEY) {
+                continue;
+            }
+            task.add_tag(&rule.tag);
+            if rule.bump_priority {
+                task.priority = Some(match task.priority {
+                    None | Some(Priority::Low) => Priority::Medium,
+                    Some(Priority::Medium) => Priority::High,
+                    Some(Priority::High | Priority::Critical) => Priority::Critical,
+                });
+            }
+            task.metadata.insert(STALE_KEY.into(), now.to_rfc3339());
+            escalated.push(StaleTask {
+                id: task.id,
+                description: task.description.clone(),
+                days: (now - task.created_at.with_timezone(&Local)).num_days(),
+                priority: task.priority,
+            });
+        }
+        if !escalated.is_empty() {
+            self.save()?;
+        }
+        Ok(escalated)
+    }
+
+    /// Moves task `id` to the [trash](crate::trash) and saves. Returns the removed task.
+    pub fn delete(&mut self, id: usize) -> Result<Option<Task>, Box<dyn std::error::Error>> {
+        let Some(index) = self.tasks.iter().position(|t| t.id == id) else {
+            return Ok(None);
+        };
+        self.move_to_trash(vec![self.tasks[index].clone()])?;
+        let task = self.tasks.remove(index);
+        self.adopt_children(&task);
+        self.save()?;
+        Ok(Some(task))
+    }
+
+    /// The groups of pending tasks with descriptions at least `threshold` alike (see
+    /// [`duplicates::groups`]), as IDs with the oldest task first.
+    pub fn duplicate_groups(&self, threshold: f64) -> Vec<Vec<usize>> {
+        let pending: Vec<&Task> = self.pending().collect();
+        duplicates::groups(&pending, threshold)
+            .iter()
+            .map(|group| group.iter().map(|t| t.id).collect())
+            .collect()
+    }
+
+    /// Merges each of `groups` into its first task, in one save so `undo` takes back every
+    /// merge together.
+    pub fn merge_duplicate_groups(
+        &mut self,
+        groups: &[Vec<usize>],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.batch(|list| {
+            for group in groups {
+                list.merge_duplicates(group[0], group)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Merges tasks `others` into task `keep` (see [`duplicates::absorb`]), points subtasks,
+    /// dependencies and links on them at it instead, moves them to the trash and saves.
+    /// Returns `false` when there is no task `keep`.
+    pub fn merge_duplicates(
+        &mut self,
+        keep: usize,
+        others: &[usize],
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let others: Vec<usize> = others.iter().copied().filter(|id| *id != keep).collect();
+        let merged: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|t| others.contains(&t.id))
+            .cloned()
+            .collect();
+        let Some(keeper) = self.get_mut(keep) else {
+            return Ok(false);
+        };
+        for other in &merged {
+            duplicates::absorb(keeper, other);
+        }
+        let redirect = |id: usize| if others.contains(&id) { keep } else { id };
+        for task in &mut self.tasks {
+            task.parent = task.parent.map(redirect).filter(|id| *id != task.id);
+            for ids in [&mut task.depends_on, &mut task.related] {
+                let mut seen = HashSet::new();
+                ids.retain_mut(|id| {
+                    *id = redirect(*id);
+                    *id != task.id && seen.insert(*id)
+                });
+            }
+        }
+        if !merged.is_empty() {
+            self.move_to_trash(merged)?;
+            self.tasks.retain(|t| !others.contains(&t.id));
+        }
+        debug!(keep, ?others, "merged duplicates");
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Removes tasks `ids` without putting them in the trash, for tasks that live on
+    /// elsewhere, and saves. Returns the removed tasks.
+    pub fn remove(&mut self, ids: &[usize]) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+        let (removed, kept): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|t| ids.contains(&t.id));
+        self.tasks = kept;
+        for task in &removed {
+            self.adopt_children(task);
+        }
+        if !removed.is_empty() {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Moves task `id` and its subtasks to `target`, saving both lists; a subtask whose
+    /// parent doesn't move goes to the top level. Each keeps the iCalendar UID calendars and
+    /// syncs know it by as its `external.id`. When the list can't be saved, the tasks are taken
+    /// back out of `target`.
+    pub fn move_to(
+        &mut self,
+        id: usize,
+        target: &mut TodoList,
+    ) -> Result<Move, Box<dyn std::error::Error>> {
+        if self.get(id).is_none() {
+            return Ok(Move::NotFound);
+        }
+        let mut ids = vec![id];
+        ids.extend(self.descendants(id));
+        let mut moving: Vec<Task> = ids.iter().filter_map(|id| self.get(*id)).cloned().collect();
+        for task in &mut moving {
+            // Its new ID would change the UID calendars and syncs know it by, so it keeps this one.
+            if !task.metadata.contains_key("external.id") {
+                task.metadata
+                    .insert("external.id".into(), format!("ical:{}", ical::uid(task)));
+            }
+            if task.parent.is_some_and(|parent| !ids.contains(&parent)) {
+                task.parent = None;
+            }
+        }
+        let externals: Vec<String> = moving
+            .iter()
+            .filter_map(|t| t.metadata.get("external.id").cloned())
+            .collect();
+        if let Some(task) = moving.iter().find(|t| target.has_external_id(t)) {
+            return Ok(Move::AlreadyThere(task.id));
+        }
+        target.merge(moving)?;
+        if let Err(e) = self.remove(&ids) {
+            let added: Vec<usize> = target
+                .tasks()
+                .iter()
+                .filter(|t| {
+                    t.metadata
+                        .get("external.id")
+                        .is_some_and(|external| externals.contains(external))
+                })
+                .map(|t| t.id)
+                .collect();
+            target.remove(&added)?;
+            return Err(e);
+        }
+        let new_id = target
+            .tasks()
+            .iter()
+            .find(|t| t.metadata.get("external.id") == externals.first())
+            .map_or(id, |t| t.id);
+        Ok(Move::Moved {
+            id: new_id,
+            subtasks: ids.len() - 1,
+        })
+    }
+
+    /// Adds `tasks` to the trash, dropping those older than the configured `trash_days`.
+    fn move_to_trash(&self, tasks: Vec<Task>) -> Result<(), Box<dyn std::error::Error>> {
+        if self.dry_run {
+            return Ok(());
+        }
+        let _lock = self.lock()?;
+        let path = trash::path_for(self.path());
+        let mut trash = trash::load(&path)?;
+        let now = dates::now();
+        trash.extend(tasks.into_iter().map(|task| Trashed {
+            deleted_at: now,
+            task,
+        }));
+        if let Some(cutoff) = trash::configured_cutoff(now) {
+            trash::purge_before(&mut trash, cutoff);
+        }
+        trash::save(&path, &trash)
+    }
+
+    /// The tasks in the trash, oldest first.
+    pub fn trashed(&self) -> Result<Vec<Trashed>, Box<dyn std::error::Error>> {
+        trash::load(&trash::path_for(self.path()))
+    }
+
+    /// Puts the task most recently trashed with ID `id` back in the list and saves. It keeps
+    /// its ID unless another task has taken it, and its parent and dependencies if they are
+    /// still there.
+    /// Returns the task's ID, or `None` when no such task is in the trash.
+    pub fn restore_trashed(
+        &mut self,
+        id: usize,
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        let mut task = {
+            let _lock = self.lock()?;
+            let path = trash::path_for(self.path());
+            let mut trash = trash::load(&path)?;
+            let Some(index) = trash.iter().rposition(|t| t.task.id == id) else {
+                return Ok(None);
+            };
+            let trashed = trash.remove(index);
+            if !self.dry_run {
+                trash::save(&path, &trash)?;
+            }
+            trashed.task
+        };
+        // Already back, e.g. through `undo`.
+        if self.tasks.contains(&task) {
+            return Ok(Some(id));
+        }
+        if task.parent.is_some_and(|parent| self.get(parent).is_none()) {
+            task.parent = None;
+        }
+        task.depends_on
+            .retain(|dependency| self.get(*dependency).is_some());
+        task.related.retain(|other| self.get(*other).is_some());
+        let id = if self.get(id).is_none() {
+            self.put(id, Some(task));
+            id
+        } else {
+            self.insert(task)
+        };
+        self.save()?;
+        Ok(Some(self.current_id(id)))
+    }
+
+    /// Deletes every task in the trash for good. Returns how many there were.
+    pub fn empty_trash(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
+        let path = trash::path_for(self.path());
+        let count = trash::load(&path)?.len();
+        if !self.dry_run {
+            trash::save(&path, &[])?;
+        }
+        Ok(count)
+    }
+
+    /// Tasks whose description or one of whose notes matches `pattern`.
+    pub fn matching(&self, pattern: &Regex) -> Vec<&Task> {
+        self.tasks.iter().filter(|t| mentions(t, pattern)).collect()
+    }
+
+    /// Removes every task whose description or notes match `pattern` for good: from the list,
+    /// and from everything kept next to it, i.e. the operation log (so `undo` and `redo` can't
+    /// bring them back), the trash, the snapshots, the archive and the
+    /// [journal](crate::journal). Returns how many were removed from the list, and how many
+    /// copies from the rest. A git repository of [`sync git`](crate::git) keeps its history.
+    pub fn purge(&mut self, pattern: &Regex) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        let (removed, kept): (Vec<Task>, Vec<Task>) =
+            std::mem::take(&mut self.tasks)
+                .into_iter()
+                .partition::<Vec<_>, _>(|t| mentions(t, pattern));
+        self.tasks = kept;
+        for task in &removed {
+            self.adopt_children(task);
+        }
+        if self.dry_run {
+            return Ok((removed.len(), 0));
+        }
+        if !removed.is_empty() {
+            self.save()?;
+        }
+        // Also earlier versions of the removed tasks, under another description.
+        let uuids: BTreeSet<&str> = removed.iter().filter_map(|t| t.uuid.as_deref()).collect();
+        let forget = |task: &Task| {
+            mentions(task, pattern)
+                || task
+                    .uuid
+                    .as_deref()
+                    .is_some_and(|uuid| uuids.contains(uuid))
+        };
+        let _lock = self.lock()?;
+        let path = self.path();
+        let copies = oplog::forget(&oplog::path_for(path), forget)?
+            + trash::forget(&trash::path_for(path), forget)?
+            + snapshot::forget(path, forget)?
+            + archive::forget(&archive::path_for(path), forget)?
+            // The journal only has the description at the time, and the task's ID.
+            + journal::forget(|description, id| {
+                pattern.is_match(description) || removed.iter().any(|t| t.id == id)
+            })?;
+        // Stores that keep earlier versions of tasks, such as the log backend, drop them.
+        self.storage.compact()?;
+        self.version = self.storage.version();
+        self.archived = OnceCell::new();
+        Ok((removed.len(), copies))
+    }
+
+    /// Tasks moved to the [archive](crate::archive), read from it on first use. An archive
+    /// that can't be read counts as empty, with a warning.
+    pub fn archived(&self) -> &[Task] {
+        self.archived.get_or_init(|| {
+            archive::load(&archive::path_for(self.path())).unwrap_or_else(|e| {
+                warn!(error = %e, "could not read the archive");
+                vec![]
+            })
+        })
+    }
+
+    /// Whether `task` is one of the [archived](Self::archived) tasks.
+    pub fn is_archived(&self, task: &Task) -> bool {
+        self.archived
+            .get()
+            .is_some_and(|archived| archived.as_ptr_range().contains(&(task as *const Task)))
+    }
+
+    /// Moves the completed tasks created before `cutoff` to the archive and saves. A task
+    /// stays while any of its subtasks does. Returns how many were moved.
+    pub fn archive(
+        &mut self,
+        cutoff: DateTime<Local>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let old = |task: &Task| task.completed && task.created_at < cutoff;
+        let mut moving: HashSet<usize> =
+            self.tasks.iter().filter(|t| old(t)).map(|t| t.id).collect();
+        loop {
+            let needed: Vec<usize> = self
+                .tasks
+                .iter()
+                .filter(|t| !moving.contains(&t.id))
+                .filter_map(|t| t.parent)
+                .filter(|parent| moving.contains(parent))
+                .collect();
+            if needed.is_empty() {
+                break;
+            }
+            for parent in needed {
+                moving.remove(&parent);
+            }
+        }
+        if moving.is_empty() {
+            return Ok(0);
+        }
+        let moved: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|t| moving.contains(&t.id))
+            .cloned()
+            .collect();
+        if !self.dry_run {
+            let _lock = self.lock()?;
+            archive::append(&archive::path_for(self.path()), &moved)?;
+        }
+        self.tasks.retain(|t| !moving.contains(&t.id));
+        self.save()?;
+        if let Some(cache) = self.archived.get_mut() {
+            cache.extend_from_slice(&moved);
+        }
+        Ok(moved.len())
+    }
+
+    /// Moves every task to the trash and saves. Returns how many were removed.
+    pub fn clear(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
+        let count = self.tasks.len();
+        if count > 0 {
+            self.move_to_trash(self.tasks.clone())?;
+        }
+        self.tasks.clear();
+        self.save()?;
+        Ok(count)
+    }
+
+    /// Writes the tasks as last saved to a recovery [snapshot](crate::snapshot) named after
+    /// `reason`, and returns the snapshot's name.
+    pub fn snapshot(&self, reason: &str) -> Result<String, Box<dyn std::error::Error>> {
+        snapshot::take(self.path(), reason, &self.saved.borrow())
+    }
+
+    /// Takes a snapshot of the list as last loaded or saved, tagged `name` (see [`snapshot`]).
+    pub fn tag_snapshot(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        snapshot::tag(self.path(), name, &self.saved.borrow())
+    }
+
+    /// Replaces the tasks with those in snapshot `name` and saves, after taking a snapshot of
+    /// the current tasks. Returns the new snapshot's name.
+    pub fn restore(&mut self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let tasks = snapshot::load(self.path(), name)?;
+        let before = self.snapshot("restore")?;
+        self.tasks = tasks;
+        self.save()?;
+        Ok(before)
+    }
+}
+
+/// IDs of the tasks in `tasks` that are in a cycle of parents, such as a task that is its
+/// own parent, in the order of `tasks`. Only hand-edited or imported data has them.
+pub fn parent_cycles(tasks: &[Task]) -> Vec<usize> {
+    let parents: HashMap<usize, usize> = tasks
+        .iter()
+        .filter_map(|t| Some((t.id, t.parent?)))
+        .collect();
+    // Each task is walked up from once: `done` holds the ones already walked.
+    let mut done = HashSet::new();
+    let mut cycles = HashSet::new();
+    for task in tasks {
+        let mut path = vec![];
+        let mut next = Some(task.id);
+        while let Some(id) = next.filter(|id| !done.contains(id)) {
+            if let Some(start) = path.iter().position(|p| *p == id) {
+                cycles.extend(path[start..].iter().copied());
+                break;
+            }
+            path.push(id);
+            next = parents.get(&id).copied();
+        }
+        done.extend(path);
+    }
+    tasks
+        .iter()
+        .map(|t| t.id)
+        .filter(|id| cycles.contains(id))
+        .collect()
+}
+
+impl Default for TodoList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The operations, as named in the log, whose removed tasks went to the trash.
+const TRASHING_OPERATIONS: &[&str] = &["delete", "clear", "dedupe"];
+
+/// The tasks `operation` removed from the list.
+fn removed(operation: &Operation) -> impl Iterator<Item = &Task> {
+    operation
+        .changes
+        .iter()
+        .filter(|change| change.after.is_none())
+        .filter_map(|change| change.before.as_ref())
+}
+
+/// Whether `pattern` matches the description or one of the notes of `task`.
+fn mentions(task: &Task, pattern: &Regex) -> bool {
+    pattern.is_match(&task.description) || task.notes.iter().any(|n| pattern.is_match(&n.text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    fn task(description: &str) -> Task {
+        Task::builder().description(description).build()
+    }
+
+    fn files_under(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .flat_map(|entry| match entry.path() {
+                path if path.is_dir() => files_under(&path),
+                path => vec![path],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn saves_are_applied_on_top_of_another_process_saving() {
+        let dir = TempDir::new();
+        let path = dir.path().join("tasks.json");
+        let mut first = TodoList::open(&path);
+        let id = first.add(task("Buy milk")).unwrap();
+        let mut second = TodoList::open(&path);
+
+        first.complete(id).unwrap();
+        let added = first.add(task("Added first")).unwrap();
+        second.update(id, |t| t.add_tag("shop")).unwrap();
+        let renumbered = second.add(task("Added second")).unwrap();
+        // It wanted the ID the first one took, and was given the next.
+        assert_eq!(renumbered, added + 1);
+
+        let list = TodoList::open(&path);
+        let milk = list.get(id).unwrap();
+        assert!(milk.completed);
+        assert_eq!(milk.tags, ["shop"]);
+        let descriptions: Vec<&str> = list
+            .tasks()
+            .iter()
+            .map(|t| t.description.as_str())
+            .collect();
+        assert_eq!(descriptions, ["Buy milk", "Added first", "Added second"]);
+        assert_eq!(second.tasks(), list.tasks());
+    }
+
+    #[test]
+    fn a_task_changed_on_both_sides_keeps_the_fields_changed_elsewhere() {
+        let dir = TempDir::new();
+        let path = dir.path().join("tasks.json");
+        let mut first = TodoList::open(&path);
+        let id = first.add(task("Buy milk")).unwrap();
+        let mut second = TodoList::open(&path);
+
+        first
+            .update(id, |t| t.priority = Some(Priority::High))
+            .unwrap();
+        second
+            .update(id, |t| t.description = "Buy oat milk".into())
+            .unwrap();
+
+        let task = TodoList::open(&path).get(id).cloned().unwrap();
+        assert_eq!(task.description, "Buy oat milk");
+        assert_eq!(task.priority, Some(Priority::High));
+    }
+
+    #[test]
+    fn each_save_can_be_undone() {
+        let dir = TempDir::new();
+        let path = dir.path().join("tasks.json");
+        let mut list = TodoList::open(&path);
+        let id = list.add(task("Buy milk")).unwrap();
+        list.update(id, |t| t.description = "Buy oat milk".into())
+            .unwrap();
+
+        assert_eq!(list.undo(1).unwrap().len(), 1);
+        assert_eq!(
+            TodoList::open(&path).get(id).unwrap().description,
+            "Buy milk"
+        );
+        list.undo(1).unwrap();
+        assert!(TodoList::open(&path).tasks().is_empty());
+        list.redo(2).unwrap();
+        assert_eq!(
+            TodoList::open(&path).get(id).unwrap().description,
+            "Buy oat milk"
+        );
+    }
+
+    #[test]
+    fn undoing_a_delete_takes_the_task_out_of_the_trash() {
+        let dir = TempDir::new();
+        let path = dir.path().join("tasks.json");
+        let mut list = TodoList::open(&path);
+        let id = list.add(task("Buy milk")).unwrap();
+        list.set_operation("delete");
+        list.delete(id).unwrap();
+        assert_eq!(list.trashed().unwrap().len(), 1);
+
+        list.undo(1).unwrap();
+        assert!(list.get(id).is_some());
+        assert!(list.trashed().unwrap().is_empty());
+        assert_eq!(list.restore_trashed(id).unwrap(), None);
+
+        list.redo(1).unwrap();
+        assert!(list.get(id).is_none());
+        assert_eq!(list.trashed().unwrap()[0].task.id, id);
+    }
+
+    #[test]
+    fn purge_cannot_be_undone() {
+        let dir = TempDir::new();
+        let path = dir.path().join("tasks.json");
+        let mut list = TodoList::open(&path);
+        let secret = list.add(task("Rotate the key")).unwrap();
+        list.add(task("Buy milk")).unwrap();
+        list.update(secret, |t| t.description = "Rotate the key hunter2".into())
+            .unwrap();
+
+        let pattern = Regex::new("hunter2").unwrap();
+        assert_eq!(list.purge(&pattern).unwrap().0, 1);
+        list.undo(10).unwrap();
+        list.redo(10).unwrap();
+
+        let list = TodoList::open(&path);
+        assert!(list.tasks().iter().all(|t| t.id != secret));
+        // Nor under its description from before it matched.
+        let log = fs::read_to_string(oplog::path_for(&path)).unwrap();
+        assert!(!log.contains("Rotate the key"));
+        assert!(oplog::history(&oplog::path_for(&path))
+            .unwrap()
+            .undone
+            .iter()
+            .all(|operation| operation.changes.iter().all(|c| c.id != secret)));
+    }
+
+    #[test]
+    fn purge_reaches_the_trash_snapshots_and_archive() {
+        let dir = TempDir::new();
+        let path = dir.path().join("tasks.json");
+        let mut list = TodoList::open(&path);
+        let trashed = list.add(task("api key one")).unwrap();
+        list.delete(trashed).unwrap();
+        list.add(task("api key two")).unwrap();
+        list.snapshot("test").unwrap();
+        list.tag_snapshot("kept").unwrap();
+        let archived = list.add(task("api key three")).unwrap();
+        list.complete(archived).unwrap();
+        list.archive(Local::now() + chrono::Duration::days(1))
+            .unwrap();
+        list.add(task("Buy milk")).unwrap();
+
+        let (removed, copies) = list.purge(&Regex::new("api key").unwrap()).unwrap();
+        assert_eq!(removed, 1);
+        assert!(copies >= 4);
+        let leaks: Vec<PathBuf> = files_under(dir.path())
+            .into_iter()
+            .filter(|path| fs::read_to_string(path).is_ok_and(|text| text.contains("api key")))
+            .collect();
+        assert!(leaks.is_empty(), "still in {:?}", leaks);
+        assert_eq!(list.tasks().len(), 1);
+    }
+
+    #[test]
+    fn purge_matches_notes() {
+        let dir = TempDir::new();
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        let noted = list.add(task("Log in to the bank")).unwrap();
+        list.add_note(noted, "password is hunter2").unwrap();
+        list.add(task("Buy milk")).unwrap();
+
+        let pattern = Regex::new("hunter2").unwrap();
+        assert_eq!(list.matching(&pattern).len(), 1);
+        assert_eq!(list.purge(&pattern).unwrap().0, 1);
+        assert!(list.get(noted).is_none());
+    }
+
+    #[test]
+    fn a_parent_cannot_be_below_the_task() {
+        let dir = TempDir::new();
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        let a = list.add(task("A")).unwrap();
+        let b = list.add(task("B")).unwrap();
+        let c = list.add(task("C")).unwrap();
+        list.update(b, |t| t.parent = Some(a)).unwrap();
+        list.update(c, |t| t.parent = Some(b)).unwrap();
+
+        assert!(list.check_parent(Some(a), a).is_err());
+        assert!(list.check_parent(Some(a), c).is_err());
+        assert!(list.check_parent(Some(c), a).is_ok());
+        assert!(list.check_parent(None, c).is_ok());
+        assert!(list.check_parent(None, 99).is_err());
+        // Already in a cycle, from a hand-edited file.
+        list.update(a, |t| t.parent = Some(c)).unwrap();
+        assert!(list.check_parent(None, b).is_err());
+    }
+
+    #[test]
+    fn moving_a_task_takes_its_subtasks_along() {
+        let dir = TempDir::new();
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        let mut other = TodoList::open(dir.path().join("work.json"));
+        other.add(task("Already here")).unwrap();
+        let parent = list.add(task("Plan the trip")).unwrap();
+        let child = list.add(task("Book the flights")).unwrap();
+        list.update(child, |t| t.parent = Some(parent)).unwrap();
+        list.add(task("Buy milk")).unwrap();
+
+        let Move::Moved { id, subtasks } = list.move_to(parent, &mut other).unwrap() else {
+            panic!("not moved");
+        };
+        assert_eq!(subtasks, 1);
+        assert_eq!(other.get(id).unwrap().description, "Plan the trip");
+        assert!(other
+            .children(id)
+            .any(|t| t.description == "Book the flights"));
+        assert_eq!(list.tasks().len(), 1);
+        // It keeps its UID, so moving a copy back in is refused.
+        let back = other.get(id).unwrap().clone();
+        list.merge([back]).unwrap();
+        let returned = list.tasks().last().unwrap().id;
+        assert!(matches!(
+            list.move_to(returned, &mut other).unwrap(),
+            Move::AlreadyThere(_)
+        ));
+        assert!(matches!(
+            list.move_to(99, &mut other).unwrap(),
+            Move::NotFound
+        ));
+    }
+
+    #[test]
+    fn contexts_count_pending_tasks_under_the_first_spelling() {
+        let dir = TempDir::new();
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        for (description, place) in [("Buy milk", "Shop"), ("Buy eggs", "shop"), ("Call", "home")] {
+            let id = list.add(task(description)).unwrap();
+            list.update(id, |t| t.location = Some(place.into()))
+                .unwrap();
+        }
+        list.complete(3).unwrap();
+        assert_eq!(list.contexts(), [("Shop".to_string(), 2)]);
+        assert!(list.get(1).unwrap().in_context(Some("SHOP")));
+        assert!(!list.get(1).unwrap().in_context(Some("home")));
+    }
+
+    #[test]
+    fn parent_cycles_finds_only_the_tasks_in_them() {
+        let mut tasks: Vec<Task> = (1..=6)
+            .map(|id| {
+                let mut task = task(&id.to_string());
+                task.id = id;
+                task
+            })
+            .collect();
+        // 1 -> 2 -> 3 -> 1, 4 below the cycle, 5 its own parent, 6 on its own.
+        for (id, parent) in [(1, 2), (2, 3), (3, 1), (4, 1), (5, 5)] {
+            tasks[id - 1].parent = Some(parent);
+        }
+        assert_eq!(parent_cycles(&tasks), vec![1, 2, 3, 5]);
+        assert!(parent_cycles(&tasks[3..4]).is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Turning e-mails in a Maildir into tasks.
+
+use crate::Task;
+use mailparse::MailHeaderMap;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+/// Which messages become tasks.
+pub struct Rules {
+    pub unread: bool,
+    pub flagged: bool,
+    pub from: Option<Regex>,
+    pub subject: Option<Regex>,
+}
+
+struct Message {
+    subject: String,
+    from: String,
+    message_id: Option<String>,
+}
+
+/// Maildir info flags from a file name such as `1700000000.M1P2.host:2,FS`.
+fn flags(name: &str) -> &str {
+    name.rsplit_once(":2,")
+        .map(|(_, flags)| flags)
+        .unwrap_or("")
+}
+
+fn read_message(path: &Path) -> Result<Message, Box<dyn std::error::Error>> {
+    let bytes = fs::read(path)?;
+    let (headers, _) = mailparse::parse_headers(&bytes)?;
+    Ok(Message {
+        subject: headers
+            .get_first_value("Subject")
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        from: headers.get_first_value("From").unwrap_or_default(),
+        message_id: headers
+            .get_first_value("Message-ID")
+            .map(|id| {
+                id.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+            .filter(|id| !id.is_empty()),
+    })
+}
+
+/// Scans `new/` and `cur/` of the Maildir at `root` and returns a task for every message
+/// selected by `rules`. A message qualifies when it is unread (with `rules.unread`) or flagged
+/// (with `rules.flagged`), and matches the optional sender and subject patterns.
+pub fn ingest(root: &Path, rules: &Rules) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    if !root.join("cur").is_dir() && !root.join("new").is_dir() {
+        return Err(format!("{} is not a Maildir (no cur/ or new/)", root.display()).into());
+    }
+    let mut tasks = Vec::new();
+    for sub in ["new", "cur"] {
+        let dir = root.join(sub);
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            let flags = flags(name);
+            if flags.contains('T') {
+                continue;
+            }
+            let unread = sub == "new" || !flags.contains('S');
+            let flagged = flags.contains('F');
+            if !((rules.unread && unread) || (rules.flagged && flagged)) {
+                continue;
+            }
+            let message = match read_message(&path) {
+                Ok(message) => message,
+                Err(e) => {
+                    debug!(path = %path.display(), error = %e, "skipping unreadable message");
+                    continue;
+                }
+            };
+            if rules
+                .from
+                .as_ref()
+                .is_some_and(|re| !re.is_match(&message.from))
+                || rules
+                    .subject
+                    .as_ref()
+                    .is_some_and(|re| !re.is_match(&message.subject))
+            {
+                continue;
+            }
+            let description = if message.subject.is_empty() {
+                format!("E-mail from {}", message.from)
+            } else {
+                message.subject
+            };
+            let mut builder = Task::builder()
+                .description(description)
+                .tags(vec!["email".to_string()])
+                .metadata("email.from", message.from);
+            if let Some(id) = message.message_id {
+                builder = builder
+                    .metadata("url", format!("mid:{}", id))
+                    .metadata("external.id", format!("message-id:{}", id))
+                    .metadata("email.message_id", id);
+            }
+            tasks.push(builder.build());
+        }
+    }
+    Ok(tasks)
+}
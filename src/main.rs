@@ -1,7 +1,60 @@
-use clap::{Parser, Subcommand};
-use serde::{Deserialize, Serialize};
+use chrono::Local;
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use error::Error;
+use output::OutputFormat;
+use regex::Regex;
+use status::StatusFormat;
+use std::ffi::OsString;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use todo_core::{
+    agenda::{self, Reason},
+    archive,
+    config::{self, Color, Hyperlinks},
+    dates, dirs, duplicates, feed,
+    formats::{self, Format},
+    git,
+    import::{self, Duplicates},
+    json_schema, keep, maildir, markdown, obsidian, oplog, outline, parent_cycles, queue,
+    recurrence::Rule,
+    report, scan,
+    search::{self, Field, Matcher},
+    select::{self, Selector, Target, TaskRef},
+    snapshot, stats, summary,
+    templates::{self, Template},
+    timelog, trash, Backend, Completion, Filter, FollowUp, JsonFile, Lock, Move, Priority, SortBy,
+    Status, Storage, Task, TodoList, WaitingOn, DEFAULT_LIST,
+};
+use tracing::{debug, info, info_span};
+use tracing_subscriber::EnvFilter;
+
+mod access;
+mod attach;
+mod batch;
+mod bench;
+mod caldav;
+mod clipboard;
+mod completions;
+mod dedupe;
+mod doctor;
+mod edit;
+mod error;
+mod init;
+mod matrix;
+mod notify;
+mod output;
+mod plugin;
+mod pomodoro;
+mod quick;
+mod rpc;
+mod serve;
+mod shell;
+mod status;
+mod triage;
+mod update;
 
 #[derive(Parser)]
 #[command(name = "rust-todo")]
@@ -9,186 +62,5081 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace; overridden by RUST_LOG)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Write log output to this file instead of stderr
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// Where tasks are stored [default: `backend` in the config file, or json]
+    #[arg(long, global = true, value_enum)]
+    backend: Option<Backend>,
+    /// Print how long loading, filtering and saving took to stderr
+    #[arg(long, global = true)]
+    timings: bool,
+    /// Which named list to use (see list-lists) [default: the project's .todo.json, `list` in
+    /// the config file, or "default"]
+    #[arg(long, global = true, value_name = "NAME")]
+    list: Option<String>,
+    /// Use the project's list: the .todo.json here or in the nearest directory above, or a new
+    /// one here when there is none
+    #[arg(long, global = true, conflicts_with_all = ["list", "global"])]
+    local: bool,
+    /// Use your own lists, even in a project with a .todo.json
+    #[arg(long, global = true)]
+    global: bool,
+    /// Keep the tasks in this file, or in tasks.json in this directory, with the named lists
+    /// next to it, e.g. on a USB stick (also RUST_TODO_DATA)
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "local")]
+    data_file: Option<PathBuf>,
+    /// When to use colour [default: `color` in the config file, or auto]
+    #[arg(long, global = true, value_enum, value_name = "WHEN")]
+    color: Option<Color>,
+    /// Pretend it is this time, e.g. "monday" or "2024-05-06 08:30" (also RUST_TODO_NOW)
+    #[arg(long, global = true, hide = true, value_name = "WHEN")]
+    now: Option<String>,
+    #[command(flatten)]
+    safety: Safety,
+}
+
+/// `--yes` and `--dry-run`, which every command takes.
+#[derive(Args, Clone, Copy, Default)]
+struct Safety {
+    /// Go ahead without asking, e.g. before deleting tasks or changing several at once
+    #[arg(short, long, global = true)]
+    yes: bool,
+    /// Show what the command would change, without saving anything
+    #[arg(long, global = true)]
+    dry_run: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Add a new task to the to-do list
-    Add {
+    Add(AddArgs),
+    /// Add a task from one line of text, reading #tags, !priority and a due date such as
+    /// "tomorrow 3pm" from it, e.g. 'Call dentist tomorrow 3pm #health !high'
+    Quick {
+        /// The text; quote it, in single quotes if it has a !priority
+        #[arg(required = true, num_args = 1..)]
+        text: Vec<String>,
+    },
+    /// Queue a task for the list without waiting for other rust-todo processes; it is added
+    /// the next time the list is used. For scripts and cron jobs running at the same time
+    Enqueue {
         /// The task description
         description: String,
+        /// Tag to add (repeatable)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
     },
     /// List all tasks
-    List {
-        /// Show only completed tasks
-        #[arg(short, long)]
-        completed: bool,
-        /// Show only pending tasks
+    List(ListArgs),
+    /// Show every detail of a task: dates, dependencies, time tracked, notes and history
+    Show {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        #[arg(long, value_enum, default_value_t = ShowFormat::Text)]
+        format: ShowFormat,
+    },
+    /// List the inbox: pending tasks not yet filed with tags, a priority or a due date
+    Inbox,
+    /// Walk through the inbox, filing each task with tags, a priority and a due date
+    Triage,
+    /// Find tasks by description, tag, location, person or imported metadata, best match first
+    Search(SearchArgs),
+    /// Change a task's priority
+    Priority {
+        /// IDs, ranges or aliases of the tasks (e.g. 3 5 7-10), then the new priority: low,
+        /// medium, high or critical
+        #[arg(required = true, value_name = "ID|LEVEL")]
+        words: Vec<String>,
+        /// Act on the tasks matching KEY=VALUE instead (see `complete --help`)
+        #[arg(long, value_name = "KEY=VALUE", value_parser = Selector::parse)]
+        filter: Vec<Selector>,
+    },
+    /// Add tags to tasks, or remove them with --remove
+    Tag {
+        /// IDs, ranges or aliases of the tasks (e.g. 3 5 7-10), then the tags to add or
+        /// remove; write a tag that is a number as #2024
+        #[arg(required = true, value_name = "ID|TAG")]
+        words: Vec<String>,
+        /// Act on the tasks matching KEY=VALUE instead; every word is then a tag
+        #[arg(long, value_name = "KEY=VALUE", value_parser = Selector::parse)]
+        filter: Vec<Selector>,
+        /// Remove the tags instead of adding them
         #[arg(short, long)]
-        pending: bool,
+        remove: bool,
+    },
+    /// Change a task's description, due date, priority, tags or repeat rule, or edit it in $EDITOR
+    Edit {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        #[command(flatten)]
+        args: edit::EditArgs,
     },
     /// Mark a task as complete
+    #[command(visible_alias = "done")]
     Complete {
-        /// The ID of the task to complete
-        id: usize,
+        #[command(flatten)]
+        targets: Targets,
+        /// Also complete all of their pending subtasks
+        #[arg(short, long)]
+        recursive: bool,
+        /// Reopen the tasks that are already completed instead
+        #[arg(long)]
+        toggle: bool,
+    },
+    /// Return completed or cancelled tasks to pending
+    Reopen {
+        #[command(flatten)]
+        targets: Targets,
     },
-    /// Delete a task
+    /// Delete tasks
     Delete {
-        /// The ID of the task to delete
-        id: usize,
+        #[command(flatten)]
+        targets: Targets,
     },
     /// Clear all tasks
-    Clear {
-        /// Confirm clearing all tasks
+    Clear,
+    /// Show or empty the trash of deleted tasks
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+    /// Bring a deleted task back from the trash
+    Restore {
+        /// The ID the task had
+        id: usize,
+    },
+    /// Move old completed tasks to the archive, out of the way of list and search
+    Archive {
+        /// Archive completed tasks created more than this many days ago
+        #[arg(long, value_name = "DAYS", default_value_t = 30)]
+        older_than: u32,
+    },
+    /// Rewrite the list's store without the older versions of tasks it keeps, e.g. those
+    /// appended by --backend log
+    Compact,
+    /// List or restore the recovery snapshots taken before clear and import
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+    /// Revert the last changes to the list
+    Undo {
+        /// How many changes to revert
+        #[arg(default_value_t = 1)]
+        count: usize,
+    },
+    /// Re-apply changes reverted with undo
+    Redo {
+        /// How many changes to re-apply
+        #[arg(default_value_t = 1)]
+        count: usize,
+    },
+    /// Show the named lists and how many tasks each has
+    ListLists,
+    /// Create a new, empty named list
+    NewList {
+        /// Name of the list (letters, digits, '-' and '_')
+        name: String,
+    },
+    /// Delete a named list and all its tasks
+    DeleteList {
+        /// Name of the list
+        name: String,
+    },
+    /// Move a task and its subtasks to another list, or to a list file
+    MoveTo {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// The named list to move it to
+        #[arg(
+            value_name = "LIST",
+            required_unless_present = "file",
+            conflicts_with = "file"
+        )]
+        to: Option<String>,
+        /// Move it to this list file instead, which is created if needed
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+    },
+    /// Add notes to a task or read them
+    Note {
+        #[command(subcommand)]
+        action: NoteAction,
+    },
+    /// Attach files or web addresses to a task, or remove one
+    Attach {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// Files or web addresses, e.g. ./spec.pdf or https://example.com/issues/4
+        #[arg(value_name = "FILE|URL", required_unless_present = "remove")]
+        targets: Vec<String>,
+        /// Remove the attachment with this number (see show) instead
+        #[arg(long, value_name = "N", conflicts_with = "targets")]
+        remove: Option<usize>,
+    },
+    /// Open a task's attachment with the default application
+    Open {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// Number of the attachment (see show); needed when there are several
+        #[arg(short, long, value_name = "N")]
+        attachment: Option<usize>,
+    },
+    /// Add a reminder to a task (a task can have several)
+    RemindAt {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// When to remind: "2024-05-01 14:30", "2024-05-01", "14:30" or RFC 3339
+        datetime: String,
+    },
+    /// Show desktop notifications as tasks come due and reminders fire, until stopped
+    NotifyDaemon {
+        /// How long before tasks are due to notify, e.g. 30m, 2h or 1d (repeatable; default
+        /// from [notify] in the configuration, or 30m and when due)
+        #[arg(long = "before", value_name = "LEAD")]
+        before: Vec<String>,
+        /// Seconds between checks
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+        /// Check once and exit, e.g. from cron
+        #[arg(long)]
+        once: bool,
+    },
+    /// Print reminders that are due and mark them as delivered, and escalate tasks that went
+    /// stale (see `[stale]` in the configuration)
+    RemindCheck,
+    /// Make a task depend on others, so it is blocked until they are done
+    Depend {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// The tasks it depends on
+        #[arg(long, required = true, num_args = 1.., value_name = "ID")]
+        on: Vec<TaskRef>,
+        /// Stop depending on them
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Set the task to add when a task is completed, e.g. the next step
+    FollowUp {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// Description of the follow-up task
+        #[arg(required_unless_present = "clear")]
+        description: Option<String>,
+        /// How long after the completion it is due, e.g. "2h", "3d" or "1w"
+        #[arg(long, value_name = "DURATION")]
+        due_in: Option<String>,
+        /// Remove the follow-up
+        #[arg(long, conflicts_with_all = ["description", "due_in"])]
+        clear: bool,
+    },
+    /// Mark two tasks as related, e.g. a follow-up and the original, without one blocking
+    /// the other
+    Link {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// The related task's ID or alias, or the start of its description
+        other: TaskRef,
+        /// Unlink them
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Show the tasks in columns by status: backlog, in progress and done
+    Board {
+        /// Only show tasks with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Show at most this many done tasks, the most recent first
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        done: usize,
+    },
+    /// Move a task to another column of the board
+    Move {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// The status to move it to
+        #[arg(value_enum)]
+        status: Status,
+    },
+    /// Show the tasks that can be worked on now, most important first
+    Next {
+        /// Show at most this many
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        /// Show tasks in every context, not only the current one
+        #[arg(long)]
+        any_context: bool,
+    },
+    /// Show what is due today, or on each day of the coming week, overdue tasks first
+    Agenda {
+        /// Show the next seven days, with a calendar of the week
         #[arg(short, long)]
-        yes: bool,
+        week: bool,
+        /// Show tasks in every context, not only the current one
+        #[arg(long)]
+        any_context: bool,
+    },
+    /// Show or change the context that list and next are limited to
+    Context {
+        #[command(subcommand)]
+        action: Option<ContextAction>,
+    },
+    /// Start tracking time on a task, stopping the timer running on any other
+    Start {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+    },
+    /// Stop tracking time
+    Stop,
+    /// Work on a task in timed rounds with breaks, counting down in the terminal
+    Pomodoro {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// Minutes of work per round
+        #[arg(long, value_name = "MINUTES", default_value_t = 25)]
+        work: u64,
+        /// Minutes of break after each round
+        #[arg(long = "break", value_name = "MINUTES", default_value_t = 5)]
+        rest: u64,
+        /// How many rounds to work
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        rounds: u32,
+        /// Send a desktop notification when each work period and break ends
+        #[arg(long)]
+        notify: bool,
+    },
+    /// Show the time tracked on each task each day
+    Log {
+        /// Start of the period: "7d", "4w" or a date
+        #[arg(long, value_name = "WHEN", default_value = "7d")]
+        since: String,
+        /// Show a total per task for the whole period instead
+        #[arg(long)]
+        by_task: bool,
+    },
+    /// Show when tasks were created, changed, completed, reopened and deleted
+    History {
+        /// Only the history of this task (its ID or alias, or the start of its description)
+        id: Option<TaskRef>,
+        /// Only changes since then: "yesterday", "7d", "2w" or a date [default: 7d without an
+        /// ID, all of it with one]
+        #[arg(long, value_name = "WHEN")]
+        since: Option<String>,
+    },
+    /// Summarise a week's work for a status update, or tracked time (see focus)
+    #[command(args_conflicts_with_subcommands = true)]
+    Report {
+        #[command(subcommand)]
+        report: Option<ReportKind>,
+        #[command(flatten)]
+        args: ReportArgs,
+    },
+    /// Record that a task is blocked waiting on someone
+    WaitingOn {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// Who you are waiting for
+        #[arg(required_unless_present = "clear")]
+        person: Option<String>,
+        /// Stop waiting (the task becomes actionable again)
+        #[arg(long, conflicts_with = "person")]
+        clear: bool,
+    },
+    /// Assign a task to someone, on a list several people share
+    Assign {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// Who is to do it; "me" is `user` from the config file, or your login name
+        #[arg(required_unless_present = "clear", value_parser = config::assignee)]
+        user: Option<String>,
+        /// Unassign the task
+        #[arg(long, conflicts_with = "user")]
+        clear: bool,
+    },
+    /// Hide a task from list and next until it can be acted on
+    Snooze {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// When it comes back: "monday", "tomorrow 2pm", "in 3 days", 2024-05-01...; a day
+        /// without a time means 09:00
+        #[arg(long, value_name = "WHEN", required_unless_present = "clear")]
+        until: Option<String>,
+        /// Bring it back now
+        #[arg(long, conflicts_with = "until")]
+        clear: bool,
+    },
+    /// Serve JSON-RPC 2.0 requests on stdin/stdout, one JSON object per line (for editor plugins)
+    Rpc,
+    /// Serve the list over HTTP as a REST API (GET/POST /tasks, GET/PATCH/DELETE /tasks/{id})
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// Address to listen on; anything but localhost makes the list reachable from the
+        /// network, without a password
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Let web pages from this origin call the API, e.g. http://localhost:3000
+        #[arg(long, value_name = "ORIGIN")]
+        allow_origin: Option<String>,
+        /// TOML file of users' tokens and their roles in each list; requests then need a
+        /// token, and only reach lists the user is a member of
+        #[arg(long, value_name = "PATH")]
+        users: Option<PathBuf>,
+    },
+    /// Create tasks from TODO/FIXME comments in a source tree (respects .gitignore)
+    Scan {
+        /// Directory to scan
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Print a compact task summary for shell prompts and status bars, or set a task's status
+    /// with an ID and a status
+    Status {
+        /// The task to change: its ID or alias, or the start of its description
+        #[arg(requires = "state")]
+        id: Option<TaskRef>,
+        /// The status to give it
+        #[arg(value_enum)]
+        state: Option<Status>,
+        /// Output style
+        #[arg(short, long, value_enum, default_value_t = StatusFormat::Plain)]
+        format: StatusFormat,
+    },
+    /// Show counts, weekly progress, time to complete and tasks per tag
+    Stats {
+        /// Start of the period for weekly figures and completion times: "8w", "30d" or a
+        /// date
+        #[arg(long, value_name = "WHEN", default_value = "8w")]
+        since: String,
+        /// Draw bar charts and a sparkline of the weekly figures
+        #[arg(long)]
+        chart: bool,
+    },
+    /// Permanently remove every task whose description matches a pattern
+    Purge {
+        /// Regular expression matched against task descriptions
+        #[arg(long, value_name = "REGEX")]
+        matching: String,
     },
+    /// Find pending tasks with the same or nearly the same description and merge them
+    Dedupe {
+        /// How alike descriptions must be, from 0 to 1; 1 finds only identical ones
+        #[arg(long, value_name = "SIMILARITY", default_value_t = duplicates::DEFAULT_THRESHOLD, value_parser = dedupe::parse_threshold)]
+        threshold: f64,
+        /// Merge every group into its oldest task without asking
+        #[arg(long)]
+        auto: bool,
+    },
+    /// Synchronise tasks with external tools
+    Sync {
+        #[command(subcommand)]
+        target: SyncTarget,
+    },
+    /// Export all tasks
+    Export {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+        /// Replace descriptions, tags, notes and other text with stable hashes, keeping IDs,
+        /// dates and status
+        #[arg(long)]
+        anonymize: bool,
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "PATH", conflicts_with = "clipboard")]
+        output: Option<PathBuf>,
+        /// Copy to the system clipboard instead of stdout
+        #[arg(long)]
+        clipboard: bool,
+        /// Week printed by the planner format: any date in it (defaults to this week)
+        #[arg(long, value_name = "DATE")]
+        week: Option<String>,
+        /// Check the JSON against the task schema (see the schema command) before writing it
+        #[arg(long)]
+        validate: bool,
+    },
+    /// Import tasks from a file ("-" reads stdin) or the clipboard
+    Import(ImportArgs),
+    /// Apply a JSON array of operations (add, complete, tag, ...) from a file ("-" reads
+    /// stdin) in one save; if one fails, none is applied
+    Apply {
+        /// The file of operations
+        file: PathBuf,
+    },
+    /// Save tasks as a template, e.g. a release checklist, and add them again from it
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+    /// Publish open and recently completed tasks as a feed for a static site or feed reader
+    Publish(PublishArgs),
+    /// Print the JSON Schema of exported tasks and of the data file
+    Schema,
+    /// Turn messages from a mail source into tasks
+    Ingest {
+        #[command(subcommand)]
+        source: IngestSource,
+    },
+    /// Run a chat bot that manages the list from a chat room
+    Bot {
+        #[command(subcommand)]
+        network: BotNetwork,
+    },
+    /// Measure performance on synthetic data
+    Bench {
+        #[command(subcommand)]
+        target: bench::BenchTarget,
+    },
+    /// Type commands at a prompt without reloading the list for each one
+    Shell,
+    /// Check the data file and environment for problems
+    Doctor,
+    /// Set up where tasks are kept, your lists and how listings look, step by step
+    Init,
+    /// Carry your settings to another machine
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print a shell completion script, which also completes task IDs
+    Completions { shell: completions::Shell },
+    /// Print pending task IDs and descriptions for the completion scripts
+    #[command(name = completions::IDS_COMMAND, hide = true)]
+    CompleteIds {
+        /// The subcommands typed so far
+        command: Vec<String>,
+    },
+    /// Update rust-todo to the latest GitHub release
+    SelfUpdate {
+        /// Only report whether a newer version is available
+        #[arg(long)]
+        check: bool,
+    },
+    /// Any other command runs rust-todo-COMMAND from PATH, a plugin
+    #[command(external_subcommand)]
+    Plugin(Vec<OsString>),
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Task {
-    id: usize,
-    description: String,
+#[derive(Args)]
+struct AddArgs {
+    /// The task description
+    #[arg(required_unless_present_any = ["from_grep", "stdin"])]
+    description: Option<String>,
+    /// Read `path:line: TODO: ...` lines (grep -n / rg -n output) from stdin, one task each
+    #[arg(long, conflicts_with = "description")]
+    from_grep: bool,
+    /// Read one task per line from stdin, e.g. "Book flights #travel !high @due:friday"; the
+    /// other options apply to every task
+    #[arg(long, conflicts_with_all = ["description", "from_grep"])]
+    stdin: bool,
+    /// Named place or context where the task can be done, e.g. "work" or "hardware-store"
+    #[arg(short, long, value_name = "PLACE")]
+    location: Option<String>,
+    /// Due date: "tomorrow 5pm", "next friday", "in 3 days", 2024-05-01, ...
+    #[arg(short, long, value_name = "WHEN")]
+    due: Option<String>,
+    /// How important the task is
+    #[arg(short, long, value_enum)]
+    priority: Option<Priority>,
+    /// Tag the task (repeatable)
+    #[arg(short, long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+    /// Repeat the task: "daily", "every 2 weeks", "every monday and thursday", an RRULE, ...
+    #[arg(long, value_name = "RULE", value_parser = Rule::parse)]
+    repeat: Option<Rule>,
+    /// Make the task a subtask of this task (its ID or alias, or the start of its description)
+    #[arg(long, value_name = "ID")]
+    parent: Option<TaskRef>,
+    /// A name to use instead of the ID in other commands, e.g. deploy-v2
+    #[arg(long, value_name = "NAME", value_parser = edit::parse_alias, conflicts_with_all = ["from_grep", "stdin"])]
+    alias: Option<String>,
+}
+
+/// The tasks a command acts on, by ID or by `--filter`. All of them are changed in one save,
+/// which `undo` reverts as one.
+#[derive(Args)]
+struct Targets {
+    /// IDs or ranges of the tasks, e.g. 3 5 7-10, or their aliases or the start of their
+    /// descriptions
+    #[arg(value_name = "ID", value_parser = select::parse_target, required_unless_present = "filter")]
+    ids: Vec<Target>,
+    /// Act on the tasks matching KEY=VALUE instead: tag=NAME, priority=LEVEL,
+    /// status=pending|completed, place=NAME or assignee=NAME|me (repeatable; all must match)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = Selector::parse, conflicts_with = "ids")]
+    filter: Vec<Selector>,
+}
+
+#[derive(Args, Default)]
+struct ListArgs {
+    /// Show only completed tasks
+    #[arg(short, long)]
     completed: bool,
-    created_at: String,
+    /// Show only pending tasks
+    #[arg(short, long)]
+    pending: bool,
+    /// Show only tasks that can be done at this place or context, instead of the current one
+    #[arg(long, value_name = "PLACE")]
+    near: Option<String>,
+    /// Show tasks in every context, not only the current one (see the context command)
+    #[arg(long, conflicts_with = "near")]
+    any_context: bool,
+    /// Show only tasks waiting on someone, longest-waiting first
+    #[arg(short, long)]
+    waiting: bool,
+    /// Show only tasks assigned to this person; "me" is you (see assign)
+    #[arg(long, value_name = "USER", value_parser = config::assignee)]
+    assignee: Option<String>,
+    /// Show only tasks with this status; repeat for any of several
+    #[arg(long, value_enum, value_name = "STATUS")]
+    status: Vec<Status>,
+    /// Show only tasks due by this date ("friday", 2024-05-01, ...)
+    #[arg(long, value_name = "WHEN")]
+    due_before: Option<String>,
+    /// Show only pending tasks that are past their due date
+    #[arg(long)]
+    overdue: bool,
+    /// Show only tasks with this tag (repeatable; all must match)
+    #[arg(short, long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+    /// Show only the inbox (see the inbox command)
+    #[arg(long)]
+    inbox: bool,
+    /// Include the archived tasks (see the archive command)
+    #[arg(long, conflicts_with = "pending")]
+    archived: bool,
+    /// Include the tasks snoozed until later (see the snooze command)
+    #[arg(short, long)]
+    all: bool,
+    /// Order of the list [default: `sort` in the config file, or id]
+    #[arg(long, value_enum)]
+    sort: Option<SortBy>,
+    /// Reverse the order
+    #[arg(long)]
+    reverse: bool,
+    /// Show how long ago each task was added, and completed or cancelled
+    #[arg(long)]
+    show_dates: bool,
+    /// Show the tasks in groups, each in the order of the list
+    #[arg(long, value_enum, value_name = "GROUP", conflicts_with = "format")]
+    group_by: Option<outline::GroupBy>,
+    /// Output format; json, csv and tsv include every field, for jq or spreadsheets
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Number the tasks, then read a number to act on that task (complete by default)
+    #[arg(long, value_enum, value_name = "ACTION", num_args = 0..=1, default_missing_value = "complete", conflicts_with = "format")]
+    pick: Option<PickAction>,
 }
 
-struct TodoList {
-    tasks: Vec<Task>,
-    file_path: PathBuf,
+#[derive(Clone, Copy, ValueEnum)]
+enum ShowFormat {
+    /// The task's details, one per line
+    Text,
+    /// A JSON object of the task, as in `list --format json`, plus blocked_by, blocks,
+    /// subtasks, backlinks and history
+    Json,
 }
 
-impl TodoList {
-    fn new() -> Self {
-        let file_path = Self::get_data_path();
-        let tasks = Self::load_tasks(&file_path);
-        TodoList { tasks, file_path }
-    }
+#[derive(Clone, Copy, ValueEnum)]
+enum PickAction {
+    /// Mark the task as complete
+    Complete,
+    /// Open the task in $EDITOR
+    Edit,
+    /// Print all of the task's details
+    Show,
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Text to look for, ignoring case
+    query: String,
+    /// Treat the query as a regular expression (case-sensitive unless it starts with `(?i)`)
+    #[arg(short, long, conflicts_with = "fuzzy")]
+    regex: bool,
+    /// Match the query's letters in order with anything between, e.g. "qrtrv" finds
+    /// "quarterly review"
+    #[arg(short, long)]
+    fuzzy: bool,
+    /// Leave out completed tasks
+    #[arg(short, long)]
+    pending: bool,
+    /// Also search the archived tasks (see the archive command)
+    #[arg(long, conflicts_with = "pending")]
+    archived: bool,
+    /// Show at most this many results
+    #[arg(short = 'n', long, value_name = "N")]
+    limit: Option<usize>,
+    /// Output format; json, csv and tsv list the matching tasks best first, with every field
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// File (or, for Keep, directory) to import
+    #[arg(required_unless_present = "clipboard", conflicts_with = "clipboard")]
+    path: Option<PathBuf>,
+    /// Read from the system clipboard: JSON, or one task per line
+    #[arg(long)]
+    clipboard: bool,
+    /// Input format
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// What to do with a task whose description matches one already in the list
+    #[arg(long, value_enum, default_value_t = Duplicates::Keep)]
+    duplicates: Duplicates,
+    /// Check the JSON against the task schema (see the schema command) and import nothing
+    /// unless it conforms
+    #[arg(long)]
+    validate: bool,
+}
+
+#[derive(Args)]
+struct PublishArgs {
+    /// Write an RSS 2.0 feed to this file, or upload it to this http(s) URL with PUT
+    #[arg(long, value_name = "PATH|URL", required_unless_present = "json_feed")]
+    rss: Option<String>,
+    /// Write a JSON Feed to this file, or upload it to this http(s) URL with PUT
+    #[arg(long, value_name = "PATH|URL")]
+    json_feed: Option<String>,
+    /// Include tasks completed this far back: a number of days or weeks like 7d or 2w, or a
+    /// date
+    #[arg(long, default_value = "7d", value_name = "WHEN")]
+    since: String,
+    /// Only publish tasks with this tag, e.g. to keep private tasks out of a public feed
+    #[arg(long)]
+    tag: Option<String>,
+    /// Title of the feed (defaults to the list name)
+    #[arg(long)]
+    title: Option<String>,
+    /// Web page the feed belongs to
+    #[arg(long, value_name = "URL")]
+    link: Option<String>,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    /// Write Markdown, e.g. for a wiki or a chat message (the default)
+    #[arg(long, conflicts_with = "html")]
+    markdown: bool,
+    /// Write an HTML page instead, e.g. for an e-mail
+    #[arg(long)]
+    html: bool,
+    /// Start of the report: a weekday (the last one), "yesterday", "7d" or a date
+    #[arg(long, value_name = "WHEN", default_value = "monday")]
+    since: String,
+    /// How to group the completed tasks
+    #[arg(long, value_enum, default_value_t)]
+    by: report::GroupBy,
+    /// Write the report to this file instead of stdout
+    #[arg(short, long, value_name = "PATH", conflicts_with = "clipboard")]
+    output: Option<PathBuf>,
+    /// Copy the report to the system clipboard instead of stdout
+    #[arg(long)]
+    clipboard: bool,
+}
+
+#[derive(Subcommand)]
+enum ReportKind {
+    /// Deep-work hours per day of a week, from start/stop and pomodoro sessions, and the tags
+    /// they went to
+    Focus {
+        /// Report the week with this date in it (defaults to this week)
+        #[arg(long, value_name = "DATE", num_args = 0..=1, default_missing_value = "today")]
+        week: Option<String>,
+        /// Shortest stretch of work counted as a focus session
+        #[arg(long, value_name = "MINUTES", default_value_t = 25)]
+        min: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum NoteAction {
+    /// Add a timestamped note to a task
+    Add {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+        /// The note; read from stdin when left out, for notes of several lines
+        text: Option<String>,
+    },
+    /// Show a task's notes, oldest first
+    Show {
+        /// The task's ID or alias, or the start of its description
+        id: TaskRef,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    /// Show the deleted tasks, oldest first
+    List,
+    /// Delete the tasks in the trash for good
+    Empty,
+}
+
+#[derive(Subcommand)]
+enum TemplateAction {
+    /// Show the templates and how many tasks each adds
+    List,
+    /// Save tasks as a template, with their due dates as offsets from now
+    Save {
+        /// Letters, digits, '-' and '_'
+        name: String,
+        #[command(flatten)]
+        targets: Targets,
+        /// Also save their subtasks, and theirs
+        #[arg(short, long)]
+        recursive: bool,
+        /// Count the due dates from this date instead of now ("monday", 2024-05-01, ...)
+        #[arg(long, value_name = "WHEN")]
+        from: Option<String>,
+        /// Replace the template if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add the tasks of a template, due counting from now
+    Apply {
+        /// The template's name, as shown by `template list`
+        name: String,
+        /// Count the due dates from this date instead ("monday", 2024-05-01, ...)
+        #[arg(long, value_name = "WHEN")]
+        start: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotAction {
+    /// Show the snapshots of the list, oldest first, then the tagged ones
+    List,
+    /// Replace the list with a snapshot (the current tasks are snapshotted first)
+    Restore {
+        /// The snapshot's name or tag, as shown by `snapshot list`
+        name: String,
+    },
+    /// Take a snapshot of the list under a name, e.g. before-vacation, kept until deleted by
+    /// hand
+    Tag {
+        /// Letters, digits, '-', '_' and '.'
+        name: String,
+    },
+    /// Show how the list has changed since a snapshot
+    Diff {
+        /// The snapshot's name or tag, as shown by `snapshot list`
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContextAction {
+    /// Limit list and next to the tasks in this context, e.g. work or @home, until changed
+    Set { name: String },
+    /// Show the tasks in every context again
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Write the settings as a bundle for `config import`
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Replace the settings with a bundle from `config export`; the old file is kept as
+    /// config.toml.bak
+    Import {
+        /// The bundle, or - for stdin
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncTarget {
+    /// Two-way sync with `#todo` checkboxes in an Obsidian vault
+    Obsidian {
+        /// Path to the vault directory
+        #[arg(long, value_name = "PATH")]
+        vault: PathBuf,
+        /// Note (relative to the vault) that receives tasks created on the command line
+        #[arg(long, default_value = "rust-todo.md", value_name = "NOTE")]
+        inbox: String,
+    },
+    /// Two-way sync with a Markdown checklist file (created if missing)
+    Markdown {
+        /// The checklist file
+        file: PathBuf,
+    },
+    /// Two-way sync of tasks with due dates with a CalDAV task list (password from
+    /// CALDAV_PASSWORD)
+    Caldav {
+        /// URL of the task list (calendar collection), e.g.
+        /// https://cloud.example.com/remote.php/dav/calendars/me/tasks/
+        #[arg(long, value_name = "URL")]
+        url: String,
+        /// User name to log in with
+        #[arg(long)]
+        user: String,
+    },
+    /// Keep the data directory in a git repository: commit after every change, and pull and
+    /// push here
+    Git {
+        /// Share the lists through this repository (set as the `origin` remote)
+        #[arg(long, value_name = "URL")]
+        remote: Option<String>,
+    },
+    /// Merge two versions of a list file; run by git while pulling
+    #[command(hide = true)]
+    MergeDriver {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum IngestSource {
+    /// Read a local Maildir (flagged messages by default)
+    Maildir {
+        /// Path to the Maildir (the directory containing cur/ and new/)
+        path: PathBuf,
+        /// Include unread messages
+        #[arg(long)]
+        unread: bool,
+        /// Include flagged messages (the default when neither option is given)
+        #[arg(long)]
+        flagged: bool,
+        /// Only messages whose From header matches this regular expression
+        #[arg(long, value_name = "REGEX")]
+        from: Option<String>,
+        /// Only messages whose subject matches this regular expression
+        #[arg(long, value_name = "REGEX")]
+        subject: Option<String>,
+    },
+}
 
-    fn get_data_path() -> PathBuf {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let mut path = PathBuf::from(home);
-        path.push(".rust-todo.json");
-        path
+#[derive(Subcommand)]
+enum BotNetwork {
+    /// Answer `!todo` commands in a Matrix room (token from MATRIX_ACCESS_TOKEN)
+    Matrix {
+        /// Homeserver base URL, e.g. https://matrix.org
+        #[arg(long, value_name = "URL")]
+        homeserver: String,
+        /// Room ID or alias to join, e.g. "#family:matrix.org"
+        #[arg(long)]
+        room: String,
+    },
+}
+
+/// Fails unless a new task can be a subtask of task `parent`: it exists, and isn't in a
+/// cycle of subtasks from hand-edited data.
+fn check_parent(list: &TodoList, parent: usize) -> Result<(), Error> {
+    if list.get(parent).is_none() {
+        return Err(Error::NotFound(format!(
+            "there is no task {} to add a subtask to",
+            parent
+        )));
     }
+    list.check_parent(None, parent).map_err(Error::Parse)
+}
 
-    fn load_tasks(path: &PathBuf) -> Vec<Task> {
-        if path.exists() {
-            match fs::read_to_string(path) {
-                Ok(content) => serde_json::from_str(&content).unwrap_or_else(|_| vec![]),
-                Err(_) => vec![],
-            }
-        } else {
-            vec![]
-        }
+fn add(list: &mut TodoList, args: AddArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let parent = args.parent.map(|p| task_id(list, &p)).transpose()?;
+    if let Some(parent) = parent {
+        check_parent(list, parent)?;
     }
+    let due = args
+        .due
+        .as_deref()
+        .map(|d| dates::parse_due(d, dates::now()))
+        .transpose()
+        .map_err(Error::Parse)?;
+    let mut task = Task::builder()
+        .description(args.description.unwrap_or_default())
+        .location(args.location)
+        .due(due)
+        .priority(args.priority)
+        .repeat(args.repeat)
+        .parent(parent)
+        .build();
+    for tag in &args.tags {
+        task.add_tag(tag);
+    }
+    if let Some(alias) = &args.alias {
+        alias_free(list, alias, None)?;
+    }
+    task.alias = args.alias;
+    list.add(task)?;
+    println!("✓ Task added successfully!");
+    Ok(())
+}
 
-    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string_pretty(&self.tasks)?;
-        fs::write(&self.file_path, json)?;
-        Ok(())
+/// `quick`: adds the task [read](quick::parse) from `text`, and says how it was read.
+fn quick_add(list: &mut TodoList, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let parsed = quick::parse(text, dates::now()).map_err(Error::Parse)?;
+    let mut task = Task::builder()
+        .description(parsed.description.clone())
+        .priority(parsed.priority)
+        .due(parsed.due.as_ref().map(|(at, _)| *at))
+        .location(parsed.location.clone())
+        .build();
+    for tag in &parsed.tags {
+        task.add_tag(tag);
+    }
+    let tags: String = task.tags.iter().map(|t| format!(" #{}", t)).collect();
+    let id = list.add(task)?;
+    println!("✓ Added task {}: {}", id, parsed.description);
+    if let Some((at, words)) = &parsed.due {
+        println!("  due:      {} (from {:?})", show_due(*at), words);
+    }
+    if let Some(priority) = parsed.priority {
+        println!("  priority: {}", priority);
+    }
+    if let Some(context) = &parsed.location {
+        println!("  context:  @{}", context);
+    }
+    if !tags.is_empty() {
+        println!("  tags:    {}", tags);
     }
+    Ok(())
+}
 
-    fn add_task(&mut self, description: String) -> Result<(), Box<dyn std::error::Error>> {
-        let id = self
-            .tasks
-            .iter()
-            .map(|t| t.id)
-            .max()
-            .unwrap_or(0)
-            + 1;
-        let task = Task {
-            id,
-            description,
-            completed: false,
-            created_at: chrono::Local::now().to_rfc3339(),
-        };
-        self.tasks.push(task);
-        self.save()?;
-        println!("✓ Task added successfully!");
+/// `add --stdin`: adds a task for each line on stdin, in one save. The options apply to
+/// every task, unless its line sets the same thing.
+fn add_from_stdin(list: &mut TodoList, args: AddArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let parent = args.parent.as_ref().map(|p| task_id(list, p)).transpose()?;
+    if let Some(parent) = parent {
+        check_parent(list, parent)?;
+    }
+    let now = dates::now();
+    let due = args
+        .due
+        .as_deref()
+        .map(|d| dates::parse_due(d, now))
+        .transpose()
+        .map_err(Error::Parse)?;
+    let mut tasks = vec![];
+    for (n, line) in std::io::stdin().lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut task = batch::parse_line(&line, now)
+            .map_err(|e| Error::Parse(format!("line {}: {}", n + 1, e)))?;
+        task.priority = task.priority.or(args.priority);
+        task.due = task.due.or(due);
+        task.location = task.location.or_else(|| args.location.clone());
+        task.repeat = args.repeat.clone();
+        task.parent = parent;
+        for tag in &args.tags {
+            task.add_tag(tag);
+        }
+        tasks.push(task);
+    }
+    let count = tasks.len();
+    list.batch(|list| {
+        for task in tasks {
+            list.insert(task);
+        }
         Ok(())
+    })?;
+    println!("✓ Added {} task(s).", count);
+    Ok(())
+}
+
+/// `enqueue`: queues a task for the list without opening it.
+fn enqueue(
+    backend: Backend,
+    name: &str,
+    description: String,
+    tags: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = backend.path(&TodoList::named_data_path(name)?);
+    if name != DEFAULT_LIST && !path.exists() {
+        return Err(Error::NotFound(format!("there is no list named {:?}", name)).into());
     }
+    let mut task = Task::builder().description(description).build();
+    for tag in tags {
+        task.add_tag(tag);
+    }
+    queue::push(&path, &task)
+}
 
-    fn list_tasks(&self, show_completed: bool, show_pending: bool) {
-        let filtered_tasks: Vec<&Task> = if show_completed {
-            self.tasks.iter().filter(|t| t.completed).collect()
-        } else if show_pending {
-            self.tasks.iter().filter(|t| !t.completed).collect()
-        } else {
-            self.tasks.iter().collect()
-        };
+/// The up-to-date [summary](summary) of list `name`, if there is one.
+fn saved_summary(backend: Backend, name: &str) -> Option<summary::Summary> {
+    let path = backend.path(&TodoList::named_data_path(name).ok()?);
+    summary::read(&path)
+}
 
-        if filtered_tasks.is_empty() {
-            println!("No tasks found.");
-            return;
+/// Creates one task per TODO found in grep output on stdin.
+fn add_from_grep(list: &mut TodoList) -> Result<(), Box<dyn std::error::Error>> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let (added, skipped) = list.add_from_grep(&input)?;
+    println!(
+        "✓ Added {} task(s) from grep output ({} already present).",
+        added, skipped
+    );
+    Ok(())
+}
+
+/// `--color`, which overrides `color` in the configuration.
+static COLOR: OnceLock<Color> = OnceLock::new();
+
+/// Whether output may use ANSI colours: stdout is a terminal and `NO_COLOR` is not set.
+fn use_color() -> bool {
+    match COLOR.get().copied().unwrap_or(config::current().color) {
+        Color::Always => true,
+        Color::Never => false,
+        Color::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    }
+}
+
+/// Whether `list` and `show` may make links clickable: stdout is a terminal known to support
+/// OSC 8 hyperlinks, unless the configuration says otherwise. Other terminals get plain text.
+fn use_hyperlinks() -> bool {
+    match config::current().hyperlinks {
+        Hyperlinks::Always => true,
+        Hyperlinks::Never => false,
+        Hyperlinks::Auto => {
+            let var = |name: &str| std::env::var(name).unwrap_or_default();
+            let term = var("TERM");
+            std::io::stdout().is_terminal()
+                && term != "dumb"
+                && (["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"]
+                    .contains(&var("TERM_PROGRAM").as_str())
+                    || var("VTE_VERSION").parse::<u32>().is_ok_and(|v| v >= 5000)
+                    || ["kitty", "alacritty", "foot", "ghostty"]
+                        .iter()
+                        .any(|name| term.contains(name))
+                    || ["WT_SESSION", "KONSOLE_VERSION", "DOMTERM"]
+                        .iter()
+                        .any(|name| std::env::var_os(name).is_some()))
         }
+    }
+}
+
+/// `text` linking to `url` on terminals that support it, or as it is with `on` false.
+fn hyperlink(text: &str, url: &str, on: bool) -> String {
+    if on {
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// `text` with the web addresses in it made clickable, with `on`.
+fn linkify(text: &str, on: bool) -> String {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    if !on {
+        return text.to_string();
+    }
+    let url = URL.get_or_init(|| Regex::new(r#"https?://[^\s<>"]+[^\s<>".,;:!?)\]]"#).unwrap());
+    url.replace_all(text, |c: &regex::Captures| hyperlink(&c[0], &c[0], true))
+        .into_owned()
+}
+
+/// A clickable form of the metadata `value` of `key`, for the keys that hold links.
+fn metadata_link(key: &str, value: &str, on: bool) -> String {
+    let url = match key {
+        "url" if value.contains(':') => Some(value.to_string()),
+        "source.file" => std::path::absolute(value)
+            .ok()
+            .filter(|path| path.exists())
+            .map(|path| format!("file://{}", path.display())),
+        _ => None,
+    };
+    match url {
+        Some(url) => hyperlink(value, &url, on),
+        None => linkify(value, on),
+    }
+}
+
+/// `text` in the theme style `style` (see [`config::Theme`]), or as it is without colour.
+fn paint(style: &str, text: &str, color: bool) -> String {
+    match config::sgr(style) {
+        Ok(codes) if color && !codes.is_empty() => format!("\x1b[{}m{}\x1b[0m", codes, text),
+        _ => text.to_string(),
+    }
+}
+
+/// `emoji`, or `word` when the theme turns emoji off.
+fn mark(emoji: &'static str, word: &'static str) -> &'static str {
+    if config::current().theme.emoji {
+        emoji
+    } else {
+        word
+    }
+}
+
+/// A due date as shown to the user, in the configured `date_format`.
+fn show_due(due: chrono::DateTime<Local>) -> String {
+    match &config::current().date_format {
+        Some(format) => dates::format_due_as(due, format),
+        None => dates::format_due(due),
+    }
+}
+
+/// How long ago `task` was added and closed, e.g. ` (created 3d ago, done 2h ago)`, for
+/// `list --show-dates`.
+fn ages(task: &Task, now: chrono::DateTime<Local>) -> String {
+    let mut ages = vec![];
+    ages.push(format!(
+        "created {} ago",
+        dates::format_age(now - task.created_at.with_timezone(&Local))
+    ));
+    if let Some(at) = task.completed_at.filter(|_| task.completed) {
+        let closed = match task.status() {
+            Status::Cancelled => "cancelled",
+            _ => "done",
+        };
+        ages.push(format!("{} {} ago", closed, dates::format_age(now - at)));
+    }
+    if ages.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", ages.join(", "))
+    }
+}
 
-        println!("\n📋 Your To-Do List:\n");
-        for task in filtered_tasks {
-            let status = if task.completed { "✓" } else { " " };
-            let checkbox = if task.completed { "[x]" } else { "[ ]" };
-            println!("{} {} {} - {}", checkbox, status, task.id, task.description);
+/// Prints the tasks `args` selects and returns their IDs in the order shown.
+fn list_tasks(list: &TodoList, args: ListArgs) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    if let Some(reason) = list.read_only() {
+        eprintln!(
+            "🔒 {} is read-only ({}); changes can't be saved.",
+            list.path().display(),
+            reason
+        );
+    }
+    let now = dates::now();
+    let filter = Filter {
+        completed: match (args.completed, args.pending) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        near: match args.near {
+            Some(place) => Some(place),
+            None if args.any_context => None,
+            None => current_context(args.format == OutputFormat::Text),
+        },
+        waiting: args.waiting,
+        assignee: args.assignee,
+        status: args.status,
+        due_before: args
+            .due_before
+            .as_deref()
+            .map(|d| dates::parse_due(d, now))
+            .transpose()
+            .map_err(Error::Parse)?,
+        overdue: args.overdue,
+        tags: args.tags,
+        inbox: args.inbox,
+        archived: args.archived,
+        snoozed: args.all,
+        sort: args.sort.or(config::current().sort).unwrap_or_default(),
+        reverse: args.reverse,
+    };
+    let tasks = list.filter(&filter);
+    if args.format != OutputFormat::Text {
+        print!("{}", output::render(&tasks, args.format)?);
+        return Ok(tasks.iter().map(|t| t.id).collect());
+    }
+    let snoozed = if args.all {
+        0
+    } else {
+        list.pending().filter(|t| t.is_snoozed(now)).count()
+    };
+    let snoozed_hint = || {
+        if snoozed > 0 {
+            println!("({} snoozed task(s) hidden; --all shows them)", snoozed);
         }
-        println!();
+    };
+    if tasks.is_empty() {
+        println!("No tasks found.");
+        snoozed_hint();
+        return Ok(vec![]);
     }
 
-    fn complete_task(&mut self, id: usize) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            if task.completed {
-                println!("Task {} is already completed.", id);
+    let color = use_color();
+    let links = use_hyperlinks();
+    let theme = &config::current().theme;
+    if dirs::is_local(list.path()) {
+        let project = list.path().parent().unwrap_or(Path::new("."));
+        println!(
+            "\n{}To-Do List for {}:\n",
+            mark("📋 ", ""),
+            project.display()
+        );
+    } else {
+        println!("\n{}Your To-Do List:\n", mark("📋 ", ""));
+    }
+    let groups = match args.group_by {
+        Some(by) => outline::group(list, &tasks, by),
+        None => vec![(String::new(), tasks)],
+    };
+    let mut shown = vec![];
+    for (index, (heading, tasks)) in groups.into_iter().enumerate() {
+        if !heading.is_empty() {
+            if index > 0 {
+                println!();
+            }
+            println!("{}", paint(&theme.heading, &heading, color));
+        }
+        for (depth, task) in outline::tree(tasks) {
+            shown.push(task.id);
+            let number = match args.pick {
+                Some(_) => format!("{:>3}) ", shown.len()),
+                None => String::new(),
+            };
+            let indent = match depth {
+                0 => String::new(),
+                _ => format!("{}└ ", "  ".repeat(depth - 1)),
+            };
+            let status = match task.status() {
+                Status::Done => mark("✓", " "),
+                Status::Cancelled => mark("✗", " "),
+                _ => " ",
+            };
+            let checkbox = if task.completed {
+                &theme.checkbox_done
+            } else {
+                &theme.checkbox_pending
+            };
+            let tags: String = task.tags.iter().map(|t| format!(" #{}", t)).collect();
+            let bell = if task.reminders.iter().any(|r| !r.delivered) {
+                mark(" 🔔", " reminder")
+            } else {
+                ""
+            };
+            let location = match &task.location {
+                Some(place) => format!(" {} {}", mark("📍", "at"), place),
+                None => String::new(),
+            };
+            let attached = match task.attachments.len() {
+                0 => String::new(),
+                n => format!(" {} {}", mark("📎", "attachments:"), n),
+            };
+            let waiting = match &task.waiting_on {
+                Some(w) if !task.completed => format!(
+                    " {}waiting on {} ({})",
+                    mark("⏳ ", ""),
+                    w.person,
+                    dates::format_age(dates::now() - w.since)
+                ),
+                _ => String::new(),
+            };
+            let blockers = list.blockers(task);
+            let blocked = if task.completed || blockers.is_empty() {
+                String::new()
             } else {
-                task.completed = true;
-                self.save()?;
-                println!("✓ Task {} marked as complete!", id);
+                let ids: Vec<String> = blockers.iter().map(|id| id.to_string()).collect();
+                format!(" {}blocked by {}", mark("⛓ ", ""), ids.join(", "))
+            };
+            let overdue = task.is_overdue(now);
+            let due = match task.due {
+                Some(due) if overdue => {
+                    format!(" {} {} (overdue)", mark("📅", "due"), show_due(due))
+                }
+                Some(due) => format!(" {} {}", mark("📅", "due"), show_due(due)),
+                None => String::new(),
+            };
+            let repeat = match &task.repeat {
+                Some(rule) => format!(" {} {}", mark("🔁", "repeats"), rule),
+                None => String::new(),
+            };
+            let priority = match task.priority {
+                Some(level) => format!(" {} {}", mark("⚑", "priority"), level),
+                None => String::new(),
+            };
+            let alias = match &task.alias {
+                Some(alias) => format!(" {} {}", mark("🔖", "alias"), alias),
+                None => String::new(),
+            };
+            let assignee = match &task.assignee {
+                Some(person) => format!(" {} {}", mark("👤", "for"), person),
+                None => String::new(),
+            };
+            let archived = if list.is_archived(task) {
+                mark(" 🗄 archived", " archived")
+            } else {
+                ""
+            };
+            let progress = match task.status() {
+                Status::InProgress => mark(" ▶ in progress", " in progress"),
+                // Waiting on someone in particular is marked with them.
+                Status::Waiting if task.waiting_on.is_none() => mark(" ⏸ waiting", " waiting"),
+                Status::Cancelled => mark("", " cancelled"),
+                _ => "",
+            };
+            let snoozed = match task.snoozed_until {
+                Some(until) if task.is_snoozed(now) => {
+                    format!(" {} until {}", mark("💤", "snoozed"), show_due(until))
+                }
+                _ => String::new(),
+            };
+            let timer = if task.is_running() {
+                mark(" ⏱", " timing")
+            } else {
+                ""
+            };
+            let icon = match &task.icon {
+                Some(icon) => format!("{} ", icon),
+                None => String::new(),
+            };
+            let ages = if args.show_dates {
+                ages(task, now)
+            } else {
+                String::new()
+            };
+            let line = format!(
+                "{}{} {} {}{} - {}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}",
+                number,
+                checkbox,
+                status,
+                indent,
+                task.id,
+                icon,
+                linkify(&task.description, links),
+                alias,
+                assignee,
+                priority,
+                tags,
+                due,
+                repeat,
+                location,
+                attached,
+                bell,
+                waiting,
+                blocked,
+                progress,
+                snoozed,
+                timer,
+                archived,
+                ages
+            );
+            let style = match task.priority {
+                _ if task.completed => &theme.completed,
+                _ if overdue => &theme.overdue,
+                Some(Priority::Critical) => &theme.critical,
+                Some(Priority::High) => &theme.high,
+                _ => "",
+            };
+            match task.color {
+                // A task's own colour wins over the theme.
+                Some(flag) if color => println!("\x1b[{}m{}\x1b[0m", flag.ansi(), line),
+                _ => println!("{}", paint(style, &line, color)),
             }
-        } else {
-            println!("Task with ID {} not found.", id);
         }
-        Ok(())
     }
+    println!();
+    snoozed_hint();
+    Ok(shown)
+}
 
-    fn delete_task(&mut self, id: usize) -> Result<(), Box<dyn std::error::Error>> {
-        let initial_len = self.tasks.len();
-        self.tasks.retain(|t| t.id != id);
-        if self.tasks.len() < initial_len {
-            self.save()?;
-            println!("✓ Task {} deleted successfully!", id);
-        } else {
-            println!("Task with ID {} not found.", id);
-        }
-        Ok(())
+/// `list --pick`: lists the tasks numbered, then reads a number and acts on that task. A
+/// letter after the number picks the action: `c` completes, `e` edits and `s` shows.
+fn pick(
+    list: &mut TodoList,
+    args: ListArgs,
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let default = args.pick.unwrap_or(PickAction::Complete);
+    let shown = list_tasks(list, args)?;
+    if shown.is_empty() {
+        return Ok(());
     }
-
-    fn clear_all(&mut self, confirmed: bool) -> Result<(), Box<dyn std::error::Error>> {
-        if !confirmed {
-            println!("⚠️  This will delete all tasks. Use --yes to confirm.");
+    loop {
+        print!(
+            "Pick 1-{} (add e to edit, s to show; Enter to quit): ",
+            shown.len()
+        );
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            println!();
             return Ok(());
         }
-        let count = self.tasks.len();
-        self.tasks.clear();
-        self.save()?;
-        println!("✓ Cleared {} task(s).", count);
-        Ok(())
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let digits = line
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(line.len());
+        let (number, letter) = line.split_at(digits);
+        let action = match letter.trim() {
+            "" => default,
+            "c" => PickAction::Complete,
+            "e" => PickAction::Edit,
+            "s" => PickAction::Show,
+            other => {
+                println!("Unknown action {:?}.", other);
+                continue;
+            }
+        };
+        let Some(&id) = number
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| shown.get(n.wrapping_sub(1)))
+        else {
+            println!("Pick a number between 1 and {}.", shown.len());
+            continue;
+        };
+        return match action {
+            PickAction::Complete => complete(list, id, false, safety),
+            PickAction::Edit => edit::run(list, id, edit::EditArgs::in_editor()),
+            PickAction::Show => show(list, id, ShowFormat::Text),
+        };
     }
 }
 
-fn main() {
-    let cli = Cli::parse();
-    let mut todo_list = TodoList::new();
-
-    let result = match cli.command {
-        Commands::Add { description } => todo_list.add_task(description),
-        Commands::List { completed, pending } => {
-            todo_list.list_tasks(completed, pending);
-            Ok(())
-        }
-        Commands::Complete { id } => todo_list.complete_task(id),
-        Commands::Delete { id } => todo_list.delete_task(id),
-        Commands::Clear { yes } => todo_list.clear_all(yes),
+/// Prints every field of `task`, one per line, and the tasks that link to it.
+fn show_task(list: &TodoList, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+    let links = use_hyperlinks();
+    let now = dates::now();
+    let ago = |at: chrono::DateTime<Local>| {
+        format!(
+            "{} ({} ago)",
+            at.format("%Y-%m-%d %H:%M"),
+            dates::format_age(now - at)
+        )
+    };
+    println!("\n{} - {}", task.id, linkify(&task.description, links));
+    println!("  status:     {}", task.status());
+    if let Some(alias) = &task.alias {
+        println!("  alias:      {}", alias);
+    }
+    println!(
+        "  created:    {}",
+        ago(task.created_at.with_timezone(&Local))
+    );
+    if let Some(at) = task.completed_at.filter(|_| task.completed) {
+        println!("  closed:     {}", ago(at));
+    }
+    if let Some(priority) = task.priority {
+        println!("  priority:   {}", priority);
+    }
+    if let Some(due) = task.due {
+        let overdue = if task.is_overdue(now) {
+            " (overdue)"
+        } else {
+            ""
+        };
+        println!("  due:        {}{}", show_due(due), overdue);
+    }
+    if let Some(rule) = &task.repeat {
+        println!("  repeats:    {}", rule);
+    }
+    if let Some(until) = task.snoozed_until.filter(|_| task.is_snoozed(dates::now())) {
+        println!("  snoozed:    until {}", show_due(until));
+    }
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task.tags.iter().map(|t| format!("#{}", t)).collect();
+        println!("  tags:       {}", tags.join(" "));
+    }
+    if let Some(place) = &task.location {
+        println!("  place:      {}", place);
+    }
+    let related = |ids: &[usize]| {
+        ids.iter()
+            .map(|id| match list.get(*id) {
+                Some(other) if other.completed => format!("{} ({}, done)", id, other.description),
+                Some(other) => format!("{} ({})", id, other.description),
+                None => id.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
     };
+    if let Some(parent) = task.parent {
+        println!("  subtask of: {}", related(&[parent]));
+    }
+    let subtasks: Vec<usize> = list.children(task.id).map(|t| t.id).collect();
+    if !subtasks.is_empty() {
+        println!("  subtasks:   {}", related(&subtasks));
+    }
+    if !task.depends_on.is_empty() {
+        println!("  depends on: {}", related(&task.depends_on));
+    }
+    let blocks = list.dependents(task.id);
+    if !blocks.is_empty() {
+        println!("  blocks:     {}", related(&blocks));
+    }
+    if let Some(follow_up) = &task.follow_up {
+        let due = match &follow_up.due_in {
+            Some(offset) => format!(" (due {} after)", offset),
+            None => String::new(),
+        };
+        println!("  follow-up:  {}{}", follow_up.description, due);
+    }
+    if !task.related.is_empty() {
+        println!("  related:    {}", related(&task.related));
+    }
+    let backlinks = list.backlinks(task.id);
+    if !backlinks.is_empty() {
+        println!("  backlinks:  {}", related(&backlinks));
+    }
+    if !task.time.is_empty() {
+        let running = if task.is_running() {
+            " (timer running)"
+        } else {
+            ""
+        };
+        println!(
+            "  tracked:    {} in {} session(s){}",
+            dates::format_duration(task.tracked(now)),
+            task.time.len(),
+            running
+        );
+    }
+    if let Some(person) = &task.assignee {
+        println!("  assigned:   {}", person);
+    }
+    if let Some(waiting) = &task.waiting_on {
+        println!(
+            "  waiting on: {} (since {})",
+            waiting.person,
+            show_due(waiting.since)
+        );
+    }
+    for reminder in &task.reminders {
+        let state = if reminder.delivered {
+            " (delivered)"
+        } else {
+            ""
+        };
+        println!(
+            "  reminder:   {}{}",
+            reminder.at.format("%Y-%m-%d %H:%M"),
+            state
+        );
+    }
+    for (n, attachment) in task.attachments.iter().enumerate() {
+        let missing = if attach::missing(attachment) {
+            " (missing)"
+        } else {
+            ""
+        };
+        println!(
+            "  attached:   {}. {}{}",
+            n + 1,
+            hyperlink(attachment, &attach::url(attachment), links),
+            missing
+        );
+    }
+    for (key, value) in &task.metadata {
+        println!("  {}: {}", key, metadata_link(key, value, links));
+    }
+    if let Some(uuid) = &task.uuid {
+        println!("  uuid:       {}", uuid);
+    }
+    for note in &task.notes {
+        println!("  note, {}:", note.at.format("%Y-%m-%d %H:%M"));
+        for line in note.text.lines() {
+            println!("    {}", linkify(line, links));
+        }
+    }
+    let history = list.history_of(task)?;
+    if !history.is_empty() {
+        println!("  history:");
+        let earlier = history.len().saturating_sub(SHOWN_HISTORY);
+        if earlier > 0 {
+            println!(
+                "    … {} earlier change(s); see `history {}`",
+                earlier, task.id
+            );
+        }
+        for event in &history[earlier..] {
+            println!(
+                "    {}  {} ({})",
+                event.at.format("%Y-%m-%d %H:%M"),
+                event.kind,
+                event.operation
+            );
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// How many of a task's latest changes `show` lists.
+const SHOWN_HISTORY: usize = 10;
+
+/// `show`: every detail of task `id`, as a card or as JSON.
+fn show(list: &TodoList, id: usize, format: ShowFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let task = list.get(id).ok_or_else(|| Error::task_not_found(id))?;
+    match format {
+        ShowFormat::Text => show_task(list, task),
+        ShowFormat::Json => {
+            let card = output::Card {
+                task,
+                time_tracked: (!task.time.is_empty())
+                    .then(|| task.tracked(dates::now()).num_seconds()),
+                blocked_by: list.blockers(task),
+                blocks: list.dependents(id),
+                subtasks: list.children(id).map(|t| t.id).collect(),
+                backlinks: list.backlinks(id),
+                history: list
+                    .history_of(task)?
+                    .into_iter()
+                    .map(|event| output::Change {
+                        at: event.at,
+                        change: event.kind.to_string(),
+                        operation: event.operation,
+                    })
+                    .collect(),
+            };
+            println!("{}", serde_json::to_string_pretty(&card)?);
+            Ok(())
+        }
+    }
+}
+
+fn search_tasks(list: &TodoList, args: SearchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.query.trim().is_empty() {
+        return Err("the search query is empty".into());
+    }
+    let matcher = if args.regex {
+        Matcher::regex(&args.query)?
+    } else if args.fuzzy {
+        Matcher::fuzzy(&args.query)
+    } else {
+        Matcher::substring(&args.query)
+    };
+    let archived = if args.archived { list.archived() } else { &[] };
+    let tasks = list
+        .tasks()
+        .iter()
+        .chain(archived)
+        .filter(|t| !(args.pending && t.completed));
+    let mut hits = search::search(tasks, &matcher);
+    let total = hits.len();
+    if let Some(limit) = args.limit {
+        hits.truncate(limit);
+    }
+    if args.format != OutputFormat::Text {
+        let tasks: Vec<&Task> = hits.iter().map(|hit| hit.task).collect();
+        print!("{}", output::render(&tasks, args.format)?);
+        return Ok(());
+    }
+    if hits.is_empty() {
+        println!("No tasks match {:?}.", args.query);
+        return Ok(());
+    }
+
+    let color = use_color();
+    println!("\n🔍 {} match(es) for {:?}:\n", total, args.query);
+    for hit in &hits {
+        let task = hit.task;
+        let checkbox = if task.completed { "[x]" } else { "[ ]" };
+        let description = match hit.field {
+            Field::Description if color => {
+                let mut marked = String::new();
+                let mut at = 0;
+                for range in &hit.ranges {
+                    marked.push_str(&task.description[at..range.start]);
+                    marked.push_str(&format!(
+                        "\x1b[1;4m{}\x1b[0m",
+                        &task.description[range.clone()]
+                    ));
+                    at = range.end;
+                }
+                marked.push_str(&task.description[at..]);
+                marked
+            }
+            _ => task.description.clone(),
+        };
+        let tags: String = task.tags.iter().map(|t| format!(" #{}", t)).collect();
+        let matched = match &hit.field {
+            Field::Description => String::new(),
+            Field::Tag(tag) => format!(" (tag #{})", tag),
+            Field::Location => format!(" (📍 {})", task.location.as_deref().unwrap_or_default()),
+            Field::WaitingOn => format!(
+                " (⏳ waiting on {})",
+                task.waiting_on.as_ref().map_or("", |w| w.person.as_str())
+            ),
+            Field::Metadata(key) => format!(" ({}: {})", key, task.metadata[key]),
+            Field::Note => " (in a note)".to_string(),
+        };
+        let archived = if list.is_archived(task) {
+            " 🗄 archived"
+        } else {
+            ""
+        };
+        println!(
+            "{} {} - {}{}{}{}",
+            checkbox, task.id, description, tags, matched, archived
+        );
+    }
+    if hits.len() < total {
+        println!(
+            "\n… and {} more (raise --limit to see them)",
+            total - hits.len()
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// The ID of the task `task` names; see [`TodoList::lookup`]. An ID is taken as it is, for
+/// the command to report when there is no such task.
+fn task_id(list: &TodoList, task: &TaskRef) -> Result<usize, Error> {
+    const SHOWN: usize = 5;
+    let name = match task {
+        TaskRef::Id(id) => return Ok(*id),
+        TaskRef::Name(name) => name,
+    };
+    match list.lookup(name).as_slice() {
+        [id] => Ok(*id),
+        [] => Err(Error::NotFound(format!(
+            "no task has the alias {:?} or a description starting with it",
+            name
+        ))),
+        ids => {
+            let mut candidates: Vec<String> = ids
+                .iter()
+                .take(SHOWN)
+                .filter_map(|id| list.get(*id))
+                .map(|t| format!("{} ({})", t.id, t.description))
+                .collect();
+            if ids.len() > SHOWN {
+                candidates.push(format!("and {} more", ids.len() - SHOWN));
+            }
+            Err(Error::Parse(format!(
+                "{:?} could be task {}; give the ID, or more of the description",
+                name,
+                candidates.join(", ")
+            )))
+        }
+    }
+}
+
+/// Fails if a pending task other than `id` already has `alias`.
+fn alias_free(list: &TodoList, alias: &str, id: Option<usize>) -> Result<(), String> {
+    let taken = list.pending().find(|t| {
+        Some(t.id) != id
+            && t.alias
+                .as_deref()
+                .is_some_and(|other| other.eq_ignore_ascii_case(alias))
+    });
+    match taken {
+        Some(task) => Err(format!("task {} already has the alias {}", task.id, alias)),
+        None => Ok(()),
+    }
+}
+
+/// The IDs `targets` names: those given, or those matching its filter. Prints a message and
+/// returns none when the filter matches nothing.
+fn resolve_targets(
+    list: &TodoList,
+    targets: &[Target],
+    filter: &[Selector],
+) -> Result<Vec<usize>, Error> {
+    if filter.is_empty() {
+        let mut ranges = vec![];
+        for target in targets {
+            ranges.push(match target {
+                Target::Ids(range) => range.clone(),
+                Target::Task(task) => task_id(list, task).map(|id| id..=id)?,
+            });
+        }
+        // Check them all first, so a mistyped ID doesn't leave the others half done.
+        let existing = list.tasks().iter().map(|t| t.id).collect();
+        let (ids, missing) = select::ids(&ranges, &existing);
+        return match missing.as_slice() {
+            [] => Ok(ids),
+            [id] if id.start() == id.end() => Err(Error::task_not_found(*id.start())),
+            _ => {
+                let missing: Vec<String> = missing
+                    .iter()
+                    .map(|range| {
+                        if range.start() == range.end() {
+                            range.start().to_string()
+                        } else {
+                            format!("{}-{}", range.start(), range.end())
+                        }
+                    })
+                    .collect();
+                Err(Error::NotFound(format!(
+                    "no tasks found for {}",
+                    missing.join(", ")
+                )))
+            }
+        };
+    }
+    let matching = select::matching(list.tasks(), filter);
+    if matching.is_empty() {
+        println!("No tasks match the filter.");
+    }
+    Ok(matching)
+}
+
+/// Runs `action` on each task `targets` names, saving once at the end.
+fn for_each_target(
+    list: &mut TodoList,
+    targets: &Targets,
+    (verb, always): (&str, bool),
+    safety: Safety,
+    mut action: impl FnMut(&mut TodoList, usize) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ids = resolve_targets(list, &targets.ids, &targets.filter)?;
+    if (always || ids.len() > 1) && !confirm_tasks(list, &ids, verb, safety)? {
+        return Ok(());
+    }
+    list.batch(|list| ids.into_iter().try_for_each(|id| action(list, id)))
+}
+
+/// Whether to go ahead with what `question` asks, e.g. "Delete these 3 tasks?", shown after
+/// `details` such as the tasks in question. `--yes`, `--dry-run` and `confirm = false` in the
+/// config file go ahead without asking. Without a terminal to ask on, it goes ahead only when
+/// `unattended`, and otherwise says to use `--yes`.
+fn confirmed(
+    question: &str,
+    details: &[String],
+    safety: Safety,
+    unattended: bool,
+) -> std::io::Result<bool> {
+    if safety.yes || safety.dry_run || !config::current().confirm {
+        return Ok(true);
+    }
+    if !(std::io::stdin().is_terminal() && std::io::stderr().is_terminal()) {
+        if !unattended {
+            for line in details {
+                println!("{}", line);
+            }
+            println!("⚠️  {} Use --yes to confirm.", question);
+        }
+        return Ok(unattended);
+    }
+    for line in details {
+        eprintln!("{}", line);
+    }
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let yes = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+    if !yes {
+        println!("Nothing changed.");
+    }
+    Ok(yes)
+}
+
+/// How many tasks [`confirm_tasks`] lists before summing up the rest.
+const CONFIRM_SHOWN: usize = 10;
+
+/// Asks whether to `verb` (e.g. "Delete") the tasks `ids`, listing them; see [`confirmed`].
+/// Scripts without a terminal go ahead, as they did before there was asking.
+fn confirm_tasks(
+    list: &TodoList,
+    ids: &[usize],
+    verb: &str,
+    safety: Safety,
+) -> std::io::Result<bool> {
+    let description = |id: &usize| list.get(*id).map_or("", |t| t.description.as_str());
+    if let [id] = ids {
+        let question = format!("{} task {} ({})?", verb, id, description(id));
+        return confirmed(&question, &[], safety, true);
+    }
+    let mut details: Vec<String> = ids
+        .iter()
+        .take(CONFIRM_SHOWN)
+        .map(|id| format!("  {} - {}", id, description(id)))
+        .collect();
+    if ids.len() > CONFIRM_SHOWN {
+        details.push(format!("  … and {} more", ids.len() - CONFIRM_SHOWN));
+    }
+    let question = format!("{} these {} tasks?", verb, ids.len());
+    confirmed(&question, &details, safety, true)
+}
+
+/// Splits the words of `tag` and `priority` into the leading IDs, ranges and aliases and the
+/// rest. The start of a description could be a tag, so it doesn't count. With a filter, every
+/// word is in the rest.
+fn split_ids<'a>(
+    list: &TodoList,
+    words: &'a [String],
+    filter: &[Selector],
+) -> Result<(Vec<Target>, &'a [String]), String> {
+    if !filter.is_empty() {
+        return Ok((vec![], words));
+    }
+    let aliased = |word: &str| {
+        list.tasks().iter().any(|t| {
+            t.alias
+                .as_deref()
+                .is_some_and(|a| a.eq_ignore_ascii_case(word))
+        })
+    };
+    // Keep at least one word for what to set.
+    let targets: Vec<Target> = words[..words.len().saturating_sub(1)]
+        .iter()
+        .map_while(|word| match select::parse_target(word) {
+            Ok(Target::Task(_)) if !aliased(word) => None,
+            target => target.ok(),
+        })
+        .collect();
+    if targets.is_empty() {
+        return Err(
+            "give the IDs or aliases of the tasks first (e.g. 3 5 7-10), or use --filter".into(),
+        );
+    }
+    let rest = &words[targets.len()..];
+    Ok((targets, rest))
+}
+
+fn set_priorities(
+    list: &mut TodoList,
+    words: &[String],
+    filter: &[Selector],
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (targets, rest) = split_ids(list, words, filter)?;
+    let [level] = rest else {
+        return Err(Error::Parse(
+            "give one priority after the IDs: low, medium, high or critical".into(),
+        )
+        .into());
+    };
+    let level = Priority::from_str(level, true).map_err(|_| {
+        Error::Parse(format!(
+            "unknown priority {:?} (use low, medium, high or critical)",
+            level
+        ))
+    })?;
+    let ids = resolve_targets(list, &targets, filter)?;
+    let verb = format!("Make {} the priority of", level);
+    if ids.len() > 1 && !confirm_tasks(list, &ids, &verb, safety)? {
+        return Ok(());
+    }
+    list.batch(|list| {
+        ids.into_iter()
+            .try_for_each(|id| set_priority(list, id, level))
+    })
+}
+
+fn tag_many(
+    list: &mut TodoList,
+    words: &[String],
+    filter: &[Selector],
+    remove: bool,
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (targets, tags) = split_ids(list, words, filter)?;
+    let ids = resolve_targets(list, &targets, filter)?;
+    let verb = if remove { "Untag" } else { "Tag" };
+    if ids.len() > 1 && !confirm_tasks(list, &ids, verb, safety)? {
+        return Ok(());
+    }
+    list.batch(|list| {
+        ids.into_iter()
+            .try_for_each(|id| tag(list, id, tags, remove))
+    })
+}
+
+fn set_priority(
+    list: &mut TodoList,
+    id: usize,
+    level: Priority,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match list.update(id, |task| task.priority = Some(level))? {
+        Some(()) => println!("✓ Task {} priority set to {}.", id, level),
+        None => return Err(Error::task_not_found(id).into()),
+    }
+    Ok(())
+}
+
+fn tag(
+    list: &mut TodoList,
+    id: usize,
+    tags: &[String],
+    remove: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let changed = list.update(id, |task| {
+        tags.iter()
+            .filter(|tag| {
+                if remove {
+                    task.remove_tag(tag)
+                } else {
+                    task.add_tag(tag)
+                }
+            })
+            .count()
+    })?;
+    match (changed, remove) {
+        (None, _) => return Err(Error::task_not_found(id).into()),
+        (Some(count), false) => println!("✓ Added {} tag(s) to task {}.", count, id),
+        (Some(count), true) => println!("✓ Removed {} tag(s) from task {}.", count, id),
+    }
+    Ok(())
+}
+
+/// Returns a completed or cancelled task to pending, keeping its ID, creation date, notes
+/// and time log.
+fn reopen(list: &mut TodoList, id: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let task = list.get(id).ok_or_else(|| Error::task_not_found(id))?;
+    if !task.completed {
+        println!("Task {} is not completed.", id);
+        return Ok(());
+    }
+    list.update(id, |task| task.set_status(Status::Todo))?;
+    println!("↺ Task {} reopened.", id);
+    Ok(())
+}
+
+/// Completes task `id`, and with `recursive` its pending subtasks first. Without, completing a
+/// task with pending subtasks asks first, and without a terminal to ask on needs `--yes`.
+fn complete(
+    list: &mut TodoList,
+    id: usize,
+    recursive: bool,
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pending: Vec<usize> = list
+        .descendants(id)
+        .into_iter()
+        .filter(|d| list.get(*d).is_some_and(|t| !t.completed))
+        .collect();
+    if !recursive && !pending.is_empty() && list.get(id).is_some_and(|t| !t.completed) {
+        let mut details = vec![format!(
+            "Task {} still has {} pending subtask(s):",
+            id,
+            pending.len()
+        )];
+        details.extend(pending.iter().map(|d| {
+            let description = list.get(*d).map_or("", |t| t.description.as_str());
+            format!("  {} - {}", d, description)
+        }));
+        let question = format!(
+            "Complete task {} and leave them pending? (--recursive completes them too)",
+            id
+        );
+        if !confirmed(&question, &details, safety, false)? {
+            return Ok(());
+        }
+    }
+    if recursive {
+        // Deepest first, so each subtask is done before its parent.
+        for subtask in pending.iter().rev() {
+            list.complete(*subtask)?;
+        }
+        if !pending.is_empty() {
+            println!("✓ Completed {} subtask(s).", pending.len());
+        }
+    }
+    let blockers = list
+        .get(id)
+        .map(|task| list.blockers(task))
+        .unwrap_or_default();
+    let last = list.tasks().iter().map(|t| t.id).max().unwrap_or(0);
+    let completion = list.complete(id)?;
+    let done = matches!(
+        completion,
+        Completion::Completed(_) | Completion::Repeated { .. }
+    );
+    match completion {
+        Completion::Completed(_) => println!("✓ Task {} marked as complete!", id),
+        Completion::Repeated { next, .. } => println!(
+            "✓ Task {} marked as complete! Next occurrence is task {}, due {}.",
+            id,
+            next.id,
+            next.due.map(show_due).unwrap_or_default()
+        ),
+        Completion::AlreadyCompleted => println!("Task {} is already completed.", id),
+        Completion::NotFound => return Err(Error::task_not_found(id).into()),
+    }
+    // The follow-up is the new task linking back to this one.
+    for follow_up in list.backlinks(id).into_iter().filter(|b| *b > last) {
+        if let Some(task) = list.get(follow_up) {
+            let due = match task.due {
+                Some(due) => format!(", due {}", show_due(due)),
+                None => String::new(),
+            };
+            println!(
+                "➜ Added follow-up task {}: {}{}",
+                task.id, task.description, due
+            );
+        }
+    }
+    if done && !recursive && !pending.is_empty() {
+        let ids: Vec<String> = pending.iter().map(|d| d.to_string()).collect();
+        println!(
+            "⚠️  Task {} still has {} pending subtask(s): {}. Use --recursive to complete them too.",
+            id,
+            pending.len(),
+            ids.join(", ")
+        );
+    }
+    if done && !blockers.is_empty() {
+        let ids: Vec<String> = blockers.iter().map(|d| d.to_string()).collect();
+        println!(
+            "⚠️  Task {} depends on {} unfinished task(s): {}.",
+            id,
+            blockers.len(),
+            ids.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn depend(
+    list: &mut TodoList,
+    id: usize,
+    on: &[usize],
+    remove: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(missing) = std::iter::once(&id)
+        .chain(on)
+        .find(|other| list.get(**other).is_none())
+    {
+        return Err(Error::task_not_found(*missing).into());
+    }
+    list.depend(id, on, remove)?;
+    let ids: Vec<String> = on.iter().map(|other| other.to_string()).collect();
+    if remove {
+        println!("✓ Task {} no longer depends on {}.", id, ids.join(", "));
+    } else {
+        println!("✓ Task {} now depends on {}.", id, ids.join(", "));
+    }
+    Ok(())
+}
+
+/// `start`: starts the timer on task `id`.
+fn start(list: &mut TodoList, id: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    if task.completed {
+        return Err(format!("task {} is already completed", id).into());
+    }
+    if task.is_running() {
+        println!("⏱ Already tracking time on task {}.", id);
+        return Ok(());
+    }
+    let description = task.description.clone();
+    if let Some((stopped, ran)) = list.start_timer(id, dates::now())? {
+        println!(
+            "⏹ Stopped task {} after {}.",
+            stopped.id,
+            dates::format_duration(ran)
+        );
+    }
+    println!("⏱ Tracking time on task {}: {}", id, description);
+    Ok(())
+}
+
+/// `stop`: stops the running timer.
+fn stop(list: &mut TodoList) -> Result<(), Box<dyn std::error::Error>> {
+    let now = dates::now();
+    match list.stop_timer(now)? {
+        Some((task, ran)) => println!(
+            "⏹ Stopped task {} after {} ({} in total): {}",
+            task.id,
+            dates::format_duration(ran),
+            dates::format_duration(task.tracked(now)),
+            task.description
+        ),
+        None => println!("No timer is running."),
+    }
+    Ok(())
+}
+
+/// `log`: time tracked per day and task, or per task with `by_task`.
+fn time_log(list: &TodoList, since: &str, by_task: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let now = dates::now();
+    let since = stats::parse_since(since, now).map_err(Error::Parse)?;
+    let days = timelog::by_day(list.tasks(), since, now);
+    if let Some(task) = list.running() {
+        println!(
+            "⏱ Tracking task {} for {}: {}",
+            task.id,
+            dates::format_duration(now - task.time.last().map_or(now, |i| i.start)),
+            task.description
+        );
+    }
+    if days.is_empty() {
+        println!("No time tracked since {}.", since.format("%Y-%m-%d"));
+        return Ok(());
+    }
+    let description = |id: usize| list.get(id).map_or("", |t| t.description.as_str());
+    if by_task {
+        println!("\n⏱ Time tracked since {}\n", since.format("%Y-%m-%d"));
+        for (id, spent) in timelog::by_task(&days) {
+            println!(
+                "  {:>8}  {} - {}",
+                dates::format_duration(spent),
+                id,
+                description(id)
+            );
+        }
+    } else {
+        println!();
+        for (day, tasks) in &days {
+            let spent: chrono::Duration = tasks.values().sum();
+            println!(
+                "{}  {}",
+                day.format("%a %Y-%m-%d"),
+                dates::format_duration(spent)
+            );
+            for (id, spent) in tasks {
+                println!(
+                    "  {:>8}  {} - {}",
+                    dates::format_duration(*spent),
+                    id,
+                    description(*id)
+                );
+            }
+        }
+    }
+    println!(
+        "\nTotal: {}\n",
+        dates::format_duration(timelog::total(&days))
+    );
+    Ok(())
+}
+
+/// `history`: what happened to task `id`, or to every task, since `since`.
+fn history(
+    list: &TodoList,
+    id: Option<usize>,
+    since: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let now = dates::now();
+    let since = match (since, id) {
+        (Some(since), _) => Some(stats::parse_since(since, now).map_err(Error::Parse)?),
+        (None, None) => Some(stats::parse_since("7d", now)?),
+        (None, Some(_)) => None,
+    };
+    let history = oplog::history(&oplog::path_for(list.path()))?;
+    let events: Vec<oplog::Event> = oplog::events(&history)
+        .into_iter()
+        .filter(|event| id.is_none_or(|id| event.id == id))
+        .filter(|event| since.is_none_or(|since| event.at >= since))
+        .collect();
+    if events.is_empty() {
+        match (id, since) {
+            (Some(id), _) if list.get(id).is_none() => return Err(Error::task_not_found(id).into()),
+            (Some(id), None) => println!("No recorded changes to task {}.", id),
+            (Some(id), Some(since)) => println!(
+                "No changes to task {} since {}.",
+                id,
+                since.format("%Y-%m-%d %H:%M")
+            ),
+            (None, since) => println!(
+                "No changes since {}.",
+                since.unwrap_or(now).format("%Y-%m-%d %H:%M")
+            ),
+        }
+        return Ok(());
+    }
+    match id {
+        Some(id) => println!(
+            "\n🕘 History of task {} - {}\n",
+            id,
+            events[events.len() - 1].description
+        ),
+        None => println!(
+            "\n🕘 Changes since {}\n",
+            since.unwrap_or(now).format("%Y-%m-%d %H:%M")
+        ),
+    }
+    for event in &events {
+        let when = event.at.format("%Y-%m-%d %H:%M");
+        match id {
+            Some(_) => println!("  {}  {} ({})", when, event.kind, event.operation),
+            None => println!(
+                "  {}  {} - {}: {} ({})",
+                when, event.id, event.description, event.kind, event.operation
+            ),
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// `report focus`: time in sessions of at least `min` minutes on each day of the week with
+/// `week` in it, and per tag.
+/// `report`: what was done since `args.since` and what is still open.
+fn status_report(list: &TodoList, args: &ReportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let now = dates::now();
+    let since = stats::parse_since(&args.since, now).map_err(Error::Parse)?;
+    let mut tasks = list.tasks().to_vec();
+    tasks.extend(archive::load(&archive::path_for(list.path()))?);
+    let report = report::Report::of(&tasks, since, now);
+    let content = if args.html {
+        report.render_html(args.by)
+    } else {
+        report.render_markdown(args.by)
+    };
+    if args.clipboard {
+        clipboard::copy(&content)?;
+        eprintln!(
+            "✓ Copied the report ({} done, {} open) to the clipboard",
+            report.done.len(),
+            report.open.len()
+        );
+        return Ok(());
+    }
+    match &args.output {
+        Some(path) => {
+            fs::write(path, content)?;
+            eprintln!("✓ Wrote the report to {}", path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn focus_report(
+    list: &TodoList,
+    week: Option<&str>,
+    min: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const WIDTH: usize = 20;
+    let now = dates::now();
+    let day = match week {
+        Some(date) => dates::parse_due(date, now)
+            .map_err(Error::Parse)?
+            .date_naive(),
+        None => now.date_naive(),
+    };
+    let min = chrono::Duration::minutes(min as i64);
+    let (monday, focus) = timelog::week_focus(list.tasks(), day, now, min)?;
+    if focus.days.is_empty() {
+        println!(
+            "No focus sessions in the week of {}.",
+            monday.format("%a %Y-%m-%d")
+        );
+        return Ok(());
+    }
+    println!("\n🎯 Focus, week of {}\n", monday.format("%a %Y-%m-%d"));
+    let longest = focus.days.values().map(|(spent, _)| *spent).max();
+    for offset in 0..7 {
+        let day = monday + chrono::Days::new(offset);
+        let label = day.format("%a %m-%d");
+        match focus.days.get(&day) {
+            Some((spent, sessions)) => {
+                let longest = longest.unwrap_or(*spent).num_seconds().max(1);
+                let width = (spent.num_seconds() * WIDTH as i64 / longest).max(1) as usize;
+                println!(
+                    "{}  {:>8}  {:<WIDTH$}  {} session(s)",
+                    label,
+                    dates::format_duration(*spent),
+                    "█".repeat(width),
+                    sessions
+                );
+            }
+            None => println!("{}  {:>8}", label, "-"),
+        }
+    }
+    let total: chrono::Duration = focus.days.values().map(|(spent, _)| *spent).sum();
+    let sessions: usize = focus.days.values().map(|(_, n)| n).sum();
+    println!(
+        "\nTotal: {} in {} session(s) of {} or more",
+        dates::format_duration(total),
+        sessions,
+        dates::format_duration(min)
+    );
+    if focus.short > chrono::Duration::zero() {
+        println!(
+            "Not counted: {} in shorter stretches",
+            dates::format_duration(focus.short)
+        );
+    }
+    if !focus.tags.is_empty() {
+        let mut tags: Vec<_> = focus.tags.iter().collect();
+        tags.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        println!("\nBy tag:");
+        for (tag, spent) in tags {
+            let name = match tag.as_str() {
+                "" => "(no tag)".to_string(),
+                tag => format!("#{}", tag),
+            };
+            println!("  {:>8}  {}", dates::format_duration(*spent), name);
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// `link`: relates two tasks, or unrelates them with `remove`.
+fn link(
+    list: &mut TodoList,
+    id: usize,
+    other: usize,
+    remove: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(missing) = [id, other].into_iter().find(|id| list.get(*id).is_none()) {
+        return Err(Error::task_not_found(missing).into());
+    }
+    let changed = list.link(id, other, remove)?;
+    match (changed, remove) {
+        (true, false) => println!("✓ Linked task {} to task {}.", id, other),
+        (true, true) => println!("✓ Unlinked tasks {} and {}.", id, other),
+        (false, false) => println!("Tasks {} and {} are already linked.", id, other),
+        (false, true) => println!("Tasks {} and {} aren't linked.", id, other),
+    }
+    Ok(())
+}
+
+/// `follow-up`: sets what to add when task `id` is completed, or removes it with `clear`.
+fn follow_up(
+    list: &mut TodoList,
+    id: usize,
+    description: Option<String>,
+    due_in: Option<String>,
+    clear: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(offset) = &due_in {
+        dates::parse_duration(offset).map_err(Error::Parse)?;
+    }
+    let follow_up = description.map(|description| FollowUp {
+        description,
+        due_in,
+    });
+    let set = follow_up.is_some();
+    if list
+        .update(id, |task| task.follow_up = follow_up)?
+        .is_none()
+    {
+        return Err(Error::task_not_found(id).into());
+    }
+    if clear || !set {
+        println!("✓ Task {} no longer has a follow-up.", id);
+    } else {
+        println!("✓ Completing task {} will add its follow-up.", id);
+    }
+    Ok(())
+}
+
+/// The context set with `context set`, if any, noting on stderr that it applies when `note`.
+fn current_context(note: bool) -> Option<String> {
+    let context = config::context()?;
+    if note {
+        eprintln!(
+            "📍 Only tasks @{}; --any-context shows all of them.",
+            context
+        );
+    }
+    Some(context)
+}
+
+/// `context`: shows the current context and the contexts in use, or changes it.
+fn context(
+    list: &TodoList,
+    action: Option<ContextAction>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        Some(ContextAction::Set { name }) => {
+            let name = name.trim().trim_start_matches('@');
+            if name.is_empty() {
+                return Err(Error::Parse("the context has no name".into()).into());
+            }
+            let path = config::set_context(Some(name))?;
+            println!("📍 Context set to @{} in {}", name, path.display());
+        }
+        Some(ContextAction::Clear) => {
+            if config::context().is_none() {
+                println!("No context is set.");
+                return Ok(());
+            }
+            let path = config::set_context(None)?;
+            println!("✓ Context cleared in {}", path.display());
+        }
+        None => {
+            let current = config::context();
+            let current = current.as_deref();
+            match current {
+                Some(context) => println!("📍 Current context: @{}", context),
+                None => println!("No context is set; list and next show every task."),
+            }
+            let contexts = list.contexts();
+            if !contexts.is_empty() {
+                println!("\nContexts in use:");
+                for (name, count) in contexts {
+                    let marker = if current.is_some_and(|c| c.eq_ignore_ascii_case(&name)) {
+                        "▶"
+                    } else {
+                        " "
+                    };
+                    println!("  {} @{} ({} pending)", marker, name, count);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `next`: what can be worked on now.
+fn next(
+    list: &TodoList,
+    limit: Option<usize>,
+    any_context: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let context = if any_context {
+        None
+    } else {
+        current_context(true)
+    };
+    let mut tasks = list.actionable();
+    tasks.retain(|t| t.in_context(context.as_deref()));
+    if tasks.is_empty() {
+        println!("Nothing to do right now.");
+        return Ok(());
+    }
+    let blocked = list
+        .pending()
+        .filter(|t| !list.blockers(t).is_empty())
+        .count();
+    println!("\n▶ Next up:\n");
+    for task in tasks.iter().take(limit.unwrap_or(usize::MAX)) {
+        let priority = match task.priority {
+            Some(level) => format!(" ⚑ {}", level),
+            None => String::new(),
+        };
+        let due = match task.due {
+            Some(due) => format!(" 📅 {}", show_due(due)),
+            None => String::new(),
+        };
+        println!("  {} - {}{}{}", task.id, task.description, priority, due);
+    }
+    if blocked > 0 {
+        println!("\n({} task(s) blocked by dependencies)", blocked);
+    }
+    println!();
+    Ok(())
+}
+
+/// Days shown by `agenda --week`.
+const AGENDA_WEEK: usize = 7;
+
+/// `agenda`: the tasks coming up today, or each day of the week with `week`, after the overdue
+/// ones; see [`agenda`].
+fn show_agenda(
+    list: &TodoList,
+    week: bool,
+    any_context: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let context = if any_context {
+        None
+    } else {
+        current_context(true)
+    };
+    let now = dates::now();
+    let tasks: Vec<&Task> = list
+        .pending()
+        .filter(|t| t.in_context(context.as_deref()))
+        .collect();
+    let days = if week { AGENDA_WEEK } else { 1 };
+    let agenda = agenda::build(&tasks, days, now);
+    let color = use_color();
+    let theme = &config::current().theme;
+    let priority = |task: &Task| match task.priority {
+        Some(level) => format!(" {} {}", mark("⚑", "priority"), level),
+        None => String::new(),
+    };
+
+    println!(
+        "\n{}Agenda for {}:\n",
+        mark("📆 ", ""),
+        now.format("%A, %B %-d")
+    );
+    if !agenda.overdue.is_empty() {
+        println!("{}", paint(&theme.overdue, "Overdue", color));
+        for task in &agenda.overdue {
+            let due = task.due.map(show_due).unwrap_or_default();
+            println!(
+                "         {} - {}{} (due {})",
+                task.id,
+                task.description,
+                priority(task),
+                due
+            );
+        }
+        println!();
+    }
+    let today = now.date_naive();
+    for day in &agenda.days {
+        let name = match (day.date - today).num_days() {
+            0 => format!("Today, {}", day.date.format("%a %b %-d")),
+            1 => format!("Tomorrow, {}", day.date.format("%a %b %-d")),
+            _ => day.date.format("%A, %b %-d").to_string(),
+        };
+        println!("{}", paint(&theme.heading, &name, color));
+        if day.entries.is_empty() {
+            println!("         Nothing due.");
+        }
+        for entry in &day.entries {
+            let time = if dates::is_end_of_day(entry.at) {
+                String::new()
+            } else {
+                entry.at.format("%H:%M").to_string()
+            };
+            let why = match entry.reason {
+                Reason::Due => String::new(),
+                Reason::Repeats => format!(" {} again", mark("🔁", "repeats")),
+                Reason::Unsnoozed => format!(" {}back from snooze", mark("💤 ", "")),
+            };
+            println!(
+                "  {:>5}  {} - {}{}{}",
+                time,
+                entry.task.id,
+                entry.task.description,
+                priority(entry.task),
+                why
+            );
+        }
+        println!();
+    }
+    if week {
+        agenda_calendar(&agenda.days);
+    }
+    Ok(())
+}
+
+/// The days of `agenda --week` side by side, as wide as the terminal allows.
+fn agenda_calendar(days: &[agenda::Day]) {
+    let terminal = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+        .unwrap_or(100);
+    let width = (terminal.saturating_sub(3 * (days.len() - 1)) / days.len()).max(8);
+    let fit = |text: &str| -> String {
+        let chars = text.chars().count();
+        if chars > width {
+            let mut cut: String = text.chars().take(width - 1).collect();
+            cut.push('…');
+            cut
+        } else {
+            format!("{}{}", text, " ".repeat(width - chars))
+        }
+    };
+    let color = use_color();
+    let headings: Vec<String> = days
+        .iter()
+        .map(|day| {
+            let heading = fit(&day.date.format("%a %-d").to_string());
+            paint(&config::current().theme.heading, &heading, color)
+        })
+        .collect();
+    println!("{}", headings.join(" │ ").trim_end());
+    println!("{}", vec!["─".repeat(width); days.len()].join("─┼─"));
+    let rows = days.iter().map(|day| day.entries.len()).max().unwrap_or(0);
+    for row in 0..rows {
+        let cells: Vec<String> = days
+            .iter()
+            .map(|day| match day.entries.get(row) {
+                Some(entry) if dates::is_end_of_day(entry.at) => fit(&entry.task.description),
+                Some(entry) => fit(&format!(
+                    "{} {}",
+                    entry.at.format("%H:%M"),
+                    entry.task.description
+                )),
+                None => " ".repeat(width),
+            })
+            .collect();
+        println!("{}", cells.join(" │ ").trim_end());
+    }
+    println!();
+}
+
+/// The columns of `board`; cancelled tasks are left off.
+const BOARD: [Status; 4] = [
+    Status::Todo,
+    Status::InProgress,
+    Status::Waiting,
+    Status::Done,
+];
+
+/// `board`: the tasks in a column for each status, as wide as the terminal allows.
+fn board(
+    list: &TodoList,
+    tag: Option<String>,
+    done: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tag = tag.as_deref().map(todo_core::normalize_tag);
+    let now = dates::now();
+    let columns: Vec<Vec<&Task>> = BOARD
+        .iter()
+        .map(|status| list.column(*status, tag.as_deref()))
+        .collect();
+    let terminal = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+        .unwrap_or(100);
+    let width = (terminal.saturating_sub(3 * (columns.len() - 1)) / columns.len()).max(12);
+    let fit = |text: &str| -> String {
+        let chars = text.chars().count();
+        if chars > width {
+            let mut cut: String = text.chars().take(width - 1).collect();
+            cut.push('…');
+            cut
+        } else {
+            format!("{}{}", text, " ".repeat(width - chars))
+        }
+    };
+    let color = use_color();
+    let theme = &config::current().theme;
+    let headings: Vec<String> = BOARD
+        .iter()
+        .zip(&columns)
+        .map(|(status, tasks)| {
+            paint(
+                &theme.heading,
+                &fit(&format!("{} ({})", status.title(), tasks.len())),
+                color,
+            )
+        })
+        .collect();
+    println!("\n{}", headings.join(" │ ").trim_end());
+    let rule = vec!["─".repeat(width); columns.len()];
+    println!("{}", rule.join("─┼─"));
+    let rows = columns
+        .iter()
+        .zip(BOARD)
+        .map(|(tasks, status)| match status {
+            Status::Done => tasks.len().min(done),
+            _ => tasks.len(),
+        })
+        .max()
+        .unwrap_or(0);
+    for row in 0..rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .zip(BOARD)
+            .map(|(tasks, status)| match tasks.get(row) {
+                Some(task) if status != Status::Done || row < done => {
+                    let card = fit(&format!("{} {}", task.id, task.description));
+                    let style = match task.priority {
+                        _ if status == Status::Done => &theme.completed,
+                        _ if task.is_overdue(now) => &theme.overdue,
+                        Some(Priority::Critical) => &theme.critical,
+                        Some(Priority::High) => &theme.high,
+                        _ => "",
+                    };
+                    paint(style, &card, color)
+                }
+                _ => " ".repeat(width),
+            })
+            .collect();
+        println!("{}", cells.join(" │ ").trim_end());
+    }
+    let hidden = columns[BOARD.len() - 1].len().saturating_sub(done);
+    if hidden > 0 {
+        println!(
+            "
+({} older done task(s) not shown; raise --done to see them)",
+            hidden
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// `move` and `status <id> <status>`: moves task `id` to `status`, completing it through
+/// [`complete`] when that is done so repeats and follow-ups work as they do there. Cancelling
+/// stops the task's timer, and leaving waiting stops waiting on anyone.
+fn move_task(
+    list: &mut TodoList,
+    id: usize,
+    status: Status,
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    if task.status() == status {
+        println!("Task {} is already {}.", id, status);
+        return Ok(());
+    }
+    if status == Status::Done {
+        return complete(list, id, false, safety);
+    }
+    let running = task.is_running();
+    let now = dates::now();
+    list.batch(|list| {
+        if running && status == Status::Cancelled {
+            list.stop_timer(now)?;
+        }
+        list.update(id, |task| {
+            if status != Status::Waiting {
+                task.waiting_on = None;
+            }
+            task.set_status(status)
+        })
+    })?;
+    println!("✓ Task {} is now {}.", id, status);
+    Ok(())
+}
+
+/// `attach`: adds files and web addresses to task `id`, skipping those it already has.
+fn attach(
+    list: &mut TodoList,
+    id: usize,
+    targets: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if list.get(id).is_none() {
+        return Err(Error::task_not_found(id).into());
+    }
+    let references = targets
+        .iter()
+        .map(|target| attach::reference(target))
+        .collect::<Result<Vec<String>, _>>()?;
+    let added = list
+        .update(id, |task| {
+            let mut added = vec![];
+            for reference in references {
+                if task.attachments.contains(&reference) {
+                    println!("{} is already attached to task {}.", reference, id);
+                } else {
+                    task.attachments.push(reference.clone());
+                    added.push((task.attachments.len(), reference));
+                }
+            }
+            added
+        })?
+        .unwrap_or_default();
+    for (number, reference) in added {
+        println!("📎 Attached {} to task {} as {}.", reference, id, number);
+    }
+    Ok(())
+}
+
+/// `attach --remove`: removes attachment `number`, counting from 1, from task `id`.
+fn detach(list: &mut TodoList, id: usize, number: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let removed = list
+        .update(id, |task| {
+            (1..=task.attachments.len())
+                .contains(&number)
+                .then(|| task.attachments.remove(number - 1))
+        })?
+        .ok_or_else(|| Error::task_not_found(id))?;
+    match removed {
+        Some(attachment) => {
+            println!("✓ Removed {} from task {}.", attachment, id);
+            Ok(())
+        }
+        None => Err(Error::NotFound(format!("task {} has no attachment {}", id, number)).into()),
+    }
+}
+
+/// `open`: opens attachment `number` of task `id`, or its only one.
+fn open_attachment(
+    list: &TodoList,
+    id: usize,
+    number: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let task = list.get(id).ok_or_else(|| Error::task_not_found(id))?;
+    let attachments = &task.attachments;
+    let attachment = match number {
+        _ if attachments.is_empty() => {
+            return Err(Error::NotFound(format!(
+                "task {} has no attachments (add one with `attach {} FILE|URL`)",
+                id, id
+            ))
+            .into())
+        }
+        None if attachments.len() == 1 => &attachments[0],
+        None => {
+            let choices: Vec<String> = attachments
+                .iter()
+                .enumerate()
+                .map(|(n, a)| format!("  {}. {}", n + 1, a))
+                .collect();
+            return Err(Error::Parse(format!(
+                "task {} has {} attachments; pick one with --attachment N:\n{}",
+                id,
+                attachments.len(),
+                choices.join("\n")
+            ))
+            .into());
+        }
+        Some(n) => attachments
+            .get(n.wrapping_sub(1))
+            .ok_or_else(|| Error::NotFound(format!("task {} has no attachment {}", id, n)))?,
+    };
+    attach::open(attachment)?;
+    println!("↗ Opened {}", attachment);
+    Ok(())
+}
+
+fn note(list: &mut TodoList, action: NoteAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        NoteAction::Add { id, text } => {
+            let id = task_id(list, &id)?;
+            if list.get(id).is_none() {
+                return Err(Error::task_not_found(id).into());
+            }
+            let text = match text {
+                Some(text) => text,
+                None => {
+                    let mut text = String::new();
+                    std::io::stdin().read_to_string(&mut text)?;
+                    text
+                }
+            };
+            let text = text.trim();
+            if text.is_empty() {
+                return Err("the note is empty".into());
+            }
+            list.add_note(id, text)?;
+            println!("✓ Note added to task {}.", id);
+        }
+        NoteAction::Show { id } => {
+            let id = task_id(list, &id)?;
+            let Some(task) = list.get(id) else {
+                return Err(Error::task_not_found(id).into());
+            };
+            println!("\n{} - {}\n", task.id, task.description);
+            if task.notes.is_empty() {
+                println!("No notes yet; add one with `note add {} \"...\"`.", id);
+            }
+            for note in &task.notes {
+                println!("  {}", note.at.format("%Y-%m-%d %H:%M"));
+                for line in note.text.lines() {
+                    println!("    {}", line);
+                }
+            }
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn remind_at(list: &mut TodoList, id: usize, when: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let at = dates::parse_datetime(when, dates::now()).map_err(Error::Parse)?;
+    if list.remind_at(id, at)? {
+        println!(
+            "✓ Reminder set for task {} at {}",
+            id,
+            at.format("%Y-%m-%d %H:%M")
+        );
+    } else {
+        return Err(Error::task_not_found(id).into());
+    }
+    Ok(())
+}
+
+fn remind_check(list: &mut TodoList) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(rule) = &config::current().stale {
+        for stale in list.escalate_stale(rule, dates::now())? {
+            let mut changes = vec![];
+            if !rule.tag.is_empty() {
+                changes.push(format!("tagged #{}", todo_core::normalize_tag(&rule.tag)));
+            }
+            if let (true, Some(priority)) = (rule.bump_priority, stale.priority) {
+                changes.push(format!("now {} priority", priority));
+            }
+            let changes = if changes.is_empty() {
+                String::new()
+            } else {
+                format!("; {}", changes.join(", "))
+            };
+            println!(
+                "🕸  {} - {} (open for {} days{})",
+                stale.id, stale.description, stale.days, changes
+            );
+        }
+    }
+    for due in list.fire_reminders(dates::now())? {
+        println!(
+            "⏰ {} - {} (reminder for {})",
+            due.id,
+            due.description,
+            due.at.format("%Y-%m-%d %H:%M")
+        );
+    }
+    Ok(())
+}
+
+fn notify_daemon(
+    list: &mut TodoList,
+    before: &[String],
+    interval: u64,
+    once: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let leads = if before.is_empty() {
+        config::current().notify.lead_times()
+    } else {
+        before
+            .iter()
+            .map(|lead| dates::parse_duration(lead))
+            .collect()
+    }
+    .map_err(Error::Parse)?;
+    notify::run(
+        list,
+        &notify::Options {
+            leads,
+            interval: interval.max(1),
+            once,
+        },
+    )
+}
+
+/// `move-to`: moves task `id` and its subtasks from `list` to `target`, called `label` in
+/// messages. The tasks are saved
+/// in `target` before they are removed from `list`, and taken out of `target` again when
+/// that fails, so a failure leaves them in one list or the other.
+fn move_to(
+    list: &mut TodoList,
+    id: usize,
+    mut target: TodoList,
+    label: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    if target.path() == list.path() {
+        return Err(format!("task {} is already in {}", id, label).into());
+    }
+    let description = task.description.clone();
+    target.set_operation("move-to");
+    match list.move_to(id, &mut target)? {
+        Move::Moved {
+            id: new_id,
+            subtasks,
+        } => {
+            print!("✓ Moved task {}: {}", id, description);
+            if subtasks > 0 {
+                print!(" (with {} subtask(s))", subtasks);
+            }
+            println!(" to {} as task {}.", label, new_id);
+            Ok(())
+        }
+        Move::AlreadyThere(task) => Err(format!("task {} is already in {}", task, label).into()),
+        Move::NotFound => Err(Error::task_not_found(id).into()),
+    }
+}
+
+/// `snooze`: hides task `id` until `until`, or brings it back with `None`.
+fn snooze(
+    list: &mut TodoList,
+    id: usize,
+    until: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    let now = dates::now();
+    let Some(until) = until else {
+        if !task.is_snoozed(now) {
+            println!("Task {} isn't snoozed.", id);
+            return Ok(());
+        }
+        list.update(id, |task| task.snoozed_until = None)?;
+        println!("✓ Task {} is back.", id);
+        return Ok(());
+    };
+    if task.completed {
+        return Err(format!("task {} is already completed", id).into());
+    }
+    let until = dates::parse_start(&until, now).map_err(Error::Parse)?;
+    if until <= now {
+        return Err(Error::Parse(format!("{} has already passed", show_due(until))).into());
+    }
+    list.update(id, |task| task.snoozed_until = Some(until))?;
+    println!("💤 Task {} is snoozed until {}.", id, show_due(until));
+    Ok(())
+}
+
+/// `assign`: gives the task to `user`, or takes it from whoever has it.
+fn assign(
+    list: &mut TodoList,
+    id: usize,
+    user: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    match user {
+        Some(user) => {
+            let previous = task.assignee.clone();
+            list.update(id, |task| task.assignee = Some(user.clone()))?;
+            match previous.filter(|p| !p.eq_ignore_ascii_case(&user)) {
+                Some(previous) => println!(
+                    "✓ Task {} is now assigned to {} instead of {}.",
+                    id, user, previous
+                ),
+                None => println!("✓ Task {} is assigned to {}.", id, user),
+            }
+        }
+        None if task.assignee.is_none() => println!("Task {} isn't assigned to anyone.", id),
+        None => {
+            list.update(id, |task| task.assignee = None)?;
+            println!("✓ Task {} is no longer assigned.", id);
+        }
+    }
+    Ok(())
+}
+
+fn waiting_on(
+    list: &mut TodoList,
+    id: usize,
+    person: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    match person {
+        Some(person) => {
+            let waiting = WaitingOn {
+                person: person.clone(),
+                since: dates::now(),
+            };
+            list.update(id, |task| {
+                task.waiting_on = Some(waiting);
+                if !task.completed {
+                    task.set_status(Status::Waiting);
+                }
+            })?;
+            println!("✓ Task {} is now waiting on {}.", id, person);
+        }
+        None if task.waiting_on.is_none() => println!("Task {} is not waiting on anyone.", id),
+        None => {
+            list.update(id, |task| {
+                task.waiting_on = None;
+                if task.status() == Status::Waiting {
+                    task.set_status(Status::Todo);
+                }
+            })?;
+            println!("✓ Task {} is no longer waiting.", id);
+        }
+    }
+    Ok(())
+}
+
+fn delete(list: &mut TodoList, id: usize) -> Result<(), Box<dyn std::error::Error>> {
+    match list.delete(id)? {
+        Some(_) => {
+            println!("✓ Task {} deleted successfully!", id);
+            println!("  `restore {}` brings it back from the trash.", id);
+        }
+        None => return Err(Error::task_not_found(id).into()),
+    }
+    Ok(())
+}
+
+fn trash(
+    list: &mut TodoList,
+    action: TrashAction,
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        TrashAction::List => {
+            let trashed = list.trashed()?;
+            if trashed.is_empty() {
+                println!("The trash is empty.");
+                return Ok(());
+            }
+            println!("🗑  Trash:\n");
+            for entry in trashed {
+                println!(
+                    "{:>4} - {} (deleted {})",
+                    entry.task.id,
+                    entry.task.description,
+                    entry.deleted_at.format("%Y-%m-%d %H:%M")
+                );
+            }
+        }
+        TrashAction::Empty => {
+            let question = format!(
+                "Delete the {} task(s) in the trash for good?",
+                list.trashed()?.len()
+            );
+            if !confirmed(&question, &[], safety, false)? {
+                return Ok(());
+            }
+            println!("✓ Deleted {} task(s) for good.", list.empty_trash()?);
+        }
+    }
+    Ok(())
+}
+
+fn restore(list: &mut TodoList, id: usize) -> Result<(), Box<dyn std::error::Error>> {
+    match list.restore_trashed(id)? {
+        Some(new_id) if new_id != id => {
+            println!(
+                "✓ Restored task {} as task {} (its ID was taken).",
+                id, new_id
+            )
+        }
+        Some(_) => println!("✓ Restored task {}.", id),
+        None => {
+            return Err(Error::NotFound(format!(
+                "task {} is not in the trash (see `trash list`)",
+                id
+            ))
+            .into())
+        }
+    }
+    Ok(())
+}
+
+fn archive_tasks(list: &mut TodoList, older_than: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let cutoff = dates::now() - chrono::Duration::days(i64::from(older_than));
+    match list.archive(cutoff)? {
+        0 => println!(
+            "No completed tasks older than {} day(s) to archive.",
+            older_than
+        ),
+        count => println!(
+            "✓ Archived {} task(s). `list --archived` and `search --archived` still show them.",
+            count
+        ),
+    }
+    Ok(())
+}
+
+/// A file size for people, e.g. `340 KB`.
+fn show_size(bytes: u64) -> String {
+    match bytes {
+        0..1_000 => format!("{} bytes", bytes),
+        1_000..1_000_000 => format!("{:.1} KB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+fn compact(list: &mut TodoList) -> Result<(), Box<dyn std::error::Error>> {
+    let size = |list: &TodoList| fs::metadata(list.path()).map_or(0, |m| m.len());
+    let before = size(list);
+    list.compact()?;
+    println!(
+        "✓ Compacted {}: {} → {}.",
+        list.path().display(),
+        show_size(before),
+        show_size(size(list))
+    );
+    Ok(())
+}
+
+fn show_stats(list: &TodoList, since: &str, chart: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let now = dates::now();
+    let since = stats::parse_since(since, now).map_err(Error::Parse)?;
+    let history = oplog::history(&oplog::path_for(list.path()))?;
+    let stats = stats::compute(list.tasks(), &history, since, now);
+
+    println!("\n📊 Statistics since {}\n", since.format("%Y-%m-%d"));
+    println!("  Pending:   {:>5}", stats.pending);
+    println!("  Completed: {:>5}", stats.completed);
+    println!("  Overdue:   {:>5}", stats.overdue);
+    let completed: usize = stats.weeks.values().map(|w| w.completed).sum();
+    match stats.average_completion {
+        Some(average) => println!(
+            "  Average time to complete: {} (over {} task(s))",
+            dates::format_age(average),
+            completed
+        ),
+        None => println!("  Average time to complete: no tasks completed in this period"),
+    }
+    if stats.tracked > chrono::Duration::zero() {
+        println!("  Time tracked: {}", dates::format_duration(stats.tracked));
+    }
+
+    println!("\n  Week of      Added   Done");
+    let max = stats
+        .weeks
+        .values()
+        .map(|w| w.added.max(w.completed))
+        .max()
+        .unwrap_or(0);
+    for (monday, week) in &stats.weeks {
+        let bar = if chart {
+            format!("  {}", stats::bar(week.added, week.completed, max, 20))
+        } else {
+            String::new()
+        };
+        println!(
+            "  {}  {:>5}  {:>5}{}",
+            monday.format("%Y-%m-%d"),
+            week.added,
+            week.completed,
+            bar.trim_end()
+        );
+    }
+    if chart {
+        let done: Vec<usize> = stats.weeks.values().map(|w| w.completed).collect();
+        println!("  (█ done, ░ added but not done)");
+        println!("\n  Completed per week: {}", stats::sparkline(&done));
+    }
+
+    if !stats.tags.is_empty() {
+        println!("\n  Tags:");
+        let width = stats
+            .tags
+            .iter()
+            .map(|(tag, ..)| tag.len())
+            .max()
+            .unwrap_or(0);
+        for (tag, pending, completed) in &stats.tags {
+            println!(
+                "  #{:<width$}  {:>4} pending  {:>4} done",
+                tag,
+                pending,
+                completed,
+                width = width
+            );
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn purge(
+    list: &mut TodoList,
+    pattern: &str,
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let regex = Regex::new(pattern)?;
+    let matching = list.matching(&regex);
+    let mut details = vec![if matching.is_empty() {
+        format!("No tasks in the list match {:?}.", pattern)
+    } else {
+        format!("The following {} task(s) would be purged:", matching.len())
+    }];
+    details.extend(
+        matching
+            .iter()
+            .map(|task| format!("  {} - {}", task.id, task.description)),
+    );
+    // Commits already made keep the text; purging can't rewrite them.
+    let history = git::repository_for(list.path()).map(|dir| {
+        format!(
+            "⚠️  The git history in {} still has them: rewrite it, e.g. with git filter-repo, \
+             and force-push to remove them there too.",
+            dir.display()
+        )
+    });
+    details.extend(history.clone());
+    let question = "Purge them for good, also from the undo history, the trash, snapshots, \
+                    the archive and the journal? Nothing can bring them back.";
+    if !confirmed(question, &details, safety, false)? {
+        return Ok(());
+    }
+    let (count, copies) = list.purge(&regex)?;
+    match (count, copies) {
+        (0, 0) => println!("No tasks match {:?}.", pattern),
+        (count, 0) => println!("✓ Purged {} task(s).", count),
+        (count, copies) => println!(
+            "✓ Purged {} task(s), and {} copies of them from the undo history, \
+             trash, snapshots, archive and journal.",
+            count, copies
+        ),
+    }
+    if let Some(history) = history.filter(|_| count + copies > 0) {
+        eprintln!("{}", history);
+    }
+    Ok(())
+}
+
+fn export(
+    list: &TodoList,
+    format: Format,
+    anonymize: bool,
+    output: Option<&Path>,
+    to_clipboard: bool,
+    week: Option<&str>,
+    validate: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if validate && format != Format::Json {
+        return Err("--validate only applies to --format json".into());
+    }
+    let tasks: Vec<Task> = if anonymize {
+        list.tasks().iter().map(Task::anonymized).collect()
+    } else {
+        list.tasks().to_vec()
+    };
+    if validate {
+        conforms(&serde_json::to_value(&tasks)?)?;
+    }
+    let now = dates::now();
+    let day = match week {
+        Some(date) => dates::parse_datetime(date, now)
+            .map_err(Error::Parse)?
+            .date_naive(),
+        None => now.date_naive(),
+    };
+    let content = formats::render(format, &tasks, day)?;
+    if to_clipboard {
+        let text = String::from_utf8(content).map_err(|_| "a PDF cannot be copied as text")?;
+        clipboard::copy(&text)?;
+        eprintln!("✓ Copied {} task(s) to the clipboard", tasks.len());
+        return Ok(());
+    }
+    match output {
+        Some(path) => {
+            fs::write(path, content)?;
+            eprintln!("✓ Exported {} task(s) to {}", tasks.len(), path.display());
+        }
+        None => std::io::stdout().write_all(&content)?,
+    }
+    Ok(())
+}
+
+fn publish(
+    list: &TodoList,
+    args: &PublishArgs,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let now = dates::now();
+    let since = stats::parse_since(&args.since, now).map_err(Error::Parse)?;
+    let history = oplog::history(&oplog::path_for(list.path()))?;
+    let tag = args.tag.as_deref().map(todo_core::normalize_tag);
+    let tasks: Vec<Task> = list
+        .tasks()
+        .iter()
+        .filter(|t| tag.as_ref().is_none_or(|tag| t.tags.contains(tag)))
+        .cloned()
+        .collect();
+    let items = feed::items(&tasks, &history, since);
+    let default_title = format!("{} tasks", name);
+    let is_url = |target: &str| target.starts_with("http://") || target.starts_with("https://");
+    let targets = [
+        (args.rss.as_deref(), "RSS"),
+        (args.json_feed.as_deref(), "JSON Feed"),
+    ];
+    for (target, kind) in targets {
+        let Some(target) = target else { continue };
+        let channel = feed::Channel {
+            title: args.title.as_deref().unwrap_or(&default_title),
+            link: args.link.as_deref(),
+            feed_url: Some(target).filter(|t| is_url(t)),
+        };
+        let (content, content_type) = if kind == "RSS" {
+            (
+                feed::render_rss(&channel, &items, now),
+                "application/rss+xml",
+            )
+        } else {
+            (
+                feed::render_json(&channel, &items)?,
+                "application/feed+json",
+            )
+        };
+        if is_url(target) {
+            ureq::put(target)
+                .header("Content-Type", content_type)
+                .send(content.as_bytes())?;
+        } else {
+            fs::write(target, content)?;
+        }
+        eprintln!(
+            "✓ Published {} task(s) as {} to {}",
+            items.len(),
+            kind,
+            target
+        );
+    }
+    Ok(())
+}
+
+/// Fails with every place `document` departs from the task schema.
+fn conforms(document: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    json_schema::validate(document).map_err(|errors| {
+        Error::Parse(format!(
+            "the tasks don't match the schema (see `rust-todo schema`):\n  {}",
+            errors.join("\n  ")
+        ))
+        .into()
+    })
+}
+
+/// How many of the problems found in imported input `import` lists.
+const MAX_PROBLEMS: usize = 20;
+
+/// An error reading `path`, naming it, that still exits with [`error::IO`].
+fn read_error(path: &Path, error: std::io::Error) -> std::io::Error {
+    std::io::Error::new(
+        error.kind(),
+        format!("could not read {}: {}", path.display(), error),
+    )
+}
+
+fn import(
+    list: &mut TodoList,
+    args: ImportArgs,
+    backend: Backend,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ImportArgs {
+        path,
+        clipboard: from_clipboard,
+        format,
+        duplicates,
+        validate,
+    } = args;
+    if validate && format != Format::Json {
+        return Err("--validate only applies to --format json".into());
+    }
+    let path = path.as_deref().unwrap_or(Path::new("-"));
+    let read = || -> Result<String, Box<dyn std::error::Error>> {
+        if from_clipboard {
+            clipboard::paste()
+        } else if path == Path::new("-") {
+            let mut buffer = String::new();
+            std::io::stdin().read_to_string(&mut buffer)?;
+            Ok(buffer)
+        } else {
+            Ok(fs::read_to_string(path).map_err(|e| read_error(path, e))?)
+        }
+    };
+    let parse = || -> Result<formats::Parsed, Box<dyn std::error::Error>> {
+        if format == Format::Keep {
+            if from_clipboard {
+                return Err("Keep takeouts are directories and cannot be pasted".into());
+            }
+            let tasks = keep::parse_dir(path).map_err(|e| {
+                error::context(e, format_args!("could not read {}", path.display()))
+            })?;
+            return Ok(formats::Parsed {
+                tasks,
+                ..Default::default()
+            });
+        }
+        let content = read()?;
+        if validate {
+            let document =
+                serde_json::from_str(&content).map_err(|e| format!("not valid JSON: {}", e))?;
+            conforms(&document)?;
+        }
+        if format == Format::Json && from_clipboard {
+            return Ok(formats::Parsed {
+                tasks: clipboard::parse(&content)?,
+                ..Default::default()
+            });
+        }
+        formats::parse(format, &content)
+    };
+    let formats::Parsed {
+        tasks: imported,
+        problems,
+        unsupported,
+    } = parse().map_err(error::as_parse)?;
+    let cycles = parent_cycles(&imported);
+    if !cycles.is_empty() {
+        return Err(Error::Parse(format!(
+            "tasks {:?} being imported are in a cycle of subtasks",
+            cycles
+        ))
+        .into());
+    }
+    if imported.is_empty() {
+        if let Some(first) = problems.first() {
+            return Err(Error::Parse(format!(
+                "nothing could be imported; {} ({} problem(s) in all)",
+                first,
+                problems.len()
+            ))
+            .into());
+        }
+    }
+    let (imported, conflicts) = import::resolve_duplicates(list, imported, duplicates);
+    let snapshot = if list.tasks().is_empty() || imported.is_empty() || list.is_dry_run() {
+        None
+    } else {
+        Some(list.snapshot("import").map_err(|e| {
+            format!(
+                "could not write a recovery snapshot, nothing imported: {}",
+                e
+            )
+        })?)
+    };
+    let report = list.merge(imported)?;
+    println!("✓ Imported {} task(s).", report.added);
+    if report.duplicates > 0 {
+        println!("  Skipped {} already imported task(s).", report.duplicates);
+    }
+    match duplicates {
+        _ if conflicts == 0 => {}
+        Duplicates::Keep => println!(
+            "  {} task(s) have the same description as one already in the list (see --duplicates).",
+            conflicts
+        ),
+        Duplicates::Skip => println!(
+            "  Skipped {} task(s) with the same description as one already in the list.",
+            conflicts
+        ),
+        Duplicates::Rename => println!(
+            "  Marked {} task(s) with the same description as one already in the list as \"(imported)\".",
+            conflicts
+        ),
+    }
+    if unsupported > 0 {
+        println!(
+            "  Skipped {} item(s) with no task equivalent (habits, rewards).",
+            unsupported
+        );
+    }
+    if !problems.is_empty() {
+        println!("  {} problem(s) in the input:", problems.len());
+        for problem in problems.iter().take(MAX_PROBLEMS) {
+            println!("    {}", problem);
+        }
+        if problems.len() > MAX_PROBLEMS {
+            println!("    … and {} more", problems.len() - MAX_PROBLEMS);
+        }
+    }
+    if let Some(snapshot) = snapshot.filter(|_| report.added > 0) {
+        println!(
+            "  To go back to the list before the import: {}",
+            restore_command(backend, name, &snapshot)
+        );
+    }
+    Ok(())
+}
+
+fn ingest(list: &mut TodoList, source: IngestSource) -> Result<(), Box<dyn std::error::Error>> {
+    let IngestSource::Maildir {
+        path,
+        unread,
+        flagged,
+        from,
+        subject,
+    } = source;
+    let rules = maildir::Rules {
+        unread,
+        flagged: flagged || !unread,
+        from: from.as_deref().map(Regex::new).transpose()?,
+        subject: subject.as_deref().map(Regex::new).transpose()?,
+    };
+    let report = list.merge(maildir::ingest(&path, &rules)?)?;
+    println!(
+        "✓ Created {} task(s) from {} ({} already ingested).",
+        report.added,
+        path.display(),
+        report.duplicates
+    );
+    Ok(())
+}
+
+fn sync_git(list: &mut TodoList, remote: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let synced = git::sync(list.path(), remote, &std::env::current_exe()?)?;
+    list.reload();
+    if synced.created {
+        println!("✓ Tracking the lists in {} with git", synced.dir.display());
+    }
+    let report = synced.report;
+    if !report.remote {
+        println!("Committed locally. Add a remote with `rust-todo sync git --remote <url>` to share the lists.");
+        return Ok(());
+    }
+    println!(
+        "✓ Synced with {}: {}, {}",
+        git::remote_url(&synced.dir)?,
+        if report.pulled {
+            "pulled changes"
+        } else {
+            "nothing to pull"
+        },
+        if report.pushed {
+            "pushed"
+        } else {
+            "nothing to push"
+        }
+    );
+    Ok(())
+}
+
+fn sync(list: &mut TodoList, target: SyncTarget) -> Result<(), Box<dyn std::error::Error>> {
+    match target {
+        SyncTarget::Obsidian { vault, inbox } => {
+            let report = obsidian::sync(list, &vault, &inbox)?;
+            println!(
+                "✓ Synced with {}: {} imported, {} completed here, {} checked off in the vault, {} renamed, {} added to {}",
+                vault.display(),
+                report.imported,
+                report.completed_in_store,
+                report.completed_in_vault,
+                report.renamed,
+                report.appended,
+                inbox
+            );
+            if !report.orphaned.is_empty() {
+                println!(
+                    "⚠️  Vault references unknown task IDs {:?}; remove their ^todo-<id> markers to re-import them.",
+                    report.orphaned
+                );
+            }
+        }
+        SyncTarget::Caldav { url, user } => {
+            let password = std::env::var(caldav::PASSWORD_ENV)
+                .map_err(|_| format!("set {} to the CalDAV password", caldav::PASSWORD_ENV))?;
+            let report = caldav::sync(list, &caldav::Client::new(&url, &user, &password))?;
+            println!(
+                "✓ Synced with {}: {} uploaded, {} updated there, {} updated here, {} added here, {} deleted there, {} unlinked",
+                url,
+                report.uploaded,
+                report.updated_there,
+                report.updated_here,
+                report.added_here,
+                report.deleted_there,
+                report.unlinked
+            );
+        }
+        SyncTarget::Git { remote } => sync_git(list, remote.as_deref())?,
+        SyncTarget::MergeDriver { base, ours, theirs } => {
+            git::merge_files(&base, &ours, &theirs)?;
+        }
+        SyncTarget::Markdown { file } => {
+            let report = markdown::sync(list, &file)?;
+            println!(
+                "✓ Synced with {}: store +{} ~{} -{}, file +{} ~{} -{}",
+                file.display(),
+                report.created_in_store,
+                report.updated_in_store,
+                report.deleted_in_store,
+                report.created_in_file,
+                report.updated_in_file,
+                report.deleted_in_file
+            );
+            for conflict in &report.conflicts {
+                let (kept, lost) = if conflict.kept_file {
+                    ("file", "store")
+                } else {
+                    ("store", "file")
+                };
+                println!(
+                    "⚠️  Task {}: kept the {}'s {}, dropping {} from the {}.",
+                    conflict.id, kept, conflict.field, conflict.lost, lost
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Undoes (or with `redo`, re-applies) the last `count` operations and says what changed.
+fn undo(list: &mut TodoList, count: usize, redo: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let operations = if redo {
+        list.redo(count)?
+    } else {
+        list.undo(count)?
+    };
+    if operations.is_empty() {
+        println!("Nothing to {}.", if redo { "redo" } else { "undo" });
+        return Ok(());
+    }
+    for operation in &operations {
+        println!(
+            "{} {} from {} ({} task(s))",
+            if redo { "↷ Redid" } else { "↶ Undid" },
+            operation.name,
+            operation.at.format("%Y-%m-%d %H:%M"),
+            operation.changes.len()
+        );
+        for change in &operation.changes {
+            let (now, verb) = match (&change.before, &change.after, redo) {
+                (Some(before), None, false) => (before, "restored"),
+                (None, Some(after), false) => (after, "removed"),
+                (Some(before), _, false) => (before, "reverted"),
+                (None, Some(after), true) => (after, "re-added"),
+                (Some(before), None, true) => (before, "deleted again"),
+                (_, Some(after), true) => (after, "re-applied"),
+                (None, None, _) => continue,
+            };
+            println!("  {} {} - {}", verb, change.id, now.description);
+        }
+    }
+    Ok(())
+}
+
+fn clear(
+    list: &mut TodoList,
+    safety: Safety,
+    backend: Backend,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let question = format!("Move all {} task(s) to the trash?", list.tasks().len());
+    if !confirmed(&question, &[], safety, false)? {
+        return Ok(());
+    }
+    if list.tasks().is_empty() {
+        println!("✓ Cleared 0 task(s).");
+        return Ok(());
+    }
+    if list.is_dry_run() {
+        println!(
+            "✓ Cleared {} task(s); they are in the trash.",
+            list.clear()?
+        );
+        return Ok(());
+    }
+    let snapshot = list.snapshot("clear").map_err(|e| {
+        format!(
+            "could not write a recovery snapshot, nothing cleared: {}",
+            e
+        )
+    })?;
+    let count = list.clear()?;
+    println!("✓ Cleared {} task(s); they are in the trash.", count);
+    println!(
+        "  To get them back: {}",
+        restore_command(backend, name, &snapshot)
+    );
+    Ok(())
+}
+
+/// The command line that restores `snapshot` of the list `name`.
+fn restore_command(backend: Backend, name: &str, snapshot: &str) -> String {
+    let mut command = "rust-todo".to_string();
+    if let Some(value) = backend
+        .to_possible_value()
+        .filter(|_| backend != Backend::Json)
+    {
+        command.push_str(&format!(" --backend {}", value.get_name()));
+    }
+    if name != config::current().list.as_deref().unwrap_or(DEFAULT_LIST) {
+        command.push_str(&format!(" --list {}", name));
+    }
+    format!("{} snapshot restore {}", command, snapshot)
+}
+
+fn snapshots(
+    list: &mut TodoList,
+    action: SnapshotAction,
+    backend: Backend,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        SnapshotAction::List => {
+            let names = snapshot::list(list.path());
+            let tags = snapshot::tags(list.path());
+            if names.is_empty() && tags.is_empty() {
+                println!("No snapshots of this list yet.");
+            }
+            for snapshot in &names {
+                let count = snapshot::load(list.path(), snapshot)?.len();
+                println!("{:<32} {} task(s)", snapshot, count);
+            }
+            if !tags.is_empty() {
+                let gap = if names.is_empty() { "" } else { "\n" };
+                println!("{}Tagged:", gap);
+            }
+            for tag in tags {
+                let count = snapshot::load(list.path(), &tag)?.len();
+                println!("{:<32} {} task(s)", tag, count);
+            }
+        }
+        SnapshotAction::Tag { name: tag } => {
+            list.tag_snapshot(&tag)?;
+            println!(
+                "✓ Tagged a snapshot of {} task(s) as {}.",
+                list.tasks().len(),
+                tag
+            );
+            println!(
+                "  Bring it back with: {}",
+                restore_command(backend, name, &tag)
+            );
+        }
+        SnapshotAction::Diff { name: snapshot } => {
+            let then = snapshot::load(list.path(), &snapshot)?;
+            let changes = oplog::diff(&then, list.tasks());
+            if changes.is_empty() {
+                println!("No changes since {}.", snapshot);
+                return Ok(());
+            }
+            println!("Changes since {}:\n", snapshot);
+            print_changes(changes);
+            println!();
+        }
+        SnapshotAction::Restore { name: snapshot } => {
+            let before = list.restore(&snapshot)?;
+            println!(
+                "✓ Restored {} task(s) from {}.",
+                list.tasks().len(),
+                snapshot
+            );
+            println!(
+                "  The tasks it replaced: {}",
+                restore_command(backend, name, &before)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn template(list: &mut TodoList, action: TemplateAction) -> Result<(), Box<dyn std::error::Error>> {
+    let now = dates::now();
+    // A date without a time keeps the time of day, so offsets in hours land as they did.
+    let counting_from = |when: Option<String>| -> Result<_, Error> {
+        let Some(when) = when else {
+            return Ok(now);
+        };
+        let at = dates::parse_due(&when, now).map_err(Error::Parse)?;
+        if !dates::is_end_of_day(at) {
+            return Ok(at);
+        }
+        Ok(at
+            .date_naive()
+            .and_time(now.time())
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .unwrap_or(at))
+    };
+    match action {
+        TemplateAction::List => {
+            let names = templates::names();
+            if names.is_empty() {
+                println!("No templates yet. Save tasks as one with: template save NAME ID...");
+            }
+            for name in &names {
+                match templates::load(name) {
+                    Ok(Some(template)) => {
+                        println!("{:<24} {} task(s)", name, template.tasks.len())
+                    }
+                    Ok(None) => {}
+                    Err(e) => println!("{:<24} ⚠️  {}", name, e),
+                }
+            }
+            if let Some(dir) = templates::dir().filter(|_| !names.is_empty()) {
+                println!("\nIn {}", dir.display());
+            }
+        }
+        TemplateAction::Save {
+            name,
+            targets,
+            recursive,
+            from,
+            force,
+        } => {
+            let start = counting_from(from)?;
+            let mut ids = vec![];
+            for id in resolve_targets(list, &targets.ids, &targets.filter)? {
+                let below = if recursive {
+                    list.descendants(id)
+                } else {
+                    vec![]
+                };
+                for id in std::iter::once(id).chain(below) {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+            }
+            if ids.is_empty() {
+                return Ok(());
+            }
+            let tasks: Vec<&Task> = ids.iter().filter_map(|id| list.get(*id)).collect();
+            let path = Template::from_tasks(&tasks, start).save(&name, force)?;
+            println!(
+                "✓ Saved {} task(s) as template {} in {}.",
+                tasks.len(),
+                name,
+                path.display()
+            );
+            println!("  Add them again with: rust-todo template apply {}", name);
+        }
+        TemplateAction::Apply { name, start } => {
+            let template = templates::load(&name)?.ok_or_else(|| {
+                Error::NotFound(format!(
+                    "there is no template named {:?} (see template list)",
+                    name
+                ))
+            })?;
+            let ids = list.add_template(&template, counting_from(start)?)?;
+            let ids: Vec<String> = ids.iter().map(usize::to_string).collect();
+            println!(
+                "✓ Added {} task(s) from template {}: {}.",
+                ids.len(),
+                name,
+                ids.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Names of the fields that differ between two versions of a task, as stored.
+fn changed_fields(before: &Task, after: &Task) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(before)), Ok(serde_json::Value::Object(after))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return vec![];
+    };
+    let mut fields: Vec<String> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/// Tasks stored for a list, read from the backend's file (or the only one there is) without
+/// creating it.
+fn stored_tasks(
+    json_path: &Path,
+    backend: Backend,
+) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    let found = std::iter::once(backend)
+        .chain(Backend::value_variants().iter().copied())
+        .find(|b| b.path(json_path).exists());
+    match found {
+        Some(backend) => backend.storage(json_path)?.load(),
+        None => Ok(vec![]),
+    }
+}
+
+fn list_lists(backend: Backend) -> Result<(), Box<dyn std::error::Error>> {
+    for name in TodoList::list_names() {
+        let tasks = stored_tasks(&TodoList::named_data_path(&name)?, backend)?;
+        let pending = tasks.iter().filter(|t| !t.completed).count();
+        println!("{:<16} {} pending, {} total", name, pending, tasks.len());
+    }
+    Ok(())
+}
+
+fn new_list(name: &str, backend: Backend) -> Result<(), Box<dyn std::error::Error>> {
+    let json_path = TodoList::named_data_path(name)?;
+    if dirs::stored(&json_path) || name == DEFAULT_LIST {
+        return Err(format!("a list named {:?} already exists", name).into());
+    }
+    fs::create_dir_all(TodoList::lists_dir()?)?;
+    backend.storage(&json_path)?.save(&[])?;
+    println!("✓ Created list {:?}. Use it with --list {}.", name, name);
+    Ok(())
+}
+
+fn delete_list(name: &str, safety: Safety) -> Result<(), Box<dyn std::error::Error>> {
+    if name == DEFAULT_LIST {
+        return Err("the default list cannot be deleted; use `clear` to empty it".into());
+    }
+    let json_path = TodoList::named_data_path(name)?;
+    let files: Vec<PathBuf> = dirs::stores(&json_path)
+        .iter()
+        .flat_map(|store| {
+            [
+                store.clone(),
+                oplog::path_for(store),
+                trash::path_for(store),
+                archive::path_for(store),
+                summary::path_for(store),
+            ]
+        })
+        .filter(|path| path.exists())
+        .collect();
+    if files.is_empty() {
+        return Err(Error::NotFound(format!("there is no list named {:?}", name)).into());
+    }
+    let question = format!("Delete the list {:?} and all its tasks?", name);
+    if !confirmed(&question, &[], safety, false)? {
+        return Ok(());
+    }
+    if safety.dry_run {
+        println!("Dry run: nothing was deleted. The list's files are:");
+        for path in files {
+            println!("  {}", path.display());
+        }
+        return Ok(());
+    }
+    for path in files {
+        fs::remove_file(path)?;
+    }
+    println!("✓ Deleted list {:?}.", name);
+    Ok(())
+}
+
+fn init_tracing(verbose: u8, log_file: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let default_level = match verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match log_file {
+        Some(path) => {
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            builder
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .init();
+        }
+        None => builder.with_writer(std::io::stderr).init(),
+    }
+    Ok(())
+}
+
+/// Opens the named list in the chosen backend. A new SQLite database or task log starts with
+/// a copy of the JSON file's tasks, so switching backends keeps the list.
+fn open_list(backend: Backend, name: &str) -> Result<TodoList, Box<dyn std::error::Error>> {
+    let json_path = TodoList::named_data_path(name)?;
+    if name != DEFAULT_LIST && !dirs::stored(&json_path) {
+        return Err(Error::NotFound(format!(
+            "there is no list named {:?}; create it with `rust-todo new-list {}`",
+            name, name
+        ))
+        .into());
+    }
+    warn_if_other_store_newer(backend, &json_path);
+    match backend {
+        Backend::Json => Ok(TodoList::open(json_path)),
+        backend => {
+            let path = backend.path(&json_path);
+            let fresh = !path.exists();
+            let storage = backend.storage(&json_path)?;
+            // Under the lock, so a process saving the new database at the same time isn't
+            // overwritten.
+            let _lock = Lock::acquire(&path)?;
+            if fresh && json_path.exists() && storage.load()?.is_empty() {
+                let tasks = JsonFile::new(&json_path).load()?;
+                storage.save(&tasks)?;
+                info!(count = tasks.len(), from = %json_path.display(), "copied tasks into the new store");
+            }
+            Ok(TodoList::with_storage(storage))
+        }
+    }
+}
+
+/// Warns when the list was saved with another backend after it was last saved with
+/// `backend`, as the changes made there aren't in the store about to be used.
+fn warn_if_other_store_newer(backend: Backend, json_path: &Path) {
+    let modified = |backend: Backend| {
+        std::fs::metadata(backend.path(json_path))
+            .and_then(|m| m.modified())
+            .ok()
+    };
+    let Some(ours) = modified(backend) else {
+        return;
+    };
+    for other in Backend::value_variants().iter().filter(|b| **b != backend) {
+        if modified(*other).is_some_and(|theirs| theirs > ours) {
+            let name = |b: Backend| b.to_possible_value().map(|v| v.get_name().to_string());
+            eprintln!(
+                "Warning: {} was saved after {}; changes made with --backend {} aren't in this list.",
+                other.path(json_path).display(),
+                backend.path(json_path).display(),
+                name(*other).unwrap_or_default(),
+            );
+        }
+    }
+}
+
+/// Whether `command` changes the opened list, and so can't run when it is read-only.
+fn changes_list(command: &Commands) -> bool {
+    !matches!(
+        command,
+        Commands::List(ListArgs { pick: None, .. })
+            | Commands::Show { .. }
+            | Commands::Inbox
+            | Commands::Search(_)
+            | Commands::Snapshot {
+                action: SnapshotAction::List
+                    | SnapshotAction::Tag { .. }
+                    | SnapshotAction::Diff { .. }
+            }
+            | Commands::Trash {
+                action: TrashAction::List
+            }
+            | Commands::Template {
+                action: TemplateAction::List | TemplateAction::Save { .. }
+            }
+            | Commands::Note {
+                action: NoteAction::Show { .. }
+            }
+            | Commands::Open { .. }
+            | Commands::Status { id: None, .. }
+            | Commands::Next { .. }
+            | Commands::Agenda { .. }
+            | Commands::Context { action: None }
+            | Commands::Board { .. }
+            | Commands::Log { .. }
+            | Commands::Report { .. }
+            | Commands::History { .. }
+            | Commands::Stats { .. }
+            | Commands::Export { .. }
+            | Commands::Publish(_)
+            | Commands::Schema
+            | Commands::ListLists
+            | Commands::NewList { .. }
+            | Commands::DeleteList { .. }
+            | Commands::Rpc
+            | Commands::Serve { .. }
+            | Commands::Shell
+            | Commands::Bench { .. }
+            | Commands::Doctor
+            | Commands::Init
+            | Commands::Config { .. }
+            | Commands::Completions { .. }
+            | Commands::CompleteIds { .. }
+            | Commands::SelfUpdate { .. }
+            | Commands::Plugin(_)
+    )
+}
+
+fn command_name(command: &Commands) -> &'static str {
+    match command {
+        Commands::Add(_) => "add",
+        Commands::Quick { .. } => "quick",
+        Commands::Enqueue { .. } => "enqueue",
+        Commands::List(_) => "list",
+        Commands::Show { .. } => "show",
+        Commands::Inbox => "inbox",
+        Commands::Triage => "triage",
+        Commands::Search(_) => "search",
+        Commands::Priority { .. } => "priority",
+        Commands::Tag { .. } => "tag",
+        Commands::Edit { .. } => "edit",
+        Commands::Complete { .. } => "complete",
+        Commands::Reopen { .. } => "reopen",
+        Commands::Delete { .. } => "delete",
+        Commands::Clear => "clear",
+        Commands::Snapshot { .. } => "snapshot",
+        Commands::Trash { .. } => "trash",
+        Commands::Restore { .. } => "restore",
+        Commands::Archive { .. } => "archive",
+        Commands::Compact => "compact",
+        Commands::Undo { .. } => "undo",
+        Commands::Redo { .. } => "redo",
+        Commands::ListLists => "list-lists",
+        Commands::NewList { .. } => "new-list",
+        Commands::DeleteList { .. } => "delete-list",
+        Commands::MoveTo { .. } => "move-to",
+        Commands::Note { .. } => "note",
+        Commands::Attach { .. } => "attach",
+        Commands::Open { .. } => "open",
+        Commands::RemindAt { .. } => "remind-at",
+        Commands::RemindCheck => "remind-check",
+        Commands::NotifyDaemon { .. } => "notify-daemon",
+        Commands::WaitingOn { .. } => "waiting-on",
+        Commands::Assign { .. } => "assign",
+        Commands::Snooze { .. } => "snooze",
+        Commands::Depend { .. } => "depend",
+        Commands::Link { .. } => "link",
+        Commands::FollowUp { .. } => "follow-up",
+        Commands::Next { .. } => "next",
+        Commands::Agenda { .. } => "agenda",
+        Commands::Context { .. } => "context",
+        Commands::Board { .. } => "board",
+        Commands::Move { .. } => "move",
+        Commands::Start { .. } => "start",
+        Commands::Stop => "stop",
+        Commands::Pomodoro { .. } => "pomodoro",
+        Commands::Log { .. } => "log",
+        Commands::Report { .. } => "report",
+        Commands::History { .. } => "history",
+        Commands::Rpc => "rpc",
+        Commands::Serve { .. } => "serve",
+        Commands::Scan { .. } => "scan",
+        Commands::Status { .. } => "status",
+        Commands::Stats { .. } => "stats",
+        Commands::Purge { .. } => "purge",
+        Commands::Dedupe { .. } => "dedupe",
+        Commands::Sync { .. } => "sync",
+        Commands::Export { .. } => "export",
+        Commands::Import(_) => "import",
+        Commands::Apply { .. } => "apply",
+        Commands::Template { .. } => "template",
+        Commands::Publish(_) => "publish",
+        Commands::Schema => "schema",
+        Commands::Ingest { .. } => "ingest",
+        Commands::Bot { .. } => "bot",
+        Commands::Bench { .. } => "bench",
+        Commands::Shell => "shell",
+        Commands::Doctor => "doctor",
+        Commands::Init => "init",
+        Commands::Config { .. } => "config",
+        Commands::Completions { .. } => "completions",
+        Commands::CompleteIds { .. } => completions::IDS_COMMAND,
+        Commands::SelfUpdate { .. } => "self-update",
+        Commands::Plugin(_) => "plugin",
+    }
+}
+
+/// Runs `command` on `todo_list`, the list called `name`, for the command line and the shell
+/// alike.
+fn run(
+    command: Commands,
+    todo_list: &mut TodoList,
+    backend: Backend,
+    name: &str,
+    safety: Safety,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let operation = command_name(&command);
+    todo_list.set_operation(operation);
+    let changes = changes_list(&command);
+    check_dry_run(&command, safety)?;
+    todo_list.set_dry_run(safety.dry_run);
+    // Refuse before reading input or opening an editor, rather than when saving.
+    if let Some(reason) = todo_list.read_only().filter(|_| changes) {
+        return Err(format!(
+            "{} is read-only ({}), so tasks can't be changed. Viewing commands such as `list`, `status` and `export` still work.",
+            todo_list.path().display(),
+            reason
+        )
+        .into());
+    }
+    let result = match command {
+        Commands::Add(args) if args.from_grep => add_from_grep(todo_list),
+        Commands::Add(args) if args.stdin => add_from_stdin(todo_list, args),
+        Commands::Add(args) => add(todo_list, args),
+        Commands::Quick { text } => quick_add(todo_list, &text.join(" ")),
+        Commands::Enqueue { description, tags } => enqueue(backend, name, description, &tags),
+        Commands::List(args) if args.pick.is_some() => pick(todo_list, args, safety),
+        Commands::List(args) => list_tasks(todo_list, args).map(drop),
+        Commands::Show { id, format } => show(todo_list, task_id(todo_list, &id)?, format),
+        Commands::Inbox => list_tasks(
+            todo_list,
+            ListArgs {
+                pending: true,
+                inbox: true,
+                ..ListArgs::default()
+            },
+        )
+        .map(drop),
+        Commands::Triage => triage::run(
+            todo_list,
+            &mut std::io::stdin().lock(),
+            &mut std::io::stdout(),
+        ),
+        Commands::Search(args) => search_tasks(todo_list, args),
+        Commands::Priority { words, filter } => set_priorities(todo_list, &words, &filter, safety),
+        Commands::Tag {
+            words,
+            filter,
+            remove,
+        } => tag_many(todo_list, &words, &filter, remove, safety),
+        Commands::Edit { id, args } => {
+            let id = task_id(todo_list, &id)?;
+            edit::run(todo_list, id, args)
+        }
+        Commands::Complete {
+            targets,
+            recursive,
+            toggle,
+        } => for_each_target(
+            todo_list,
+            &targets,
+            ("Complete", false),
+            safety,
+            |list, id| match list.get(id) {
+                Some(task) if toggle && task.completed => reopen(list, id),
+                _ => complete(list, id, recursive, safety),
+            },
+        ),
+        Commands::Reopen { targets } => {
+            for_each_target(todo_list, &targets, ("Reopen", false), safety, reopen)
+        }
+        Commands::Delete { targets } => {
+            for_each_target(todo_list, &targets, ("Delete", true), safety, delete)
+        }
+        Commands::Clear => clear(todo_list, safety, backend, name),
+        Commands::Snapshot { action } => snapshots(todo_list, action, backend, name),
+        Commands::Trash { action } => trash(todo_list, action, safety),
+        Commands::Restore { id } => restore(todo_list, id),
+        Commands::Archive { older_than } => archive_tasks(todo_list, older_than),
+        Commands::Compact => compact(todo_list),
+        Commands::Undo { count } => undo(todo_list, count, false),
+        Commands::Redo { count } => undo(todo_list, count, true),
+        Commands::ListLists => list_lists(backend),
+        Commands::NewList { name } => new_list(&name, backend),
+        Commands::MoveTo { id, to, file } => {
+            let id = task_id(todo_list, &id)?;
+            let target = match (to, file) {
+                (_, Some(file)) => (file.display().to_string(), TodoList::open(file)),
+                (Some(list), None) => {
+                    let target = open_list(backend, &list)?;
+                    (list, target)
+                }
+                (None, None) => unreachable!("clap requires a list or --file"),
+            };
+            move_to(todo_list, id, target.1, &target.0)
+        }
+        Commands::DeleteList { name } => delete_list(&name, safety),
+        Commands::Note { action } => note(todo_list, action),
+        Commands::Attach {
+            id,
+            targets,
+            remove,
+        } => {
+            let id = task_id(todo_list, &id)?;
+            match remove {
+                Some(number) => detach(todo_list, id, number),
+                None => attach(todo_list, id, &targets),
+            }
+        }
+        Commands::Open { id, attachment } => {
+            open_attachment(todo_list, task_id(todo_list, &id)?, attachment)
+        }
+        Commands::RemindAt { id, datetime } => {
+            let id = task_id(todo_list, &id)?;
+            remind_at(todo_list, id, &datetime)
+        }
+        Commands::RemindCheck => remind_check(todo_list),
+        Commands::NotifyDaemon {
+            before,
+            interval,
+            once,
+        } => notify_daemon(todo_list, &before, interval, once),
+        Commands::Assign { id, user, .. } => {
+            let id = task_id(todo_list, &id)?;
+            assign(todo_list, id, user)
+        }
+        Commands::WaitingOn { id, person, .. } => {
+            let id = task_id(todo_list, &id)?;
+            waiting_on(todo_list, id, person)
+        }
+        Commands::Snooze { id, until, .. } => {
+            let id = task_id(todo_list, &id)?;
+            snooze(todo_list, id, until)
+        }
+        Commands::Depend { id, on, remove } => {
+            let id = task_id(todo_list, &id)?;
+            let on = on
+                .iter()
+                .map(|other| task_id(todo_list, other))
+                .collect::<Result<Vec<usize>, Error>>()?;
+            depend(todo_list, id, &on, remove)
+        }
+        Commands::Link { id, other, remove } => {
+            let (id, other) = (task_id(todo_list, &id)?, task_id(todo_list, &other)?);
+            link(todo_list, id, other, remove)
+        }
+        Commands::FollowUp {
+            id,
+            description,
+            due_in,
+            clear,
+        } => {
+            let id = task_id(todo_list, &id)?;
+            follow_up(todo_list, id, description, due_in, clear)
+        }
+        Commands::Next { limit, any_context } => next(todo_list, limit, any_context),
+        Commands::Agenda { week, any_context } => show_agenda(todo_list, week, any_context),
+        Commands::Context { action } => context(todo_list, action),
+        Commands::Board { tag, done } => board(todo_list, tag, done),
+        Commands::Move { id, status } => {
+            let id = task_id(todo_list, &id)?;
+            move_task(todo_list, id, status, safety)
+        }
+        Commands::Start { id } => {
+            let id = task_id(todo_list, &id)?;
+            start(todo_list, id)
+        }
+        Commands::Stop => stop(todo_list),
+        Commands::Pomodoro {
+            id,
+            work,
+            rest,
+            rounds,
+            notify,
+        } => pomodoro::run(
+            todo_list,
+            task_id(todo_list, &id)?,
+            &pomodoro::Options {
+                work: std::time::Duration::from_secs(work * 60),
+                rest: std::time::Duration::from_secs(rest * 60),
+                rounds,
+                notify,
+            },
+        ),
+        Commands::Log { since, by_task } => time_log(todo_list, &since, by_task),
+        Commands::History { id, since } => {
+            let id = id.map(|id| task_id(todo_list, &id)).transpose()?;
+            history(todo_list, id, since.as_deref())
+        }
+        Commands::Report {
+            report: Some(ReportKind::Focus { week, min }),
+            ..
+        } => focus_report(todo_list, week.as_deref(), min),
+        Commands::Report { report: None, args } => status_report(todo_list, &args),
+        Commands::Rpc => rpc::serve(todo_list),
+        Commands::Serve {
+            port,
+            host,
+            allow_origin,
+            users,
+        } => users
+            .as_deref()
+            .map(access::Access::load)
+            .transpose()
+            .and_then(|access| {
+                serve::run(
+                    todo_list,
+                    &|name| open_list(backend, name),
+                    serve::Options {
+                        host,
+                        port,
+                        allow_origin,
+                        list: name.to_string(),
+                        access,
+                    },
+                )
+            }),
+        Commands::Scan { dir } => scan::scan(todo_list, &dir).map(|report| {
+            println!(
+                "✓ Scanned {} file(s): {} new TODO(s), {} moved, {} resolved.",
+                report.files, report.added, report.moved, report.resolved
+            );
+        }),
+        Commands::Status {
+            id: Some(id),
+            state: Some(state),
+            ..
+        } => {
+            let id = task_id(todo_list, &id)?;
+            move_task(todo_list, id, state, safety)
+        }
+        Commands::Status { format, .. } => {
+            let counts = summary::Summary::of(todo_list.tasks(), dates::now());
+            println!("{}", status::render(&counts, format));
+            // So the next prompt can use it; see the fast path in `main`.
+            if todo_list.read_only().is_none() {
+                if let Err(e) = summary::write(todo_list.path(), todo_list.tasks()) {
+                    debug!(error = %e, "could not write the summary");
+                }
+            }
+            Ok(())
+        }
+        Commands::Stats { since, chart } => show_stats(todo_list, &since, chart),
+        Commands::Purge { matching } => purge(todo_list, &matching, safety),
+        Commands::Dedupe { threshold, auto } => dedupe::run(
+            todo_list,
+            threshold,
+            auto,
+            &mut std::io::stdin().lock(),
+            &mut std::io::stdout(),
+        ),
+        Commands::Sync { target } => sync(todo_list, target),
+        Commands::Export {
+            format,
+            anonymize,
+            output,
+            clipboard,
+            week,
+            validate,
+        } => export(
+            todo_list,
+            format,
+            anonymize,
+            output.as_deref(),
+            clipboard,
+            week.as_deref(),
+            validate,
+        ),
+        Commands::Import(args) => import(todo_list, args, backend, name),
+        Commands::Apply { file } => {
+            let input = if file == Path::new("-") {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                std::fs::read_to_string(&file).map_err(|e| read_error(&file, e))?
+            };
+            let applied = batch::apply(todo_list, &input)?;
+            println!("✓ Applied {} operation(s).", applied);
+            Ok(())
+        }
+        Commands::Template { action } => template(todo_list, action),
+        Commands::Publish(args) => publish(todo_list, &args, name),
+        Commands::Schema => {
+            print!("{}", json_schema::SCHEMA);
+            Ok(())
+        }
+        Commands::Ingest { source } => ingest(todo_list, source),
+        Commands::Bot {
+            network: BotNetwork::Matrix { homeserver, room },
+        } => match std::env::var(matrix::TOKEN_ENV) {
+            Ok(token) => matrix::MatrixBot::new(&homeserver, token).run(&room, todo_list),
+            Err(_) => Err(format!("set {} to the bot's access token", matrix::TOKEN_ENV).into()),
+        },
+        Commands::Bench { target } => bench::run(target),
+        Commands::Shell => shell::run(todo_list, backend, name),
+        Commands::Doctor => doctor::run(todo_list.storage()),
+        Commands::Init => unreachable!("init runs before the list is opened"),
+        Commands::Config { action } => config_command(action),
+        Commands::Completions { shell } => {
+            print!("{}", completions::script(shell, Cli::command()));
+            Ok(())
+        }
+        Commands::CompleteIds { command } => completions::print_ids(todo_list, &command),
+        Commands::SelfUpdate { check } => update::self_update(check),
+        Commands::Plugin(args) => {
+            let status = plugin::run(todo_list, name, &args)?;
+            if !status.success() {
+                return Err(format!(
+                    "`rust-todo-{}` exited with {}",
+                    args[0].to_string_lossy(),
+                    status
+                )
+                .into());
+            }
+            Ok(())
+        }
+    };
+    if safety.dry_run {
+        if result.is_ok() && changes {
+            dry_run_report(todo_list);
+        }
+        // The shell goes on with the list as saved.
+        todo_list.set_dry_run(false);
+        todo_list.reload();
+        return result;
+    }
+    if result.is_ok() && changes {
+        commit_change(todo_list, operation, name);
+    }
+    result
+}
+
+/// Whether `command` only changes the task list, so `--dry-run` can keep it from saving
+/// anything. Those that also write other files, send things out or run on are left out.
+fn previewable(command: &Commands) -> bool {
+    !matches!(
+        command,
+        Commands::Enqueue { .. }
+            | Commands::Snapshot {
+                action: SnapshotAction::Restore { .. } | SnapshotAction::Tag { .. }
+            }
+            | Commands::Compact
+            | Commands::NewList { .. }
+            | Commands::MoveTo { .. }
+            | Commands::RemindCheck
+            | Commands::NotifyDaemon { .. }
+            | Commands::Context { action: Some(_) }
+            | Commands::Pomodoro { .. }
+            | Commands::Rpc
+            | Commands::Serve { .. }
+            | Commands::Sync { .. }
+            | Commands::Export {
+                output: Some(_),
+                ..
+            }
+            | Commands::Export {
+                clipboard: true,
+                ..
+            }
+            | Commands::Template {
+                action: TemplateAction::Save { .. }
+            }
+            | Commands::Publish(_)
+            | Commands::Ingest { .. }
+            | Commands::Bot { .. }
+            | Commands::Bench { .. }
+            | Commands::Shell
+            | Commands::Init
+            | Commands::Config { .. }
+            | Commands::SelfUpdate { .. }
+            | Commands::Plugin(_)
+    )
+}
+
+/// Refuses `--dry-run` for commands it can't keep from changing anything.
+fn check_dry_run(command: &Commands, safety: Safety) -> Result<(), Error> {
+    if safety.dry_run && !previewable(command) {
+        return Err(Error::Parse(format!(
+            "`{}` changes more than the task list, so it can't be tried with --dry-run",
+            command_name(command)
+        )));
+    }
+    Ok(())
+}
+
+/// After `--dry-run`: what the command would have saved.
+fn dry_run_report(list: &TodoList) {
+    let changes = list.unsaved();
+    if changes.is_empty() {
+        println!("Dry run: nothing would change.");
+        return;
+    }
+    println!("Dry run: nothing was saved. This would have changed:\n");
+    print_changes(changes);
+    println!();
+}
+
+/// Prints `changes` one task a line: + added, - removed and ~ changed, with what changed.
+fn print_changes(changes: Vec<oplog::Change>) {
+    for change in changes {
+        match (change.before, change.after) {
+            (None, Some(task)) => println!("  + {} - {}", task.id, task.description),
+            (Some(task), None) => println!("  - {} - {}", task.id, task.description),
+            (Some(before), Some(after)) => println!(
+                "  ~ {} - {} ({})",
+                after.id,
+                after.description,
+                changed_fields(&before, &after).join(", ")
+            ),
+            (None, None) => {}
+        }
+    }
+}
+
+fn config_command(action: ConfigAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ConfigAction::Export { output } => {
+            let bundle = config::export()?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, bundle)?;
+                    println!("✓ Settings exported to {}", path.display());
+                }
+                None => print!("{}", bundle),
+            }
+        }
+        ConfigAction::Import { file } => {
+            let bundle = if file.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                fs::read_to_string(&file)?
+            };
+            let path = config::import(&bundle).map_err(Error::Parse)?;
+            println!("✓ Settings imported into {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// `init`: runs the setup wizard, then creates the lists it was given.
+fn init(backend: Backend) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(setup) = init::run(&mut std::io::stdin().lock(), &mut std::io::stdout())? else {
+        return Ok(());
+    };
+    println!("\n✓ Settings written to {}", setup.path.display());
+    for name in &setup.lists {
+        let json_path = TodoList::named_data_path(name)?;
+        if dirs::stored(&json_path) {
+            println!("List {:?} already exists.", name);
+            continue;
+        }
+        new_list(name, backend)?;
+    }
+    println!("Add your first task with `rust-todo add \"...\"`; `rust-todo init` again changes these answers.");
+    Ok(())
+}
+
+/// Commits the change just made when the data directory is kept in git (see `sync git`).
+fn commit_change(list: &TodoList, operation: &str, name: &str) {
+    let Some(dir) = git::repository_for(list.path()) else {
+        return;
+    };
+    if let Err(e) = git::commit(dir, &format!("rust-todo {} ({} list)", operation, name)) {
+        eprintln!("Warning: could not commit the change to git: {}", e);
+    }
+}
+
+fn main() {
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        if e.use_stderr() {
+            let _ = e.print();
+            std::process::exit(error::PARSE);
+        }
+        // --help and --version
+        e.exit()
+    });
+    // A mistyped command isn't a plugin: give clap's error, with its suggestions, instead.
+    if let Commands::Plugin(args) = &cli.command {
+        if plugin::find(&args[0].to_string_lossy()).is_none() {
+            let e = Cli::command()
+                .allow_external_subcommands(false)
+                .external_subcommand_value_parser(None::<clap::builder::ValueParser>)
+                .try_get_matches()
+                .err()
+                .unwrap_or_else(|| {
+                    Cli::command()
+                        .error(clap::error::ErrorKind::InvalidSubcommand, "unknown command")
+                });
+            let _ = e.print();
+            std::process::exit(error::PARSE);
+        }
+    }
+    // Without a data directory or configuration, e.g. when building a package.
+    if let Commands::Completions { shell } = cli.command {
+        print!("{}", completions::script(shell, Cli::command()));
+        return;
+    }
+    // Before the commands that run without a list, such as `init`.
+    if let Err(e) = check_dry_run(&cli.command, cli.safety) {
+        eprintln!("Error: {}", e);
+        std::process::exit(error::exit_code(&e));
+    }
+    if let Some(path) = cli.data_file.clone() {
+        dirs::set_data_file(path);
+    }
+    if let Err(e) = init_tracing(cli.verbose, cli.log_file.as_deref()) {
+        eprintln!("Error: could not open log file: {}", e);
+        std::process::exit(error::IO);
+    }
+
+    if let Some(color) = cli.color {
+        let _ = COLOR.set(color);
+    }
+    let fixed_now = cli
+        .now
+        .clone()
+        .or_else(|| std::env::var(dates::NOW_ENV).ok().filter(|v| !v.is_empty()));
+    if let Some(when) = fixed_now {
+        match dates::parse_now(&when, Local::now()) {
+            Ok(now) => {
+                dates::set_now(now);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(error::PARSE);
+            }
+        }
+    }
+
+    // Like importing settings, setting up must work when the current ones are invalid.
+    if let Commands::Init = cli.command {
+        // The configuration may be what is being replaced, so it is only used when valid.
+        let backend = cli.backend.or_else(|| config::load().ok()?.backend);
+        if let Err(e) = init(backend.unwrap_or_default()) {
+            eprintln!("Error: {}", e);
+            std::process::exit(error::exit_code(&*e));
+        }
+        return;
+    }
+
+    // Importing settings must work when the current ones are invalid.
+    if let Commands::Config { action } = cli.command {
+        if let Err(e) = config_command(action) {
+            eprintln!("Error: {}", e);
+            std::process::exit(error::exit_code(&*e));
+        }
+        return;
+    }
+
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: invalid configuration: {}", e);
+            std::process::exit(error::PARSE);
+        }
+    };
+    let backend = cli.backend.or(config.backend).unwrap_or_default();
+    let mut name = cli
+        .list
+        .clone()
+        .or_else(|| config.list.clone())
+        .unwrap_or_else(|| DEFAULT_LIST.to_string());
+    // A project's own list, found the way git finds a repository, unless a list or data file
+    // is named.
+    if cli.list.is_none() && !cli.global && dirs::given_data_file().is_none() {
+        let here = std::env::current_dir().unwrap_or_default();
+        let local =
+            dirs::find_local(&here).or_else(|| cli.local.then(|| here.join(dirs::LOCAL_FILE)));
+        if let Some(path) = local {
+            info!(path = %path.display(), "using the project's list");
+            dirs::use_data_file(path);
+            name = DEFAULT_LIST.to_string();
+        }
+    }
+
+    match dirs::migrate() {
+        Ok(moved) => {
+            if let Some((from, to)) = moved.first() {
+                eprintln!(
+                    "Moved your tasks from {} to {} ({} file(s) in all).",
+                    from.display(),
+                    to.display(),
+                    moved.len()
+                );
+            }
+        }
+        Err(e) => eprintln!(
+            "Warning: could not move your tasks into the data directory: {}",
+            e
+        ),
+    }
+
+    // Git runs the merge driver in the middle of `sync git`, which holds the lock.
+    if let Commands::Sync {
+        target: SyncTarget::MergeDriver { base, ours, theirs },
+    } = &cli.command
+    {
+        if let Err(e) = git::merge_files(base, ours, theirs) {
+            eprintln!("Error: {}", e);
+            std::process::exit(error::exit_code(&*e));
+        }
+        return;
+    }
+
+    // Without loading or locking the list, which is the point of enqueue.
+    if let Commands::Enqueue { description, tags } = cli.command {
+        if let Err(e) = enqueue(backend, &name, description, &tags) {
+            eprintln!("Error: {}", e);
+            std::process::exit(error::exit_code(&*e));
+        }
+        return;
+    }
+
+    // Prompts run `status` all the time, so it reads the summary saved with the list when it
+    // can, without loading or locking the list.
+    if let Commands::Status {
+        id: None, format, ..
+    } = cli.command
+    {
+        if let Some(counts) = saved_summary(backend, &name) {
+            println!("{}", status::render(&counts, format));
+            return;
+        }
+    }
+
+    // The first run quietly uses the defaults; point out where they can be chosen.
+    let first_run = config::path().is_some_and(|path| !path.exists())
+        && TodoList::get_data_path().is_ok_and(|path| !path.exists())
+        && std::io::stderr().is_terminal();
+    if first_run {
+        eprintln!(
+            "Tip: run `rust-todo init` to choose where tasks are kept and how they're listed."
+        );
+    }
+
+    let launched = Instant::now();
+    let mut todo_list = match open_list(backend, &name) {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("Error: could not open the task store: {}", e);
+            std::process::exit(error::exit_code(&*e));
+        }
+    };
+    todo_list.set_dry_run(cli.safety.dry_run);
+    todo_list.set_operation("enqueue");
+    match todo_list.add_queued() {
+        Ok(0) => {}
+        Ok(added) => eprintln!("✓ Added {} queued task(s).", added),
+        Err(e) => eprintln!("Warning: could not add the queued tasks: {}", e),
+    }
+    let _span = info_span!("command", name = command_name(&cli.command)).entered();
+    let start = Instant::now();
+    // A plugin's exit status is the command's.
+    if let Commands::Plugin(args) = &cli.command {
+        match plugin::run(&todo_list, &name, args) {
+            Ok(status) => std::process::exit(status.code().unwrap_or(error::FAILURE)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(error::exit_code(&*e));
+            }
+        }
+    }
+    let result = run(cli.command, &mut todo_list, backend, &name, cli.safety);
+
+    let elapsed = start.elapsed();
+    debug!(elapsed = ?elapsed, "command finished");
+    if cli.timings {
+        let timings = todo_list.timings();
+        eprintln!(
+            "⏱  load {:?}, filter {:?}, save {:?} ({} save(s)), total {:?}",
+            timings.load,
+            timings.filter,
+            timings.save,
+            timings.saves,
+            launched.elapsed()
+        );
+    }
 
     if let Err(e) = result {
+        debug!(error = ?e, "command failed");
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+        std::process::exit(error::exit_code(&*e));
     }
 }
@@ -0,0 +1,342 @@
+//! Two-way sync between the task list and a hand-editable Markdown checklist.
+//!
+//! Each synced line carries an HTML comment marker that Markdown renderers hide:
+//!
+//! ```text
+//! - [ ] Buy milk <!-- todo:3 -->
+//! - [x] Call the bank <!-- todo:4 -->
+//! ```
+//!
+//! The state of every line after the last sync is kept as a base snapshot, so a change on
+//! one side can be told apart from a change on the other. Tasks are merged field by field, so a
+//! task completed on one side and reworded on the other keeps both changes. When both sides
+//! changed the same field, the user is asked which value to keep; without a terminal the store
+//! wins. Either way the report names the task and field whose other value was dropped.
+
+use crate::{Task, TodoList};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    description: String,
+    done: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created_in_store: usize,
+    pub created_in_file: usize,
+    pub updated_in_store: usize,
+    pub updated_in_file: usize,
+    pub deleted_in_store: usize,
+    pub deleted_in_file: usize,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// A field of a task changed differently in the file and the store since the last sync.
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub id: usize,
+    /// `description` or `completion`.
+    pub field: &'static str,
+    /// Whether the file's value was kept rather than the store's.
+    pub kept_file: bool,
+    /// The value dropped, as shown to the user.
+    pub lost: String,
+}
+
+enum Line {
+    Text(String),
+    Item {
+        indent: String,
+        id: Option<usize>,
+        entry: Entry,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    File,
+    Store,
+}
+
+fn parse(content: &str) -> Vec<Line> {
+    let item = Regex::new(r"^(\s*)[-*+] \[([ xX])\] (.*?)\s*(?:<!-- todo:(\d+) -->)?\s*$").unwrap();
+    content
+        .lines()
+        .map(|line| match item.captures(line) {
+            Some(caps) => Line::Item {
+                indent: caps[1].to_string(),
+                id: caps.get(4).and_then(|m| m.as_str().parse().ok()),
+                entry: Entry {
+                    description: caps[3].to_string(),
+                    done: &caps[2] != " ",
+                },
+            },
+            None => Line::Text(line.to_string()),
+        })
+        .collect()
+}
+
+fn render_item(indent: &str, id: usize, entry: &Entry) -> String {
+    let check = if entry.done { "x" } else { " " };
+    format!(
+        "{}- [{}] {} <!-- todo:{} -->",
+        indent, check, entry.description, id
+    )
+}
+
+/// Where the base snapshot for `file` lives: next to the data file, keyed by the checklist path.
+fn base_path(data_file: &Path, file: &Path) -> PathBuf {
+    let key = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    let digest = Sha256::digest(key.display().to_string().as_bytes());
+    let hash: String = digest
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    data_file
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(".rust-todo-sync")
+        .join(format!("markdown-{}.json", hash))
+}
+
+fn ask(id: usize, field: &str, file: &str, store: &str) -> Side {
+    if !std::io::stdin().is_terminal() {
+        return Side::Store;
+    }
+    println!("⚠️  The {} of task {} changed in both places:", field, id);
+    println!("    file:  {}", file);
+    println!("    store: {}", store);
+    loop {
+        print!("Keep (f)ile or (s)tore version? ");
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer).unwrap_or(0) == 0 {
+            return Side::Store;
+        }
+        match answer.trim() {
+            "f" | "file" => return Side::File,
+            "s" | "store" => return Side::Store,
+            _ => continue,
+        }
+    }
+}
+
+/// Three-way merge of a single field; `None` means both sides changed it differently.
+fn merge_field<T: Clone + PartialEq>(file: &T, store: &T, base: Option<&T>) -> Option<T> {
+    if file == store {
+        Some(file.clone())
+    } else if base == Some(file) {
+        Some(store.clone())
+    } else if base == Some(store) {
+        Some(file.clone())
+    } else {
+        None
+    }
+}
+
+/// Merges one task field by field, asking the user about each field changed on both sides.
+fn merge(
+    id: usize,
+    file: &Entry,
+    store: &Entry,
+    base: Option<&Entry>,
+    report: &mut SyncReport,
+) -> Entry {
+    let description = resolve(
+        (id, "description"),
+        &file.description,
+        &store.description,
+        base.map(|b| &b.description),
+        |d| format!("{:?}", d),
+        report,
+    );
+    let done = resolve(
+        (id, "completion"),
+        &file.done,
+        &store.done,
+        base.map(|b| &b.done),
+        |&done| if done { "done" } else { "not done" }.to_string(),
+        report,
+    );
+    Entry { description, done }
+}
+
+/// Merges one field of a task, asking which value to keep if both sides changed it and
+/// recording the one dropped.
+fn resolve<T: Clone + PartialEq>(
+    (id, field): (usize, &'static str),
+    file: &T,
+    store: &T,
+    base: Option<&T>,
+    show: impl Fn(&T) -> String,
+    report: &mut SyncReport,
+) -> T {
+    if let Some(merged) = merge_field(file, store, base) {
+        return merged;
+    }
+    let side = ask(id, field, &show(file), &show(store));
+    let (kept, lost) = match side {
+        Side::File => (file, store),
+        Side::Store => (store, file),
+    };
+    report.conflicts.push(Conflict {
+        id,
+        field,
+        kept_file: matches!(side, Side::File),
+        lost: show(lost),
+    });
+    kept.clone()
+}
+
+pub fn sync(list: &mut TodoList, file: &Path) -> Result<SyncReport, Box<dyn std::error::Error>> {
+    let content = if file.exists() {
+        fs::read_to_string(file)?
+    } else {
+        String::new()
+    };
+    let base_file = base_path(list.path(), file);
+    let base: BTreeMap<usize, Entry> = fs::read_to_string(&base_file)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let mut report = SyncReport::default();
+    let mut new_base = BTreeMap::new();
+    let mut in_file = HashSet::new();
+    let mut output = Vec::new();
+    let mut store_changed = false;
+
+    for line in parse(&content) {
+        let (indent, id, entry) = match line {
+            Line::Text(text) => {
+                output.push(text);
+                continue;
+            }
+            Line::Item { indent, id, entry } => (indent, id, entry),
+        };
+        let existing = id.and_then(|id| {
+            list.tasks.iter().find(|t| t.id == id).map(|t| Entry {
+                description: t.description.clone(),
+                done: t.completed,
+            })
+        });
+
+        let id = match (id, existing) {
+            (Some(id), Some(store)) => {
+                let merged = merge(id, &entry, &store, base.get(&id), &mut report);
+                if merged != store {
+                    let task = list.get_mut(id).unwrap();
+                    task.description = merged.description.clone();
+                    task.set_completed(merged.done);
+                    report.updated_in_store += 1;
+                    store_changed = true;
+                }
+                if merged != entry {
+                    report.updated_in_file += 1;
+                }
+                output.push(render_item(&indent, id, &merged));
+                new_base.insert(id, merged);
+                id
+            }
+            (Some(id), None) if base.contains_key(&id) => {
+                // Synced before and since deleted from the store: drop the line too.
+                report.deleted_in_file += 1;
+                continue;
+            }
+            _ => {
+                let id = list.insert(
+                    Task::builder()
+                        .description(entry.description.clone())
+                        .completed(entry.done)
+                        .build(),
+                );
+                report.created_in_store += 1;
+                store_changed = true;
+                output.push(render_item(&indent, id, &entry));
+                new_base.insert(id, entry);
+                id
+            }
+        };
+        in_file.insert(id);
+    }
+
+    let removed_from_file: Vec<usize> = base
+        .keys()
+        .copied()
+        .filter(|id| !in_file.contains(id))
+        .collect();
+    if !removed_from_file.is_empty() {
+        let before = list.tasks.len();
+        list.tasks.retain(|t| !removed_from_file.contains(&t.id));
+        report.deleted_in_store = before - list.tasks.len();
+        store_changed |= report.deleted_in_store > 0;
+    }
+
+    for task in list.tasks.iter().filter(|t| !in_file.contains(&t.id)) {
+        let entry = Entry {
+            description: task.description.clone(),
+            done: task.completed,
+        };
+        output.push(render_item("", task.id, &entry));
+        new_base.insert(task.id, entry);
+        report.created_in_file += 1;
+    }
+
+    let mut rendered = output.join("\n");
+    rendered.push('\n');
+    if rendered != content {
+        fs::write(file, rendered)?;
+    }
+    if store_changed {
+        list.save()?;
+    }
+    if let Some(dir) = base_file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&base_file, serde_json::to_string_pretty(&new_base)?)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(description: &str, done: bool) -> Entry {
+        Entry {
+            description: description.into(),
+            done,
+        }
+    }
+
+    #[test]
+    fn changes_to_different_fields_are_both_kept() {
+        let mut report = SyncReport::default();
+        let base = entry("two", false);
+        let merged = merge(
+            2,
+            &entry("second", false),
+            &entry("two", true),
+            Some(&base),
+            &mut report,
+        );
+        assert_eq!(merged, entry("second", true));
+        let merged = merge(
+            2,
+            &entry("two", true),
+            &entry("second", false),
+            Some(&base),
+            &mut report,
+        );
+        assert_eq!(merged, entry("second", true));
+        assert!(report.conflicts.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+//! A Matrix bot that lets everyone in a room manage the list with `!todo` commands.
+//!
+//! Uses the plain client-server HTTP API: join the room, long-poll `/sync`, and answer each
+//! `!todo` message with an `m.notice`. The task file is re-read for every command, so the
+//! bot and the command line can be used side by side.
+
+use serde_json::{json, Value};
+use std::time::Duration;
+use todo_core::{Completion, Task, TodoList};
+use tracing::{debug, info, warn};
+
+/// Environment variable holding the bot account's access token.
+pub const TOKEN_ENV: &str = "MATRIX_ACCESS_TOKEN";
+
+const HELP: &str = "Commands: !todo list | !todo add <text> | !todo done <id> | !todo delete <id>";
+
+pub struct MatrixBot {
+    homeserver: String,
+    token: String,
+    txn: u64,
+}
+
+/// Percent-encodes a path segment such as `#family:example.org`.
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Runs one `!todo` command against the list and returns the reply text.
+fn execute(list: &mut TodoList, args: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let (command, rest) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+    let rest = rest.trim();
+    let parse_id = || {
+        rest.parse::<usize>()
+            .map_err(|_| format!("expected a task ID, got {:?}", rest))
+    };
+    Ok(match command {
+        "" | "list" => {
+            let lines: Vec<String> = list
+                .pending()
+                .map(|t| format!("{} - {}", t.id, t.description))
+                .collect();
+            if lines.is_empty() {
+                "Nothing to do 🎉".to_string()
+            } else {
+                lines.join("\n")
+            }
+        }
+        "add" if !rest.is_empty() => {
+            let id = list.add(Task::builder().description(rest).build())?;
+            format!("✓ Added task {}: {}", id, rest)
+        }
+        "done" | "complete" => {
+            let id = parse_id()?;
+            match list.complete(id)? {
+                Completion::Completed(task) => format!("✓ Completed {}: {}", id, task.description),
+                Completion::Repeated { completed, next } => format!(
+                    "✓ Completed {}: {} (next one is {})",
+                    id, completed.description, next.id
+                ),
+                Completion::AlreadyCompleted => format!("Task {} is already completed.", id),
+                Completion::NotFound => format!("Task {} not found.", id),
+            }
+        }
+        "delete" => {
+            let id = parse_id()?;
+            match list.delete(id)? {
+                Some(_) => format!("✓ Deleted task {}", id),
+                None => format!("Task {} not found.", id),
+            }
+        }
+        _ => HELP.to_string(),
+    })
+}
+
+impl MatrixBot {
+    pub fn new(homeserver: &str, token: String) -> Self {
+        MatrixBot {
+            homeserver: homeserver.trim_end_matches('/').to_string(),
+            token,
+            txn: 0,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/_matrix/client/v3{}", self.homeserver, path)
+    }
+
+    fn auth(&self) -> String {
+        format!("Bearer {}", self.token)
+    }
+
+    fn whoami(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let response: Value = ureq::get(&self.url("/account/whoami"))
+            .header("Authorization", &self.auth())
+            .call()?
+            .body_mut()
+            .read_json()?;
+        Ok(response["user_id"].as_str().unwrap_or_default().to_string())
+    }
+
+    fn join(&self, room: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let response: Value = ureq::post(&self.url(&format!("/join/{}", encode(room))))
+            .header("Authorization", &self.auth())
+            .send_json(json!({}))?
+            .body_mut()
+            .read_json()?;
+        response["room_id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("could not join {}", room).into())
+    }
+
+    fn sync(
+        &self,
+        since: Option<&str>,
+        room_id: &str,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        let filter = json!({
+            "room": { "rooms": [room_id], "timeline": { "limit": 50 } },
+            "presence": { "types": [] },
+            "account_data": { "types": [] },
+        })
+        .to_string();
+        let mut request = ureq::get(&self.url("/sync"))
+            .header("Authorization", &self.auth())
+            .query("filter", &filter)
+            .query("timeout", "30000");
+        if let Some(since) = since {
+            request = request.query("since", since);
+        }
+        Ok(request.call()?.body_mut().read_json()?)
+    }
+
+    fn reply(&mut self, room_id: &str, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.txn += 1;
+        let txn = format!(
+            "rust-todo-{}-{}",
+            chrono::Utc::now().timestamp_millis(),
+            self.txn
+        );
+        let path = format!(
+            "/rooms/{}/send/m.room.message/{}",
+            encode(room_id),
+            encode(&txn)
+        );
+        ureq::put(&self.url(&path))
+            .header("Authorization", &self.auth())
+            .send_json(json!({ "msgtype": "m.notice", "body": text }))?;
+        Ok(())
+    }
+
+    /// Joins `room` and answers `!todo` commands against `list` until interrupted.
+    pub fn run(
+        &mut self,
+        room: &str,
+        list: &mut TodoList,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let me = self.whoami()?;
+        let room_id = self.join(room)?;
+        info!(user = %me, room = %room_id, "joined room");
+        println!("✓ Listening for !todo commands in {} as {}", room, me);
+
+        // The first sync only establishes a starting point; history is not replayed.
+        let mut since = self.sync(None, &room_id)?["next_batch"]
+            .as_str()
+            .map(str::to_string);
+        loop {
+            let response = match self.sync(since.as_deref(), &room_id) {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!(error = %e, "sync failed, retrying");
+                    std::thread::sleep(Duration::from_secs(5));
+                    continue;
+                }
+            };
+            since = response["next_batch"].as_str().map(str::to_string);
+            let events = response["rooms"]["join"][&room_id]["timeline"]["events"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            for event in events {
+                if event["type"] != "m.room.message" || event["sender"] == me.as_str() {
+                    continue;
+                }
+                let Some(args) = event["content"]["body"]
+                    .as_str()
+                    .and_then(|body| body.trim().strip_prefix("!todo"))
+                else {
+                    continue;
+                };
+                debug!(sender = %event["sender"], command = args, "received command");
+                list.reload();
+                let reply = execute(list, args).unwrap_or_else(|e| format!("Error: {}", e));
+                if let Err(e) = self.reply(&room_id, &reply) {
+                    warn!(error = %e, "failed to send reply");
+                }
+            }
+        }
+    }
+}
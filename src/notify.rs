@@ -0,0 +1,117 @@
+//! `notify-daemon`: desktop notifications for tasks coming due and for reminders.
+//!
+//! Notifications go through the platform's notification tool, `notify-send` on Linux and the
+//! BSDs or `osascript` on macOS, and are printed when neither is installed. The daemon checks
+//! the list every `--interval` seconds, re-reading it each time so tasks added from the
+//! command line are picked up; each lead time of each task notifies once (see
+//! [`TodoList::due_alerts`]).
+
+use chrono::Duration;
+use std::process::{Command, Stdio};
+use todo_core::{dates, TodoList};
+use tracing::{debug, warn};
+
+pub struct Options {
+    /// How long before tasks are due to notify.
+    pub leads: Vec<Duration>,
+    /// Seconds between checks.
+    pub interval: u64,
+    /// Check once and exit, e.g. from cron.
+    pub once: bool,
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Shows a notification, or prints it when there is no notification tool.
+pub fn send(title: &str, body: &str) {
+    let script = format!(
+        "display notification {} with title {}",
+        applescript_string(body),
+        applescript_string(title)
+    );
+    let tools: [(&str, Vec<&str>); 2] = [
+        (
+            "notify-send",
+            vec![
+                "--app-name=rust-todo",
+                "--icon=appointment-soon",
+                title,
+                body,
+            ],
+        ),
+        ("osascript", vec!["-e", &script]),
+    ];
+    for (program, args) in tools {
+        match Command::new(program)
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+        {
+            Ok(status) if status.success() => return,
+            Ok(status) => warn!(program, %status, "notification tool failed"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => warn!(program, error = %e, "could not run the notification tool"),
+        }
+        break;
+    }
+    println!("🔔 {}: {}", title, body);
+}
+
+/// Sends the notifications that are due. Returns how many there were.
+fn check(list: &mut TodoList, leads: &[Duration]) -> Result<usize, Box<dyn std::error::Error>> {
+    list.reload();
+    let now = dates::now();
+    let alerts = list.due_alerts(leads, now)?;
+    let reminders = list.fire_reminders(now)?;
+    for alert in &alerts {
+        let when = if now - alert.due > Duration::hours(1) {
+            "Overdue".to_string()
+        } else if alert.due <= now {
+            "Due now".to_string()
+        } else {
+            format!("Due in {}", dates::format_age(alert.due - now))
+        };
+        send(
+            &format!("{} ({})", when, dates::format_due(alert.due)),
+            &format!("{} - {}", alert.id, alert.description),
+        );
+    }
+    for reminder in &reminders {
+        send(
+            "Reminder",
+            &format!("{} - {}", reminder.id, reminder.description),
+        );
+    }
+    Ok(alerts.len() + reminders.len())
+}
+
+/// Checks the list until the process is stopped, or once with `options.once`.
+pub fn run(list: &mut TodoList, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if !options.once {
+        let leads: Vec<String> = options
+            .leads
+            .iter()
+            .map(|lead| dates::format_age(*lead))
+            .collect();
+        println!(
+            "Notifying {} before tasks are due, checking every {}s (Ctrl-C to stop)",
+            leads.join(", "),
+            options.interval
+        );
+    }
+    loop {
+        match check(list, &options.leads) {
+            Ok(sent) => debug!(sent, "checked for notifications"),
+            // A daemon shouldn't die because the list was briefly locked or unreadable.
+            Err(e) if !options.once => warn!(error = %e, "could not check for notifications"),
+            Err(e) => return Err(e),
+        }
+        if options.once {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_secs(options.interval));
+    }
+}
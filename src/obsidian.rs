@@ -0,0 +1,212 @@
+use crate::{Task, TodoList};
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, info};
+
+/// Tag that opts a vault checkbox into syncing.
+const SYNC_TAG: &str = "#todo";
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub imported: usize,
+    pub completed_in_store: usize,
+    pub completed_in_vault: usize,
+    pub renamed: usize,
+    pub appended: usize,
+    pub orphaned: Vec<usize>,
+}
+
+struct Patterns {
+    checkbox: Regex,
+    marker: Regex,
+    tag: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Patterns {
+            checkbox: Regex::new(r"^(\s*[-*+] \[)([ xX])(\] )(.*)$").unwrap(),
+            marker: Regex::new(r"\s*\^todo-(\d+)\s*$").unwrap(),
+            tag: Regex::new(r"(^|\s)#todo\b").unwrap(),
+        }
+    }
+
+    /// Strips the marker and sync tag from the checkbox text, leaving the task description.
+    fn description(&self, text: &str) -> String {
+        let text = self.marker.replace(text, "");
+        self.tag
+            .replace_all(&text, "$1")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn format_line(prefix: &str, done: bool, description: &str, id: usize) -> String {
+    let check = if done { "x" } else { " " };
+    format!(
+        "{}{}] {} {} ^todo-{}",
+        prefix, check, description, SYNC_TAG, id
+    )
+}
+
+/// Synchronises `#todo` checkboxes in every note of `vault` with the task list.
+///
+/// Checkboxes without a `^todo-<id>` marker become new tasks and receive a marker. Marked
+/// checkboxes exchange completion state with their task, and description edits flow from
+/// whichever side was modified more recently. Pending tasks missing from the vault are
+/// appended to `inbox_note`.
+pub fn sync(
+    list: &mut TodoList,
+    vault: &Path,
+    inbox_note: &str,
+) -> Result<SyncReport, Box<dyn std::error::Error>> {
+    if !vault.is_dir() {
+        return Err(format!("vault {} is not a directory", vault.display()).into());
+    }
+    let patterns = Patterns::new();
+    let mut report = SyncReport::default();
+    let mut seen = HashSet::new();
+    let store_modified = modified(list.path());
+    let mut store_changed = false;
+
+    let mut files = Vec::new();
+    markdown_files(vault, &mut files)?;
+    files.sort();
+
+    for file in &files {
+        let content = fs::read_to_string(file)?;
+        let note_newer = match (modified(file), store_modified) {
+            (Some(note), Some(store)) => note > store,
+            (_, None) => true,
+            _ => false,
+        };
+        let mut file_changed = false;
+        let mut lines = Vec::new();
+
+        for line in content.lines() {
+            let Some(caps) = patterns.checkbox.captures(line) else {
+                lines.push(line.to_string());
+                continue;
+            };
+            let text = &caps[4];
+            if !patterns.tag.is_match(text) {
+                lines.push(line.to_string());
+                continue;
+            }
+            let prefix = caps[1].to_string();
+            let vault_done = &caps[2] != " ";
+            let description = patterns.description(text);
+            let marker_id = patterns
+                .marker
+                .captures(text)
+                .and_then(|m| m[1].parse::<usize>().ok());
+
+            let Some(id) = marker_id else {
+                let id = list.insert(
+                    Task::builder()
+                        .description(description.clone())
+                        .completed(vault_done)
+                        .build(),
+                );
+                debug!(id, note = %file.display(), "imported task from vault");
+                lines.push(format_line(&prefix, vault_done, &description, id));
+                seen.insert(id);
+                report.imported += 1;
+                store_changed = true;
+                file_changed = true;
+                continue;
+            };
+
+            let Some(task) = list.get_mut(id) else {
+                report.orphaned.push(id);
+                lines.push(line.to_string());
+                continue;
+            };
+            seen.insert(id);
+
+            if vault_done && !task.completed {
+                task.set_completed(true);
+                report.completed_in_store += 1;
+                store_changed = true;
+            } else if task.completed && !vault_done {
+                report.completed_in_vault += 1;
+                file_changed = true;
+            }
+            if task.description != description && !description.is_empty() {
+                report.renamed += 1;
+                if note_newer {
+                    task.description = description;
+                    store_changed = true;
+                } else {
+                    file_changed = true;
+                }
+            }
+            let rendered = format_line(&prefix, task.completed, &task.description, id);
+            if rendered != line {
+                lines.push(rendered);
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+
+        if file_changed {
+            let mut output = lines.join("\n");
+            if content.ends_with('\n') {
+                output.push('\n');
+            }
+            fs::write(file, output)?;
+            info!(note = %file.display(), "updated vault note");
+        }
+    }
+
+    let missing: Vec<String> = list
+        .pending()
+        .filter(|t| !seen.contains(&t.id))
+        .map(|t| format_line("- [", false, &t.description, t.id))
+        .collect();
+    if !missing.is_empty() {
+        let inbox = vault.join(inbox_note);
+        let mut content = fs::read_to_string(&inbox).unwrap_or_default();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for line in &missing {
+            content.push_str(line);
+            content.push('\n');
+        }
+        fs::write(&inbox, content)?;
+        report.appended = missing.len();
+    }
+
+    if store_changed {
+        list.save()?;
+    }
+    Ok(report)
+}
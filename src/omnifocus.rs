@@ -0,0 +1,171 @@
+//! Importing OmniFocus's TaskPaper export.
+//!
+//! ```text
+//! Errands:
+//!     - Buy milk @tags(Store) @due(2024-05-03 17:00) @flagged
+//!     - Renew passport @defer(2024-05-01) @done(2024-05-02)
+//! ```
+//!
+//! Projects, folders and action groups (headings, and items with indented children) have no
+//! equivalent yet: their names are kept as the `omnifocus.path` of each action below them, and
+//! only the leaf actions become tasks. Tags and contexts become tags, `@flagged` becomes high
+//! priority, `@due` the due date and `@done` completion. `@repeat-rule` becomes the repeat
+//! rule when [`Rule`] supports it; it, `@defer` and `@estimate` are otherwise kept as metadata.
+//!
+//! An action with a `@due` that can't be read is still imported, without a due date, and
+//! reported as a [problem](crate::import::Problem) on its line; the value is kept as
+//! `omnifocus.due`.
+
+use crate::import::Problem;
+use crate::recurrence::Rule;
+use crate::{dates, normalize_tag, Priority, Task};
+use regex::Regex;
+
+struct Entry {
+    /// Line of the input, counting from 1.
+    line: usize,
+    depth: usize,
+    heading: bool,
+    text: String,
+}
+
+/// Indentation depth in tabs, counting four spaces as one tab.
+fn depth(line: &str) -> usize {
+    let mut depth = 0;
+    let mut spaces = 0;
+    for c in line.chars() {
+        match c {
+            '\t' => depth += 1,
+            ' ' => {
+                spaces += 1;
+                if spaces == 4 {
+                    depth += 1;
+                    spaces = 0;
+                }
+            }
+            _ => break,
+        }
+    }
+    depth
+}
+
+fn entries(content: &str) -> Vec<Entry> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(number, line)| {
+            let trimmed = line.trim();
+            if let Some(text) = trimmed.strip_prefix("- ") {
+                return Some(Entry {
+                    line: number + 1,
+                    depth: depth(line),
+                    heading: false,
+                    text: text.to_string(),
+                });
+            }
+            // A project heading is `Name:` optionally followed by tags; anything else is a note.
+            let (name, tags) = trimmed.split_once(':')?;
+            if !(tags.is_empty() || tags.trim_start().starts_with('@')) {
+                return None;
+            }
+            Some(Entry {
+                line: number + 1,
+                depth: depth(line),
+                heading: true,
+                text: format!("{}{}", name, tags),
+            })
+        })
+        .collect()
+}
+
+/// Parses OmniFocus TaskPaper text into tasks, and the problems found on the way; see the
+/// module documentation for the mapping.
+pub fn parse(content: &str) -> (Vec<Task>, Vec<Problem>) {
+    let tag = Regex::new(r"(^|\s)@([\w-]+)(\(([^)]*)\))?").unwrap();
+    let entries = entries(content);
+    let now = dates::now();
+    let mut path: Vec<(usize, String)> = vec![];
+    let mut tasks = vec![];
+    let mut problems = vec![];
+    for (i, entry) in entries.iter().enumerate() {
+        path.retain(|(depth, _)| *depth < entry.depth);
+        let name = tag.replace_all(&entry.text, "").trim().to_string();
+        let has_children = entries
+            .get(i + 1)
+            .is_some_and(|next| next.depth > entry.depth);
+        if entry.heading || has_children {
+            path.push((entry.depth, name));
+            continue;
+        }
+        if name.is_empty() {
+            continue;
+        }
+
+        let mut segments: Vec<&str> = path.iter().map(|(_, name)| name.as_str()).collect();
+        let location = segments.join(" / ");
+        segments.push(&name);
+        let mut builder = Task::builder()
+            .description(name.as_str())
+            .metadata("external.id", format!("omnifocus:{}", segments.join(" / ")));
+        if !location.is_empty() {
+            builder = builder.metadata("omnifocus.path", location.as_str());
+        }
+        let mut tags = vec![];
+        for capture in tag.captures_iter(&entry.text) {
+            let value = capture.get(4).map(|v| v.as_str().trim()).unwrap_or("");
+            match &capture[2] {
+                "tags" | "context" => {
+                    for name in value.split(',') {
+                        let hierarchy: Vec<&str> = name.split(':').map(str::trim).collect();
+                        let tag = normalize_tag(&hierarchy.join("/"));
+                        if !tag.is_empty() && !tags.contains(&tag) {
+                            tags.push(tag);
+                        }
+                    }
+                }
+                "due" if !value.is_empty() => match dates::parse_due(value, now) {
+                    Ok(due) => builder = builder.due(Some(due)),
+                    Err(e) => {
+                        problems.push(Problem::at(
+                            entry.line,
+                            format!("{:?} has no due date: {}", name, e),
+                        ));
+                        builder = builder.metadata("omnifocus.due", value);
+                    }
+                },
+                "done" => builder = builder.completed(true),
+                "flagged" => builder = builder.priority(Some(Priority::High)),
+                "repeat-rule" if Rule::parse(value).is_ok() => {
+                    builder = builder.repeat(Rule::parse(value).ok());
+                }
+                "defer" | "estimate" | "repeat-rule" if !value.is_empty() => {
+                    builder = builder.metadata(format!("omnifocus.{}", &capture[2]), value);
+                }
+                _ => {}
+            }
+        }
+        tasks.push(builder.tags(tags).build());
+    }
+    (tasks, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_unreadable_due_date_is_reported_and_the_action_kept() {
+        let (tasks, problems) = parse(
+            "Errands:\n\t- Buy milk @due(someday soon) @flagged\n\t- Post letter @due(2024-05-03)\n",
+        );
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].description, "Buy milk");
+        assert_eq!(tasks[0].due, None);
+        assert_eq!(tasks[0].priority, Some(Priority::High));
+        assert_eq!(tasks[0].metadata["omnifocus.due"], "someday soon");
+        assert!(tasks[1].due.is_some());
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(2));
+    }
+}
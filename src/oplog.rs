@@ -0,0 +1,325 @@
+//! The operation log behind `undo` and `redo`.
+//!
+//! Every save appends the tasks it changed, as they were before and after, to
+//! `<data file>.oplog`, one JSON object per line. Undoing or redoing an operation appends a
+//! marker instead of rewriting the log, so the file is only ever appended to and the full
+//! history stays available.
+
+use crate::Task;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One task as it was before and after an operation; `None` when it didn't exist.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub id: usize,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
+
+/// A saved change to the list, such as one `complete` or `delete`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    /// Identifies the operation: the time it was saved, in microseconds.
+    pub seq: i64,
+    pub at: DateTime<Local>,
+    /// The command that made the change, e.g. `delete`.
+    pub name: String,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Entry {
+    Operation(Operation),
+    Undo { seq: i64, at: DateTime<Local> },
+    Redo { seq: i64, at: DateTime<Local> },
+}
+
+/// The operations that can be undone (most recent last) and redone (next one last).
+#[derive(Debug, Default)]
+pub struct History {
+    pub done: Vec<Operation>,
+    pub undone: Vec<Operation>,
+}
+
+/// The log kept next to `data_file`.
+pub fn path_for(data_file: &Path) -> PathBuf {
+    let mut name = data_file.file_name().unwrap_or_default().to_os_string();
+    name.push(".oplog");
+    data_file.with_file_name(name)
+}
+
+/// The tasks that differ between `before` and `after`, by ID.
+pub fn diff(before: &[Task], after: &[Task]) -> Vec<Change> {
+    let old: HashMap<usize, &Task> = before.iter().map(|t| (t.id, t)).collect();
+    let new: HashMap<usize, &Task> = after.iter().map(|t| (t.id, t)).collect();
+    let mut changes: Vec<Change> = after
+        .iter()
+        .filter(|t| old.get(&t.id) != Some(t))
+        .map(|t| Change {
+            id: t.id,
+            before: old.get(&t.id).map(|t| (*t).clone()),
+            after: Some(t.clone()),
+        })
+        .collect();
+    changes.extend(
+        before
+            .iter()
+            .filter(|t| !new.contains_key(&t.id))
+            .map(|t| Change {
+                id: t.id,
+                before: Some(t.clone()),
+                after: None,
+            }),
+    );
+    changes
+}
+
+fn read(path: &Path) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Replays the log into what can currently be undone and redone. A new operation clears
+/// the redo stack, as in an editor.
+pub fn history(path: &Path) -> Result<History, Box<dyn std::error::Error>> {
+    let mut history = History::default();
+    for entry in read(path)? {
+        match entry {
+            Entry::Operation(operation) => {
+                history.done.push(operation);
+                history.undone.clear();
+            }
+            Entry::Undo { seq, .. } => {
+                if let Some(index) = history.done.iter().rposition(|o| o.seq == seq) {
+                    history.undone.push(history.done.remove(index));
+                }
+            }
+            Entry::Redo { seq, .. } => {
+                if let Some(index) = history.undone.iter().rposition(|o| o.seq == seq) {
+                    history.done.push(history.undone.remove(index));
+                }
+            }
+        }
+    }
+    Ok(history)
+}
+
+/// Appends a new operation made of `changes`.
+pub fn record(
+    path: &Path,
+    name: &str,
+    changes: Vec<Change>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let at = Local::now();
+    append(
+        path,
+        &Entry::Operation(Operation {
+            seq: at.timestamp_micros(),
+            at,
+            name: name.to_string(),
+            changes,
+        }),
+    )
+}
+
+/// Rewrites the log without the changes to tasks that `forget` matches, before or after
+/// them, so they can't be undone or redone back into the list. Operations left with no
+/// changes are dropped too. Returns how many changes were removed.
+pub fn forget(
+    path: &Path,
+    forget: impl Fn(&Task) -> bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let entries = read(path)?;
+    let mut removed = 0;
+    let mut kept = vec![];
+    for entry in entries {
+        match entry {
+            Entry::Operation(mut operation) => {
+                let before = operation.changes.len();
+                operation
+                    .changes
+                    .retain(|change| !change.before.iter().chain(&change.after).any(&forget));
+                removed += before - operation.changes.len();
+                if !operation.changes.is_empty() {
+                    kept.push(Entry::Operation(operation));
+                }
+            }
+            marker => kept.push(marker),
+        }
+    }
+    if removed == 0 {
+        return Ok(0);
+    }
+    let seqs: std::collections::HashSet<i64> = kept
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Operation(operation) => Some(operation.seq),
+            _ => None,
+        })
+        .collect();
+    let mut content = String::new();
+    for entry in &kept {
+        match entry {
+            Entry::Undo { seq, .. } | Entry::Redo { seq, .. } if !seqs.contains(seq) => {}
+            entry => {
+                content.push_str(&serde_json::to_string(entry)?);
+                content.push('\n');
+            }
+        }
+    }
+    crate::storage::replace(path, content.as_bytes())?;
+    Ok(removed)
+}
+
+/// Records that operation `seq` was undone.
+pub fn mark_undone(path: &Path, seq: i64) -> Result<(), Box<dyn std::error::Error>> {
+    append(
+        path,
+        &Entry::Undo {
+            seq,
+            at: Local::now(),
+        },
+    )
+}
+
+/// Records that operation `seq` was redone.
+pub fn mark_redone(path: &Path, seq: i64) -> Result<(), Box<dyn std::error::Error>> {
+    append(
+        path,
+        &Entry::Redo {
+            seq,
+            at: Local::now(),
+        },
+    )
+}
+
+/// What happened to a task in an [`Event`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    Created,
+    Completed,
+    Cancelled,
+    Reopened,
+    Deleted,
+    /// Changed otherwise, naming the fields that changed.
+    Edited(Vec<&'static str>),
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventKind::Created => f.write_str("created"),
+            EventKind::Completed => f.write_str("completed"),
+            EventKind::Cancelled => f.write_str("cancelled"),
+            EventKind::Reopened => f.write_str("reopened"),
+            EventKind::Deleted => f.write_str("deleted"),
+            EventKind::Edited(fields) => write!(f, "changed {}", fields.join(", ")),
+        }
+    }
+}
+
+/// A change to one task, read from the log by [`events`].
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub at: DateTime<Local>,
+    pub id: usize,
+    /// The task's description after the change, or before it when it was deleted.
+    pub description: String,
+    pub kind: EventKind,
+    /// The command that made the change, e.g. `edit`.
+    pub operation: String,
+}
+
+/// The fields that differ between two versions of a task.
+fn changed_fields(before: &Task, after: &Task) -> Vec<&'static str> {
+    let mut fields = vec![];
+    let mut check = |name, changed: bool| {
+        if changed {
+            fields.push(name);
+        }
+    };
+    check("description", before.description != after.description);
+    check("alias", before.alias != after.alias);
+    check("status", before.status() != after.status());
+    check("tags", before.tags != after.tags);
+    check("place", before.location != after.location);
+    check("priority", before.priority != after.priority);
+    check("due", before.due != after.due);
+    check("parent", before.parent != after.parent);
+    check("dependencies", before.depends_on != after.depends_on);
+    check("links", before.related != after.related);
+    check("follow-up", before.follow_up != after.follow_up);
+    check("repeat", before.repeat != after.repeat);
+    check("reminders", before.reminders != after.reminders);
+    check("waiting on", before.waiting_on != after.waiting_on);
+    check("assignee", before.assignee != after.assignee);
+    check("snooze", before.snoozed_until != after.snoozed_until);
+    check("metadata", before.metadata != after.metadata);
+    check("notes", before.notes != after.notes);
+    check("attachments", before.attachments != after.attachments);
+    check("time", before.time != after.time);
+    check("icon", before.icon != after.icon);
+    check("colour", before.color != after.color);
+    fields
+}
+
+/// Every change to a task in `history` that is in effect, i.e. not undone, oldest first.
+pub fn events(history: &History) -> Vec<Event> {
+    let mut events: Vec<Event> = history
+        .done
+        .iter()
+        .flat_map(|operation| {
+            operation.changes.iter().filter_map(|change| {
+                let (kind, task) = match (&change.before, &change.after) {
+                    (None, Some(after)) => (EventKind::Created, after),
+                    (Some(before), None) => (EventKind::Deleted, before),
+                    (Some(before), Some(after)) if !before.completed && after.completed => {
+                        match after.status() {
+                            crate::Status::Cancelled => (EventKind::Cancelled, after),
+                            _ => (EventKind::Completed, after),
+                        }
+                    }
+                    (Some(before), Some(after)) if before.completed && !after.completed => {
+                        (EventKind::Reopened, after)
+                    }
+                    (Some(before), Some(after)) => {
+                        let fields = changed_fields(before, after);
+                        if fields.is_empty() {
+                            return None;
+                        }
+                        (EventKind::Edited(fields), after)
+                    }
+                    (None, None) => return None,
+                };
+                Some(Event {
+                    at: operation.at,
+                    id: change.id,
+                    description: task.description.clone(),
+                    kind,
+                    operation: operation.name.clone(),
+                })
+            })
+        })
+        .collect();
+    // Redone operations are replayed at the end of `done`.
+    events.sort_by_key(|event| event.at);
+    events
+}
@@ -0,0 +1,207 @@
+//! Arranging tasks to be shown: subtasks under their parents, and groups with a heading each,
+//! for `list` and anything else showing a list of tasks.
+
+use crate::{planner, Task, TodoList};
+use clap::ValueEnum;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// What [`group`] splits the tasks by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Under each of their tags; a task with several is shown in each group
+    Tag,
+    /// Under the top-level task they are part of
+    Project,
+    /// By the week they are due in
+    DueWeek,
+    /// By who they are assigned to
+    Assignee,
+}
+
+/// Orders `tasks` so each subtask follows its parent, with its depth below the top level.
+/// Siblings keep their order in `tasks`; a task whose parent isn't shown is a top-level one,
+/// and so are tasks in a cycle of parents from hand-edited data, so none go missing.
+pub fn tree(tasks: Vec<&Task>) -> Vec<(usize, &Task)> {
+    let shown: HashSet<usize> = tasks.iter().map(|t| t.id).collect();
+    let mut children: HashMap<usize, Vec<&Task>> = HashMap::new();
+    for task in &tasks {
+        if let Some(parent) = task.parent.filter(|p| shown.contains(p)) {
+            children.entry(parent).or_default().push(task);
+        }
+    }
+    let mut visited = HashSet::new();
+    let mut ordered = vec![];
+    let roots = tasks
+        .iter()
+        .filter(|t| !t.parent.is_some_and(|p| shown.contains(&p)));
+    // Tasks not reached from a root are in a cycle; each starts a tree of its own.
+    for root in roots.chain(tasks.iter()) {
+        let mut stack = vec![(0, *root)];
+        while let Some((depth, task)) = stack.pop() {
+            if !visited.insert(task.id) {
+                continue;
+            }
+            ordered.push((depth, task));
+            if let Some(children) = children.get(&task.id) {
+                stack.extend(children.iter().rev().map(|t| (depth + 1, *t)));
+            }
+        }
+    }
+    ordered
+}
+
+/// `tasks`, from `list`, split into groups with a heading each, keeping their order within
+/// each group. Groups are in order of their key, and tasks in none of them come last.
+pub fn group<'a>(list: &TodoList, tasks: &[&'a Task], by: GroupBy) -> Vec<(String, Vec<&'a Task>)> {
+    match by {
+        GroupBy::Tag => split(
+            tasks,
+            |t| t.tags.clone(),
+            |tag| format!("#{}", tag),
+            "No tag",
+        ),
+        GroupBy::Project => split(
+            tasks,
+            |task| {
+                let mut root = task;
+                // Bounded, in case of a cycle in hand-edited data.
+                for _ in 0..list.tasks().len() {
+                    match root.parent.and_then(|p| list.get(p)) {
+                        Some(parent) => root = parent,
+                        None => break,
+                    }
+                }
+                if root.id == task.id && list.children(task.id).next().is_none() {
+                    vec![]
+                } else {
+                    vec![root.id]
+                }
+            },
+            |id| {
+                let name = list.get(*id).map_or("", |t| t.description.as_str());
+                format!("{} - {}", id, name)
+            },
+            "No project",
+        ),
+        GroupBy::DueWeek => split(
+            tasks,
+            |t| {
+                t.due
+                    .map(|due| planner::week_start(due.date_naive()))
+                    .into_iter()
+                    .collect()
+            },
+            |monday| format!("Week of {}", monday.format("%Y-%m-%d")),
+            "No due date",
+        ),
+        GroupBy::Assignee => split(
+            tasks,
+            |t| t.assignee.iter().map(|a| a.to_lowercase()).collect(),
+            |person| {
+                let named = tasks.iter().find_map(|t| {
+                    t.assignee
+                        .as_deref()
+                        .filter(|a| a.to_lowercase() == *person)
+                });
+                named.unwrap_or(person).to_string()
+            },
+            "Unassigned",
+        ),
+    }
+}
+
+/// `tasks` under each of their `keys`, with the ones without any under `rest`.
+fn split<'a, K: Ord>(
+    tasks: &[&'a Task],
+    keys: impl Fn(&Task) -> Vec<K>,
+    heading: impl Fn(&K) -> String,
+    rest: &str,
+) -> Vec<(String, Vec<&'a Task>)> {
+    let mut groups: BTreeMap<K, Vec<&Task>> = BTreeMap::new();
+    let mut other = vec![];
+    for task in tasks {
+        let keys = keys(task);
+        if keys.is_empty() {
+            other.push(*task);
+        }
+        for key in keys {
+            groups.entry(key).or_default().push(*task);
+        }
+    }
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .map(|(key, tasks)| (heading(&key), tasks))
+        .collect();
+    if !other.is_empty() {
+        groups.push((rest.to_string(), other));
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn task(id: usize, parent: Option<usize>) -> Task {
+        let mut task = Task::builder().description(format!("Task {}", id)).build();
+        task.id = id;
+        task.parent = parent;
+        task
+    }
+
+    fn shape(ordered: Vec<(usize, &Task)>) -> Vec<(usize, usize)> {
+        ordered
+            .into_iter()
+            .map(|(depth, t)| (depth, t.id))
+            .collect()
+    }
+
+    #[test]
+    fn subtasks_follow_their_parents() {
+        let tasks = [
+            task(1, None),
+            task(2, Some(3)),
+            task(3, Some(1)),
+            task(4, None),
+        ];
+        let ordered = tree(tasks.iter().collect());
+        assert_eq!(shape(ordered), [(0, 1), (1, 3), (2, 2), (0, 4)]);
+    }
+
+    #[test]
+    fn tasks_whose_parent_isnt_shown_are_top_level() {
+        let tasks = [task(2, Some(1)), task(3, Some(2))];
+        let ordered = tree(tasks.iter().collect());
+        assert_eq!(shape(ordered), [(0, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn tasks_in_a_cycle_are_all_shown() {
+        let tasks = [task(1, Some(2)), task(2, Some(1)), task(3, None)];
+        let ordered = tree(tasks.iter().collect());
+        assert_eq!(shape(ordered), [(0, 3), (0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn groups_by_tag_with_untagged_last() {
+        let dir = TempDir::new();
+        let list = TodoList::open(dir.path().join("tasks.json"));
+        let mut home = task(1, None);
+        home.tags = vec!["home".into(), "errand".into()];
+        let plain = task(2, None);
+        let tasks = [&home, &plain];
+        let groups: Vec<(String, Vec<usize>)> = group(&list, &tasks, GroupBy::Tag)
+            .into_iter()
+            .map(|(heading, tasks)| (heading, tasks.iter().map(|t| t.id).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                ("#errand".to_string(), vec![1]),
+                ("#home".to_string(), vec![1]),
+                ("No tag".to_string(), vec![2]),
+            ]
+        );
+    }
+}
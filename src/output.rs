@@ -0,0 +1,169 @@
+//! Machine-readable output for `list`, `search` and `show`: JSON, CSV or TSV with every task
+//! field.
+
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::Serialize;
+use todo_core::{csv, dates, Task};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable list with symbols and colour
+    #[default]
+    Text,
+    /// JSON array of tasks, in the same form as `export` plus `time_tracked`
+    Json,
+    /// Comma-separated values with a header row (RFC 4180 quoting)
+    Csv,
+    /// Tab-separated values with a header row; tabs and line breaks in fields become spaces
+    Tsv,
+}
+
+const COLUMNS: [&str; 24] = [
+    "id",
+    "description",
+    "completed",
+    "status",
+    "created_at",
+    "completed_at",
+    "priority",
+    "due",
+    "tags",
+    "location",
+    "parent",
+    "depends_on",
+    "related",
+    "repeat",
+    "reminders",
+    "waiting_on",
+    "waiting_since",
+    "assignee",
+    "metadata",
+    "notes",
+    "attachments",
+    "uuid",
+    "alias",
+    "time_tracked",
+];
+
+/// A task's values in [`COLUMNS`] order. Lists are joined with `;`, dates are RFC 3339, and
+/// metadata is written as `key=value` pairs. Notes are their text alone, and tracked time is
+/// in seconds.
+fn row(task: &Task, now: DateTime<Local>) -> Vec<String> {
+    let join = |items: Vec<String>| items.join(";");
+    vec![
+        task.id.to_string(),
+        task.description.clone(),
+        task.completed.to_string(),
+        task.status().to_string(),
+        task.created_at.to_rfc3339(),
+        task.completed_at
+            .map(|at| at.to_rfc3339())
+            .unwrap_or_default(),
+        task.priority.map(|p| p.to_string()).unwrap_or_default(),
+        task.due.map(|d| d.to_rfc3339()).unwrap_or_default(),
+        join(task.tags.clone()),
+        task.location.clone().unwrap_or_default(),
+        task.parent.map(|p| p.to_string()).unwrap_or_default(),
+        join(task.depends_on.iter().map(|id| id.to_string()).collect()),
+        join(task.related.iter().map(|id| id.to_string()).collect()),
+        task.repeat
+            .as_ref()
+            .map(|r| r.to_string())
+            .unwrap_or_default(),
+        join(task.reminders.iter().map(|r| r.at.to_rfc3339()).collect()),
+        task.waiting_on
+            .as_ref()
+            .map(|w| w.person.clone())
+            .unwrap_or_default(),
+        task.waiting_on
+            .as_ref()
+            .map(|w| w.since.to_rfc3339())
+            .unwrap_or_default(),
+        task.assignee.clone().unwrap_or_default(),
+        join(
+            task.metadata
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect(),
+        ),
+        join(task.notes.iter().map(|n| n.text.clone()).collect()),
+        join(task.attachments.clone()),
+        task.uuid.clone().unwrap_or_default(),
+        task.alias.clone().unwrap_or_default(),
+        task.tracked(now).num_seconds().to_string(),
+    ]
+}
+
+fn tsv_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+fn table(tasks: &[&Task], separator: &str, field: fn(&str) -> String) -> String {
+    let now = dates::now();
+    let mut out = COLUMNS.join(separator);
+    out.push('\n');
+    for task in tasks {
+        let values: Vec<String> = row(task, now).iter().map(|v| field(v)).collect();
+        out.push_str(&values.join(separator));
+        out.push('\n');
+    }
+    out
+}
+
+/// A task in JSON output, with the total of its `time` in seconds. The total isn't stored, so
+/// `import` ignores it.
+#[derive(Serialize)]
+struct Listed<'a> {
+    #[serde(flatten)]
+    task: &'a Task,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_tracked: Option<i64>,
+}
+
+/// A task in `show --format json`: as in [`render`], plus what is worked out from the rest
+/// of the list and the task's history.
+#[derive(Serialize)]
+pub struct Card<'a> {
+    #[serde(flatten)]
+    pub task: &'a Task,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_tracked: Option<i64>,
+    /// The pending tasks it depends on.
+    pub blocked_by: Vec<usize>,
+    /// The tasks that depend on it.
+    pub blocks: Vec<usize>,
+    pub subtasks: Vec<usize>,
+    /// The tasks that link to it.
+    pub backlinks: Vec<usize>,
+    /// Its recorded changes, oldest first.
+    pub history: Vec<Change>,
+}
+
+/// A change in [`Card::history`], e.g. `changed due, tags` by `edit`.
+#[derive(Serialize)]
+pub struct Change {
+    pub at: DateTime<Local>,
+    pub change: String,
+    pub operation: String,
+}
+
+/// `tasks` in `format`, which must not be [`OutputFormat::Text`].
+pub fn render(tasks: &[&Task], format: OutputFormat) -> Result<String, Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Json => {
+            let now = dates::now();
+            let listed: Vec<Listed> = tasks
+                .iter()
+                .map(|task| Listed {
+                    task,
+                    time_tracked: (!task.time.is_empty()).then(|| task.tracked(now).num_seconds()),
+                })
+                .collect();
+            Ok(serde_json::to_string_pretty(&listed)? + "\n")
+        }
+        OutputFormat::Csv => Ok(table(tasks, ",", csv::field)),
+        OutputFormat::Tsv => Ok(table(tasks, "\t", tsv_field)),
+        OutputFormat::Text => unreachable!("text output is printed by each command"),
+    }
+}
@@ -0,0 +1,236 @@
+//! A printable PDF checklist, written directly without a PDF library.
+//!
+//! Only the standard Helvetica fonts are used, so the file needs no embedded fonts. Text is
+//! encoded as WinAnsi; characters outside it are printed as `?`.
+
+use crate::{dates, Task};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const LINE: f32 = 16.0;
+const FONT_SIZE: f32 = 11.0;
+/// Characters per line before a description wraps; Helvetica averages about half an em.
+const WRAP: usize = 80;
+
+/// Encodes `text` as a PDF string literal in WinAnsi.
+fn literal(text: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                c as u8
+            }
+            ' '..='~' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            _ => b'?',
+        };
+        out.push(byte);
+    }
+    out.push(b')');
+    out
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Lays out content streams, starting a new page whenever the current one is full.
+struct Pages {
+    pages: Vec<Vec<u8>>,
+    y: f32,
+}
+
+impl Pages {
+    fn new() -> Self {
+        Pages {
+            pages: vec![vec![]],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Moves down by `height`, breaking the page if that would run into the bottom margin.
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.pages.push(vec![]);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    fn text(&mut self, font: &str, size: f32, x: f32, text: &str) {
+        let page = self.pages.last_mut().unwrap();
+        page.extend_from_slice(format!("BT /{} {} Tf {} {} Td ", font, size, x, self.y).as_bytes());
+        page.extend_from_slice(&literal(text));
+        page.extend_from_slice(b" Tj ET\n");
+    }
+
+    /// Draws an empty box, or a ticked one for completed tasks, on the current line.
+    fn checkbox(&mut self, x: f32, checked: bool) {
+        let page = self.pages.last_mut().unwrap();
+        let y = self.y - 1.0;
+        page.extend_from_slice(format!("0.8 w {} {} 9 9 re S\n", x, y).as_bytes());
+        if checked {
+            page.extend_from_slice(
+                format!(
+                    "1.2 w {} {} m {} {} l {} {} l S\n",
+                    x + 1.5,
+                    y + 4.5,
+                    x + 3.5,
+                    y + 2.0,
+                    x + 8.0,
+                    y + 8.0
+                )
+                .as_bytes(),
+            );
+        }
+    }
+
+    fn section(&mut self, title: &str, tasks: &[&Task]) {
+        if tasks.is_empty() {
+            return;
+        }
+        self.advance(LINE * 2.0);
+        self.text("F2", 13.0, MARGIN, title);
+        self.advance(4.0);
+        for task in tasks {
+            self.item(task);
+        }
+    }
+
+    fn item(&mut self, task: &Task) {
+        let indent = MARGIN + 18.0;
+        for (i, line) in wrap(&task.description, WRAP).iter().enumerate() {
+            self.advance(LINE);
+            if i == 0 {
+                self.checkbox(MARGIN, task.completed);
+            }
+            self.text("F1", FONT_SIZE, indent, line);
+        }
+        let mut details: Vec<String> = task
+            .due
+            .map(|due| format!("due {}", dates::format_due(due)))
+            .into_iter()
+            .collect();
+        details.extend(task.tags.iter().map(|t| format!("#{}", t)));
+        if let Some(place) = &task.location {
+            details.push(format!("@ {}", place));
+        }
+        if let Some(waiting) = task.waiting_on.as_ref().filter(|_| !task.completed) {
+            details.push(format!("waiting on {}", waiting.person));
+        }
+        if !details.is_empty() {
+            self.advance(LINE * 0.8);
+            self.text("F1", 8.5, indent, &details.join("   "));
+        }
+        self.advance(LINE * 0.4);
+    }
+}
+
+/// Renders an A4 checklist: pending tasks first, then completed ones with ticked boxes.
+pub fn render(tasks: &[Task]) -> Vec<u8> {
+    let mut pages = Pages::new();
+    pages.advance(6.0);
+    pages.text("F2", 18.0, MARGIN, "To-Do List");
+    pages.text(
+        "F1",
+        9.0,
+        PAGE_WIDTH - MARGIN - 60.0,
+        &dates::now().format("%Y-%m-%d").to_string(),
+    );
+    let pending: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+    let done: Vec<&Task> = tasks.iter().filter(|t| t.completed).collect();
+    pages.section("To do", &pending);
+    pages.section("Done", &done);
+    if tasks.is_empty() {
+        pages.advance(LINE * 2.0);
+        pages.text("F1", FONT_SIZE, MARGIN, "No tasks.");
+    }
+    write_document(&pages.pages)
+}
+
+/// Assembles the page content streams into a complete PDF file with a cross-reference table.
+fn write_document(contents: &[Vec<u8>]) -> Vec<u8> {
+    // Objects 1-4 are the catalog, page tree and two fonts; each page adds a page object
+    // and its content stream.
+    let page_ids: Vec<usize> = (0..contents.len()).map(|i| 5 + i * 2).collect();
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            contents.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    for (content, id) in contents.iter().zip(&page_ids) {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                id + 1
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        out.extend_from_slice(object);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = out.len();
+    out.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    out
+}
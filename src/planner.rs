@@ -0,0 +1,106 @@
+//! A printable week planner: one column per day with scheduled tasks and room to write.
+
+use crate::Task;
+use chrono::{Datelike, Days, NaiveDate};
+
+/// Ruled lines left blank under each day's tasks for handwriting.
+const BLANK_LINES: usize = 8;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The Monday of the week containing `date`.
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday() as u64)
+}
+
+fn item(task: &Task, time: Option<&str>) -> String {
+    let time = time
+        .map(|t| format!("<span class=\"time\">{}</span> ", t))
+        .unwrap_or_default();
+    format!(
+        "<li><span class=\"box\"></span>{}{}</li>\n",
+        time,
+        escape(&task.description)
+    )
+}
+
+/// Renders the week starting on `monday` as a standalone HTML page meant to be printed in
+/// landscape. Pending tasks appear on their due day and on each day they have an undelivered
+/// reminder; pending tasks with nothing scheduled are listed in a side column.
+pub fn render_week(tasks: &[Task], monday: NaiveDate) -> String {
+    let pending: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+    let mut columns = String::new();
+    for offset in 0..7 {
+        let day = monday + Days::new(offset);
+        let mut scheduled: Vec<(String, &Task)> = pending
+            .iter()
+            .flat_map(|task| {
+                task.reminders
+                    .iter()
+                    .filter(|r| !r.delivered && r.at.date_naive() == day)
+                    .map(move |r| (r.at.format("%H:%M").to_string(), *task))
+            })
+            .collect();
+        for task in pending
+            .iter()
+            .filter(|t| t.due.is_some_and(|d| d.date_naive() == day))
+        {
+            scheduled.push(("due".to_string(), task));
+        }
+        scheduled.sort_by(|a, b| a.0.cmp(&b.0));
+        columns.push_str(&format!(
+            "<section class=\"day\">\n<h2>{}</h2>\n<ul>\n",
+            day.format("%A %-d %b")
+        ));
+        for (time, task) in &scheduled {
+            columns.push_str(&item(task, Some(time)));
+        }
+        columns.push_str(&"<li class=\"blank\"></li>\n".repeat(BLANK_LINES));
+        columns.push_str("</ul>\n</section>\n");
+    }
+    let unscheduled: String = pending
+        .iter()
+        .filter(|t| t.due.is_none() && t.reminders.iter().all(|r| r.delivered))
+        .map(|t| item(t, None))
+        .collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Week of {title}</title>
+<style>
+@page {{ size: A4 landscape; margin: 1cm; }}
+body {{ font-family: Helvetica, Arial, sans-serif; font-size: 9pt; margin: 0; }}
+h1 {{ font-size: 14pt; margin: 0 0 0.4cm; }}
+.week {{ display: grid; grid-template-columns: repeat(4, 1fr); gap: 0.3cm; }}
+section {{ border: 1px solid #444; padding: 0.2cm; break-inside: avoid; }}
+h2 {{ font-size: 10pt; margin: 0 0 0.2cm; border-bottom: 1px solid #444; }}
+ul {{ list-style: none; margin: 0; padding: 0; }}
+li {{ min-height: 0.55cm; border-bottom: 1px dotted #aaa; }}
+.box {{ display: inline-block; width: 0.25cm; height: 0.25cm; border: 1px solid #000; margin-right: 0.15cm; }}
+.time {{ color: #555; }}
+</style>
+</head>
+<body>
+<h1>Week of {title}</h1>
+<div class="week">
+{columns}<section class="day">
+<h2>Unscheduled</h2>
+<ul>
+{unscheduled}</ul>
+</section>
+</div>
+</body>
+</html>
+"#,
+        title = monday.format("%-d %B %Y"),
+        columns = columns,
+        unscheduled = unscheduled
+    )
+}
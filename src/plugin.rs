@@ -0,0 +1,87 @@
+//! Plugins: `rust-todo foo args…` runs `rust-todo-foo args…` from `PATH` when `foo` isn't a
+//! command of its own, the way git and cargo find theirs, so new commands can be added
+//! without changing this one.
+//!
+//! A plugin gets the list's tasks on stdin as JSON, as `export` writes them, and these
+//! environment variables:
+//!
+//! - `RUST_TODO`: this executable, to run commands that change the list;
+//! - `RUST_TODO_FILE`: the list's data file;
+//! - `RUST_TODO_LIST`: the list's name.
+//!
+//! The list isn't locked while a plugin runs, so it can run rust-todo itself. Its output and
+//! exit status are its own.
+
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+use todo_core::TodoList;
+use tracing::debug;
+
+/// What a plugin for `command` is called.
+fn executable_name(command: &str) -> String {
+    format!("rust-todo-{}{}", command, std::env::consts::EXE_SUFFIX)
+}
+
+/// The plugin for `command` on `PATH`, if there is one.
+pub fn find(command: &str) -> Option<PathBuf> {
+    // Only a name, so `rust-todo ../x` can't run something elsewhere.
+    if command.is_empty() || command.contains(['/', '\\']) || command.starts_with('.') {
+        return None;
+    }
+    let name = executable_name(command);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&name))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+/// Runs the plugin `args[0]` with the rest of `args` on `list`, named `name`, and waits for
+/// it to finish.
+pub fn run(
+    list: &TodoList,
+    name: &str,
+    args: &[OsString],
+) -> Result<ExitStatus, Box<dyn std::error::Error>> {
+    let Some((command, args)) = args.split_first() else {
+        return Err("no command given".into());
+    };
+    let command = command.to_string_lossy();
+    let path = find(&command).ok_or_else(|| {
+        crate::error::Error::Parse(format!(
+            "unknown command `{}`, and there is no {} on PATH",
+            command,
+            executable_name(&command)
+        ))
+    })?;
+    debug!(plugin = %path.display(), "running plugin");
+    let mut child = Command::new(&path)
+        .args(args)
+        .env("RUST_TODO", std::env::current_exe()?)
+        .env("RUST_TODO_FILE", list.path())
+        .env("RUST_TODO_LIST", name)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run {}: {}", path.display(), e))?;
+    let json = serde_json::to_vec(list.tasks())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A plugin that doesn't read the tasks closes the pipe early, which is fine.
+        match stdin.write_all(&json) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(child.wait()?)
+}
@@ -0,0 +1,122 @@
+//! `pomodoro`: work on a task in timed rounds, each followed by a break.
+//!
+//! Work periods run the task's timer, as `start` and `stop` do, so they show up in `log`, and
+//! each one finished is counted in the task's `pomodoro.count` metadata. Stopping with Ctrl-C
+//! during a work period leaves the timer running, to be ended with `stop`.
+
+use crate::error::Error;
+use crate::notify;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+use todo_core::{dates, TodoList};
+
+/// Metadata key counting the pomodoros finished on a task.
+const COUNT: &str = "pomodoro.count";
+
+pub struct Options {
+    pub work: Duration,
+    pub rest: Duration,
+    pub rounds: u32,
+    /// Send a desktop notification when each period ends, not just print it.
+    pub notify: bool,
+}
+
+/// Counts `length` down on one line of the terminal, or just waits when stdout isn't one.
+fn countdown(label: &str, length: Duration) {
+    let end = Instant::now() + length;
+    let live = std::io::stdout().is_terminal();
+    loop {
+        let left = end.saturating_duration_since(Instant::now());
+        if live {
+            let seconds = left.as_millis().div_ceil(1000);
+            print!("\r{} {:02}:{:02} ", label, seconds / 60, seconds % 60);
+            let _ = std::io::stdout().flush();
+        }
+        if left.is_zero() {
+            break;
+        }
+        std::thread::sleep(left.min(Duration::from_secs(1)));
+    }
+    if live {
+        println!();
+    }
+}
+
+fn announce(options: &Options, title: &str, body: &str) {
+    if options.notify {
+        notify::send(title, body);
+    } else {
+        println!("🔔 {}: {}", title, body);
+    }
+}
+
+pub fn run(
+    list: &mut TodoList,
+    id: usize,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(task) = list.get(id) else {
+        return Err(Error::task_not_found(id).into());
+    };
+    if task.completed {
+        return Err(format!("task {} is already completed", id).into());
+    }
+    let description = task.description.clone();
+    for round in 1..=options.rounds {
+        // Re-read first, so changes made while counting down aren't overwritten.
+        list.reload();
+        if let Some((stopped, ran)) = list.start_timer(id, dates::now())? {
+            if stopped.id != id {
+                println!(
+                    "⏹ Stopped task {} after {}.",
+                    stopped.id,
+                    dates::format_duration(ran)
+                );
+            }
+        }
+        println!(
+            "🍅 Round {} of {}: {} on {} - {}",
+            round,
+            options.rounds,
+            dates::format_duration(chrono::Duration::from_std(options.work)?),
+            id,
+            description
+        );
+        countdown("🍅", options.work);
+        list.reload();
+        let count = list.batch(|list| {
+            list.stop_timer(dates::now())?;
+            list.update(id, |task| {
+                let count = task
+                    .metadata
+                    .get(COUNT)
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .unwrap_or(0)
+                    + 1;
+                task.metadata.insert(COUNT.into(), count.to_string());
+                count
+            })?
+            .ok_or_else(|| format!("task {} was deleted during the pomodoro", id).into())
+        })?;
+        announce(
+            options,
+            "Pomodoro done",
+            &format!("{} - {} ({} so far)", id, description, count),
+        );
+        if options.rest.is_zero() {
+            continue;
+        }
+        countdown("☕", options.rest);
+        let next = if round < options.rounds {
+            "back to work"
+        } else {
+            "all rounds done"
+        };
+        announce(
+            options,
+            "Break over",
+            &format!("{} - {}", next, description),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! The queue: tasks added by `enqueue` without loading or locking the list, so many scripts
+//! and cron jobs can add tasks at once without waiting on each other. Queued tasks join the
+//! list the next time it is opened by any other command.
+//!
+//! Like a Maildir, each queued task is a file of its own in `<data file>.queue/`, written
+//! under `tmp/` and then renamed into place, so a task is either fully queued or not at all
+//! and writers never touch the same file. Names start with the time, so tasks are added in
+//! the order they were queued.
+
+use crate::Task;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The queue directory of the list stored in `data_file`.
+pub fn dir_for(data_file: &Path) -> PathBuf {
+    let mut name = data_file.file_name().unwrap_or_default().to_os_string();
+    name.push(".queue");
+    data_file.with_file_name(name)
+}
+
+/// Queues `task` for the list stored in `data_file`.
+pub fn push(data_file: &Path, task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = dir_for(data_file);
+    fs::create_dir_all(dir.join("tmp"))?;
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let name = format!(
+        "{}.{:09}.{}.{}.json",
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let staged = dir.join("tmp").join(&name);
+    fs::write(&staged, serde_json::to_string(task)?)?;
+    fs::rename(&staged, dir.join(name))?;
+    Ok(())
+}
+
+/// The queued tasks for the list stored in `data_file`, oldest first, with their files.
+/// Files that can't be read are left for a later attempt.
+pub fn pending(data_file: &Path) -> Vec<(PathBuf, Task)> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir_for(data_file))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .filter_map(|path| {
+            let task = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some((path, task))
+        })
+        .collect()
+}
@@ -0,0 +1,118 @@
+//! `quick`: a task from one line of text, the way Todoist's quick add reads it. Words
+//! starting with `#` are tags, `!high` (or any other priority) sets the priority, `@phone`
+//! the context, and a date anywhere in the text, such as `tomorrow 3pm`, `monday at 10` or
+//! `in 3 days`, is the due date:
+//!
+//! ```text
+//! rust-todo quick 'Call dentist tomorrow 3pm #health !high @phone'
+//! ```
+//!
+//! The longest run of words that reads as a date is taken, the last one if there are several,
+//! along with a `by`, `on` or `due` just before it. A lone three-letter word such as `sun` or
+//! `wed` is left in the description rather than read as a weekday.
+
+use chrono::{DateTime, Local};
+use todo_core::{dates, Priority};
+
+/// At most how many words a due date in the text can take, as in `next friday at 5pm`.
+const DATE_WORDS: usize = 4;
+
+/// Words that introduce a due date and go with it.
+const DATE_PREFIXES: [&str; 3] = ["by", "on", "due"];
+
+/// What [`parse`] read from the text.
+pub struct Parsed {
+    pub description: String,
+    pub tags: Vec<String>,
+    pub priority: Option<Priority>,
+    /// The context, from `@name`.
+    pub location: Option<String>,
+    /// The due date, with the words it was read from.
+    pub due: Option<(DateTime<Local>, String)>,
+}
+
+pub fn parse(text: &str, now: DateTime<Local>) -> Result<Parsed, String> {
+    let (mut words, mut tags, mut priority, mut location) = (vec![], vec![], None, None);
+    for word in text.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            tags.push(tag.to_string());
+        } else if let Some(level) = word.strip_prefix('!').filter(|level| !level.is_empty()) {
+            priority = Some(
+                <Priority as clap::ValueEnum>::from_str(level, true)
+                    .map_err(|_| format!("unknown priority {:?}", level))?,
+            );
+        } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            location = Some(context.to_string());
+        } else {
+            words.push(word);
+        }
+    }
+    let mut due = None;
+    'search: for len in (1..=DATE_WORDS.min(words.len())).rev() {
+        for start in (0..=words.len() - len).rev() {
+            let span = words[start..start + len].join(" ");
+            let abbreviation = len == 1 && span.len() <= 3 && span.chars().all(char::is_alphabetic);
+            if abbreviation {
+                continue;
+            }
+            if let Ok(at) = dates::parse_due(&span, now) {
+                let prefixed =
+                    start > 0 && DATE_PREFIXES.contains(&words[start - 1].to_lowercase().as_str());
+                let from = if prefixed { start - 1 } else { start };
+                words.drain(from..start + len);
+                due = Some((at, span));
+                break 'search;
+            }
+        }
+    }
+    if words.is_empty() {
+        return Err("the task has no description".into());
+    }
+    Ok(Parsed {
+        description: words.join(" "),
+        tags,
+        priority,
+        location,
+        due,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Local> {
+        // A Wednesday.
+        Local.with_ymd_and_hms(2026, 10, 14, 9, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn a_day_at_a_bare_hour_is_the_due_date() {
+        let parsed = parse("Meeting monday at 10", now()).unwrap();
+        assert_eq!(parsed.description, "Meeting");
+        let (due, words) = parsed.due.unwrap();
+        assert_eq!(due, Local.with_ymd_and_hms(2026, 10, 19, 10, 0, 0).unwrap());
+        assert_eq!(words, "monday at 10");
+    }
+
+    #[test]
+    fn at_without_a_time_stays_in_the_description() {
+        let parsed = parse("Look at the garden friday", now()).unwrap();
+        assert_eq!(parsed.description, "Look at the garden");
+        let parsed = parse("Meet friday at the station", now()).unwrap();
+        assert_eq!(parsed.description, "Meet at the station");
+        assert_eq!(parsed.due.unwrap().1, "friday");
+    }
+
+    #[test]
+    fn reads_tags_priority_context_and_a_prefixed_date() {
+        let parsed = parse("Call dentist by tomorrow 3pm #health !high @phone", now()).unwrap();
+        assert_eq!(parsed.description, "Call dentist");
+        assert_eq!(parsed.tags, ["health"]);
+        assert_eq!(parsed.priority, Some(Priority::High));
+        assert_eq!(parsed.location.as_deref(), Some("phone"));
+        let (due, _) = parsed.due.unwrap();
+        assert_eq!(due, Local.with_ymd_and_hms(2026, 10, 15, 15, 0, 0).unwrap());
+    }
+}
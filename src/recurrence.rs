@@ -0,0 +1,391 @@
+//! Recurrence rules for repeating tasks.
+//!
+//! Rules are written in plain words (`daily`, `every 2 weeks`, `every monday and thursday`,
+//! `every weekday`, `every other month`) or as an iCalendar RRULE
+//! (`FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR`). They are stored in their plain-word form.
+//! Intervals go up to [`MAX_INTERVAL`].
+
+use chrono::{DateTime, Days, Local, Months, NaiveDate, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn unit(self) -> &'static str {
+        match self {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+            Frequency::Yearly => "year",
+        }
+    }
+}
+
+/// How often a task repeats: every `interval` units, on the given weekdays for weekly rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// For weekly rules, the days of the week it falls on; empty means the due date's weekday.
+    pub weekdays: Vec<Weekday>,
+}
+
+/// The longest interval a rule can have, in its units.
+pub const MAX_INTERVAL: u32 = 1000;
+
+/// Rejects intervals of 0 and over [`MAX_INTERVAL`].
+fn check_interval(interval: u32) -> Result<u32, String> {
+    if interval == 0 {
+        return Err("the repeat interval must be at least 1".into());
+    }
+    if interval > MAX_INTERVAL {
+        return Err(format!(
+            "the repeat interval can be at most {}, not {}",
+            MAX_INTERVAL, interval
+        ));
+    }
+    Ok(interval)
+}
+
+const WORKDAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+/// Parses "monday", "mon, wed and fri", "weekday" or "weekdays".
+fn parse_weekdays(text: &str) -> Option<Vec<Weekday>> {
+    if matches!(text, "weekday" | "weekdays") {
+        return Some(WORKDAYS.to_vec());
+    }
+    let mut days = vec![];
+    for word in text.replace(" and ", ",").split(',') {
+        let word = word.trim().trim_end_matches('s');
+        let day: Weekday = word.parse().ok()?;
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    }
+    days.sort_by_key(|d| d.num_days_from_monday());
+    Some(days)
+}
+
+fn parse_rrule(input: &str) -> Result<Rule, String> {
+    let body = input.strip_prefix("rrule:").unwrap_or(input);
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        weekdays: vec![],
+    };
+    let mut frequency = None;
+    for part in body.split(';').filter(|p| !p.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("invalid RRULE part {:?}", part))?;
+        match key {
+            "freq" => {
+                frequency = Some(match value {
+                    "daily" => Frequency::Daily,
+                    "weekly" => Frequency::Weekly,
+                    "monthly" => Frequency::Monthly,
+                    "yearly" => Frequency::Yearly,
+                    _ => return Err(format!("unsupported RRULE frequency {:?}", value)),
+                })
+            }
+            "interval" => {
+                rule.interval = value
+                    .parse()
+                    .map_err(|_| format!("invalid RRULE interval {:?}", value))?
+            }
+            "byday" => {
+                for code in value.split(',') {
+                    let day = match code {
+                        "mo" => Weekday::Mon,
+                        "tu" => Weekday::Tue,
+                        "we" => Weekday::Wed,
+                        "th" => Weekday::Thu,
+                        "fr" => Weekday::Fri,
+                        "sa" => Weekday::Sat,
+                        "su" => Weekday::Sun,
+                        _ => return Err(format!("unsupported RRULE day {:?}", code)),
+                    };
+                    rule.weekdays.push(day);
+                }
+            }
+            // Week start only matters for rules this subset can't express.
+            "wkst" => {}
+            _ => return Err(format!("unsupported RRULE part {:?}", part)),
+        }
+    }
+    rule.frequency = frequency.ok_or("an RRULE needs FREQ")?;
+    check_interval(rule.interval)?;
+    if !rule.weekdays.is_empty() && rule.frequency != Frequency::Weekly {
+        if rule.frequency == Frequency::Daily && rule.interval == 1 {
+            // FREQ=DAILY;BYDAY=MO,TU,... is the usual way of writing "every weekday".
+            rule.frequency = Frequency::Weekly;
+        } else {
+            return Err("BYDAY is only supported for weekly rules".into());
+        }
+    }
+    Ok(rule)
+}
+
+impl Rule {
+    /// Parses a rule in plain words or RRULE syntax; see the module documentation.
+    pub fn parse(input: &str) -> Result<Rule, String> {
+        let text = input.trim().to_lowercase();
+        if text.starts_with("rrule:") || text.starts_with("freq=") {
+            return parse_rrule(&text);
+        }
+        let rule = |frequency, interval, weekdays| Rule {
+            frequency,
+            interval,
+            weekdays,
+        };
+        let text = match text.as_str() {
+            "daily" => return Ok(rule(Frequency::Daily, 1, vec![])),
+            "weekly" => return Ok(rule(Frequency::Weekly, 1, vec![])),
+            "monthly" => return Ok(rule(Frequency::Monthly, 1, vec![])),
+            "yearly" | "annually" => return Ok(rule(Frequency::Yearly, 1, vec![])),
+            "weekdays" => return Ok(rule(Frequency::Weekly, 1, WORKDAYS.to_vec())),
+            _ => text.strip_prefix("every ").ok_or_else(|| {
+                format!(
+                    "could not understand repeat rule {:?} (try \"daily\", \"every 2 weeks\", \"every monday\" or an RRULE)",
+                    input.trim()
+                )
+            })?,
+        };
+        let (interval, rest) = match text.split_once(' ') {
+            Some(("other", rest)) => (2, rest),
+            Some((number, rest)) if number.parse::<u32>().is_ok() => {
+                (number.parse::<u32>().unwrap(), rest)
+            }
+            _ => (1, text),
+        };
+        check_interval(interval)?;
+        let (unit, on) = match rest.split_once(" on ") {
+            Some((unit, days)) => (unit, Some(days)),
+            None => (rest, None),
+        };
+        let frequency = match unit.trim_end_matches('s') {
+            "day" => Frequency::Daily,
+            "week" => Frequency::Weekly,
+            "month" => Frequency::Monthly,
+            "year" => Frequency::Yearly,
+            _ if on.is_none() => {
+                let days = parse_weekdays(rest)
+                    .ok_or_else(|| format!("unknown repeat unit {:?}", rest))?;
+                return Ok(rule(Frequency::Weekly, interval, days));
+            }
+            _ => return Err(format!("unknown repeat unit {:?}", unit)),
+        };
+        let weekdays = match on {
+            Some(days) if frequency == Frequency::Weekly => {
+                parse_weekdays(days).ok_or_else(|| format!("unknown weekdays {:?}", days))?
+            }
+            Some(_) => return Err("\"on <day>\" only works with weeks".into()),
+            None => vec![],
+        };
+        Ok(rule(frequency, interval, weekdays))
+    }
+
+    /// The first occurrence after `from` on a date later than `from`'s, at the same time of day,
+    /// or an error if it would be past the last date that can be stored.
+    pub fn step(&self, from: DateTime<Local>) -> Result<DateTime<Local>, String> {
+        let date = from.date_naive();
+        let next = match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(self.interval.into())),
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                date.checked_add_days(Days::new(7 * u64::from(self.interval)))
+            }
+            Frequency::Weekly => self.next_weekday(date),
+            Frequency::Monthly => date.checked_add_months(Months::new(self.interval)),
+            Frequency::Yearly => self
+                .interval
+                .checked_mul(12)
+                .and_then(|months| date.checked_add_months(Months::new(months))),
+        };
+        let next = next.ok_or_else(|| {
+            format!(
+                "repeating {} after {} goes past the last date there is",
+                self, date
+            )
+        })?;
+        let naive = next.and_time(from.time());
+        Ok(Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap_or_else(|| Local.from_utc_datetime(&naive)))
+    }
+
+    /// The next listed weekday after `date`: a later one in the same week, or else the first one
+    /// in the week `interval` weeks on.
+    fn next_weekday(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let monday = date.week(Weekday::Mon).checked_first_day()?;
+        let offsets = || {
+            self.weekdays
+                .iter()
+                .map(|d| d.num_days_from_monday() as u64)
+        };
+        let later = offsets()
+            .filter_map(|offset| monday.checked_add_days(Days::new(offset)))
+            .filter(|d| *d > date)
+            .min();
+        later.or_else(|| {
+            let first = offsets().min()?;
+            monday.checked_add_days(Days::new(7 * u64::from(self.interval) + first))
+        })
+    }
+
+    /// When the occurrence after one due at `due` falls: the first step that is still in the
+    /// future, so occurrences missed while the task was overdue are skipped.
+    pub fn next_due(
+        &self,
+        due: DateTime<Local>,
+        now: DateTime<Local>,
+    ) -> Result<DateTime<Local>, String> {
+        let mut next = self.step(due)?;
+        while next <= now {
+            next = self.step(next)?;
+        }
+        Ok(next)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = || {
+            self.weekdays
+                .iter()
+                .map(|d| weekday_name(*d))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match (self.interval, self.weekdays.is_empty()) {
+            _ if self.weekdays == WORKDAYS && self.interval == 1 => f.write_str("every weekday"),
+            (1, true) => write!(f, "every {}", self.frequency.unit()),
+            (1, false) => write!(f, "every {}", days()),
+            (n, true) => write!(f, "every {} {}s", n, self.frequency.unit()),
+            (n, false) => write!(f, "every {} weeks on {}", n, days()),
+        }
+    }
+}
+
+impl TryFrom<String> for Rule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Rule::parse(&value)
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> Self {
+        rule.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, 9, 30, 0).unwrap()
+    }
+
+    fn step(rule: &str, from: DateTime<Local>) -> DateTime<Local> {
+        Rule::parse(rule).unwrap().step(from).unwrap()
+    }
+
+    #[test]
+    fn steps_by_the_interval_keeping_the_time_of_day() {
+        // 2024-03-04 is a Monday.
+        let monday = at(2024, 3, 4);
+        assert_eq!(step("daily", monday), at(2024, 3, 5));
+        assert_eq!(step("every 2 weeks", monday), at(2024, 3, 18));
+        assert_eq!(step("every other month", monday), at(2024, 5, 4));
+        assert_eq!(step("FREQ=YEARLY;INTERVAL=3", monday), at(2027, 3, 4));
+        assert_eq!(step("monthly", at(2024, 1, 31)), at(2024, 2, 29));
+    }
+
+    #[test]
+    fn steps_to_the_next_listed_weekday() {
+        let monday = at(2024, 3, 4);
+        assert_eq!(step("every monday and thursday", monday), at(2024, 3, 7));
+        assert_eq!(
+            step("every monday and thursday", at(2024, 3, 7)),
+            at(2024, 3, 11)
+        );
+        assert_eq!(step("every 2 weeks on monday", monday), at(2024, 3, 18));
+        assert_eq!(step("every weekday", at(2024, 3, 8)), at(2024, 3, 11));
+    }
+
+    #[test]
+    fn skips_occurrences_missed_while_overdue() {
+        let rule = Rule::parse("every week").unwrap();
+        let next = rule.next_due(at(2024, 3, 4), at(2024, 3, 20)).unwrap();
+        assert_eq!(next, at(2024, 3, 25));
+    }
+
+    #[test]
+    fn rejects_intervals_out_of_range() {
+        assert!(Rule::parse("every 0 days").is_err());
+        assert!(Rule::parse("every 1000 days").is_ok());
+        assert!(Rule::parse("every 1001 days").is_err());
+        assert!(Rule::parse("every 4294967295 years").is_err());
+        assert!(Rule::parse("FREQ=YEARLY;INTERVAL=5000").is_err());
+    }
+
+    #[test]
+    fn stepping_past_the_last_date_is_an_error() {
+        let last = NaiveDate::MAX - Days::new(10);
+        let from = Local
+            .from_local_datetime(&last.and_hms_opt(9, 0, 0).unwrap())
+            .earliest()
+            .unwrap();
+        for rule in ["daily", "every 1000 days", "yearly", "every monday"] {
+            let rule = Rule::parse(rule).unwrap();
+            let mut next = Ok(from);
+            for _ in 0..20 {
+                next = next.and_then(|at| rule.step(at));
+            }
+            assert!(next.is_err(), "{} stepped to {:?}", rule, next);
+        }
+        // Rules built by hand skip the check in `parse`, but still can't overflow.
+        let huge = |frequency| Rule {
+            frequency,
+            interval: u32::MAX,
+            weekdays: vec![Weekday::Mon],
+        };
+        for frequency in [
+            Frequency::Daily,
+            Frequency::Weekly,
+            Frequency::Monthly,
+            Frequency::Yearly,
+        ] {
+            assert!(huge(frequency).step(at(2024, 3, 4)).is_err());
+        }
+    }
+}
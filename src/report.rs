@@ -0,0 +1,202 @@
+//! A status report for a stretch of time, e.g. a week: the tasks completed in it, grouped by
+//! day or tag, and the ones still open, as Markdown or HTML to paste into a status update.
+
+use crate::Task;
+use chrono::{DateTime, Local, NaiveDate};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// How the completed tasks are grouped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// By the day they were completed on
+    #[default]
+    Day,
+    /// By tag; a task with several tags is under each of them
+    Tag,
+}
+
+pub struct Report<'a> {
+    pub since: DateTime<Local>,
+    pub until: DateTime<Local>,
+    /// The tasks completed from `since` to `until`, in the order they were completed.
+    pub done: Vec<&'a Task>,
+    /// The tasks still open, overdue ones first, then by due date.
+    pub open: Vec<&'a Task>,
+}
+
+impl<'a> Report<'a> {
+    /// The report of `tasks`, which may include archived ones, from `since` to `until`.
+    pub fn of(tasks: &'a [Task], since: DateTime<Local>, until: DateTime<Local>) -> Self {
+        let mut done: Vec<&Task> = tasks
+            .iter()
+            .filter(|t| t.status() == crate::Status::Done)
+            .filter(|t| t.completed_at.is_some_and(|at| at >= since && at <= until))
+            .collect();
+        done.sort_by_key(|t| t.completed_at);
+        let mut open: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+        open.sort_by_key(|t| (!t.is_overdue(until), t.due.is_none(), t.due));
+        Report {
+            since,
+            until,
+            done,
+            open,
+        }
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "Status report: {} to {}",
+            self.since.format("%Y-%m-%d"),
+            self.until.format("%Y-%m-%d")
+        )
+    }
+
+    /// The completed tasks under their headings.
+    fn groups(&self, by: GroupBy) -> Vec<(String, Vec<&'a Task>)> {
+        match by {
+            GroupBy::Day => {
+                let mut days: BTreeMap<NaiveDate, Vec<&Task>> = BTreeMap::new();
+                for task in &self.done {
+                    if let Some(at) = task.completed_at {
+                        days.entry(at.date_naive()).or_default().push(task);
+                    }
+                }
+                days.into_iter()
+                    .map(|(day, tasks)| (day.format("%A %Y-%m-%d").to_string(), tasks))
+                    .collect()
+            }
+            GroupBy::Tag => {
+                let mut tags: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+                let mut untagged = vec![];
+                for task in &self.done {
+                    if task.tags.is_empty() {
+                        untagged.push(*task);
+                    }
+                    for tag in &task.tags {
+                        tags.entry(tag).or_default().push(task);
+                    }
+                }
+                let mut groups: Vec<_> = tags
+                    .into_iter()
+                    .map(|(tag, tasks)| (format!("#{}", tag), tasks))
+                    .collect();
+                if !untagged.is_empty() {
+                    groups.push(("Untagged".to_string(), untagged));
+                }
+                groups
+            }
+        }
+    }
+
+    /// What follows an open task's description: its due date, and whether it is overdue.
+    fn due(&self, task: &Task) -> Option<String> {
+        let due = task.due?.format("%Y-%m-%d");
+        Some(if task.is_overdue(self.until) {
+            format!("overdue since {}", due)
+        } else {
+            format!("due {}", due)
+        })
+    }
+
+    pub fn render_markdown(&self, by: GroupBy) -> String {
+        let mut out = format!("# {}\n\n## Done ({})\n", self.title(), self.done.len());
+        if self.done.is_empty() {
+            out.push_str("\nNothing was completed.\n");
+        }
+        for (heading, tasks) in self.groups(by) {
+            let _ = writeln!(out, "\n### {}\n", heading);
+            for task in tasks {
+                let tags = match by {
+                    GroupBy::Day if !task.tags.is_empty() => {
+                        let tags: Vec<String> =
+                            task.tags.iter().map(|t| format!("`#{}`", t)).collect();
+                        format!(" {}", tags.join(" "))
+                    }
+                    _ => String::new(),
+                };
+                let _ = writeln!(out, "- {}{}", markdown_escape(&task.description), tags);
+            }
+        }
+        let _ = writeln!(out, "\n## Still open ({})\n", self.open.len());
+        if self.open.is_empty() {
+            out.push_str("Nothing is left.\n");
+        }
+        for task in &self.open {
+            let due = self
+                .due(task)
+                .map(|due| format!(" ({})", due))
+                .unwrap_or_default();
+            let _ = writeln!(out, "- {}{}", markdown_escape(&task.description), due);
+        }
+        out
+    }
+
+    pub fn render_html(&self, by: GroupBy) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+             <style>body {{ font-family: sans-serif; max-width: 40em; }} .tag {{ color: #666; }} \
+             .overdue {{ color: #b00; }}</style>\n</head>\n<body>\n<h1>{0}</h1>\n\
+             <h2>Done ({1})</h2>\n",
+            escape(&self.title()),
+            self.done.len()
+        );
+        if self.done.is_empty() {
+            out.push_str("<p>Nothing was completed.</p>\n");
+        }
+        for (heading, tasks) in self.groups(by) {
+            let _ = writeln!(out, "<h3>{}</h3>\n<ul>", escape(&heading));
+            for task in tasks {
+                let tags: String = match by {
+                    GroupBy::Day => task
+                        .tags
+                        .iter()
+                        .map(|t| format!(" <span class=\"tag\">#{}</span>", escape(t)))
+                        .collect(),
+                    GroupBy::Tag => String::new(),
+                };
+                let _ = writeln!(out, "<li>{}{}</li>", escape(&task.description), tags);
+            }
+            out.push_str("</ul>\n");
+        }
+        let _ = writeln!(out, "<h2>Still open ({})</h2>", self.open.len());
+        if self.open.is_empty() {
+            out.push_str("<p>Nothing is left.</p>\n");
+        } else {
+            out.push_str("<ul>\n");
+            for task in &self.open {
+                let due = match self.due(task) {
+                    Some(due) if task.is_overdue(self.until) => {
+                        format!(" <span class=\"overdue\">({})</span>", due)
+                    }
+                    Some(due) => format!(" ({})", due),
+                    None => String::new(),
+                };
+                let _ = writeln!(out, "<li>{}{}</li>", escape(&task.description), due);
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escapes the characters that would turn a description into links, emphasis or HTML.
+fn markdown_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,249 @@
+//! Line-delimited JSON-RPC 2.0 over stdin/stdout for editor integrations.
+//!
+//! Each request is one JSON object per line, and each response is one line:
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"add","params":{"description":"Write docs"}}
+//! ← {"jsonrpc":"2.0","id":1,"result":{"id":7,"description":"Write docs",...}}
+//! ```
+//!
+//! A notification, a request without an `id`, is carried out but not answered, even when it
+//! fails.
+//!
+//! Methods: `list` (`{"filter": "all"|"pending"|"completed"}`), `add` (`{"description"}`),
+//! `complete` (`{"id"}`) and `delete` (`{"id"}`). The task file is re-read before every request
+//! so changes made from the command line in the meantime are visible.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use todo_core::{Completion, Task, TodoList};
+use tracing::debug;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const TASK_NOT_FOUND: i64 = -32001;
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    jsonrpc: String,
+    /// `None` for a notification; `"id": null` is still a request.
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Reads a field that is there, even as `null`, as `Some`.
+fn present<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for RpcError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        RpcError::new(INTERNAL_ERROR, e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    #[serde(default)]
+    filter: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AddParams {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: usize,
+}
+
+fn params<T: for<'de> Deserialize<'de>>(value: Value) -> Result<T, RpcError> {
+    let value = if value.is_null() { json!({}) } else { value };
+    serde_json::from_value(value).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value(task: &Task) -> Value {
+    serde_json::to_value(task).unwrap_or(Value::Null)
+}
+
+fn dispatch(list: &mut TodoList, method: &str, raw: Value) -> Result<Value, RpcError> {
+    match method {
+        "list" => {
+            let p: ListParams = params(raw)?;
+            let tasks: Vec<Value> = match p.filter.as_deref().unwrap_or("all") {
+                "all" => list.tasks().iter().map(to_value).collect(),
+                "pending" => list.pending().map(to_value).collect(),
+                "completed" => list.completed().map(to_value).collect(),
+                other => {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        format!("unknown filter {:?}", other),
+                    ))
+                }
+            };
+            Ok(Value::Array(tasks))
+        }
+        "add" => {
+            let p: AddParams = params(raw)?;
+            if p.description.trim().is_empty() {
+                return Err(RpcError::new(INVALID_PARAMS, "description is empty"));
+            }
+            let id = list.add(Task::builder().description(p.description).build())?;
+            Ok(list.get(id).map(to_value).unwrap_or_default())
+        }
+        "complete" => {
+            let p: IdParams = params(raw)?;
+            match list.complete(p.id)? {
+                Completion::Completed(task)
+                | Completion::Repeated {
+                    completed: task, ..
+                } => Ok(to_value(&task)),
+                Completion::AlreadyCompleted => {
+                    Ok(list.get(p.id).map(to_value).unwrap_or_default())
+                }
+                Completion::NotFound => Err(RpcError::new(
+                    TASK_NOT_FOUND,
+                    format!("task {} not found", p.id),
+                )),
+            }
+        }
+        "delete" => {
+            let p: IdParams = params(raw)?;
+            if list.delete(p.id)?.is_none() {
+                return Err(RpcError::new(
+                    TASK_NOT_FOUND,
+                    format!("task {} not found", p.id),
+                ));
+            }
+            Ok(json!({ "deleted": p.id }))
+        }
+        other => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method {:?}", other),
+        )),
+    }
+}
+
+/// The response to `line`, or `None` for a notification.
+fn handle(list: &mut TodoList, line: &str) -> Option<Value> {
+    let raw: Value = match serde_json::from_str(line) {
+        Ok(raw) => raw,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ))
+        }
+    };
+    let request: Request = match serde_json::from_value(raw) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, e.to_string()),
+            ))
+        }
+    };
+    if request.jsonrpc != "2.0" {
+        return Some(error_response(
+            request.id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, r#""jsonrpc" must be "2.0""#),
+        ));
+    }
+    debug!(method = %request.method, "rpc request");
+    list.reload();
+    let result = dispatch(list, &request.method, request.params);
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, e),
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// Answers requests until stdin is closed.
+pub fn serve(list: &mut TodoList) -> Result<(), Box<dyn std::error::Error>> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle(list, &line) {
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list() -> TodoList {
+        TodoList::open(std::env::temp_dir().join("rust-todo-rpc-tests/missing/tasks.json"))
+    }
+
+    #[test]
+    fn notifications_get_no_response() {
+        let mut list = list();
+        assert_eq!(
+            handle(&mut list, r#"{"jsonrpc":"2.0","method":"list"}"#),
+            None
+        );
+        assert_eq!(
+            handle(&mut list, r#"{"jsonrpc":"2.0","method":"nope"}"#),
+            None
+        );
+        let response = handle(&mut list, r#"{"jsonrpc":"2.0","id":null,"method":"list"}"#);
+        assert_eq!(
+            response,
+            Some(json!({"jsonrpc": "2.0", "id": null, "result": []}))
+        );
+    }
+
+    #[test]
+    fn only_version_2_is_answered() {
+        let mut list = list();
+        for line in [
+            r#"{"id":4,"method":"list"}"#,
+            r#"{"jsonrpc":"1.0","id":4,"method":"list"}"#,
+        ] {
+            let response = handle(&mut list, line).unwrap();
+            assert_eq!(response["id"], 4);
+            assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Importing Remember The Milk's JSON account export (`rememberthemilk_export_*.json`).
+//!
+//! List names and tags become tags, and RTM priorities 1–3 map to high, medium and low.
+//! Recurrence rules become repeat rules; those outside what [`Rule`] supports are kept verbatim
+//! as `rtm.repeat`, and task notes as `rtm.notes`. Deleted tasks are skipped. Every task records its RTM ID as
+//! `external.id`, so importing a newer export only adds what is new.
+//!
+//! A task that can't be read, e.g. without a name, is skipped, and a date that can't be read
+//! is left out; both are reported as [problems](crate::import::Problem) naming the task's
+//! place in the export.
+
+use crate::import::Problem;
+use crate::recurrence::Rule;
+use crate::{dates, normalize_tag, Priority, Task};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+struct Export {
+    #[serde(default)]
+    lists: Vec<List>,
+    /// Read one by one, so one that can't be read doesn't stop the others.
+    #[serde(default)]
+    tasks: Vec<Value>,
+    #[serde(default)]
+    notes: Vec<Note>,
+}
+
+#[derive(Deserialize)]
+struct List {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct RtmTask {
+    id: String,
+    #[serde(default)]
+    series_id: Option<String>,
+    #[serde(default)]
+    list_id: Option<String>,
+    name: String,
+    #[serde(default)]
+    priority: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Dates are milliseconds since the epoch; see [`date`].
+    #[serde(default)]
+    date_created: Value,
+    #[serde(default)]
+    date_due: Value,
+    #[serde(default)]
+    date_due_has_time: bool,
+    #[serde(default)]
+    date_completed: Value,
+    #[serde(default)]
+    date_trashed: Value,
+    #[serde(default)]
+    repeat: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Note {
+    series_id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    content: String,
+}
+
+/// Converts an RTM millisecond timestamp to local time.
+fn timestamp(millis: i64) -> Option<DateTime<Local>> {
+    DateTime::from_timestamp_millis(millis).map(|at| at.with_timezone(&Local))
+}
+
+/// The date in `value`, `field` of the task `task` describes: RTM's milliseconds since the
+/// epoch, as a number or a string. One that can't be read is added to `problems` and left
+/// out.
+fn date(
+    value: &Value,
+    field: &str,
+    task: &str,
+    problems: &mut Vec<Problem>,
+) -> Option<DateTime<Local>> {
+    if value.is_null() {
+        return None;
+    }
+    let millis = value
+        .as_i64()
+        .or_else(|| value.as_str()?.trim().parse().ok());
+    let at = millis.and_then(timestamp);
+    if at.is_none() {
+        problems.push(Problem {
+            line: None,
+            message: format!("{}: could not read {} {}", task, field, value),
+        });
+    }
+    at
+}
+
+/// RTM stores date-only due dates as midnight; like `--due`, they mean the end of that day.
+fn due_date(due: DateTime<Local>, has_time: bool) -> Option<DateTime<Local>> {
+    if has_time {
+        return Some(due);
+    }
+    dates::end_of_day(due.date_naive()).ok()
+}
+
+fn priority(value: Option<&str>) -> Option<Priority> {
+    match value? {
+        "P1" | "1" => Some(Priority::High),
+        "P2" | "2" => Some(Priority::Medium),
+        "P3" | "3" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// The tasks in the export, and the problems found in it.
+pub fn parse(content: &str) -> Result<(Vec<Task>, Vec<Problem>), Box<dyn std::error::Error>> {
+    let export: Export = serde_json::from_str(content)?;
+    let lists: HashMap<&str, &str> = export
+        .lists
+        .iter()
+        .map(|l| (l.id.as_str(), l.name.as_str()))
+        .collect();
+    let mut notes: HashMap<&str, Vec<String>> = HashMap::new();
+    for note in &export.notes {
+        let text = [note.title.trim(), note.content.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        notes.entry(note.series_id.as_str()).or_default().push(text);
+    }
+
+    let mut tasks = vec![];
+    let mut problems = vec![];
+    for (index, item) in export.tasks.into_iter().enumerate() {
+        let item: RtmTask = match serde_json::from_value(item) {
+            Ok(item) => item,
+            Err(e) => {
+                problems.push(Problem {
+                    line: None,
+                    message: format!("task {}: {}", index + 1, e),
+                });
+                continue;
+            }
+        };
+        if !item.date_trashed.is_null() {
+            continue;
+        }
+        let described = format!("task {} ({:?})", index + 1, item.name.trim());
+        let mut tags: Vec<String> = item
+            .list_id
+            .as_deref()
+            .and_then(|id| lists.get(id))
+            .filter(|name| !name.eq_ignore_ascii_case("inbox"))
+            .map(|name| normalize_tag(name))
+            .into_iter()
+            .collect();
+        for tag in item.tags.iter().map(|t| normalize_tag(t)) {
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        let mut builder = Task::builder()
+            .description(item.name.trim())
+            .completed(!item.date_completed.is_null())
+            .tags(tags)
+            .priority(priority(item.priority.as_deref()))
+            .due(
+                date(&item.date_due, "date_due", &described, &mut problems)
+                    .and_then(|due| due_date(due, item.date_due_has_time)),
+            )
+            .metadata("external.id", format!("rtm:{}", item.id));
+        if let Some(repeat) = item.repeat.filter(|r| !r.is_empty()) {
+            match Rule::parse(&repeat) {
+                Ok(rule) => builder = builder.repeat(Some(rule)),
+                Err(_) => builder = builder.metadata("rtm.repeat", repeat),
+            }
+        }
+        if let Some(url) = item.url.filter(|u| !u.is_empty()) {
+            builder = builder.metadata("url", url);
+        }
+        if let Some(series_notes) = item.series_id.as_deref().and_then(|id| notes.get(id)) {
+            builder = builder.metadata("rtm.notes", series_notes.join("\n\n"));
+        }
+        let mut task = builder.build();
+        if let Some(created) = date(
+            &item.date_created,
+            "date_created",
+            &described,
+            &mut problems,
+        ) {
+            task.created_at = created.fixed_offset();
+        }
+        tasks.push(task);
+    }
+    Ok((tasks, problems))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_tasks_and_dates_are_reported() {
+        let (tasks, problems) = parse(
+            r#"{"tasks": [
+                {"id": "1", "name": "Buy milk", "date_due": "soon", "date_created": 1714000000000},
+                {"id": "2"},
+                {"id": "3", "name": "Call", "date_due": "1714000000000", "date_due_has_time": true}
+            ]}"#,
+        )
+        .unwrap();
+        let names: Vec<&str> = tasks.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(names, ["Buy milk", "Call"]);
+        assert_eq!(tasks[0].due, None);
+        assert_eq!(tasks[0].created_at.timestamp_millis(), 1714000000000);
+        assert_eq!(tasks[1].due.unwrap().timestamp_millis(), 1714000000000);
+        let messages: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with(r#"task 1 ("Buy milk"): could not read date_due"#));
+        assert!(messages[1].starts_with("task 2: missing field `name`"));
+    }
+}
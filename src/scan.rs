@@ -0,0 +1,109 @@
+use crate::{comments, Task, TodoList};
+use ignore::WalkBuilder;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+/// Metadata key recording which directory a task was scanned from.
+const SCAN_ROOT: &str = "scan.root";
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub files: usize,
+    pub added: usize,
+    pub moved: usize,
+    pub resolved: usize,
+}
+
+struct Found {
+    file: String,
+    line: usize,
+    text: String,
+}
+
+fn find_todos(root: &Path) -> Result<(Vec<Found>, usize), Box<dyn std::error::Error>> {
+    let mut found = Vec::new();
+    let mut files = 0;
+    for entry in WalkBuilder::new(root).build() {
+        let entry = entry?;
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        // Binary and non-UTF-8 files are skipped.
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        files += 1;
+        let file = entry
+            .path()
+            .strip_prefix("./")
+            .unwrap_or(entry.path())
+            .display()
+            .to_string();
+        for (index, line) in content.lines().enumerate() {
+            if let Some(text) = comments::todo_text(line) {
+                found.push(Found {
+                    file: file.clone(),
+                    line: index + 1,
+                    text,
+                });
+            }
+        }
+    }
+    Ok((found, files))
+}
+
+/// Turns TODO/FIXME comments under `root` into tasks. Tasks from an earlier scan of the same
+/// root whose comment has disappeared are marked complete.
+pub fn scan(list: &mut TodoList, root: &Path) -> Result<ScanReport, Box<dyn std::error::Error>> {
+    let root_key = fs::canonicalize(root)?.display().to_string();
+    let (found, files) = find_todos(root)?;
+    let mut report = ScanReport {
+        files,
+        ..ScanReport::default()
+    };
+    let mut present = HashSet::new();
+
+    for todo in found {
+        let existing = list.tasks.iter_mut().find(|t| {
+            t.description == todo.text && t.metadata.get("source.file") == Some(&todo.file)
+        });
+        let id = match existing {
+            Some(task) => {
+                let line = todo.line.to_string();
+                if task.metadata.get("source.line") != Some(&line) {
+                    task.metadata.insert("source.line".to_string(), line);
+                    report.moved += 1;
+                }
+                task.metadata
+                    .insert(SCAN_ROOT.to_string(), root_key.clone());
+                task.id
+            }
+            None => {
+                report.added += 1;
+                list.insert(
+                    Task::builder()
+                        .description(todo.text)
+                        .metadata("source.file", todo.file)
+                        .metadata("source.line", todo.line.to_string())
+                        .metadata(SCAN_ROOT, root_key.clone())
+                        .build(),
+                )
+            }
+        };
+        present.insert(id);
+    }
+
+    for task in list.tasks.iter_mut() {
+        let from_this_root = task.metadata.get(SCAN_ROOT) == Some(&root_key);
+        if from_this_root && !task.completed && !present.contains(&task.id) {
+            debug!(id = task.id, "TODO comment removed, completing task");
+            task.set_completed(true);
+            report.resolved += 1;
+        }
+    }
+
+    list.save()?;
+    Ok(report)
+}
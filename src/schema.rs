@@ -0,0 +1,179 @@
+//! Versions of the stored task format, and upgrading tasks saved by older versions.
+//!
+//! The JSON data file is `{"version": N, "tasks": [...]}`; files written before versions
+//! were recorded are a bare array of tasks and count as version 1. The SQLite database keeps
+//! the version in its `user_version`. Each entry in [`MIGRATIONS`] upgrades one task, as raw
+//! JSON, from one version to the next, so a migration can still read fields that [`Task`]
+//! has since renamed or dropped. Data from a newer version of rust-todo is refused instead
+//! of being read with its new fields lost.
+
+use crate::import::Problem;
+use crate::Task;
+use serde::Serialize;
+use serde_json::Value;
+
+/// The version written by this build.
+pub const CURRENT: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a task from version `n + 1` to `n + 2`.
+const MIGRATIONS: [fn(&mut Value); CURRENT as usize - 1] = [v2];
+
+/// Version 2 only added the version to the file; tasks are unchanged.
+fn v2(_task: &mut Value) {}
+
+/// Fails for versions this build can't read.
+pub fn check(version: u32) -> Result<(), String> {
+    match version {
+        0 => Err("invalid data version 0".to_string()),
+        v if v > CURRENT => Err(format!(
+            "the tasks were saved by a newer rust-todo (data version {}, this one reads up to {}); upgrade rust-todo to use them",
+            v, CURRENT
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Reads tasks stored at `version`, upgrading them to [`CURRENT`].
+pub fn upgrade(tasks: Vec<Value>, version: u32) -> Result<Vec<Task>, String> {
+    check(version)?;
+    tasks
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut task)| {
+            for migration in &MIGRATIONS[version as usize - 1..] {
+                migration(&mut task);
+            }
+            serde_json::from_value(task).map_err(|e| format!("task {}: {}", index + 1, e))
+        })
+        .collect()
+}
+
+/// The tasks of a data file or bare array, as raw JSON, and their version.
+fn document(content: &str) -> Result<(Vec<Value>, u32), String> {
+    Ok(
+        match serde_json::from_str(content).map_err(|e| e.to_string())? {
+            Value::Array(tasks) => (tasks, 1),
+            Value::Object(mut document) => {
+                let version = document
+                    .get("version")
+                    .and_then(Value::as_u64)
+                    .ok_or("the data file has no version")?;
+                match document.remove("tasks") {
+                    Some(Value::Array(tasks)) => {
+                        (tasks, u32::try_from(version).unwrap_or(u32::MAX))
+                    }
+                    _ => return Err("the data file has no list of tasks".to_string()),
+                }
+            }
+            _ => return Err("expected a list of tasks".to_string()),
+        },
+    )
+}
+
+/// Reads a JSON data file, or a bare array of tasks as exported, of any version this build
+/// knows. Returns the tasks and the version they were stored at.
+pub fn parse(content: &str) -> Result<(Vec<Task>, u32), String> {
+    let (tasks, version) = document(content)?;
+    Ok((upgrade(tasks, version)?, version))
+}
+
+/// Like [`parse`], for `import`: tasks that can't be read are skipped, each with a
+/// [`Problem`], instead of failing the whole file.
+pub fn parse_lenient(content: &str) -> Result<(Vec<Task>, Vec<Problem>), String> {
+    let (values, version) = document(content)?;
+    check(version)?;
+    let lines = element_lines(content);
+    let lines = (lines.len() == values.len()).then_some(lines);
+    let mut tasks = vec![];
+    let mut problems = vec![];
+    for (index, mut task) in values.into_iter().enumerate() {
+        for migration in &MIGRATIONS[version as usize - 1..] {
+            migration(&mut task);
+        }
+        match serde_json::from_value(task) {
+            Ok(task) => tasks.push(task),
+            Err(e) => problems.push(Problem {
+                line: lines.as_ref().map(|lines| lines[index]),
+                message: format!("task {}: {}", index + 1, e),
+            }),
+        }
+    }
+    Ok((tasks, problems))
+}
+
+/// The line each object in the outermost array of `content` starts on, such as the tasks of
+/// a data file or of a bare array, found without parsing the JSON. Other values in the array
+/// aren't counted, so callers check that there are as many lines as items.
+pub(crate) fn element_lines(content: &str) -> Vec<usize> {
+    let mut lines = vec![];
+    let mut line = 1;
+    // Whether each enclosing value is an array, outermost first.
+    let mut enclosing: Vec<bool> = vec![];
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in content.chars() {
+        if c == '\n' {
+            line += 1;
+        }
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => {
+                let arrays = enclosing.iter().filter(|array| **array).count();
+                if arrays == 1 && enclosing.last() == Some(&true) {
+                    lines.push(line);
+                }
+                enclosing.push(false);
+            }
+            '[' => enclosing.push(true),
+            ']' | '}' => {
+                enclosing.pop();
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u32,
+    tasks: &'a [Task],
+}
+
+/// The JSON data file holding `tasks`, at [`CURRENT`].
+pub fn render(tasks: &[Task]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Document {
+        version: CURRENT,
+        tasks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+
+    #[test]
+    fn round_trips_every_field() {
+        let mut task = Task::builder().description("Call the plumber").build();
+        task.id = 3;
+        task.priority = Some(Priority::High);
+        task.add_tag("house");
+        task.location = Some("phone".into());
+        task.metadata.insert("external.id".into(), "x:1".into());
+        let tasks = vec![task];
+
+        let (parsed, version) = parse(&render(&tasks).unwrap()).unwrap();
+        assert_eq!(parsed, tasks);
+        assert_eq!(version, CURRENT);
+    }
+}
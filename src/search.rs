@@ -0,0 +1,220 @@
+//! Finding tasks by text, as a plain substring, a regular expression or a fuzzy match.
+//!
+//! Descriptions, tags, locations, the person a task waits on, imported metadata and notes
+//! are searched. Results are ranked by how well the best field matched: a whole field beats its
+//! start, which beats the start of a word, which beats anywhere else, and descriptions count
+//! more than tags, which count more than the rest.
+//!
+//! Plain and fuzzy queries ignore case and accents (see [`collate`]), so "resume" finds
+//! "Résumé" and "strasse" finds "Straße".
+
+use crate::collate::{self, Folded};
+use crate::Task;
+use regex::Regex;
+use std::ops::Range;
+
+/// How a query is matched against a task's text.
+pub enum Matcher {
+    /// The query, [folded](collate::fold), appears in the folded text.
+    Substring(String),
+    /// The regular expression matches; add `(?i)` to ignore case.
+    Regex(Regex),
+    /// The query's characters appear in order, ignoring case, accents and spaces, like
+    /// `qrtrv` in "quarterly review". Texts containing the query as a whole rank above looser
+    /// matches.
+    Fuzzy { substring: String, chars: Vec<char> },
+}
+
+/// The part of a task a query matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Description,
+    Tag(String),
+    Location,
+    WaitingOn,
+    Metadata(String),
+    Note,
+}
+
+/// A matching task, with its score and where the best match was.
+#[derive(Debug)]
+pub struct Hit<'a> {
+    pub task: &'a Task,
+    pub score: u32,
+    pub field: Field,
+    /// Byte ranges of the matched text within the field.
+    pub ranges: Vec<Range<usize>>,
+}
+
+fn at_word_start(text: &str, index: usize) -> bool {
+    !text[..index]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric)
+}
+
+/// How well a match at `range` covers `text`, from 40 to 100.
+fn quality(text: &str, range: &Range<usize>) -> u32 {
+    if range.start == 0 && range.end == text.len() {
+        100
+    } else if range.start == 0 {
+        80
+    } else if at_word_start(text, range.start) {
+        60
+    } else {
+        40
+    }
+}
+
+fn best(text: &str, ranges: Vec<Range<usize>>) -> Option<(u32, Vec<Range<usize>>)> {
+    let best = ranges.iter().map(|r| quality(text, r)).max()?;
+    Some((best, ranges))
+}
+
+fn find_regex(regex: &Regex, text: &str) -> Option<(u32, Vec<Range<usize>>)> {
+    let ranges = regex
+        .find_iter(text)
+        .map(|m| m.range())
+        .filter(|r| !r.is_empty())
+        .collect();
+    best(text, ranges)
+}
+
+fn find_folded(query: &str, text: &str, folded: &Folded) -> Option<(u32, Vec<Range<usize>>)> {
+    if query.is_empty() {
+        return None;
+    }
+    let ranges = folded
+        .text
+        .match_indices(query)
+        .map(|(start, _)| folded.original(&(start..start + query.len())))
+        .collect();
+    best(text, ranges)
+}
+
+/// The shortest stretch of `text` holding `chars` in order. Scores from 1 to 39, higher the
+/// less the matched characters are spread out.
+fn find_subsequence(text: &Folded, chars: &[char]) -> Option<(u32, Vec<Range<usize>>)> {
+    let folded: Vec<(usize, char)> = text.text.char_indices().collect();
+    let mut best: Option<Vec<usize>> = None;
+    for start in (0..folded.len()).filter(|&i| folded[i].1 == chars[0]) {
+        let mut picked = vec![start];
+        for &c in &chars[1..] {
+            let from = picked[picked.len() - 1] + 1;
+            match (from..folded.len()).find(|&i| folded[i].1 == c) {
+                Some(i) => picked.push(i),
+                None => break,
+            }
+        }
+        if picked.len() < chars.len() {
+            // Starting later can only find fewer characters.
+            break;
+        }
+        let span = |p: &[usize]| p[p.len() - 1] - p[0];
+        if best.as_deref().is_none_or(|b| span(&picked) < span(b)) {
+            best = Some(picked);
+        }
+    }
+    let picked = best?;
+    let span = picked[picked.len() - 1] - picked[0] + 1;
+    let score = 1 + (38 * chars.len() / span) as u32;
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for i in picked {
+        let (offset, c) = folded[i];
+        let range = text.original(&(offset..offset + c.len_utf8()));
+        match ranges.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => ranges.push(range),
+        }
+    }
+    Some((score, ranges))
+}
+
+impl Matcher {
+    pub fn substring(query: &str) -> Matcher {
+        Matcher::Substring(collate::fold(query).text)
+    }
+
+    pub fn regex(pattern: &str) -> Result<Matcher, regex::Error> {
+        Ok(Matcher::Regex(Regex::new(pattern)?))
+    }
+
+    pub fn fuzzy(query: &str) -> Matcher {
+        let substring = collate::fold(query).text;
+        Matcher::Fuzzy {
+            chars: substring.chars().filter(|c| !c.is_whitespace()).collect(),
+            substring,
+        }
+    }
+
+    /// How well `text` matches, from 1 to 100, and the matched byte ranges.
+    pub fn find(&self, text: &str) -> Option<(u32, Vec<Range<usize>>)> {
+        match self {
+            Matcher::Regex(regex) => find_regex(regex, text),
+            Matcher::Substring(query) => find_folded(query, text, &collate::fold(text)),
+            Matcher::Fuzzy { substring, chars } => {
+                let folded = collate::fold(text);
+                find_folded(substring, text, &folded).or_else(|| {
+                    if chars.is_empty() {
+                        None
+                    } else {
+                        find_subsequence(&folded, chars)
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// The task's best match, if any field matches.
+fn best_hit<'a>(task: &'a Task, matcher: &Matcher) -> Option<Hit<'a>> {
+    let mut fields = vec![(Field::Description, task.description.as_str(), 3)];
+    fields.extend(
+        task.tags
+            .iter()
+            .map(|t| (Field::Tag(t.clone()), t.as_str(), 2)),
+    );
+    fields.extend(task.location.as_deref().map(|l| (Field::Location, l, 1)));
+    fields.extend(
+        task.waiting_on
+            .as_ref()
+            .map(|w| (Field::WaitingOn, w.person.as_str(), 1)),
+    );
+    fields.extend(
+        task.metadata
+            .iter()
+            .map(|(key, value)| (Field::Metadata(key.clone()), value.as_str(), 1)),
+    );
+    fields.extend(task.notes.iter().map(|n| (Field::Note, n.text.as_str(), 1)));
+    let mut best: Option<Hit> = None;
+    for (field, text, weight) in fields {
+        if let Some((quality, ranges)) = matcher.find(text) {
+            let score = quality * weight;
+            if best.as_ref().is_none_or(|b| score > b.score) {
+                best = Some(Hit {
+                    task,
+                    score,
+                    field,
+                    ranges,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// The tasks matching `matcher`, best first; equal scores put pending tasks first, then go
+/// by ID.
+pub fn search<'a>(tasks: impl IntoIterator<Item = &'a Task>, matcher: &Matcher) -> Vec<Hit<'a>> {
+    let mut hits: Vec<Hit> = tasks
+        .into_iter()
+        .filter_map(|task| best_hit(task, matcher))
+        .collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.task.completed.cmp(&b.task.completed))
+            .then(a.task.id.cmp(&b.task.id))
+    });
+    hits
+}
@@ -0,0 +1,235 @@
+//! Choosing the tasks a command acts on: IDs and ranges such as `3 5 7-10`, aliases and the
+//! start of descriptions (see [`TodoList::lookup`](crate::TodoList::lookup)), or selectors
+//! such as `tag=shopping` given with `--filter`.
+
+use crate::{config, normalize_tag, Priority, Task};
+use clap::ValueEnum;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// A task named on the command line: by ID, or by anything else [`TodoList::lookup`] finds
+/// it by.
+///
+/// [`TodoList::lookup`]: crate::TodoList::lookup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskRef {
+    Id(usize),
+    Name(String),
+}
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<TaskRef, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err("give a task's ID or alias, or the start of its description".into());
+        }
+        Ok(match input.parse() {
+            Ok(id) => TaskRef::Id(id),
+            Err(_) => TaskRef::Name(input.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "{}", id),
+            TaskRef::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Tasks named on the command line: a range of IDs, or one task by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Ids(RangeInclusive<usize>),
+    Task(TaskRef),
+}
+
+/// Parses an ID, a range such as `7-10`, or the name of a task. Anything made of digits and
+/// dashes is an ID or a range.
+pub fn parse_target(input: &str) -> Result<Target, String> {
+    let range = input.trim().starts_with(|c: char| c.is_ascii_digit())
+        && input
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == ' ');
+    if range {
+        return parse_range(input).map(Target::Ids);
+    }
+    input.parse().map(Target::Task)
+}
+
+/// The most IDs a range can span, so a mistyped one such as `1-99999999999` is caught.
+pub const MAX_RANGE: usize = 100_000;
+
+/// Parses an ID or an inclusive range of IDs, e.g. `7` or `7-10`, of at most [`MAX_RANGE`].
+pub fn parse_range(input: &str) -> Result<RangeInclusive<usize>, String> {
+    let id = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("{:?} is not a task ID or a range like 7-10", input))
+    };
+    match input.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (id(start)?, id(end)?);
+            if start > end {
+                return Err(format!("the range {} is backwards", input));
+            }
+            if end - start >= MAX_RANGE {
+                return Err(format!(
+                    "the range {} spans more than {} IDs",
+                    input, MAX_RANGE
+                ));
+            }
+            Ok(start..=end)
+        }
+        None => id(input).map(|id| id..=id),
+    }
+}
+
+/// The IDs of `existing` in any of `ranges`, in ID order and without repeats, and the ranges
+/// (or single IDs) with no task in them. Only the IDs that exist are looked at, so a long range
+/// over a short list is quick.
+pub fn ids(
+    ranges: &[RangeInclusive<usize>],
+    existing: &BTreeSet<usize>,
+) -> (Vec<usize>, Vec<RangeInclusive<usize>>) {
+    let mut ids = BTreeSet::new();
+    let mut missing = vec![];
+    for range in ranges {
+        // `BTreeSet::range` panics on a backwards range, which holds no IDs anyway.
+        let found: Vec<usize> = if range.is_empty() {
+            vec![]
+        } else {
+            existing.range(range.clone()).copied().collect()
+        };
+        if found.is_empty() {
+            missing.push(range.clone());
+        }
+        ids.extend(found);
+    }
+    (ids.into_iter().collect(), missing)
+}
+
+/// A condition a task must meet to be selected by `--filter`.
+#[derive(Debug, Clone)]
+pub enum Selector {
+    /// `tag=NAME`
+    Tag(String),
+    /// `priority=LEVEL`
+    Priority(Priority),
+    /// `status=pending` or `status=completed`
+    Completed(bool),
+    /// `place=NAME`
+    Place(String),
+    /// `assignee=NAME`, or `assignee=me`
+    Assignee(String),
+}
+
+impl Selector {
+    /// Parses `key=value`, with the keys `tag`, `priority`, `status`, `place` and `assignee`.
+    pub fn parse(input: &str) -> Result<Selector, String> {
+        let (key, value) = input
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, e.g. tag=shopping, not {:?}", input))?;
+        let value = value.trim();
+        match key.trim() {
+            "tag" => Ok(Selector::Tag(normalize_tag(value))),
+            "priority" => Priority::from_str(value, true).map(Selector::Priority),
+            "status" => match value {
+                "pending" => Ok(Selector::Completed(false)),
+                "completed" | "done" => Ok(Selector::Completed(true)),
+                _ => Err(format!(
+                    "unknown status {:?} (use pending or completed)",
+                    value
+                )),
+            },
+            "place" => Ok(Selector::Place(value.to_string())),
+            "assignee" => config::assignee(value).map(Selector::Assignee),
+            other => Err(format!(
+                "unknown filter key {:?} (use tag, priority, status, place or assignee)",
+                other
+            )),
+        }
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        match self {
+            Selector::Tag(tag) => task.tags.contains(tag),
+            Selector::Priority(level) => task.priority == Some(*level),
+            Selector::Completed(completed) => task.completed == *completed,
+            Selector::Place(place) => task
+                .location
+                .as_deref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(place)),
+            Selector::Assignee(person) => task
+                .assignee
+                .as_deref()
+                .is_some_and(|a| a.eq_ignore_ascii_case(person)),
+        }
+    }
+}
+
+/// IDs of the tasks matching every selector, in ID order.
+pub fn matching(tasks: &[Task], selectors: &[Selector]) -> Vec<usize> {
+    tasks
+        .iter()
+        .filter(|t| selectors.iter().all(|s| s.matches(t)))
+        .map(|t| t.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing(ids: &[usize]) -> BTreeSet<usize> {
+        ids.iter().copied().collect()
+    }
+
+    #[test]
+    fn reads_ids_and_ranges() {
+        assert_eq!(parse_range("7"), Ok(7..=7));
+        assert_eq!(parse_range("7-10"), Ok(7..=10));
+        assert_eq!(parse_range(" 7 - 10 "), Ok(7..=10));
+        assert!(parse_range("10-7").is_err());
+        assert!(parse_range("7-").is_err());
+        assert!(parse_range("1-99999999999").is_err());
+        assert_eq!(parse_range("1-100000"), Ok(1..=100_000));
+    }
+
+    #[test]
+    fn ranges_pick_the_tasks_there_are_in_id_order() {
+        let there = existing(&[1, 2, 3, 5, 8, 9]);
+        let (ids, missing) = ids(&[8..=20, 2..=5, 9..=9], &there);
+        assert_eq!(ids, [2, 3, 5, 8, 9]);
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn overlapping_ranges_give_each_task_once() {
+        let there = existing(&[1, 2, 3, 4, 5]);
+        let (ids, _) = ids(&[1..=4, 3..=5, 4..=4], &there);
+        assert_eq!(ids, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn ranges_and_ids_with_no_task_are_missing() {
+        let there = existing(&[1, 2, 3]);
+        let (ids, missing) = ids(&[2..=2, 7..=7, 10..=12], &there);
+        assert_eq!(ids, [2]);
+        assert_eq!(missing, [7..=7, 10..=12]);
+    }
+
+    #[test]
+    fn a_long_range_over_a_short_list_is_quick() {
+        let there = existing(&[1, 2, 3]);
+        let (ids, missing) = ids(&[0..=usize::MAX], &there);
+        assert_eq!(ids, [1, 2, 3]);
+        assert!(missing.is_empty());
+    }
+}
@@ -0,0 +1,1078 @@
+//! `serve`: the list over HTTP as a small REST API, for phone shortcuts and web front ends.
+//!
+//! ```text
+//! GET    /tasks          the tasks; ?filter=pending|completed, ?tag=work and ?assignee=sam
+//!                        (or me) narrow them down
+//! GET    /tasks/{id}     one task
+//! POST   /tasks          add {"description", "tags", "priority", "due", "parent", "assignee"}
+//! PATCH  /tasks/{id}     change any of those, or "completed"
+//! DELETE /tasks/{id}     move it to the trash
+//! GET    /calendar.ics   the due dates of pending tasks as calendar events; ?tag=work as above
+//! ```
+//!
+//! Those routes are for the list `serve` was started on; `/lists/{name}/tasks...` are the same
+//! for any list, and `GET /lists` names them. With `--users`, every request carries a user's
+//! token and each list is limited to its members, by [role](crate::access::Role):
+//! `GET /lists/{name}/members` shows them, and owners change them with
+//! `PUT /lists/{name}/members/{user}` (`{"role": "editor"}`) and `DELETE`. Calendar apps
+//! can't send a token, so `calendar.ics` also takes it as `?token=`.
+//!
+//! Tasks are sent as in `export`. Errors are `{"error": "..."}` with a 4xx or 5xx status. As
+//! with [`rpc`](crate::rpc), a list is re-read before every request, so changes made from the
+//! command line in the meantime show up. Requests are answered one at a time, so a client
+//! that stops sending or reading is given up on after [`TIMEOUT`], and request lines and
+//! headers over [`MAX_LINE`] bytes, or more than [`MAX_HEADERS`] headers, are refused. Without
+//! `--users` there is no authentication, so the server only listens on localhost unless told
+//! otherwise, and browsers only let the origin given with `--allow-origin` call it.
+
+use crate::access::{Access, Role};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use todo_core::{config, dates, ical, normalize_tag, Priority, Status, Task, TodoList};
+use tracing::{debug, warn};
+
+/// Largest request body accepted, far more than any task needs.
+const MAX_BODY: usize = 1 << 20;
+
+/// Longest request line or header accepted, in bytes.
+const MAX_LINE: usize = 8 << 10;
+
+/// Most headers a request may have.
+const MAX_HEADERS: usize = 100;
+
+/// How long a client may take to send its request, or to read the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Options {
+    pub host: String,
+    pub port: u16,
+    /// Origin allowed to call the API from a browser, e.g. `http://localhost:3000`.
+    pub allow_origin: Option<String>,
+    /// The list `/tasks` is.
+    pub list: String,
+    /// Users and their roles, when requests must be authenticated.
+    pub access: Option<Access>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum Body {
+    Json(Value),
+    Text {
+        content_type: &'static str,
+        text: String,
+    },
+}
+
+struct Response {
+    status: u16,
+    body: Option<Body>,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Response {
+            status,
+            body: Some(Body::Json(body)),
+            headers: vec![],
+        }
+    }
+
+    fn text(content_type: &'static str, text: String) -> Self {
+        Response {
+            status: 200,
+            body: Some(Body::Text { content_type, text }),
+            headers: vec![],
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Response::json(status, json!({ "error": message.into() }))
+    }
+
+    fn empty(status: u16) -> Self {
+        Response {
+            status,
+            body: None,
+            headers: vec![],
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for Response {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        Response::error(500, e.to_string())
+    }
+}
+
+/// The fields a client may set when adding or changing a task. `due`, `priority` and
+/// `assignee` may be `null` to clear them.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaskFields {
+    description: Option<String>,
+    tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    priority: Option<Option<Priority>>,
+    #[serde(default, deserialize_with = "nullable")]
+    due: Option<Option<String>>,
+    parent: Option<usize>,
+    #[serde(default, deserialize_with = "nullable")]
+    assignee: Option<Option<String>>,
+    completed: Option<bool>,
+}
+
+/// Tells a field set to `null` (`Some(None)`) from one left out (`None`).
+fn nullable<'de, T: Deserialize<'de>, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The response to reading `what` from a request failing with `e`.
+fn unreadable(e: io::Error, what: &str) -> Response {
+    match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+            Response::error(408, format!("timed out waiting for the {}", what))
+        }
+        _ => Response::error(400, format!("unreadable {}", what)),
+    }
+}
+
+/// Reads a line of the request, of at most [`MAX_LINE`] bytes; `what` it is goes in errors.
+fn read_line(stream: &mut impl BufRead, what: &str) -> Result<String, Response> {
+    let mut line = String::new();
+    stream
+        .take(MAX_LINE as u64 + 1)
+        .read_line(&mut line)
+        .map_err(|e| unreadable(e, what))?;
+    if line.len() > MAX_LINE {
+        return Err(Response::error(
+            400,
+            format!("{} longer than {} bytes", what, MAX_LINE),
+        ));
+    }
+    Ok(line)
+}
+
+/// Reads one request. `Ok(None)` means the client closed the connection without sending one.
+fn read_request(stream: &mut impl BufRead) -> Result<Option<Request>, Response> {
+    let bad = |message: &str| Response::error(400, message);
+    let line = read_line(stream, "request line")?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad("malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    let mut length = 0;
+    let mut authorization = None;
+    for count in 0.. {
+        let header = read_line(stream, "header")?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(bad("too many headers"));
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        if name == "content-length" {
+            length = value
+                .trim()
+                .parse()
+                .map_err(|_| bad("invalid Content-Length"))?;
+        } else if name == "authorization" {
+            authorization = Some(value.trim().to_string());
+        }
+    }
+    if length > MAX_BODY {
+        return Err(Response::error(413, "request body too large"));
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => bad("request body shorter than its Content-Length"),
+        _ => unreadable(e, "request body"),
+    })?;
+    Ok(Some(Request {
+        method: method.to_ascii_uppercase(),
+        path: percent_decode(path),
+        query,
+        authorization,
+        body,
+    }))
+}
+
+fn to_value(task: &Task) -> Value {
+    serde_json::to_value(task).unwrap_or(Value::Null)
+}
+
+fn not_found(id: usize) -> Response {
+    Response::error(404, format!("task {} not found", id))
+}
+
+fn fields(request: &Request) -> Result<TaskFields, Response> {
+    serde_json::from_slice(&request.body)
+        .map_err(|e| Response::error(400, format!("invalid task: {}", e)))
+}
+
+/// Applies `fields` to `task`, except for `completed`, which goes through the list so
+/// repeating tasks get their next occurrence.
+fn apply(task: &mut Task, fields: &TaskFields) -> Result<(), Response> {
+    if let Some(description) = &fields.description {
+        if description.trim().is_empty() {
+            return Err(Response::error(400, "description is empty"));
+        }
+        task.description = description.trim().to_string();
+    }
+    if let Some(tags) = &fields.tags {
+        task.tags.clear();
+        for tag in tags {
+            task.add_tag(tag);
+        }
+    }
+    if let Some(priority) = fields.priority {
+        task.priority = priority;
+    }
+    if let Some(due) = &fields.due {
+        task.due = due
+            .as_deref()
+            .map(|due| dates::parse_due(due, dates::now()))
+            .transpose()
+            .map_err(|e| Response::error(400, e))?;
+    }
+    if fields.parent.is_some() {
+        task.parent = fields.parent;
+    }
+    if let Some(assignee) = &fields.assignee {
+        task.assignee = match assignee.as_deref().map(str::trim) {
+            Some("") => return Err(Response::error(400, "assignee is empty")),
+            assignee => assignee.map(str::to_string),
+        };
+    }
+    Ok(())
+}
+
+/// Lists the tasks `query` asks for; `?assignee=me` means `user`, or whoever the server runs
+/// as when requests aren't authenticated.
+fn list_tasks(list: &TodoList, query: &HashMap<String, String>, user: Option<&str>) -> Response {
+    let tag = query.get("tag").map(|tag| normalize_tag(tag));
+    let assignee = match query.get("assignee") {
+        Some(me) if me.eq_ignore_ascii_case("me") && user.is_some() => user.map(str::to_string),
+        Some(name) => match config::assignee(name) {
+            Ok(name) => Some(name),
+            Err(e) => return Response::error(400, e),
+        },
+        None => None,
+    };
+    let completed = match query.get("filter").map(String::as_str) {
+        None | Some("all") => None,
+        Some("pending") => Some(false),
+        Some("completed") => Some(true),
+        Some(other) => return Response::error(400, format!("unknown filter {:?}", other)),
+    };
+    let tasks: Vec<Value> = list
+        .tasks()
+        .iter()
+        .filter(|t| completed.is_none_or(|c| t.completed == c))
+        .filter(|t| tag.as_ref().is_none_or(|tag| t.tags.contains(tag)))
+        .filter(|t| {
+            assignee.as_ref().is_none_or(|a| {
+                t.assignee
+                    .as_ref()
+                    .is_some_and(|b| a.eq_ignore_ascii_case(b))
+            })
+        })
+        .map(to_value)
+        .collect();
+    Response::json(200, Value::Array(tasks))
+}
+
+/// Adds a task; `base` is the path the list's `/tasks` are under.
+fn add(list: &mut TodoList, request: &Request, base: &str) -> Result<Response, Response> {
+    let fields = fields(request)?;
+    if fields.description.is_none() {
+        return Err(Response::error(400, "description is required"));
+    }
+    if let Some(parent) = fields.parent {
+        list.check_parent(None, parent)
+            .map_err(|e| Response::error(400, e))?;
+    }
+    let mut task = Task::builder().description("").build();
+    apply(&mut task, &fields)?;
+    let id = list.add(task)?;
+    if fields.completed == Some(true) {
+        list.complete(id)?;
+    }
+    let mut response = Response::json(201, list.get(id).map(to_value).unwrap_or_default());
+    response
+        .headers
+        .push(("Location", format!("{}/tasks/{}", base, id)));
+    Ok(response)
+}
+
+fn change(list: &mut TodoList, id: usize, request: &Request) -> Result<Response, Response> {
+    let fields = fields(request)?;
+    if list.get(id).is_none() {
+        return Err(not_found(id));
+    }
+    if let Some(parent) = fields.parent {
+        list.check_parent(Some(id), parent)
+            .map_err(|e| Response::error(400, e))?;
+    }
+    let Some(task) = list.get_mut(id) else {
+        return Err(not_found(id));
+    };
+    apply(task, &fields)?;
+    if fields.completed == Some(false) && task.completed {
+        task.set_status(Status::Todo);
+    }
+    list.batch(|list| {
+        if fields.completed == Some(true) {
+            list.complete(id)?;
+        }
+        Ok(())
+    })?;
+    Ok(Response::json(
+        200,
+        list.get(id).map(to_value).unwrap_or_default(),
+    ))
+}
+
+/// Opens a list by name, as `--list` does.
+pub type Opener<'a> = &'a dyn Fn(&str) -> Result<TodoList, Box<dyn std::error::Error>>;
+
+struct Server<'a> {
+    /// The list `/tasks` is.
+    list: &'a mut TodoList,
+    /// Other lists, opened as they are asked for.
+    others: HashMap<String, TodoList>,
+    open: Opener<'a>,
+    options: Options,
+}
+
+fn no_list(name: &str) -> Response {
+    Response::error(404, format!("there is no list {:?}", name))
+}
+
+impl Server<'_> {
+    /// The user making `request`, when requests must be authenticated.
+    fn user(&self, request: &Request) -> Result<Option<String>, Response> {
+        let Some(access) = &self.options.access else {
+            return Ok(None);
+        };
+        let from_query = || {
+            let token = request.query.get("token")?;
+            request
+                .path
+                .ends_with("/calendar.ics")
+                .then(|| format!("Bearer {}", token))
+        };
+        let authorization = request.authorization.clone().or_else(from_query);
+        match access.user(authorization.as_deref()) {
+            Some(user) => Ok(Some(user.to_string())),
+            None => {
+                let mut response = Response::error(401, "a valid token is required");
+                response
+                    .headers
+                    .push(("WWW-Authenticate", "Bearer realm=\"rust-todo\"".into()));
+                Err(response)
+            }
+        }
+    }
+
+    /// Checks that `user` has at least the role `needed` in `list`. A list the user has no
+    /// role in doesn't exist as far as they can tell.
+    fn authorize(&self, user: Option<&str>, list: &str, needed: Role) -> Result<(), Response> {
+        let (Some(access), Some(user)) = (&self.options.access, user) else {
+            return Ok(());
+        };
+        match access.role(user, list) {
+            None => Err(no_list(list)),
+            Some(role) if role < needed => Err(Response::error(
+                403,
+                format!(
+                    "{}'s role in {} is {}; this needs {}",
+                    user, list, role, needed
+                ),
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// The list called `name`, re-read.
+    fn list(&mut self, name: &str) -> Result<&mut TodoList, Response> {
+        let list = if name == self.options.list {
+            &mut *self.list
+        } else {
+            if !self.others.contains_key(name) {
+                let list = (self.open)(name).map_err(|_| no_list(name))?;
+                self.others.insert(name.to_string(), list);
+            }
+            self.others.get_mut(name).unwrap()
+        };
+        list.reload();
+        Ok(list)
+    }
+
+    fn route(&mut self, request: &Request) -> Result<Response, Response> {
+        if let Some(access) = &mut self.options.access {
+            if let Err(e) = access.reload() {
+                warn!(error = %e, "keeping the users as they were");
+            }
+        }
+        let user = self.user(request)?;
+        let user = user.as_deref();
+        let segments: Vec<&str> = request
+            .path
+            .trim_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        match segments.as_slice() {
+            ["lists"] if request.method == "GET" => Ok(self.lists(user)),
+            ["lists", name, "members", rest @ ..] => self.members(request, user, name, rest),
+            ["lists", name, "tasks", rest @ ..] => {
+                let base = format!("/lists/{}", name);
+                self.tasks(request, user, name, &base, rest)
+            }
+            ["tasks", rest @ ..] => {
+                let name = self.options.list.clone();
+                self.tasks(request, user, &name, "", rest)
+            }
+            ["lists", name, "calendar.ics"] => self.calendar(request, user, name),
+            ["calendar.ics"] => {
+                let name = self.options.list.clone();
+                self.calendar(request, user, &name)
+            }
+            _ => Err(Response::error(404, format!("no route {}", request.path))),
+        }
+    }
+
+    fn lists(&self, user: Option<&str>) -> Response {
+        let lists: Vec<Value> = match (&self.options.access, user) {
+            (Some(access), Some(user)) => access
+                .lists(user)
+                .into_iter()
+                .map(|(name, role)| json!({ "name": name, "role": role }))
+                .collect(),
+            _ => TodoList::list_names()
+                .into_iter()
+                .map(|name| json!({ "name": name }))
+                .collect(),
+        };
+        Response::json(200, Value::Array(lists))
+    }
+
+    fn members(
+        &mut self,
+        request: &Request,
+        user: Option<&str>,
+        list: &str,
+        rest: &[&str],
+    ) -> Result<Response, Response> {
+        if self.options.access.is_none() {
+            return Err(Response::error(
+                404,
+                "lists have no members without serve --users",
+            ));
+        }
+        let needed = if request.method == "GET" {
+            Role::Viewer
+        } else {
+            Role::Owner
+        };
+        self.authorize(user, list, needed)?;
+        let access = self.options.access.as_mut().unwrap();
+        let role = match (request.method.as_str(), rest) {
+            ("GET", []) => return Ok(Response::json(200, json!(access.members(list)))),
+            ("PUT", [_]) => {
+                #[derive(Deserialize)]
+                struct Member {
+                    role: Role,
+                }
+                let member: Member = serde_json::from_slice(&request.body)
+                    .map_err(|e| Response::error(400, format!("invalid member: {}", e)))?;
+                Some(member.role)
+            }
+            ("DELETE", [_]) => None,
+            _ => {
+                return Err(Response::error(
+                    405,
+                    format!("{} is not allowed on {}", request.method, request.path),
+                ))
+            }
+        };
+        access
+            .set_role(list, rest[0], role)
+            .map_err(|e| Response::error(400, e))?;
+        Ok(Response::json(200, json!(access.members(list))))
+    }
+
+    fn tasks(
+        &mut self,
+        request: &Request,
+        user: Option<&str>,
+        name: &str,
+        base: &str,
+        rest: &[&str],
+    ) -> Result<Response, Response> {
+        let id = match rest {
+            [] => None,
+            [id] => Some(
+                id.parse::<usize>()
+                    .map_err(|_| Response::error(404, format!("no task {:?}", id)))?,
+            ),
+            _ => return Err(Response::error(404, format!("no route {}", request.path))),
+        };
+        let needed = if request.method == "GET" {
+            Role::Viewer
+        } else {
+            Role::Editor
+        };
+        self.authorize(user, name, needed)?;
+        let list = self.list(name)?;
+        match (request.method.as_str(), id) {
+            ("GET", None) => Ok(list_tasks(list, &request.query, user)),
+            ("POST", None) => add(list, request, base),
+            ("GET", Some(id)) => list
+                .get(id)
+                .map(|task| Response::json(200, to_value(task)))
+                .ok_or_else(|| not_found(id)),
+            ("PATCH", Some(id)) => change(list, id, request),
+            ("DELETE", Some(id)) => match list.delete(id)? {
+                Some(_) => Ok(Response::empty(204)),
+                None => Err(not_found(id)),
+            },
+            _ => Err(Response::error(
+                405,
+                format!("{} is not allowed on {}", request.method, request.path),
+            )),
+        }
+    }
+
+    fn calendar(
+        &mut self,
+        request: &Request,
+        user: Option<&str>,
+        name: &str,
+    ) -> Result<Response, Response> {
+        if request.method != "GET" {
+            return Err(Response::error(
+                405,
+                format!("{} is not allowed on {}", request.method, request.path),
+            ));
+        }
+        self.authorize(user, name, Role::Viewer)?;
+        let tag = request.query.get("tag").map(|tag| normalize_tag(tag));
+        let list = self.list(name)?;
+        let tasks: Vec<&Task> = list
+            .pending()
+            .filter(|t| tag.as_ref().is_none_or(|tag| t.tags.contains(tag)))
+            .collect();
+        Ok(Response::text(
+            "text/calendar; charset=utf-8",
+            ical::render_events(&tasks, &format!("rust-todo: {}", name)),
+        ))
+    }
+
+    fn handle(&mut self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (response, preflight) = match read_request(&mut reader) {
+            Ok(None) => return Ok(()),
+            Ok(Some(request)) if request.method == "OPTIONS" => (Response::empty(204), true),
+            Ok(Some(request)) => {
+                debug!(method = %request.method, path = %request.path, "http request");
+                (self.route(&request).unwrap_or_else(|e| e), false)
+            }
+            Err(response) => (response, false),
+        };
+        if response.status >= 500 {
+            warn!(status = response.status, body = ?response.body, "request failed");
+        }
+        write_response(stream, &response, preflight, &self.options)
+    }
+}
+
+fn write_response(
+    mut stream: TcpStream,
+    response: &Response,
+    preflight: bool,
+    options: &Options,
+) -> std::io::Result<()> {
+    let (body, content_type) = match &response.body {
+        Some(Body::Json(body)) => (format!("{}\n", body), Some("application/json")),
+        Some(Body::Text { content_type, text }) => (text.clone(), Some(*content_type)),
+        None => (String::new(), None),
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        body.len()
+    );
+    if let Some(content_type) = content_type {
+        head.push_str(&format!("Content-Type: {}\r\n", content_type));
+    }
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if let Some(origin) = &options.allow_origin {
+        head.push_str(&format!("Access-Control-Allow-Origin: {}\r\n", origin));
+        if preflight {
+            head.push_str("Access-Control-Allow-Methods: GET, POST, PUT, PATCH, DELETE\r\n");
+            head.push_str("Access-Control-Allow-Headers: Authorization, Content-Type\r\n");
+        }
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+/// Answers requests until the process is stopped. `list` is the list `/tasks` is, and `open`
+/// opens the others.
+pub fn run(
+    list: &mut TodoList,
+    open: Opener,
+    options: Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind((options.host.as_str(), options.port)).map_err(|e| {
+        format!(
+            "could not listen on {}:{}: {}",
+            options.host, options.port, e
+        )
+    })?;
+    println!(
+        "Serving the {} list on http://{}{} (Ctrl-C to stop)",
+        options.list,
+        listener.local_addr()?,
+        if options.access.is_some() {
+            " to its members"
+        } else {
+            ""
+        }
+    );
+    let mut server = Server {
+        list,
+        others: HashMap::new(),
+        open,
+        options,
+    };
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(error = %e, "could not accept a connection");
+                continue;
+            }
+        };
+        if let Err(e) = server.handle(stream) {
+            warn!(error = %e, "could not answer a request");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    /// A directory of its own for a test, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "rust-todo-serve-{}-{}",
+                std::process::id(),
+                name
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn no_others(name: &str) -> Result<TodoList, Box<dyn std::error::Error>> {
+        Err(format!("no list {}", name).into())
+    }
+
+    fn options(access: Option<Access>) -> Options {
+        Options {
+            host: "127.0.0.1".into(),
+            port: 0,
+            allow_origin: None,
+            list: "default".into(),
+            access,
+        }
+    }
+
+    fn request(method: &str, path: &str, body: Value) -> Request {
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        Request {
+            method: method.into(),
+            path: path.into(),
+            query: query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            authorization: None,
+            body: if body.is_null() {
+                vec![]
+            } else {
+                body.to_string().into_bytes()
+            },
+        }
+    }
+
+    /// The status and JSON body of the response to `request`.
+    fn send(server: &mut Server, request: &Request) -> (u16, Value) {
+        let response = server.route(request).unwrap_or_else(|e| e);
+        let body = match response.body {
+            Some(Body::Json(body)) => body,
+            Some(Body::Text { text, .. }) => Value::String(text),
+            None => Value::Null,
+        };
+        (response.status, body)
+    }
+
+    #[test]
+    fn tasks_are_added_read_changed_and_deleted() {
+        let dir = TempDir::new("routes");
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        let mut server = Server {
+            list: &mut list,
+            others: HashMap::new(),
+            open: &no_others,
+            options: options(None),
+        };
+
+        let added = json!({ "description": "Buy milk", "tags": ["shop"], "priority": "high" });
+        let response = server
+            .route(&request("POST", "/tasks", added))
+            .unwrap_or_else(|e| e);
+        assert_eq!(response.status, 201);
+        assert_eq!(
+            response.headers,
+            [("Location", "/tasks/1".to_string())],
+            "{:?}",
+            response.body
+        );
+        send(
+            &mut server,
+            &request("POST", "/tasks", json!({ "description": "Walk" })),
+        );
+
+        let (status, task) = send(&mut server, &request("GET", "/tasks/1", Value::Null));
+        assert_eq!(status, 200);
+        assert_eq!(task["description"], "Buy milk");
+        assert_eq!(task["tags"], json!(["shop"]));
+
+        let done = json!({ "completed": true, "description": "Buy oat milk" });
+        let (status, task) = send(&mut server, &request("PATCH", "/tasks/1", done));
+        assert_eq!(status, 200);
+        assert_eq!(task["completed"], true);
+        assert_eq!(task["description"], "Buy oat milk");
+
+        let (_, pending) = send(
+            &mut server,
+            &request("GET", "/tasks?filter=pending", Value::Null),
+        );
+        assert_eq!(pending.as_array().unwrap().len(), 1);
+        assert_eq!(pending[0]["description"], "Walk");
+
+        let deleted = send(&mut server, &request("DELETE", "/tasks/2", Value::Null));
+        assert_eq!(deleted, (204, Value::Null));
+        let (status, _) = send(&mut server, &request("GET", "/tasks/2", Value::Null));
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn bad_requests_get_4xx_answers() {
+        let dir = TempDir::new("errors");
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        let mut server = Server {
+            list: &mut list,
+            others: HashMap::new(),
+            open: &no_others,
+            options: options(None),
+        };
+        let status =
+            |server: &mut Server, method, path, body| send(server, &request(method, path, body)).0;
+        assert_eq!(status(&mut server, "GET", "/nowhere", Value::Null), 404);
+        assert_eq!(status(&mut server, "GET", "/tasks/7", Value::Null), 404);
+        assert_eq!(status(&mut server, "PUT", "/tasks", Value::Null), 405);
+        assert_eq!(
+            status(&mut server, "GET", "/lists/other/tasks", Value::Null),
+            404
+        );
+        assert_eq!(
+            status(&mut server, "GET", "/tasks?filter=someday", Value::Null),
+            400
+        );
+        assert_eq!(
+            status(&mut server, "POST", "/tasks", json!({ "tags": [] })),
+            400
+        );
+        assert_eq!(
+            status(
+                &mut server,
+                "POST",
+                "/tasks",
+                json!({ "description": "x", "colour": 1 })
+            ),
+            400
+        );
+    }
+
+    /// A server on a list called `family` with `--users`: `alice` owns it, `bob` views it,
+    /// and `carol` has no role in it.
+    fn family(dir: &TempDir, list: &mut TodoList, test: impl FnOnce(&mut Server)) {
+        let users = dir.path().join("users.toml");
+        std::fs::write(
+            &users,
+            "[users]\n\
+             alice = \"alice-token-0123456789\"\n\
+             bob = \"bob-token-0123456789\"\n\
+             carol = \"carol-token-0123456789\"\n\
+             [lists.family]\n\
+             alice = \"owner\"\n\
+             bob = \"viewer\"\n",
+        )
+        .unwrap();
+        let mut options = options(Some(Access::load(&users).unwrap()));
+        options.list = "family".into();
+        let mut server = Server {
+            list,
+            others: HashMap::new(),
+            open: &no_others,
+            options,
+        };
+        test(&mut server);
+    }
+
+    fn as_user(token: Option<&str>, method: &str, path: &str, body: Value) -> Request {
+        let mut request = request(method, path, body);
+        request.authorization = token.map(|token| format!("Bearer {}", token));
+        request
+    }
+
+    #[test]
+    fn a_viewer_reads_but_cannot_write() {
+        let dir = TempDir::new("viewer");
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        family(&dir, &mut list, |server| {
+            let add = |token| {
+                as_user(
+                    Some(token),
+                    "POST",
+                    "/tasks",
+                    json!({ "description": "Milk" }),
+                )
+            };
+            assert_eq!(send(server, &add("alice-token-0123456789")).0, 201);
+            let (status, body) = send(server, &add("bob-token-0123456789"));
+            assert_eq!(status, 403, "{}", body);
+            let delete = as_user(
+                Some("bob-token-0123456789"),
+                "DELETE",
+                "/tasks/1",
+                Value::Null,
+            );
+            assert_eq!(send(server, &delete).0, 403);
+            let read = as_user(Some("bob-token-0123456789"), "GET", "/tasks", Value::Null);
+            let (status, tasks) = send(server, &read);
+            assert_eq!(status, 200);
+            assert_eq!(tasks.as_array().unwrap().len(), 1);
+        });
+    }
+
+    #[test]
+    fn requests_without_a_known_token_get_401() {
+        let dir = TempDir::new("tokens");
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        family(&dir, &mut list, |server| {
+            let unknown = as_user(
+                Some("mallory-token-0123456789"),
+                "GET",
+                "/tasks",
+                Value::Null,
+            );
+            let response = server.route(&unknown).unwrap_or_else(|e| e);
+            assert_eq!(response.status, 401);
+            assert_eq!(response.headers[0].0, "WWW-Authenticate");
+            let missing = as_user(None, "GET", "/tasks", Value::Null);
+            assert_eq!(send(server, &missing).0, 401);
+            let mut not_bearer = as_user(None, "GET", "/tasks", Value::Null);
+            not_bearer.authorization = Some("Basic YWxpY2U6c2VjcmV0".into());
+            assert_eq!(send(server, &not_bearer).0, 401);
+        });
+    }
+
+    #[test]
+    fn a_list_without_a_role_in_it_is_not_found() {
+        let dir = TempDir::new("members");
+        let mut list = TodoList::open(dir.path().join("tasks.json"));
+        family(&dir, &mut list, |server| {
+            let carol = as_user(Some("carol-token-0123456789"), "GET", "/tasks", Value::Null);
+            assert_eq!(send(server, &carol).0, 404);
+            let lists = as_user(Some("carol-token-0123456789"), "GET", "/lists", Value::Null);
+            assert_eq!(send(server, &lists), (200, json!([])));
+            let promote = as_user(
+                Some("bob-token-0123456789"),
+                "PUT",
+                "/lists/family/members/bob",
+                json!({ "role": "owner" }),
+            );
+            assert_eq!(send(server, &promote).0, 403);
+        });
+    }
+
+    /// What [`read_request`] makes of `text`.
+    fn read(text: &str) -> Result<Option<Request>, u16> {
+        read_request(&mut text.as_bytes()).map_err(|response| response.status)
+    }
+
+    #[test]
+    fn reads_the_request_line_headers_and_body() {
+        let request = read(
+            "PATCH /tasks/3?tag=a%20b HTTP/1.1\r\nAuthorization: Bearer xyz\r\n\
+             Content-Length: 2\r\n\r\n{}",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(request.method, "PATCH");
+        assert_eq!(request.path, "/tasks/3");
+        assert_eq!(request.query["tag"], "a b");
+        assert_eq!(request.authorization.as_deref(), Some("Bearer xyz"));
+        assert_eq!(request.body, b"{}");
+        assert!(read("").unwrap().is_none());
+    }
+
+    #[test]
+    fn over_long_lines_and_too_many_headers_are_refused() {
+        let long_target = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read(&long_target).err(), Some(400));
+        let long_header = format!("GET / HTTP/1.1\r\nX-Big: {}\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read(&long_header).err(), Some(400));
+        let many = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-A: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(read(&many).err(), Some(400));
+        let enough = format!("GET / HTTP/1.1\r\n{}\r\n", "X-A: 1\r\n".repeat(MAX_HEADERS));
+        assert!(read(&enough).is_ok());
+        let huge = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(read(&huge).err(), Some(413));
+        assert_eq!(
+            read("POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\n{}").err(),
+            Some(400)
+        );
+    }
+
+    #[test]
+    fn a_client_that_stops_sending_gets_408() {
+        /// Sends the start of a request, then nothing until the read times out.
+        struct Stalled(&'static [u8]);
+
+        impl Read for Stalled {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                let n = self.0.read(buf)?;
+                Ok(n)
+            }
+        }
+
+        let mut stalled = BufReader::new(Stalled(b"GET /tasks HTTP/1.1\r\nHost: x"));
+        let status = read_request(&mut stalled).err().map(|r| r.status);
+        assert_eq!(status, Some(408));
+        let mut body = BufReader::new(Stalled(b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\n{"));
+        let status = read_request(&mut body).err().map(|r| r.status);
+        assert_eq!(status, Some(408));
+    }
+}
@@ -0,0 +1,113 @@
+//! `shell`: a prompt that runs commands against the list loaded once, parsed exactly like
+//! command-line arguments.
+//!
+//! Each change is saved as soon as it is made, as on the command line. If the data file is
+//! changed by another process in the meantime, the list is reloaded before the next command.
+
+use crate::{open_list, Backend, Cli, Commands};
+use clap::Parser;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::time::SystemTime;
+use todo_core::TodoList;
+
+/// Splits a line into words like a POSIX shell: whitespace separates words, quotes group
+/// them, and a backslash escapes the next character outside single quotes.
+fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                let escaped = chars.next().ok_or("trailing backslash")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote".into());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+pub fn run(
+    list: &mut TodoList,
+    backend: Backend,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    if interactive {
+        println!(
+            "rust-todo shell on the {} list. Type commands as on the command line (`help` lists them), `exit` to leave.",
+            name
+        );
+    }
+    let mut seen = modified(list.path());
+    loop {
+        if interactive {
+            print!("todo> ");
+            std::io::stdout().flush()?;
+        }
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            if interactive {
+                println!();
+            }
+            return Ok(());
+        }
+        let words = match split(&line) {
+            Ok(words) if words.is_empty() => continue,
+            Ok(words) if matches!(words[0].as_str(), "exit" | "quit") => return Ok(()),
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        let cli = match Cli::try_parse_from(std::iter::once("rust-todo".to_string()).chain(words)) {
+            Ok(cli) => cli,
+            Err(e) => {
+                // Also prints `help` output.
+                let _ = e.print();
+                continue;
+            }
+        };
+        if matches!(cli.command, Commands::Shell) {
+            eprintln!("Error: already in the shell");
+            continue;
+        }
+        if modified(list.path()) != seen {
+            list.reload();
+        }
+        // --list and --backend run a single command on another list.
+        let target = cli.list.as_deref().unwrap_or(name);
+        let other_backend = cli.backend.unwrap_or(backend);
+        let result = if target != name || other_backend != backend {
+            open_list(other_backend, target).and_then(|mut other| {
+                crate::run(cli.command, &mut other, other_backend, target, cli.safety)
+            })
+        } else {
+            crate::run(cli.command, list, backend, name, cli.safety)
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+        seen = modified(list.path());
+    }
+}
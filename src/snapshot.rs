@@ -0,0 +1,163 @@
+//! Recovery snapshots: copies of a whole list taken before commands that remove or add many
+//! tasks at once, such as `clear` and `import`.
+//!
+//! Snapshots are JSON [data files](crate::schema), which `import` reads too, kept in
+//! `<data file>.snapshots/` and named after the time they were taken and the command that
+//! took them, e.g. `20261015-093000-clear`. Only the most recent [`KEEP`] are kept.
+//!
+//! `snapshot tag` takes snapshots by hand under a name of your choosing, such as
+//! `before-vacation`. Those are kept in `tags/` inside the snapshot directory, never pruned,
+//! and can't be replaced: the file is created only if no tag has that name, then made
+//! read-only.
+
+use crate::{schema, storage, Task};
+use chrono::Local;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// How many snapshots are kept per list; older ones are removed when a new one is taken.
+pub const KEEP: usize = 20;
+
+/// The directory holding the snapshots of the list stored in `data_file`.
+pub fn dir_for(data_file: &Path) -> PathBuf {
+    let mut name = data_file.file_name().unwrap_or_default().to_os_string();
+    name.push(".snapshots");
+    data_file.with_file_name(name)
+}
+
+/// Writes `tasks` as a new snapshot of the list in `data_file` and returns its name.
+pub fn take(
+    data_file: &Path,
+    reason: &str,
+    tasks: &[Task],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let dir = dir_for(data_file);
+    fs::create_dir_all(&dir)?;
+    let json = schema::render(tasks)?;
+    let stamp = format!("{}-{}", Local::now().format("%Y%m%d-%H%M%S"), reason);
+    let mut name = stamp.clone();
+    for n in 2.. {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(format!("{}.json", name)))
+        {
+            Ok(mut file) => {
+                file.write_all(json.as_bytes())?;
+                break;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                name = format!("{}-{}", stamp, n);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let names = list(data_file);
+    for old in names.iter().take(names.len().saturating_sub(KEEP)) {
+        let _ = fs::remove_file(dir.join(format!("{}.json", old)));
+    }
+    Ok(name)
+}
+
+/// Names of the snapshots of the list in `data_file`, oldest first.
+pub fn list(data_file: &Path) -> Vec<String> {
+    names_in(&dir_for(data_file))
+}
+
+/// Names of the tagged snapshots of the list in `data_file`, in alphabetical order.
+pub fn tags(data_file: &Path) -> Vec<String> {
+    names_in(&tags_dir(data_file))
+}
+
+fn tags_dir(data_file: &Path) -> PathBuf {
+    dir_for(data_file).join("tags")
+}
+
+fn names_in(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Writes `tasks` as the tagged snapshot `name` of the list in `data_file`. Fails if a
+/// snapshot is already tagged `name`.
+pub fn tag(data_file: &Path, name: &str, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "{:?} can't be a tag; use letters, digits, '-', '_' and '.'",
+            name
+        )
+        .into());
+    }
+    let dir = tags_dir(data_file);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", name));
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(format!(
+                "a snapshot is already tagged {:?}, and tagged snapshots can't be replaced",
+                name
+            )
+            .into())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    file.write_all(schema::render(tasks)?.as_bytes())?;
+    let mut permissions = file.metadata()?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&path, permissions)?;
+    Ok(())
+}
+
+/// Rewrites every snapshot of the list in `data_file`, tagged ones included, without the
+/// tasks that `forget` matches. Returns how many copies of tasks were removed.
+pub fn forget(
+    data_file: &Path,
+    forget: impl Fn(&Task) -> bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut removed = 0;
+    for dir in [dir_for(data_file), tags_dir(data_file)] {
+        for name in names_in(&dir) {
+            let path = dir.join(format!("{}.json", name));
+            let (mut tasks, _) = schema::parse(&storage::read_text(&path)?)?;
+            let before = tasks.len();
+            tasks.retain(|t| !forget(t));
+            if tasks.len() < before {
+                removed += before - tasks.len();
+                // Keeps a tagged snapshot read-only.
+                storage::replace(&path, schema::render(&tasks)?.as_bytes())?;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// The tasks in snapshot `name` of the list in `data_file`, a tagged one or one taken
+/// automatically.
+pub fn load(data_file: &Path, name: &str) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+    let name = name.strip_suffix(".json").unwrap_or(name);
+    let dir = if tags(data_file).iter().any(|n| n == name) {
+        tags_dir(data_file)
+    } else if list(data_file).iter().any(|n| n == name) {
+        dir_for(data_file)
+    } else {
+        return Err(format!("there is no snapshot named {:?}", name).into());
+    };
+    let path = dir.join(format!("{}.json", name));
+    let (tasks, _) = schema::parse(&storage::read_text(&path)?)?;
+    Ok(tasks)
+}
@@ -0,0 +1,106 @@
+//! A SQLite [`Storage`] for large lists.
+//!
+//! Each task is one row holding its JSON, keyed by ID. Saving only writes rows that changed
+//! and deletes rows whose task is gone, so completing one task in a list of thousands touches
+//! a single row, and every save is one transaction. The data version is the database's
+//! `user_version`, 0 meaning a database from before versions were recorded.
+
+use crate::{schema, Storage, Task};
+use rusqlite::{params, Connection, MAIN_DB};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+pub struct Sqlite {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl Sqlite {
+    /// Opens the database at `path`, creating it and its table if needed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.into();
+        crate::storage::create_parent(&path)?;
+        let connection = Connection::open(&path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY,
+                completed INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );",
+        )?;
+        Ok(Sqlite { connection, path })
+    }
+}
+
+impl Storage for Sqlite {
+    fn load(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+        let version: u32 = self
+            .connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let mut statement = self
+            .connection
+            .prepare("SELECT data FROM tasks ORDER BY rowid")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut tasks = vec![];
+        for data in rows {
+            tasks.push(serde_json::from_str(&data?)?);
+        }
+        // Rows are rewritten in the current version as they are saved.
+        Ok(schema::upgrade(tasks, version.max(1))?)
+    }
+
+    fn save(&self, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        let mut changed = 0;
+        {
+            let mut upsert = transaction.prepare_cached(
+                "INSERT INTO tasks (id, completed, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET completed = excluded.completed, data = excluded.data
+                 WHERE data != excluded.data",
+            )?;
+            for task in tasks {
+                let data = serde_json::to_string(task)?;
+                changed += upsert.execute(params![task.id as i64, task.completed, data])?;
+            }
+            let current: HashSet<i64> = tasks.iter().map(|t| t.id as i64).collect();
+            let stored: Vec<i64> = transaction
+                .prepare("SELECT id FROM tasks")?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            let mut delete = transaction.prepare_cached("DELETE FROM tasks WHERE id = ?1")?;
+            for id in stored.into_iter().filter(|id| !current.contains(id)) {
+                changed += delete.execute([id])?;
+            }
+            transaction.pragma_update(None, "user_version", schema::CURRENT)?;
+        }
+        transaction.commit()?;
+        debug!(changed, "rows written");
+        Ok(())
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_only(&self) -> Option<String> {
+        if self.connection.is_readonly(MAIN_DB).unwrap_or(false) {
+            return Some("the database file is read-only".to_string());
+        }
+        // Transactions need a journal file next to the database.
+        crate::storage::dir_read_only(&self.path)
+    }
+
+    /// SQLite's `data_version`, which changes when another connection commits.
+    fn version(&self) -> Option<i64> {
+        self.connection
+            .pragma_query_value(None, "data_version", |row| row.get(0))
+            .ok()
+    }
+
+    /// `VACUUM`, which gives back the space of deleted rows.
+    fn compact(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.connection.execute_batch("VACUUM")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! Figures for `stats`: how many tasks are open, done and overdue, how many were added and
+//! completed each week, how long tasks take to complete, time tracked, and tasks per tag.
+//!
+//! Tasks don't record when they were completed, so completions are read from the
+//! [operation log](crate::oplog): the save that changed a task from pending to completed.
+//! Completions from before the log was kept, or undone since, are not counted, and neither
+//! are cancelled tasks.
+
+use crate::oplog::History;
+use crate::planner::week_start;
+use crate::timelog;
+use crate::{Status, Task};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+
+/// Tasks added and completed in one week.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Week {
+    pub added: usize,
+    pub completed: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub pending: usize,
+    pub completed: usize,
+    pub overdue: usize,
+    /// Every week from the one `since` falls in to the current one, keyed by its Monday.
+    pub weeks: BTreeMap<NaiveDate, Week>,
+    /// Mean time from creation to completion of the tasks completed since `since`.
+    pub average_completion: Option<Duration>,
+    /// Time tracked with `start` and `stop` since `since`.
+    pub tracked: Duration,
+    /// Pending and completed tasks per tag, most used first.
+    pub tags: Vec<(String, usize, usize)>,
+}
+
+/// Parses the start of the range: a number of days or weeks back such as `30d` or `8w`,
+/// `today`, `yesterday` or a weekday, the last one up to today (from midnight), or anything
+/// [`parse_datetime`](crate::dates::parse_datetime) accepts.
+pub fn parse_since(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let input = input.trim();
+    let days_back = match input {
+        "today" => Some(0),
+        "yesterday" => Some(1),
+        _ => input.parse::<chrono::Weekday>().ok().map(|day| {
+            (7 + now.weekday().num_days_from_monday() - day.num_days_from_monday()) as u64 % 7
+        }),
+    };
+    if let Some(days) = days_back {
+        let day = now.date_naive() - chrono::Days::new(days);
+        return day
+            .and_time(chrono::NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .ok_or_else(|| format!("{} has no midnight here", day));
+    }
+    let back = |unit: &str, days: i64| {
+        input
+            .strip_suffix(unit)
+            .and_then(|n| n.trim().parse::<i64>().ok())
+            .map(|n| now - Duration::days(n * days))
+    };
+    back("d", 1).or_else(|| back("w", 7)).map_or_else(
+        || {
+            crate::dates::parse_datetime(input, now)
+                .map_err(|e| format!("{}, or a number of days or weeks back like 30d or 8w", e))
+        },
+        Ok,
+    )
+}
+
+/// When each task completed in `history` was completed, with the task as it was then.
+pub fn completions(history: &History) -> Vec<(DateTime<Local>, &Task)> {
+    history
+        .done
+        .iter()
+        .flat_map(|operation| {
+            operation.changes.iter().filter_map(move |change| {
+                let after = change
+                    .after
+                    .as_ref()
+                    .filter(|t| t.status() == Status::Done)?;
+                match &change.before {
+                    Some(before) if before.completed => None,
+                    _ => Some((operation.at, after)),
+                }
+            })
+        })
+        .collect()
+}
+
+/// The figures for `tasks` from `since` to `now`, with completions from `history`.
+pub fn compute(
+    tasks: &[Task],
+    history: &History,
+    since: DateTime<Local>,
+    now: DateTime<Local>,
+) -> Stats {
+    let mut stats = Stats {
+        pending: tasks.iter().filter(|t| !t.completed).count(),
+        completed: tasks.iter().filter(|t| t.status() == Status::Done).count(),
+        overdue: tasks.iter().filter(|t| t.is_overdue(now)).count(),
+        tracked: timelog::total(&timelog::by_day(tasks, since, now)),
+        ..Stats::default()
+    };
+
+    let mut week = week_start(since.date_naive());
+    while week <= now.date_naive() {
+        stats.weeks.insert(week, Week::default());
+        week += Duration::weeks(1);
+    }
+    let in_range = |at: DateTime<Local>| at >= since && at <= now;
+    for task in tasks {
+        let created = task.created_at.with_timezone(&Local);
+        if in_range(created) {
+            if let Some(week) = stats.weeks.get_mut(&week_start(created.date_naive())) {
+                week.added += 1;
+            }
+        }
+    }
+    let mut durations = vec![];
+    for (at, task) in completions(history) {
+        if !in_range(at) {
+            continue;
+        }
+        if let Some(week) = stats.weeks.get_mut(&week_start(at.date_naive())) {
+            week.completed += 1;
+        }
+        durations.push(at.signed_duration_since(task.created_at));
+    }
+    if !durations.is_empty() {
+        let total: Duration = durations.iter().sum();
+        stats.average_completion = Some(total / durations.len() as i32);
+    }
+
+    let mut tags: HashMap<&str, (usize, usize)> = HashMap::new();
+    for task in tasks.iter().filter(|t| t.status() != Status::Cancelled) {
+        for tag in &task.tags {
+            let counts = tags.entry(tag).or_default();
+            if task.completed {
+                counts.1 += 1;
+            } else {
+                counts.0 += 1;
+            }
+        }
+    }
+    stats.tags = tags
+        .into_iter()
+        .map(|(tag, (pending, completed))| (tag.to_string(), pending, completed))
+        .collect();
+    stats
+        .tags
+        .sort_by(|a, b| (b.1 + b.2).cmp(&(a.1 + a.2)).then_with(|| a.0.cmp(&b.0)));
+    stats
+}
+
+/// A one-line chart of `values`, one block character per value scaled to the largest.
+pub fn sparkline(values: &[usize]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&v| BLOCKS[v * (BLOCKS.len() - 1) / max])
+        .collect()
+}
+
+/// A horizontal bar for a week, `width` characters long at `max`: `█` up to the number
+/// completed, then `░` up to the number added.
+pub fn bar(added: usize, completed: usize, max: usize, width: usize) -> String {
+    let scale = |n: usize| n * width / max.max(1);
+    let done = scale(completed);
+    format!(
+        "{}{}",
+        "█".repeat(done),
+        "░".repeat(scale(added).saturating_sub(done))
+    )
+}
@@ -0,0 +1,90 @@
+use clap::ValueEnum;
+use todo_core::summary::{self, Summary};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StatusFormat {
+    /// Plain text, e.g. "○5 !1 ◷2 ✓3"
+    Plain,
+    /// Text for a starship `custom` module; empty when nothing is pending
+    Starship,
+    /// tmux status-line segment with colour markup
+    Tmux,
+    /// JSON object for a waybar `custom` module (`"return-type": "json"`)
+    Waybar,
+}
+
+/// Renders a compact one-line segment from a list's [summary](todo_core::summary). Zero counts
+/// are left out, so an empty list prints nothing.
+pub fn render(counts: &Summary, format: StatusFormat) -> String {
+    let mut segments = Vec::new();
+    match format {
+        StatusFormat::Plain | StatusFormat::Starship => {
+            if counts.pending > 0 {
+                segments.push(format!("○{}", counts.pending));
+            }
+            if counts.overdue > 0 {
+                segments.push(format!("!{}", counts.overdue));
+            }
+            if counts.due_today > 0 {
+                segments.push(format!("◷{}", counts.due_today));
+            }
+            if counts.completed > 0 && !matches!(format, StatusFormat::Starship) {
+                segments.push(format!("✓{}", counts.completed));
+            }
+        }
+        StatusFormat::Waybar => return waybar(counts),
+        StatusFormat::Tmux => {
+            if counts.pending > 0 {
+                segments.push(format!("#[fg=yellow]○{}#[default]", counts.pending));
+            }
+            if counts.overdue > 0 {
+                segments.push(format!("#[fg=red]!{}#[default]", counts.overdue));
+            }
+            if counts.due_today > 0 {
+                segments.push(format!("#[fg=cyan]◷{}#[default]", counts.due_today));
+            }
+            if counts.completed > 0 {
+                segments.push(format!("#[fg=green]✓{}#[default]", counts.completed));
+            }
+        }
+    }
+    segments.join(" ")
+}
+
+/// Builds the waybar object. `class` is `empty`, `overdue`, `pending` or `done` for styling in
+/// CSS, and the tooltip lists up to ten pending tasks.
+fn waybar(counts: &Summary) -> String {
+    let total = counts.pending + counts.completed;
+    let class = if total == 0 {
+        "empty"
+    } else if counts.overdue > 0 {
+        "overdue"
+    } else if counts.pending > 0 {
+        "pending"
+    } else {
+        "done"
+    };
+    let mut tooltip = format!("{} pending, {} completed", counts.pending, counts.completed);
+    if counts.overdue > 0 {
+        tooltip.push_str(&format!(", {} overdue", counts.overdue));
+    }
+    if counts.due_today > 0 {
+        tooltip.push_str(&format!(", {} due today", counts.due_today));
+    }
+    for description in &counts.next {
+        tooltip.push_str(&format!("\n• {}", description));
+    }
+    if counts.pending > summary::NEXT {
+        tooltip.push_str(&format!("\n… and {} more", counts.pending - summary::NEXT));
+    }
+    serde_json::json!({
+        "text": match counts.overdue {
+            0 => format!("○{}", counts.pending),
+            overdue => format!("○{} !{}", counts.pending, overdue),
+        },
+        "tooltip": tooltip,
+        "class": class,
+        "percentage": (counts.completed * 100).checked_div(total).unwrap_or(100),
+    })
+    .to_string()
+}
@@ -0,0 +1,234 @@
+use crate::{schema, Sqlite, Task, TaskLog};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::cell::Cell;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Where a [`TodoList`](crate::TodoList) keeps its tasks between runs.
+pub trait Storage {
+    /// Reads every stored task; a store that does not exist yet holds no tasks.
+    fn load(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>>;
+
+    /// Replaces the stored tasks with `tasks`.
+    fn save(&self, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// The file backing the store. Sync state and diagnostics are kept next to it.
+    fn path(&self) -> &Path;
+
+    /// Why [`save`](Storage::save) would fail, if the store can only be read, e.g. on a
+    /// read-only filesystem. Checked without changing the stored tasks.
+    fn read_only(&self) -> Option<String> {
+        None
+    }
+
+    /// A value that changes whenever another process saves, so a list can tell whether the
+    /// tasks it loaded are still current without reading them all. `None` when the store
+    /// can't tell, and the tasks have to be compared instead.
+    fn version(&self) -> Option<i64> {
+        None
+    }
+
+    /// Rewrites the store without the space that earlier versions of its tasks take up, for
+    /// stores that keep them. The caller holds the [`Lock`].
+    fn compact(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
+
+/// The kind of store a list is kept in, chosen with `--backend` or `backend` in the
+/// [configuration](crate::config). Each keeps the list in a file of its own next to the JSON
+/// one, which a new SQLite database or task log starts out as a copy of.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// tasks.json in the data directory, rewritten on every change
+    #[default]
+    Json,
+    /// tasks.db in the data directory, which only writes the tasks that changed (suited to large lists)
+    Sqlite,
+    /// tasks.jsonl in the data directory, which appends the tasks that changed; `compact`
+    /// rewrites it without their older versions
+    Log,
+}
+
+impl Backend {
+    /// Where the list whose JSON file is `json_path` is kept with this backend.
+    pub fn path(self, json_path: &Path) -> PathBuf {
+        match self {
+            Backend::Json => json_path.to_path_buf(),
+            Backend::Sqlite => json_path.with_extension("db"),
+            Backend::Log => json_path.with_extension("jsonl"),
+        }
+    }
+
+    /// The store for the list whose JSON file is `json_path`.
+    pub fn storage(self, json_path: &Path) -> Result<Box<dyn Storage>, Box<dyn std::error::Error>> {
+        Ok(match self {
+            Backend::Json => Box::new(JsonFile::new(json_path)),
+            Backend::Sqlite => Box::new(Sqlite::open(self.path(json_path))?),
+            Backend::Log => Box::new(TaskLog::new(self.path(json_path))),
+        })
+    }
+}
+
+/// An advisory lock on a store, held until dropped, so that processes saving the same list
+/// take turns. It is taken on `<data file>.lock` rather than on the store itself, which is
+/// replaced on every save.
+pub struct Lock {
+    _file: fs::File,
+}
+
+impl Lock {
+    /// Waits until no other process holds the lock for the store at `path`, then takes it.
+    pub fn acquire(path: &Path) -> std::io::Result<Lock> {
+        create_parent(path)?;
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_file_name(name))?;
+        file.lock()?;
+        Ok(Lock { _file: file })
+    }
+}
+
+/// The default store: one pretty-printed JSON [document](crate::schema).
+pub struct JsonFile {
+    path: PathBuf,
+    /// The data version the file had when last loaded.
+    loaded_version: Cell<Option<u32>>,
+}
+
+impl JsonFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonFile {
+            path: path.into(),
+            loaded_version: Cell::new(None),
+        }
+    }
+}
+
+impl Storage for JsonFile {
+    fn load(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            debug!("task file does not exist yet");
+            return Ok(vec![]);
+        }
+        let content = read_text(&self.path)?;
+        let (tasks, version) = schema::parse(&content)?;
+        self.loaded_version.set(Some(version));
+        Ok(tasks)
+    }
+
+    /// Writes to a temporary file and renames it over the old one (see [`replace`]).
+    /// A file from an older version is first copied to `<file>.v<version>`.
+    fn save(&self, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>> {
+        let json = schema::render(tasks)?;
+        create_parent(&self.path)?;
+        if let Some(old) = self.loaded_version.get().filter(|v| *v < schema::CURRENT) {
+            let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!(".v{}", old));
+            let backup = self.path.with_file_name(name);
+            if !backup.exists() && self.path.exists() {
+                fs::copy(&self.path, &backup)?;
+                debug!(backup = %backup.display(), "kept the file from before the upgrade");
+            }
+            self.loaded_version.set(Some(schema::CURRENT));
+        }
+        Ok(replace(&self.path, json.as_bytes())?)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_only(&self) -> Option<String> {
+        file_read_only(&self.path)
+    }
+}
+
+/// Writes `content` to a temporary file and renames it over `path`, so readers and a crash
+/// mid-save only ever see a complete file.
+pub(crate) fn replace(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    // Replace what a symlink points at, not the link.
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".tmp-{}", std::process::id()));
+    let temp = target.with_file_name(name);
+    let written = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, &target)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Why the file at `path` can't be written, if it can't.
+pub(crate) fn file_read_only(path: &Path) -> Option<String> {
+    if path.exists() {
+        return fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .err()
+            .map(|e| e.to_string());
+    }
+    // The file is created on first save, so its directory has to accept new files.
+    dir_read_only(path)
+}
+
+/// Reads `path` as UTF-8 text, without the byte order mark Windows editors such as Notepad
+/// put at the start. Text that isn't UTF-8 is an error naming the file and where it goes wrong.
+pub(crate) fn read_text(path: &Path) -> std::io::Result<String> {
+    let bytes = fs::read(path)?;
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    String::from_utf8(bytes.to_vec()).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "{} is not UTF-8 text (byte {} is not valid)",
+                path.display(),
+                e.utf8_error().valid_up_to()
+            ),
+        )
+    })
+}
+
+/// Creates the directory `path` goes in if it doesn't exist yet, e.g. `%APPDATA%\rust-todo`
+/// on first run.
+pub(crate) fn create_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.exists() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+/// Why no new file can be created next to `path`, found by creating and removing one.
+pub(crate) fn dir_read_only(path: &Path) -> Option<String> {
+    // A directory that doesn't exist yet is created on save, inside its nearest ancestor.
+    let dir = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.as_os_str().is_empty() || dir.exists())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let probe = dir.join(format!(".rust-todo-probe-{}", std::process::id()));
+    match fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            None
+        }
+        Err(e) => Some(e.to_string()),
+    }
+}
@@ -0,0 +1,145 @@
+//! A few numbers about a list, kept in `<data file>.summary` and rewritten on every save, so
+//! `status` can draw a shell prompt or status bar segment without loading the whole list or
+//! taking its lock. The summary is written to a temporary file and renamed into place, so a
+//! reader sees either the old one or the new one.
+//!
+//! A summary older than the data file, e.g. after `sync git` or an edit by hand, one with
+//! tasks waiting in the [queue](crate::queue), or one whose counts have gone out of date
+//! since because a task fell due, isn't used; [`read`] returns `None` and the list has to be
+//! loaded instead.
+
+use crate::{dates, queue, Task};
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many pending task descriptions a summary keeps.
+pub const NEXT: usize = 10;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub pending: usize,
+    pub completed: usize,
+    /// Pending tasks past their due date.
+    pub overdue: usize,
+    /// Pending tasks due later today.
+    pub due_today: usize,
+    /// When the next pending task falls due, or midnight if that is sooner, after which
+    /// `overdue` and `due_today` are out of date.
+    pub valid_until: Option<DateTime<Local>>,
+    /// The first [`NEXT`] pending tasks' descriptions, in list order.
+    pub next: Vec<String>,
+}
+
+impl Summary {
+    /// The summary of `tasks` at `now`.
+    pub fn of(tasks: &[Task], now: DateTime<Local>) -> Self {
+        let pending = tasks.iter().filter(|t| !t.completed);
+        Summary {
+            pending: pending.clone().count(),
+            completed: tasks.iter().filter(|t| t.completed).count(),
+            overdue: pending.clone().filter(|t| t.is_overdue(now)).count(),
+            due_today: pending
+                .clone()
+                .filter_map(|t| t.due)
+                .filter(|due| *due >= now && due.date_naive() == now.date_naive())
+                .count(),
+            valid_until: pending
+                .clone()
+                .filter_map(|t| t.due)
+                .filter(|due| *due >= now)
+                .chain(midnight_after(now))
+                .min(),
+            next: pending.take(NEXT).map(|t| t.description.clone()).collect(),
+        }
+    }
+}
+
+/// The start of the day after `now`'s.
+fn midnight_after(now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let tomorrow = now.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?;
+    Local.from_local_datetime(&tomorrow).earliest()
+}
+
+/// The summary file of the list stored in `data_file`.
+pub fn path_for(data_file: &Path) -> PathBuf {
+    let mut name = data_file.file_name().unwrap_or_default().to_os_string();
+    name.push(".summary");
+    data_file.with_file_name(name)
+}
+
+/// Writes the summary of `tasks`, the list stored in `data_file`.
+pub fn write(data_file: &Path, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path_for(data_file);
+    let mut staged = path.clone().into_os_string();
+    staged.push(format!(".{}.tmp", std::process::id()));
+    fs::write(
+        &staged,
+        serde_json::to_string(&Summary::of(tasks, dates::now()))?,
+    )?;
+    fs::rename(&staged, &path)?;
+    Ok(())
+}
+
+/// The summary of the list stored in `data_file`, when there is one that is up to date.
+pub fn read(data_file: &Path) -> Option<Summary> {
+    let path = path_for(data_file);
+    let written = fs::metadata(&path).ok()?.modified().ok()?;
+    let saved = fs::metadata(data_file).ok()?.modified().ok()?;
+    if written < saved || queue_waiting(data_file) {
+        return None;
+    }
+    // Summaries from before `overdue` was kept don't parse, and are rewritten.
+    let summary: Summary = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    if summary
+        .valid_until
+        .is_some_and(|until| until < dates::now())
+    {
+        return None;
+    }
+    Some(summary)
+}
+
+fn queue_waiting(data_file: &Path) -> bool {
+    fs::read_dir(queue::dir_for(data_file)).is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| entry.file_name().to_string_lossy().ends_with(".json"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn due(description: &str, due: Option<DateTime<Local>>) -> Task {
+        Task::builder().description(description).due(due).build()
+    }
+
+    #[test]
+    fn overdue_counts_until_the_next_task_falls_due() {
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let hour = chrono::Duration::hours(1);
+        let mut done = due("done", Some(now - hour));
+        done.completed = true;
+        let tasks = vec![
+            due("late", Some(now - hour)),
+            due("soon", Some(now + hour)),
+            due("later", Some(now + hour * 5)),
+            due("tomorrow", Some(now + hour * 24)),
+            due("whenever", None),
+            done,
+        ];
+        let summary = Summary::of(&tasks, now);
+        assert_eq!((summary.pending, summary.completed), (5, 1));
+        assert_eq!((summary.overdue, summary.due_today), (1, 2));
+        assert_eq!(summary.valid_until, Some(now + hour));
+
+        let evening = now + hour * 11;
+        let summary = Summary::of(&tasks, evening);
+        assert_eq!((summary.overdue, summary.due_today), (3, 0));
+        assert_eq!(summary.valid_until, midnight_after(evening));
+    }
+}
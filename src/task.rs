@@ -0,0 +1,627 @@
+use crate::recurrence::Rule;
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Task {
+    pub id: usize,
+    /// Identifies the task for good, unlike its ID; set when it is added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    pub description: String,
+    /// A name to use instead of the ID on the command line, e.g. `deploy-v2`; see
+    /// [`check_alias`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Whether the task is closed, done or cancelled; see [`Task::status`].
+    pub completed: bool,
+    /// Where the task is in its workflow; see [`Task::status`].
+    #[serde(default, skip_serializing_if = "Status::is_todo")]
+    pub status: Status,
+    /// When the task was added, in the offset it was added in. Read leniently; see
+    /// [`lenient_timestamp`].
+    #[serde(deserialize_with = "lenient_timestamp")]
+    pub created_at: DateTime<FixedOffset>,
+    /// When the task was closed, done or cancelled. Unset while it's pending, and for tasks
+    /// imported closed or closed before this was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Local>>,
+    /// Labels for grouping and filtering, without the leading `#`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Named place or context where the task can be done, e.g. `home` or `phone` (not a GPS
+    /// position).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// When the task has to be done by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<DateTime<Local>>,
+    /// ID of the task this one is a subtask of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+    /// IDs of the tasks that have to be done before this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<usize>,
+    /// IDs of related tasks, e.g. the original of a follow-up. Unlike dependencies, they
+    /// don't block anything.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<usize>,
+    /// What to do next once this task is done; completing it adds the follow-up task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow_up: Option<FollowUp>,
+    /// How the task repeats; completing it schedules the next occurrence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<Rule>,
+    /// Reminder times, independent of any due date.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reminders: Vec<Reminder>,
+    /// Who the task is blocked on, for delegated work.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waiting_on: Option<WaitingOn>,
+    /// Who is to do the task, on a list several people share; see [`config::user`].
+    ///
+    /// [`config::user`]: crate::config::user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    /// Kept out of `list` and `next` until then; see [`Task::is_snoozed`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<DateTime<Local>>,
+    /// Free-form key/value data, e.g. `source.file` for tasks captured from code.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Context gathered while working on the task, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// Files and web addresses the task refers to, added with `attach`: web addresses as
+    /// given, file paths made absolute.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    /// Time worked on the task with `start` and `stop`, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub time: Vec<Interval>,
+    /// Shown before the description in listings, e.g. an emoji.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Colour of the task in listings, instead of the one for its priority or due date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<TaskColor>,
+}
+
+/// Reads a timestamp as [`dates::parse_timestamp`] does, so a hand-edited file still loads.
+/// One it can't read at all becomes the Unix epoch, which `doctor` points out.
+///
+/// [`dates::parse_timestamp`]: crate::dates::parse_timestamp
+fn lenient_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime<FixedOffset>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    Ok(crate::dates::parse_timestamp(&text).unwrap_or_default())
+}
+
+/// Turns a label into the stored tag form: no leading `#`, lowercase, words joined by `-`.
+pub fn normalize_tag(name: &str) -> String {
+    name.trim()
+        .trim_start_matches('#')
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Checks that `alias` can name a task: it starts with a letter, so it can't be taken for an
+/// ID or a range of IDs, and has only letters, digits, `-` and `_`.
+pub fn check_alias(alias: &str) -> Result<(), String> {
+    let valid = alias.starts_with(|c: char| c.is_alphabetic())
+        && alias
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "{:?} can't be an alias; start with a letter and use letters, digits, '-' and '_'",
+            alias
+        ));
+    }
+    Ok(())
+}
+
+/// Where a task is in its workflow. Done and cancelled tasks are closed, and count as
+/// completed everywhere that only knows `completed`.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    /// Not started yet
+    #[default]
+    Todo,
+    /// Being worked on
+    InProgress,
+    /// Held up, e.g. on someone else
+    Waiting,
+    /// Completed
+    Done,
+    /// Dropped without being done
+    Cancelled,
+}
+
+impl Status {
+    pub const ALL: [Status; 5] = [
+        Status::Todo,
+        Status::InProgress,
+        Status::Waiting,
+        Status::Done,
+        Status::Cancelled,
+    ];
+
+    fn is_todo(&self) -> bool {
+        *self == Status::Todo
+    }
+
+    /// Whether a task in this status is finished with: done or cancelled.
+    pub fn is_closed(self) -> bool {
+        matches!(self, Status::Done | Status::Cancelled)
+    }
+
+    /// The name of the status's column on the board.
+    pub fn title(self) -> &'static str {
+        match self {
+            Status::Todo => "Backlog",
+            Status::InProgress => "In Progress",
+            Status::Waiting => "Waiting",
+            Status::Done => "Done",
+            Status::Cancelled => "Cancelled",
+        }
+    }
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Status::Todo => "todo",
+            Status::InProgress => "in-progress",
+            Status::Waiting => "waiting",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+        })
+    }
+}
+
+/// How important a task is, ordered from lowest to highest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Critical => "critical",
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Reminder {
+    pub at: DateTime<Local>,
+    /// Set once the reminder has been reported by `remind-check`.
+    #[serde(default)]
+    pub delivered: bool,
+}
+
+/// A colour a task can be flagged with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl TaskColor {
+    /// The ANSI SGR code for the colour, bold so it stands out from the rest of the list.
+    pub fn ansi(self) -> &'static str {
+        match self {
+            TaskColor::Red => "1;31",
+            TaskColor::Green => "1;32",
+            TaskColor::Yellow => "1;33",
+            TaskColor::Blue => "1;34",
+            TaskColor::Magenta => "1;35",
+            TaskColor::Cyan => "1;36",
+        }
+    }
+}
+
+/// A note on a task; the text may span several lines.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Note {
+    pub at: DateTime<Local>,
+    pub text: String,
+}
+
+/// A task to add when another is completed, e.g. "send the invoice" after "finish the job".
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FollowUp {
+    pub description: String,
+    /// How long after the completion the follow-up is due, e.g. `3d`, as accepted by
+    /// [`parse_duration`](crate::dates::parse_duration).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_in: Option<String>,
+}
+
+impl FollowUp {
+    /// The task to add when `original` is completed at `now`. It is filed like the original
+    /// and linked to it. An offset in whole days falls due at the end of the day.
+    pub fn task(&self, original: &Task, now: DateTime<Local>) -> Task {
+        let due = self
+            .due_in
+            .as_deref()
+            .and_then(|offset| crate::dates::parse_duration(offset).ok())
+            .map(|offset| crate::dates::due_after(now, offset));
+        Task {
+            description: self.description.clone(),
+            created_at: now.fixed_offset(),
+            tags: original.tags.clone(),
+            location: original.location.clone(),
+            priority: original.priority,
+            due,
+            parent: original.parent,
+            related: vec![original.id],
+            ..Task::default()
+        }
+    }
+}
+
+/// A stretch of work on a task. `end` is unset while the timer is running.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Interval {
+    pub start: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WaitingOn {
+    pub person: String,
+    pub since: DateTime<Local>,
+}
+
+impl Task {
+    /// Where the task is in its workflow. `completed`, which says whether the task is closed,
+    /// is kept for scripts and older files, and wins when the two disagree, e.g. after an
+    /// importer or the API completed or reopened the task.
+    pub fn status(&self) -> Status {
+        match (self.completed, self.status) {
+            (true, Status::Cancelled) => Status::Cancelled,
+            (true, _) => Status::Done,
+            (false, status) if status.is_closed() => Status::Todo,
+            (false, status) => status,
+        }
+    }
+
+    /// Moves the task to `status`, keeping `completed` in step. Closing a pending task records
+    /// when in `completed_at`, and reopening a closed one clears it.
+    pub fn set_status(&mut self, status: Status) {
+        match (self.completed, status.is_closed()) {
+            (false, true) => self.completed_at = Some(crate::dates::now()),
+            (true, false) => self.completed_at = None,
+            _ => {}
+        }
+        self.status = status;
+        self.completed = status.is_closed();
+    }
+
+    /// Closes the task as done, or reopens it into the backlog, as tools that only know done
+    /// or not say; see [`set_status`](Task::set_status). A task already closed or open as
+    /// they say keeps its status, e.g. cancelled.
+    pub fn set_completed(&mut self, completed: bool) {
+        if self.completed != completed {
+            self.set_status(if completed {
+                Status::Done
+            } else {
+                Status::Todo
+            });
+        }
+    }
+
+    /// Whether the task is pending and snoozed until after `now`.
+    pub fn is_snoozed(&self, now: DateTime<Local>) -> bool {
+        !self.completed && self.snoozed_until.is_some_and(|until| until > now)
+    }
+
+    /// Whether the task is still pending after its due date.
+    pub fn is_overdue(&self, now: DateTime<Local>) -> bool {
+        !self.completed && self.due.is_some_and(|due| due < now)
+    }
+
+    /// Whether the task is at `context`, a place compared without case, or there's no context.
+    pub fn in_context(&self, context: Option<&str>) -> bool {
+        context.is_none_or(|context| {
+            self.location
+                .as_deref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(context))
+        })
+    }
+
+    /// Whether the task is in the inbox: pending, not a subtask, and not yet filed with tags,
+    /// a priority, a due date or a repeat rule.
+    pub fn in_inbox(&self) -> bool {
+        !self.completed
+            && self.parent.is_none()
+            && self.tags.is_empty()
+            && self.priority.is_none()
+            && self.due.is_none()
+            && self.repeat.is_none()
+    }
+
+    /// Whether the task's timer is running.
+    pub fn is_running(&self) -> bool {
+        self.time.last().is_some_and(|i| i.end.is_none())
+    }
+
+    /// Total time worked on the task, counting a running timer up to `now`.
+    pub fn tracked(&self, now: DateTime<Local>) -> Duration {
+        self.time
+            .iter()
+            .map(|i| i.end.unwrap_or(now) - i.start)
+            .sum()
+    }
+
+    /// Starts building a task; `description` must be set before `build` becomes available.
+    pub fn builder() -> TaskBuilder<NoDescription> {
+        TaskBuilder {
+            task: Task {
+                created_at: crate::dates::now().fixed_offset(),
+                ..Task::default()
+            },
+            state: PhantomData,
+        }
+    }
+
+    /// Adds `tag` (normalized) unless it is already present. Returns whether it was added.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        if tag.is_empty() || self.tags.contains(&tag) {
+            return false;
+        }
+        self.tags.push(tag);
+        true
+    }
+
+    /// Removes `tag` (normalized). Returns whether it was present.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        let before = self.tags.len();
+        self.tags.retain(|t| *t != tag);
+        self.tags.len() < before
+    }
+
+    /// Returns a copy with every piece of free text replaced by a short hash of itself, such as
+    /// `task-1a2b3c4d`: the description, alias, tags, place, people, notes, attachments,
+    /// follow-up and metadata values. Identical text stays identical, so tags still group
+    /// tasks, while nothing is readable; the UUID is replaced with a new one.
+    pub fn anonymized(&self) -> Task {
+        let hash = |kind: &str, text: &str| {
+            let digest = Sha256::digest(text.as_bytes());
+            let hex: String = digest
+                .iter()
+                .take(4)
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            format!("{}-{}", kind, hex)
+        };
+        let mut task = self.clone();
+        task.uuid = self.uuid.as_ref().map(|_| crate::ids::uuid());
+        task.description = hash("task", &self.description);
+        task.alias = self.alias.as_deref().map(|a| hash("alias", a));
+        task.tags = self.tags.iter().map(|t| hash("tag", t)).collect();
+        task.location = self.location.as_deref().map(|l| hash("place", l));
+        task.assignee = self.assignee.as_deref().map(|a| hash("person", a));
+        if let Some(waiting) = &mut task.waiting_on {
+            waiting.person = hash("person", &waiting.person);
+        }
+        if let Some(follow_up) = &mut task.follow_up {
+            follow_up.description = hash("task", &follow_up.description);
+        }
+        for note in &mut task.notes {
+            note.text = hash("note", &note.text);
+        }
+        for attachment in &mut task.attachments {
+            *attachment = hash("attachment", attachment);
+        }
+        for value in task.metadata.values_mut() {
+            *value = hash("value", value);
+        }
+        task
+    }
+}
+
+/// Marker for a [`TaskBuilder`] that has no description yet.
+pub struct NoDescription;
+
+/// Marker for a [`TaskBuilder`] that is ready to build.
+pub struct HasDescription;
+
+/// Builder for [`Task`]. The ID is assigned when the task is inserted into a
+/// [`TodoList`](crate::TodoList).
+pub struct TaskBuilder<State> {
+    task: Task,
+    state: PhantomData<State>,
+}
+
+impl TaskBuilder<NoDescription> {
+    pub fn description(self, description: impl Into<String>) -> TaskBuilder<HasDescription> {
+        TaskBuilder {
+            task: Task {
+                description: description.into(),
+                ..self.task
+            },
+            state: PhantomData,
+        }
+    }
+}
+
+impl<State> TaskBuilder<State> {
+    /// Builds the task done or pending. When it was done isn't known, so `completed_at` is
+    /// left for the caller to set.
+    pub fn completed(mut self, completed: bool) -> Self {
+        self.task.status = if completed {
+            Status::Done
+        } else {
+            Status::Todo
+        };
+        self.task.completed = completed;
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.task.tags = tags;
+        self
+    }
+
+    pub fn location(mut self, location: Option<String>) -> Self {
+        self.task.location = location;
+        self
+    }
+
+    pub fn priority(mut self, priority: Option<Priority>) -> Self {
+        self.task.priority = priority;
+        self
+    }
+
+    pub fn due(mut self, due: Option<DateTime<Local>>) -> Self {
+        self.task.due = due;
+        self
+    }
+
+    pub fn parent(mut self, parent: Option<usize>) -> Self {
+        self.task.parent = parent;
+        self
+    }
+
+    pub fn repeat(mut self, repeat: Option<Rule>) -> Self {
+        self.task.repeat = repeat;
+        self
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.task.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl TaskBuilder<HasDescription> {
+    pub fn build(self) -> Task {
+        self.task
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anonymizing_leaves_none_of_the_text() {
+        let at = crate::dates::now();
+        let mut task = Task::builder()
+            .description("Call Alice about the lawsuit")
+            .build();
+        task.uuid = Some(crate::ids::uuid());
+        task.alias = Some("secret-alias".into());
+        task.add_tag("divorce");
+        task.location = Some("Oak Street clinic".into());
+        task.assignee = Some("Bob Example".into());
+        task.waiting_on = Some(WaitingOn {
+            person: "Carol Example".into(),
+            since: at,
+        });
+        task.follow_up = Some(FollowUp {
+            description: "Send the settlement".into(),
+            due_in: Some("3d".into()),
+        });
+        task.notes.push(Note {
+            at,
+            text: "Her number is 555-0100".into(),
+        });
+        task.attachments
+            .push("https://example.com/private/contract.pdf".into());
+        task.metadata
+            .insert("source.file".into(), "/home/dave/notes/legal.md".into());
+        task.metadata
+            .insert("email.message_id".into(), "<abc123@mail.example>".into());
+
+        let anonymized = task.anonymized();
+        let json = serde_json::to_string(&anonymized).unwrap();
+        for text in [
+            task.description.as_str(),
+            task.uuid.as_deref().unwrap(),
+            "secret-alias",
+            "divorce",
+            "Oak Street",
+            "Bob",
+            "Carol",
+            "settlement",
+            "555-0100",
+            "example.com",
+            "legal.md",
+            "abc123",
+        ] {
+            assert!(!json.contains(text), "{:?} is still in {}", text, json);
+        }
+        assert_eq!(anonymized.description, task.anonymized().description);
+        assert_eq!(anonymized.notes[0].at, at);
+        assert!(anonymized.metadata.contains_key("source.file"));
+    }
+
+    #[test]
+    fn created_at_is_read_leniently() {
+        let read = |created: &str| -> Task {
+            serde_json::from_value(serde_json::json!({
+                "id": 1,
+                "description": "Buy milk",
+                "completed": false,
+                "created_at": created,
+            }))
+            .unwrap()
+        };
+        let task = read("2026-10-15T10:00:00.123456789+02:00");
+        assert_eq!(
+            serde_json::to_value(&task).unwrap()["created_at"],
+            "2026-10-15T10:00:00.123456789+02:00"
+        );
+        assert_eq!(
+            read("2026-10-15").created_at,
+            crate::dates::parse_timestamp("2026-10-15").unwrap()
+        );
+        assert_eq!(
+            read("yesterday-ish").created_at,
+            DateTime::<FixedOffset>::default()
+        );
+    }
+}
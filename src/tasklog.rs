@@ -0,0 +1,208 @@
+//! An append-only [`Storage`] for large lists: saving appends the tasks that changed rather
+//! than rewriting the whole file, so `add` and `complete` write a line or two however many
+//! tasks there are.
+//!
+//! The file is [JSON Lines](https://jsonlines.org). The first line holds the data version,
+//! and each line after it a task, which replaces any earlier line with the same ID, or the
+//! removal of one:
+//!
+//! ```text
+//! {"version":2}
+//! {"id":1,"description":"Buy milk","completed":false,"created_at":"..."}
+//! {"id":1,"description":"Buy milk","completed":true,"created_at":"..."}
+//! {"removed":1}
+//! ```
+//!
+//! Replaced and removed tasks stay in the file until [`compact`](Storage::compact) rewrites
+//! it with one line per task.
+
+use crate::storage::{create_parent, file_read_only, read_text, replace};
+use crate::{schema, Storage, Task};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Removal {
+    removed: usize,
+}
+
+pub struct TaskLog {
+    path: PathBuf,
+    /// The data version the file had when last loaded, or `None` before it was.
+    loaded_version: Cell<Option<u32>>,
+    /// The tasks as last loaded or saved, to tell which ones a save changes.
+    saved: RefCell<HashMap<usize, Task>>,
+    /// Whether the last load found a line cut short at the end, which the next save drops.
+    torn: Cell<bool>,
+}
+
+impl TaskLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        TaskLog {
+            path: path.into(),
+            loaded_version: Cell::new(None),
+            saved: RefCell::default(),
+            torn: Cell::new(false),
+        }
+    }
+
+    /// Writes the file afresh with one line per task.
+    fn rewrite(&self, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut content = json!({ "version": schema::CURRENT }).to_string();
+        content.push('\n');
+        for task in tasks {
+            content.push_str(&serde_json::to_string(task)?);
+            content.push('\n');
+        }
+        create_parent(&self.path)?;
+        replace(&self.path, content.as_bytes())?;
+        *self.saved.borrow_mut() = tasks.iter().map(|t| (t.id, t.clone())).collect();
+        self.loaded_version.set(Some(schema::CURRENT));
+        self.torn.set(false);
+        debug!(tasks = tasks.len(), "task log rewritten");
+        Ok(())
+    }
+}
+
+impl Storage for TaskLog {
+    fn load(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
+        self.torn.set(false);
+        if !self.path.exists() {
+            debug!("task log does not exist yet");
+            self.saved.borrow_mut().clear();
+            self.loaded_version.set(Some(schema::CURRENT));
+            return Ok(vec![]);
+        }
+        let content = read_text(&self.path)?;
+        let records: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+        let version = match records.first() {
+            Some(line) => {
+                serde_json::from_str::<Header>(line)
+                    .map_err(|e| format!("line 1: not a task log header: {}", e))?
+                    .version
+            }
+            None => schema::CURRENT,
+        };
+        // Tasks from an older version are read as raw JSON and upgraded once replayed.
+        let current = version == schema::CURRENT;
+        let (mut tasks, mut old): (BTreeMap<usize, Task>, BTreeMap<usize, Value>) =
+            Default::default();
+        for (index, line) in records.iter().enumerate().skip(1) {
+            let read = if line.starts_with(r#"{"removed""#) {
+                serde_json::from_str(line).map(|Removal { removed }| {
+                    tasks.remove(&removed);
+                    old.remove(&removed);
+                })
+            } else if current {
+                serde_json::from_str(line).map(|task: Task| {
+                    tasks.insert(task.id, task);
+                })
+            } else {
+                serde_json::from_str(line).map(|task: Value| {
+                    let id = task.get("id").and_then(Value::as_u64).unwrap_or_default();
+                    old.insert(id as usize, task);
+                })
+            };
+            match read {
+                Ok(()) => {}
+                // A save cut short by a crash leaves half a line at the end.
+                Err(e) if e.is_eof() && index + 1 == records.len() => {
+                    warn!(error = %e, path = %self.path.display(), "ignoring an incomplete last line");
+                    self.torn.set(true);
+                }
+                Err(e) => return Err(format!("line {}: {}", index + 1, e).into()),
+            }
+        }
+        // In ID order, as SQLite returns them, rather than the order they were last changed.
+        let tasks: Vec<Task> = if current {
+            tasks.into_values().collect()
+        } else {
+            schema::upgrade(old.into_values().collect(), version)?
+        };
+        *self.saved.borrow_mut() = tasks.iter().map(|t| (t.id, t.clone())).collect();
+        self.loaded_version.set(Some(version));
+        debug!(
+            records = records.len(),
+            tasks = tasks.len(),
+            "task log replayed"
+        );
+        Ok(tasks)
+    }
+
+    /// Appends a line for each task added or changed since the last load or save, and one
+    /// for each task removed. A file that wasn't loaded first, is from an older version or
+    /// ends in an incomplete line is rewritten instead.
+    fn save(&self, tasks: &[Task]) -> Result<(), Box<dyn std::error::Error>> {
+        let current = self.loaded_version.get() == Some(schema::CURRENT);
+        if !current || self.torn.get() || !self.path.exists() {
+            return self.rewrite(tasks);
+        }
+        let mut saved = self.saved.borrow_mut();
+        let mut appended = String::new();
+        let mut changed = 0;
+        for task in tasks {
+            if saved.get(&task.id) == Some(task) {
+                continue;
+            }
+            appended.push_str(&serde_json::to_string(task)?);
+            appended.push('\n');
+            saved.insert(task.id, task.clone());
+            changed += 1;
+        }
+        let ids: HashSet<usize> = tasks.iter().map(|t| t.id).collect();
+        let mut removed: Vec<usize> = saved
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .copied()
+            .collect();
+        removed.sort();
+        for id in &removed {
+            appended.push_str(&json!({ "removed": id }).to_string());
+            appended.push('\n');
+            saved.remove(id);
+        }
+        if appended.is_empty() {
+            return Ok(());
+        }
+        let mut file = fs::OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(appended.as_bytes())?;
+        file.sync_data()?;
+        debug!(changed, removed = removed.len(), "appended to the task log");
+        Ok(())
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_only(&self) -> Option<String> {
+        file_read_only(&self.path)
+    }
+
+    /// The file's size and modification time, which every append changes.
+    fn version(&self) -> Option<i64> {
+        let metadata = fs::metadata(&self.path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some((metadata.len() as i64) ^ (modified.as_nanos() as i64).rotate_left(32))
+    }
+
+    fn compact(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+        let tasks = self.load()?;
+        self.rewrite(&tasks)
+    }
+}
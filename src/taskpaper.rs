@@ -0,0 +1,70 @@
+//! Reading and writing the TaskPaper plain-text format.
+//!
+//! ```text
+//! Inbox:
+//!     - Buy milk
+//!     - File taxes @done(2024-04-10)
+//! ```
+//!
+//! Project headings are accepted on import but not kept, since tasks don't belong to
+//! projects yet. Tags other than `@done` stay in the description so nothing is lost.
+
+use crate::Task;
+use regex::Regex;
+
+/// Parses TaskPaper text into tasks; IDs are assigned when they are inserted into a list.
+pub fn parse(content: &str) -> Vec<Task> {
+    let done = Regex::new(r"(^|\s)@done(\([^)]*\))?").unwrap();
+    content
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("- "))
+        .map(|text| {
+            let completed = done.is_match(text);
+            let description = done.replace_all(text, "").trim().to_string();
+            Task::builder()
+                .description(description)
+                .completed(completed)
+                .build()
+        })
+        .filter(|task| !task.description.is_empty())
+        .collect()
+}
+
+/// Renders tasks as a single `Inbox:` project, marking completed ones with `@done`.
+pub fn render(tasks: &[Task]) -> String {
+    let mut output = String::from("Inbox:\n");
+    for task in tasks {
+        output.push_str("\t- ");
+        output.push_str(&task.description);
+        if task.completed {
+            output.push_str(" @done");
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, sample_tasks};
+
+    #[test]
+    fn round_trips_descriptions_and_completion() {
+        let tasks = parse(&render(&sample_tasks()));
+        assert_eq!(tasks.len(), 3);
+        assert!(!find(&tasks, "Plan the trip").completed);
+        assert!(!find(&tasks, "Book the flights").completed);
+        assert!(find(&tasks, "Renew the passport").completed);
+    }
+
+    #[test]
+    fn skips_projects_and_keeps_other_tags_in_the_description() {
+        let text =
+            "Inbox:\n\t- Buy milk @errand\n\t- File taxes @done(2024-04-10)\nNotes about it\n";
+        let tasks = parse(text);
+        assert_eq!(tasks.len(), 2);
+        assert!(!find(&tasks, "Buy milk @errand").completed);
+        assert!(find(&tasks, "File taxes").completed);
+    }
+}
@@ -0,0 +1,426 @@
+//! Taskwarrior's JSON format, as written by `task export` and read by `task import`.
+//!
+//! Importing maps pending and waiting tasks to pending ones and completed tasks to completed
+//! ones. Deleted tasks and recurrence templates are skipped; the templates' pending instances
+//! carry the recurrence and become repeating tasks. Projects become tags, and H/M/L
+//! priorities map to high, medium and low. A task that others depend on becomes their
+//! parent's subtask. Annotations and recurrences outside what [`Rule`] supports are kept as
+//! `taskwarrior.annotations` and `taskwarrior.recur`. Every task records its UUID as
+//! `external.id`, so importing a newer export only adds what is new. Tasks that can't be read
+//! are skipped as [problems](crate::import::Problem).
+//!
+//! Exporting reverses this: subtasks become dependencies of their parent. Tasks imported from
+//! Taskwarrior keep their UUID, and others get one derived from their ID and creation time,
+//! so exporting twice gives the same UUIDs. Completion times aren't recorded, so completed
+//! tasks are marked as ended at the time of export.
+
+use crate::import::Problem;
+use crate::recurrence::{Frequency, Rule};
+use crate::{dates, normalize_tag, schema, Priority, Task};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Serialize, Deserialize)]
+struct Annotation {
+    entry: String,
+    description: String,
+}
+
+/// `depends` is a comma-separated string before Taskwarrior 2.6 and an array since.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Depends {
+    List(Vec<String>),
+    Joined(String),
+}
+
+impl Depends {
+    fn uuids(&self) -> Vec<String> {
+        match self {
+            Depends::List(uuids) => uuids.clone(),
+            Depends::Joined(text) => text
+                .split(',')
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Imported {
+    uuid: String,
+    description: String,
+    status: String,
+    #[serde(default)]
+    entry: Option<String>,
+    #[serde(default)]
+    due: Option<String>,
+    #[serde(default)]
+    priority: Option<String>,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+    #[serde(default)]
+    recur: Option<String>,
+    #[serde(default)]
+    depends: Option<Depends>,
+}
+
+#[derive(Serialize)]
+struct Exported {
+    uuid: String,
+    description: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recur: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    depends: Vec<String>,
+}
+
+fn parse_date(text: &str) -> Option<DateTime<Local>> {
+    NaiveDateTime::parse_from_str(text, DATE_FORMAT)
+        .ok()
+        .map(|naive| naive.and_utc().with_timezone(&Local))
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(text)
+                .ok()
+                .map(|at| at.with_timezone(&Local))
+        })
+}
+
+fn format_date(at: DateTime<impl chrono::TimeZone>) -> String {
+    at.with_timezone(&Utc).format(DATE_FORMAT).to_string()
+}
+
+/// A Taskwarrior recurrence such as `weekly`, `biweekly`, `2w` or `3months` as a rule.
+fn recur_rule(recur: &str) -> Option<Rule> {
+    let text = recur.trim().to_lowercase();
+    let rule = |frequency, interval| Rule {
+        frequency,
+        interval,
+        weekdays: vec![],
+    };
+    match text.as_str() {
+        "biweekly" | "fortnight" => return Some(rule(Frequency::Weekly, 2)),
+        "quarterly" => return Some(rule(Frequency::Monthly, 3)),
+        "semiannual" => return Some(rule(Frequency::Monthly, 6)),
+        "annual" | "yearly" => return Some(rule(Frequency::Yearly, 1)),
+        _ => {}
+    }
+    if let Ok(rule) = Rule::parse(&text) {
+        return Some(rule);
+    }
+    let digits = text.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = text.split_at(digits);
+    let count = if count.is_empty() {
+        1
+    } else {
+        count.parse().ok()?
+    };
+    let (frequency, count) = match unit.trim() {
+        "d" | "day" | "days" => (Frequency::Daily, count),
+        "w" | "wk" | "wks" | "week" | "weeks" => (Frequency::Weekly, count),
+        "mo" | "mos" | "month" | "months" => (Frequency::Monthly, count),
+        "q" | "qtr" | "qtrs" | "quarter" | "quarters" => (Frequency::Monthly, 3 * count),
+        "y" | "yr" | "yrs" | "year" | "years" => (Frequency::Yearly, count),
+        _ => return None,
+    };
+    (count > 0).then(|| rule(frequency, count))
+}
+
+/// The rule as a Taskwarrior recurrence, if Taskwarrior can express it.
+fn recur_value(rule: &Rule) -> Option<String> {
+    if !rule.weekdays.is_empty() {
+        return (rule.to_string() == "every weekday").then(|| "weekdays".to_string());
+    }
+    let unit = match rule.frequency {
+        Frequency::Daily => "d",
+        Frequency::Weekly => "w",
+        Frequency::Monthly => "mo",
+        Frequency::Yearly => "y",
+    };
+    Some(format!("{}{}", rule.interval, unit))
+}
+
+fn priority(value: Option<&str>) -> Option<Priority> {
+    match value? {
+        "H" => Some(Priority::High),
+        "M" => Some(Priority::Medium),
+        "L" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// A version 8 (custom) UUID from a hash, for tasks that don't have one yet.
+fn derived_uuid(task: &Task) -> String {
+    let digest = Sha256::digest(format!(
+        "rust-todo:{}:{}",
+        task.id,
+        task.created_at.to_rfc3339()
+    ));
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x80;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Reads `task export` output: a JSON array, or the older one-object-per-line form. Tasks
+/// that can't be read are left out, each with a problem.
+fn read(content: &str) -> Result<(Vec<Imported>, Vec<Problem>), Box<dyn std::error::Error>> {
+    let mut imported = vec![];
+    let mut problems = vec![];
+    if content.trim_start().starts_with('[') {
+        let values: Vec<serde_json::Value> = serde_json::from_str(content)?;
+        let lines = schema::element_lines(content);
+        let lines = (lines.len() == values.len()).then_some(lines);
+        for (index, value) in values.into_iter().enumerate() {
+            match serde_json::from_value(value) {
+                Ok(task) => imported.push(task),
+                Err(e) => problems.push(Problem {
+                    line: lines.as_ref().map(|lines| lines[index]),
+                    message: format!("task {}: {}", index + 1, e),
+                }),
+            }
+        }
+        return Ok((imported, problems));
+    }
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim().trim_end_matches(',');
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(task) => imported.push(task),
+            Err(e) => {
+                // The position is within the line, which the problem gives already.
+                let message = e.to_string();
+                let position = format!(" at line {} column {}", e.line(), e.column());
+                let message = message.strip_suffix(&position).unwrap_or(&message);
+                problems.push(Problem::at(number + 1, message));
+            }
+        }
+    }
+    Ok((imported, problems))
+}
+
+pub fn parse(content: &str) -> Result<(Vec<Task>, Vec<Problem>), Box<dyn std::error::Error>> {
+    let (imported, problems) = read(content)?;
+    let imported: Vec<Imported> = imported
+        .into_iter()
+        .filter(|t| !matches!(t.status.as_str(), "deleted" | "recurring"))
+        .collect();
+    // Provisional IDs, so dependencies can become subtasks until the list assigns real ones.
+    let ids: HashMap<&str, usize> = imported
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.uuid.as_str(), i + 1))
+        .collect();
+    let mut parents: HashMap<usize, usize> = HashMap::new();
+
+    let mut tasks = vec![];
+    for (i, item) in imported.iter().enumerate() {
+        let mut tags: Vec<String> = item
+            .project
+            .as_deref()
+            .map(normalize_tag)
+            .into_iter()
+            .collect();
+        for tag in item.tags.iter().map(|t| normalize_tag(t)) {
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        let mut builder = Task::builder()
+            .description(item.description.trim())
+            .completed(item.status == "completed")
+            .tags(tags)
+            .priority(priority(item.priority.as_deref()))
+            .due(item.due.as_deref().and_then(parse_date))
+            .metadata("external.id", format!("taskwarrior:{}", item.uuid));
+        if let Some(recur) = item.recur.as_deref() {
+            match recur_rule(recur) {
+                Some(rule) => builder = builder.repeat(Some(rule)),
+                None => builder = builder.metadata("taskwarrior.recur", recur),
+            }
+        }
+        if !item.annotations.is_empty() {
+            let notes: Vec<&str> = item
+                .annotations
+                .iter()
+                .map(|a| a.description.as_str())
+                .collect();
+            builder = builder.metadata("taskwarrior.annotations", notes.join("\n"));
+        }
+        let mut task = builder.build();
+        task.id = i + 1;
+        if let Some(created) = item.entry.as_deref().and_then(parse_date) {
+            task.created_at = created.fixed_offset();
+        }
+        tasks.push(task);
+    }
+    for item in &imported {
+        let id = ids[item.uuid.as_str()];
+        for uuid in item
+            .depends
+            .as_ref()
+            .map(Depends::uuids)
+            .unwrap_or_default()
+        {
+            let Some(&dependency) = ids.get(uuid.as_str()) else {
+                continue;
+            };
+            // Circular dependencies would make a task its own ancestor.
+            let mut ancestor = Some(id);
+            while let Some(a) = ancestor.filter(|&a| a != dependency) {
+                ancestor = parents.get(&a).copied();
+            }
+            if ancestor.is_none() {
+                parents.entry(dependency).or_insert(id);
+            }
+        }
+    }
+    for task in &mut tasks {
+        task.parent = parents.get(&task.id).copied();
+    }
+    Ok((tasks, problems))
+}
+
+/// Renders tasks as a JSON array for `task import`.
+pub fn render(tasks: &[Task]) -> Result<String, Box<dyn std::error::Error>> {
+    let uuids: HashMap<usize, String> = tasks
+        .iter()
+        .map(|task| {
+            let uuid = task
+                .metadata
+                .get("external.id")
+                .and_then(|id| id.strip_prefix("taskwarrior:"))
+                .map(str::to_string)
+                .unwrap_or_else(|| derived_uuid(task));
+            (task.id, uuid)
+        })
+        .collect();
+    let now = format_date(dates::now());
+    let exported: Vec<Exported> = tasks
+        .iter()
+        .map(|task| {
+            let entry = format_date(task.created_at);
+            // Taskwarrior only repeats tasks with a due date, from a "recurring" template.
+            let recur = task
+                .repeat
+                .as_ref()
+                .filter(|_| task.due.is_some() && !task.completed)
+                .and_then(recur_value);
+            let mut annotations: Vec<Annotation> = task
+                .metadata
+                .get("taskwarrior.annotations")
+                .map(|notes| {
+                    notes
+                        .lines()
+                        .map(|note| Annotation {
+                            entry: entry.clone(),
+                            description: note.to_string(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(waiting) = &task.waiting_on {
+                annotations.push(Annotation {
+                    entry: format_date(waiting.since),
+                    description: format!("Waiting on {}", waiting.person),
+                });
+            }
+            Exported {
+                uuid: uuids[&task.id].clone(),
+                description: task.description.clone(),
+                status: match (task.completed, &recur) {
+                    (true, _) => "completed",
+                    (false, Some(_)) => "recurring",
+                    (false, None) => "pending",
+                },
+                end: task.completed.then(|| now.clone()),
+                entry: Some(entry),
+                due: task.due.map(format_date),
+                priority: match task.priority {
+                    Some(Priority::Critical | Priority::High) => Some("H"),
+                    Some(Priority::Medium) => Some("M"),
+                    Some(Priority::Low) => Some("L"),
+                    None => None,
+                },
+                tags: task.tags.clone(),
+                annotations,
+                recur,
+                depends: tasks
+                    .iter()
+                    .filter(|t| t.parent == Some(task.id))
+                    .map(|t| uuids[&t.id].clone())
+                    .collect(),
+            }
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&exported)? + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, sample_tasks};
+
+    #[test]
+    fn round_trips_through_task_export() {
+        let sample = sample_tasks();
+        let (tasks, problems) = parse(&render(&sample).unwrap()).unwrap();
+        assert!(problems.is_empty());
+        let trip = find(&tasks, "Plan the trip");
+        assert_eq!(trip.priority, Some(Priority::High));
+        assert_eq!(trip.tags, ["travel"]);
+        assert_eq!(trip.due, sample[0].due);
+        assert_eq!(find(&tasks, "Book the flights").parent, Some(trip.id));
+        assert!(find(&tasks, "Renew the passport").completed);
+    }
+
+    #[test]
+    fn skips_deleted_tasks_and_recurrence_templates() {
+        let json = r#"[
+            {"uuid": "a", "status": "pending", "description": "Water the plants", "recur": "daily", "annotations": [{"entry": "20261015T100000Z", "description": "the ones inside"}]},
+            {"uuid": "b", "status": "recurring", "description": "Water the plants", "recur": "daily"},
+            {"uuid": "c", "status": "deleted", "description": "Gone"}
+        ]"#;
+        let (tasks, problems) = parse(json).unwrap();
+        assert!(problems.is_empty());
+        assert_eq!(tasks.len(), 1);
+        assert!(tasks[0].repeat.is_some());
+        assert_eq!(
+            tasks[0].metadata["taskwarrior.annotations"],
+            "the ones inside"
+        );
+    }
+}
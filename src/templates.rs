@@ -0,0 +1,233 @@
+//! Templates: batches of tasks added together again and again, such as a release checklist,
+//! kept as TOML files in the `templates` directory of the [configuration
+//! directory](crate::dirs::config_dir). `template save` writes one from tasks in the list, and
+//! they can be written or changed by hand:
+//!
+//! ```toml
+//! [[task]]
+//! description = "Release"
+//! tags = ["release"]
+//!
+//! [[task]]
+//! description = "Freeze the branch"
+//! parent = 1
+//! due = "2d"
+//!
+//! [[task]]
+//! description = "Publish the packages"
+//! parent = 1
+//! priority = "high"
+//! due = "5d"
+//! depends_on = [2]
+//! ```
+//!
+//! `due` is how long after the template is applied the task falls due, as in `follow-up
+//! --due-in`; an offset in whole days is the end of that day. `parent` and `depends_on` are
+//! positions of other tasks in the template, counting from 1.
+
+use crate::recurrence::Rule;
+use crate::{dates, dirs, storage, Priority, Task};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const DIR: &str = "templates";
+
+/// A template: the tasks it adds, in order.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    #[serde(default, rename = "task")]
+    pub tasks: Vec<Item>,
+}
+
+/// One task of a [`Template`].
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Item {
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// How long after the template is applied it is due, e.g. `3d`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<Rule>,
+    /// Position of its parent in the template, from 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+    /// Positions of the tasks it depends on in the template, from 1.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<usize>,
+}
+
+/// The directory holding the templates.
+pub fn dir() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(DIR))
+}
+
+/// The file of template `name`, whether or not it exists.
+pub fn path(name: &str) -> Result<PathBuf, String> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || !valid {
+        return Err(format!(
+            "invalid template name {:?}: use letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    let dir = dir().ok_or("no configuration directory on this system")?;
+    Ok(dir.join(format!("{}.toml", name)))
+}
+
+/// Names of the saved templates, sorted.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Reads template `name`; `None` when there is no such template.
+pub fn load(name: &str) -> Result<Option<Template>, String> {
+    let path = path(name)?;
+    let text = match storage::read_text(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("could not read {}: {}", path.display(), e)),
+    };
+    let template: Template =
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    template
+        .check()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Some(template))
+}
+
+/// How long after `start` `due` is, as a template writes it: in days when it is at the end
+/// of a day, in hours or minutes otherwise. A due date before `start` is due at once.
+fn offset(due: DateTime<Local>, start: DateTime<Local>) -> String {
+    if dates::is_end_of_day(due) {
+        let days = (due.date_naive() - start.date_naive()).num_days().max(0);
+        return format!("{}d", days);
+    }
+    let minutes = (due - start).num_minutes().max(0);
+    if minutes % 60 == 0 {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+impl Template {
+    /// A template adding `tasks` again, with due dates relative to `start`. Parents and
+    /// dependencies outside `tasks` are left out.
+    pub fn from_tasks(tasks: &[&Task], start: DateTime<Local>) -> Template {
+        let position = |id: usize| tasks.iter().position(|t| t.id == id).map(|i| i + 1);
+        let tasks = tasks
+            .iter()
+            .map(|task| Item {
+                description: task.description.clone(),
+                tags: task.tags.clone(),
+                priority: task.priority,
+                location: task.location.clone(),
+                due: task.due.map(|due| offset(due, start)),
+                repeat: task.repeat.clone(),
+                parent: task.parent.and_then(position),
+                depends_on: task
+                    .depends_on
+                    .iter()
+                    .filter_map(|id| position(*id))
+                    .collect(),
+            })
+            .collect();
+        Template { tasks }
+    }
+
+    /// Checks what parsing can't: the due offsets, and that positions name other tasks in
+    /// the template without a task ending up under itself.
+    pub fn check(&self) -> Result<(), String> {
+        let count = self.tasks.len();
+        for (i, item) in self.tasks.iter().enumerate() {
+            let n = i + 1;
+            if let Some(due) = &item.due {
+                dates::parse_duration(due).map_err(|e| format!("task {}: {}", n, e))?;
+            }
+            for position in item.parent.iter().chain(&item.depends_on) {
+                if *position == n || !(1..=count).contains(position) {
+                    return Err(format!(
+                        "task {}: there is no other task {} in the template",
+                        n, position
+                    ));
+                }
+            }
+        }
+        for (i, item) in self.tasks.iter().enumerate() {
+            // Following the parents up from a task reaches the top within `count` steps.
+            let mut parent = item.parent;
+            for _ in 0..count {
+                parent = parent.and_then(|p| self.tasks[p - 1].parent);
+            }
+            if parent.is_some() {
+                return Err(format!("task {}: its parents go round in a circle", i + 1));
+            }
+        }
+        Ok(())
+    }
+
+    /// The tasks to add when the template is applied at `start`, without their parents and
+    /// dependencies, which need the IDs they are added under.
+    pub fn tasks(&self, start: DateTime<Local>) -> Vec<Task> {
+        self.tasks
+            .iter()
+            .map(|item| Task {
+                description: item.description.clone(),
+                created_at: dates::now().fixed_offset(),
+                tags: item.tags.clone(),
+                priority: item.priority,
+                location: item.location.clone(),
+                due: item
+                    .due
+                    .as_deref()
+                    .and_then(|due| dates::parse_duration(due).ok())
+                    .map(|offset| dates::due_after(start, offset)),
+                repeat: item.repeat.clone(),
+                ..Task::default()
+            })
+            .collect()
+    }
+
+    /// Writes the template as `name`, replacing one of that name only with `overwrite`.
+    /// Returns the file written.
+    pub fn save(&self, name: &str, overwrite: bool) -> Result<PathBuf, String> {
+        let path = path(name)?;
+        if path.exists() && !overwrite {
+            return Err(format!(
+                "a template named {:?} already exists; use --force to replace it",
+                name
+            ));
+        }
+        let text = toml::to_string(self).map_err(|e| e.to_string())?;
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, text)
+        };
+        write().map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,56 @@
+//! Helpers for the unit tests.
+
+use crate::{dates, Priority, Task};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A directory of its own for a test, removed with everything in it when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "rust-todo-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).expect("could not create a temporary directory");
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Tasks for the file formats to write and read back: a pending task with a priority, a tag
+/// and a due time, a subtask of it, and a completed task.
+pub fn sample_tasks() -> Vec<Task> {
+    let mut trip = Task::builder().description("Plan the trip").build();
+    trip.id = 1;
+    trip.priority = Some(Priority::High);
+    trip.add_tag("travel");
+    trip.due = Some(dates::parse_due("2026-10-20 15:00", dates::now()).unwrap());
+    let mut flights = Task::builder().description("Book the flights").build();
+    flights.id = 2;
+    flights.parent = Some(1);
+    let mut passport = Task::builder().description("Renew the passport").build();
+    passport.id = 3;
+    passport.set_completed(true);
+    vec![trip, flights, passport]
+}
+
+/// The task in `tasks` described as `description`.
+pub fn find<'a>(tasks: &'a [Task], description: &str) -> &'a Task {
+    tasks
+        .iter()
+        .find(|t| t.description == description)
+        .unwrap_or_else(|| panic!("no task {:?} in {:?}", description, tasks))
+}
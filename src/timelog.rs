@@ -0,0 +1,191 @@
+//! Time tracked with `start` and `stop`, split into days for `log` and `stats`, and taken as
+//! focus sessions for `report focus`.
+//!
+//! An interval that runs past midnight counts towards each day it covers, and a running timer
+//! counts up to now. Only tasks still in the list are counted; archived ones are not.
+
+use crate::{planner, Task};
+use chrono::{DateTime, Days, Duration, Local, NaiveDate, NaiveTime};
+use std::collections::BTreeMap;
+
+/// Time worked on each task on each day from `since` to `now`, by date and then task ID.
+pub fn by_day(
+    tasks: &[Task],
+    since: DateTime<Local>,
+    now: DateTime<Local>,
+) -> BTreeMap<NaiveDate, BTreeMap<usize, Duration>> {
+    let mut days: BTreeMap<NaiveDate, BTreeMap<usize, Duration>> = BTreeMap::new();
+    for task in tasks {
+        for interval in &task.time {
+            let mut start = interval.start.max(since);
+            let end = interval.end.unwrap_or(now).min(now);
+            while start < end {
+                let midnight = start
+                    .date_naive()
+                    .succ_opt()
+                    .and_then(|day| day.and_hms_opt(0, 0, 0))
+                    .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+                    .unwrap_or(end);
+                let until = midnight.min(end);
+                *days
+                    .entry(start.date_naive())
+                    .or_default()
+                    .entry(task.id)
+                    .or_insert_with(Duration::zero) += until - start;
+                start = until;
+            }
+        }
+    }
+    days
+}
+
+/// Deep work in a period, from [`focus`].
+#[derive(Debug, Default)]
+pub struct Focus {
+    /// Time in focus sessions and how many there were, by the day each started.
+    pub days: BTreeMap<NaiveDate, (Duration, usize)>,
+    /// Time in focus sessions by tag, counting a task with several tags towards each, and
+    /// under `""` for tasks without tags.
+    pub tags: BTreeMap<String, Duration>,
+    /// Time in intervals too short to count as focus sessions.
+    pub short: Duration,
+}
+
+/// The focus sessions from `since` to `until`: intervals of at least `min` each, from `start`
+/// and `stop` or `pomodoro`. A running timer counts up to `until`.
+pub fn focus(
+    tasks: &[Task],
+    since: DateTime<Local>,
+    until: DateTime<Local>,
+    min: Duration,
+) -> Focus {
+    let mut focus = Focus::default();
+    for task in tasks {
+        for interval in &task.time {
+            let start = interval.start.max(since);
+            let end = interval.end.unwrap_or(until).min(until);
+            if start >= end {
+                continue;
+            }
+            let length = end - start;
+            if length < min {
+                focus.short += length;
+                continue;
+            }
+            let day = focus
+                .days
+                .entry(start.date_naive())
+                .or_insert((Duration::zero(), 0));
+            day.0 += length;
+            day.1 += 1;
+            let untagged = [String::new()];
+            let tags = if task.tags.is_empty() {
+                &untagged[..]
+            } else {
+                &task.tags[..]
+            };
+            for tag in tags {
+                *focus.tags.entry(tag.clone()).or_insert_with(Duration::zero) += length;
+            }
+        }
+    }
+    focus
+}
+
+/// The focus sessions in the week, Monday to Sunday, that `day` is in, up to `now`, with the
+/// Monday it starts on; see [`focus`].
+pub fn week_focus(
+    tasks: &[Task],
+    day: NaiveDate,
+    now: DateTime<Local>,
+    min: Duration,
+) -> Result<(NaiveDate, Focus), String> {
+    let monday = planner::week_start(day);
+    let start_of = |day: NaiveDate| {
+        day.and_time(NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .ok_or_else(|| format!("{} has no midnight here", day))
+    };
+    let since = start_of(monday)?;
+    let until = start_of(monday + Days::new(7))?.min(now);
+    Ok((monday, focus(tasks, since, until, min)))
+}
+
+/// Time worked on each task in `days`, as returned by [`by_day`], most first and then by ID.
+pub fn by_task(days: &BTreeMap<NaiveDate, BTreeMap<usize, Duration>>) -> Vec<(usize, Duration)> {
+    let mut totals: BTreeMap<usize, Duration> = BTreeMap::new();
+    for (id, spent) in days.values().flatten() {
+        *totals.entry(*id).or_insert_with(Duration::zero) += *spent;
+    }
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    totals
+}
+
+/// Total time in `days`, as returned by [`by_day`].
+pub fn total(days: &BTreeMap<NaiveDate, BTreeMap<usize, Duration>>) -> Duration {
+    days.values().flat_map(|tasks| tasks.values()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Interval;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap()
+    }
+
+    fn worked(id: usize, intervals: &[(DateTime<Local>, Option<DateTime<Local>>)]) -> Task {
+        let mut task = Task::builder().description(format!("Task {}", id)).build();
+        task.id = id;
+        task.time = intervals
+            .iter()
+            .map(|(start, end)| Interval {
+                start: *start,
+                end: *end,
+            })
+            .collect();
+        task
+    }
+
+    #[test]
+    fn intervals_past_midnight_count_towards_each_day() {
+        let tasks = [worked(1, &[(at(4, 22), Some(at(5, 1)))])];
+        let days = by_day(&tasks, at(1, 0), at(6, 0));
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        assert_eq!(days[&date(4)][&1], Duration::hours(2));
+        assert_eq!(days[&date(5)][&1], Duration::hours(1));
+        assert_eq!(total(&days), Duration::hours(3));
+    }
+
+    #[test]
+    fn a_running_timer_counts_up_to_now() {
+        let tasks = [worked(1, &[(at(4, 9), None)])];
+        let days = by_day(&tasks, at(1, 0), at(4, 11));
+        assert_eq!(total(&days), Duration::hours(2));
+    }
+
+    #[test]
+    fn totals_per_task_come_most_first() {
+        let tasks = [
+            worked(1, &[(at(4, 9), Some(at(4, 10)))]),
+            worked(
+                2,
+                &[(at(4, 10), Some(at(4, 11))), (at(5, 9), Some(at(5, 10)))],
+            ),
+            worked(3, &[(at(5, 10), Some(at(5, 11)))]),
+        ];
+        let days = by_day(&tasks, at(1, 0), at(6, 0));
+        assert_eq!(
+            by_task(&days),
+            [
+                (2, Duration::hours(2)),
+                (1, Duration::hours(1)),
+                (3, Duration::hours(1))
+            ]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Todoist's CSV format, used by its project export ("Export as a template") and import.
+//!
+//! Importing turns each `task` row into a task. `@labels` in the content become tags,
+//! priorities p1–p3 map to critical, high and medium, and the indent level makes subtasks.
+//! The date becomes a due date or, for "every …" dates, a repeat rule; dates neither can read
+//! are kept as `todoist.date`. Descriptions, comments (`note` rows) and the section a task is
+//! in are kept as `todoist.description`, `todoist.notes` and `todoist.section`. The CSV has no
+//! task IDs, so `external.id` is derived from the section and the task's place in the tree,
+//! and importing the same export twice adds nothing. Rows of an unknown type, tasks without
+//! content and indents that aren't numbers are reported as
+//! [problems](crate::import::Problem).
+//!
+//! Exporting writes pending tasks only, since Todoist's import has no completed state.
+
+use crate::import::Problem;
+use crate::recurrence::Rule;
+use crate::{csv, dates, normalize_tag, Priority, Task};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const COLUMNS: [&str; 10] = [
+    "TYPE",
+    "CONTENT",
+    "DESCRIPTION",
+    "PRIORITY",
+    "INDENT",
+    "AUTHOR",
+    "RESPONSIBLE",
+    "DATE",
+    "DATE_LANG",
+    "TIMEZONE",
+];
+
+/// Todoist shows at most this many levels of subtasks.
+const MAX_INDENT: usize = 5;
+
+fn priority(value: &str) -> Option<Priority> {
+    match value.trim() {
+        "1" => Some(Priority::Critical),
+        "2" => Some(Priority::High),
+        "3" => Some(Priority::Medium),
+        _ => None,
+    }
+}
+
+fn priority_value(priority: Option<Priority>) -> &'static str {
+    match priority {
+        Some(Priority::Critical) => "1",
+        Some(Priority::High) => "2",
+        Some(Priority::Medium) => "3",
+        _ => "4",
+    }
+}
+
+fn short_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn parse(content: &str) -> Result<(Vec<Task>, Vec<Problem>), Box<dyn std::error::Error>> {
+    let mut records = csv::parse_lines(content).into_iter();
+    let (_, header) = records.next().ok_or("the Todoist CSV is empty")?;
+    let column: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_uppercase(), i))
+        .collect();
+    for required in ["TYPE", "CONTENT"] {
+        if !column.contains_key(required) {
+            return Err(format!("not a Todoist CSV: no {} column", required).into());
+        }
+    }
+    let label = Regex::new(r"(^|\s)@([\w/-]+)").unwrap();
+    let now = dates::now();
+
+    let mut tasks: Vec<Task> = vec![];
+    let mut problems = vec![];
+    let mut notes: HashMap<usize, Vec<String>> = HashMap::new();
+    // IDs and content of the current task's ancestors, by indent level.
+    let mut path: Vec<(usize, String)> = vec![];
+    let mut section = String::new();
+    for (line, record) in records {
+        let get = |name: &str| {
+            column
+                .get(name)
+                .and_then(|&i| record.get(i))
+                .map(|v| v.trim())
+                .unwrap_or("")
+        };
+        match get("TYPE").to_lowercase().as_str() {
+            "section" => {
+                section = get("CONTENT").to_string();
+                path.clear();
+            }
+            "note" => {
+                if let Some((id, _)) = path.last() {
+                    notes
+                        .entry(*id)
+                        .or_default()
+                        .push(get("CONTENT").to_string());
+                }
+            }
+            "task" => {
+                let content = get("CONTENT");
+                let description = label.replace_all(content, "").trim().to_string();
+                if description.is_empty() {
+                    problems.push(Problem::at(line, "task without content"));
+                    continue;
+                }
+                let indent = match get("INDENT") {
+                    "" => 1,
+                    value => value.parse::<usize>().unwrap_or_else(|_| {
+                        problems.push(Problem::at(
+                            line,
+                            format!(
+                                "INDENT {:?} isn't a number; imported at the top level",
+                                value
+                            ),
+                        ));
+                        1
+                    }),
+                }
+                .max(1);
+                path.truncate(indent - 1);
+                let parent = path.last().map(|(id, _)| *id);
+                let mut key: Vec<&str> = vec![&section];
+                key.extend(path.iter().map(|(_, name)| name.as_str()));
+                key.push(&description);
+
+                let tags = label
+                    .captures_iter(content)
+                    .map(|c| normalize_tag(&c[2]))
+                    .collect();
+                let mut builder = Task::builder()
+                    .description(description.as_str())
+                    .tags(tags)
+                    .priority(priority(get("PRIORITY")))
+                    .parent(parent)
+                    .metadata(
+                        "external.id",
+                        format!("todoist:{}", short_hash(&key.join("\n"))),
+                    );
+                let date = get("DATE");
+                if !date.is_empty() {
+                    if date.to_lowercase().starts_with("every") {
+                        match Rule::parse(date) {
+                            Ok(rule) => builder = builder.repeat(Some(rule)),
+                            Err(_) => builder = builder.metadata("todoist.date", date),
+                        }
+                    } else {
+                        match dates::parse_due(date, now) {
+                            Ok(due) => builder = builder.due(Some(due)),
+                            Err(_) => builder = builder.metadata("todoist.date", date),
+                        }
+                    }
+                }
+                if !get("DESCRIPTION").is_empty() {
+                    builder = builder.metadata("todoist.description", get("DESCRIPTION"));
+                }
+                if !section.is_empty() {
+                    builder = builder.metadata("todoist.section", section.as_str());
+                }
+                let mut task = builder.build();
+                // Provisional IDs, so subtasks can point at their parent until the list
+                // assigns real ones.
+                task.id = tasks.len() + 1;
+                path.push((task.id, description));
+                tasks.push(task);
+            }
+            // Todoist writes `meta` rows for the project's view settings.
+            "" | "meta" => {}
+            other => problems.push(Problem::at(line, format!("unknown row type {:?}", other))),
+        }
+    }
+    for task in &mut tasks {
+        if let Some(notes) = notes.remove(&task.id) {
+            task.metadata
+                .insert("todoist.notes".to_string(), notes.join("\n\n"));
+        }
+    }
+    Ok((tasks, problems))
+}
+
+/// Renders the pending tasks as a Todoist CSV, subtasks indented below their parent.
+pub fn render(tasks: &[Task]) -> String {
+    let pending: Vec<&Task> = tasks.iter().filter(|t| !t.completed).collect();
+    let mut out = csv::record(&COLUMNS);
+    let mut stack: Vec<(usize, &Task)> = pending
+        .iter()
+        .rev()
+        .filter(|t| !t.parent.is_some_and(|p| pending.iter().any(|o| o.id == p)))
+        .map(|t| (1, *t))
+        .collect();
+    while let Some((indent, task)) = stack.pop() {
+        let mut content = task.description.clone();
+        for tag in &task.tags {
+            content.push_str(&format!(" @{}", tag));
+        }
+        let date = match (&task.repeat, task.due) {
+            (Some(rule), _) => rule.to_string(),
+            (None, Some(due)) => dates::format_due(due),
+            (None, None) => task
+                .metadata
+                .get("todoist.date")
+                .cloned()
+                .unwrap_or_default(),
+        };
+        let description = task
+            .metadata
+            .get("todoist.description")
+            .map(String::as_str)
+            .unwrap_or("");
+        out.push_str(&csv::record(&[
+            "task",
+            &content,
+            description,
+            priority_value(task.priority),
+            &indent.min(MAX_INDENT).to_string(),
+            "",
+            "",
+            &date,
+            if date.is_empty() { "" } else { "en" },
+            "",
+        ]));
+        stack.extend(
+            pending
+                .iter()
+                .rev()
+                .filter(|t| t.parent == Some(task.id))
+                .map(|t| (indent + 1, *t)),
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, sample_tasks};
+
+    #[test]
+    fn round_trips_pending_tasks_and_subtasks() {
+        let (tasks, problems) = parse(&render(&sample_tasks())).unwrap();
+        assert!(problems.is_empty());
+        // Todoist's import has no completed state, so the completed task isn't written.
+        assert_eq!(tasks.len(), 2);
+        let trip = find(&tasks, "Plan the trip");
+        assert_eq!(trip.priority, Some(Priority::High));
+        assert_eq!(trip.tags, ["travel"]);
+        assert_eq!(find(&tasks, "Book the flights").parent, Some(trip.id));
+    }
+
+    #[test]
+    fn reads_labels_repeating_dates_and_notes() {
+        let csv =
+            "TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,AUTHOR,RESPONSIBLE,DATE,DATE_LANG,TIMEZONE\n\
+                   task,Water the plants @home,,4,1,,,every day,en,\n\
+                   note,Only the ones inside,,,,,,,,\n\
+                   task,Someday,,4,1,,,whenever it suits,en,\n\
+                   chore,Unknown,,,,,,,,\n";
+        let (tasks, problems) = parse(csv).unwrap();
+        assert_eq!(problems.len(), 1);
+        let plants = find(&tasks, "Water the plants");
+        assert_eq!(plants.tags, ["home"]);
+        assert!(plants.repeat.is_some());
+        assert_eq!(plants.metadata["todoist.notes"], "Only the ones inside");
+        assert_eq!(
+            find(&tasks, "Someday").metadata["todoist.date"],
+            "whenever it suits"
+        );
+    }
+}
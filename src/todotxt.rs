@@ -0,0 +1,256 @@
+//! Reading and writing the [todo.txt](https://github.com/todotxt/todo.txt) plain-text format,
+//! one task per line:
+//!
+//! ```text
+//! (A) 2026-10-01 Call the plumber +house @phone due:2026-10-20
+//! x 2026-10-12 2026-10-02 Renew passport +travel pri:B
+//! ```
+//!
+//! Priorities `(A)` to `(D)` become critical, high, medium and low, and any later letter low.
+//! `+project` becomes a tag and the first `@context` the task's place; the others stay in the
+//! description. Those at the end of a line are taken out of the description, and written
+//! back there on export, while those in the middle of the text stay where they are. `due:`
+//! sets the due date, and the completion date the task's `completed_at`. A completed task's
+//! priority is written as `pri:`, as todo.txt tools do.
+
+use crate::import::Problem;
+use crate::{dates, Priority, Task};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn priority_from(letter: char) -> Option<Priority> {
+    match letter {
+        'A' => Some(Priority::Critical),
+        'B' => Some(Priority::High),
+        'C' => Some(Priority::Medium),
+        'D'..='Z' => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+fn letter(priority: Priority) -> char {
+    match priority {
+        Priority::Critical => 'A',
+        Priority::High => 'B',
+        Priority::Medium => 'C',
+        Priority::Low => 'D',
+    }
+}
+
+fn date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, DATE_FORMAT).ok()
+}
+
+fn start_of(date: NaiveDate) -> Option<DateTime<Local>> {
+    date.and_time(NaiveTime::MIN)
+        .and_local_timezone(Local)
+        .earliest()
+}
+
+/// Whether `word` is metadata that todo.txt keeps in the text: a project, a context or a
+/// `key:value` pair.
+fn is_marker(word: &str) -> bool {
+    (word.len() > 1 && word.starts_with(['+', '@']))
+        || word.split_once(':').is_some_and(|(key, value)| {
+            !key.is_empty() && !value.is_empty() && !value.starts_with("//")
+        })
+}
+
+/// Parses todo.txt content into tasks, with a [`Problem`] for each line read only in part or
+/// not at all. IDs are assigned when the tasks are inserted into a list.
+pub fn parse(content: &str) -> (Vec<Task>, Vec<Problem>) {
+    let (mut tasks, mut problems) = (vec![], vec![]);
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let mut rest = &words[..];
+        let completed = rest[0] == "x";
+        if completed {
+            rest = &rest[1..];
+        }
+        let mut priority = None;
+        if let Some(word) = rest.first() {
+            let letters: Vec<char> = word.chars().collect();
+            if let ['(', letter, ')'] = letters[..] {
+                if let Some(level) = priority_from(letter) {
+                    priority = Some(level);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        let mut completed_on = None;
+        if completed {
+            if let Some(day) = rest.first().and_then(|w| date(w)) {
+                completed_on = Some(day);
+                rest = &rest[1..];
+            }
+        }
+        let mut created = None;
+        if let Some(day) = rest.first().and_then(|w| date(w)) {
+            created = Some(day);
+            rest = &rest[1..];
+        }
+        words = rest.to_vec();
+
+        // Markers at the end of the line are taken out of the description; those before the
+        // last word of text stay in it.
+        let text_end = words
+            .iter()
+            .rposition(|word| !is_marker(word))
+            .map_or(0, |i| i + 1);
+        let (mut tags, mut location, mut due) = (vec![], None, None);
+        let mut kept = vec![];
+        for (i, word) in words.iter().enumerate() {
+            let trailing = i >= text_end;
+            if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+                tags.push(project.to_string());
+                if !trailing {
+                    kept.push(*word);
+                }
+            } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+                if location.is_none() {
+                    location = Some(context.to_string());
+                    if !trailing {
+                        kept.push(*word);
+                    }
+                } else {
+                    kept.push(*word);
+                }
+            } else if let Some(value) = word.strip_prefix("due:").filter(|_| trailing) {
+                match date(value).and_then(|day| dates::end_of_day(day).ok()) {
+                    Some(at) => due = Some(at),
+                    None => {
+                        problems.push(Problem::at(
+                            number,
+                            format!("due date {:?} isn't a date; imported without it", value),
+                        ));
+                    }
+                }
+            } else if let Some(value) = word.strip_prefix("pri:").filter(|_| trailing) {
+                match value.chars().next().and_then(priority_from) {
+                    Some(level) if value.len() == 1 => priority = Some(level),
+                    _ => kept.push(*word),
+                }
+            } else {
+                kept.push(*word);
+            }
+        }
+        let description = kept.join(" ");
+        if description.is_empty() {
+            problems.push(Problem::at(number, "task without a description"));
+            continue;
+        }
+        let mut task = Task::builder()
+            .description(description)
+            .priority(priority)
+            .due(due)
+            .location(location)
+            .completed(completed)
+            .build();
+        if let Some(at) = created.and_then(start_of) {
+            task.created_at = at.fixed_offset();
+        }
+        task.completed_at = completed_on.and_then(start_of);
+        for tag in &tags {
+            task.add_tag(tag);
+        }
+        tasks.push(task);
+    }
+    (tasks, problems)
+}
+
+/// Renders tasks as todo.txt lines.
+pub fn render(tasks: &[Task]) -> String {
+    let mut output = String::new();
+    for task in tasks {
+        let mut words: Vec<String> = vec![];
+        let created = task
+            .created_at
+            .with_timezone(&Local)
+            .format(DATE_FORMAT)
+            .to_string();
+        if task.completed {
+            words.push("x".into());
+            // Without a completion date, a creation date would be read as one.
+            if let Some(done) = task.completed_at {
+                words.push(done.format(DATE_FORMAT).to_string());
+                words.push(created);
+            }
+        } else {
+            if let Some(level) = task.priority {
+                words.push(format!("({})", letter(level)));
+            }
+            words.push(created);
+        }
+        words.push(task.description.clone());
+        let present: Vec<&str> = task.description.split_whitespace().collect();
+        for tag in &task.tags {
+            let project = format!("+{}", tag);
+            if !present.iter().any(|w| w.eq_ignore_ascii_case(&project)) {
+                words.push(project);
+            }
+        }
+        if let Some(place) = &task.location {
+            let context = format!(
+                "@{}",
+                place.split_whitespace().collect::<Vec<_>>().join("-")
+            );
+            if !present.contains(&context.as_str()) {
+                words.push(context);
+            }
+        }
+        if let Some(due) = task.due {
+            words.push(format!("due:{}", due.format(DATE_FORMAT)));
+        }
+        if let (true, Some(level)) = (task.completed, task.priority) {
+            words.push(format!("pri:{}", letter(level)));
+        }
+        output.push_str(&words.join(" "));
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, sample_tasks};
+
+    #[test]
+    fn round_trips_priorities_tags_and_dates() {
+        let sample = sample_tasks();
+        let (tasks, problems) = parse(&render(&sample));
+        assert!(problems.is_empty());
+        let trip = find(&tasks, "Plan the trip");
+        assert_eq!(trip.priority, Some(Priority::High));
+        assert_eq!(trip.tags, ["travel"]);
+        // todo.txt dates have no time.
+        assert_eq!(
+            trip.due.map(|d| d.date_naive()),
+            sample[0].due.map(|d| d.date_naive())
+        );
+        let passport = find(&tasks, "Renew the passport");
+        assert!(passport.completed);
+        assert_eq!(
+            passport.completed_at.map(|d| d.date_naive()),
+            sample[2].completed_at.map(|d| d.date_naive())
+        );
+    }
+
+    #[test]
+    fn only_the_first_context_is_the_place() {
+        let (tasks, _) =
+            parse("(E) Call @mum about +xmas plans @phone\nx 2026-10-12 Renew passport pri:A\n");
+        let call = find(&tasks, "Call @mum about +xmas plans @phone");
+        assert_eq!(call.priority, Some(Priority::Low));
+        assert_eq!(call.tags, ["xmas"]);
+        assert_eq!(call.location.as_deref(), Some("mum"));
+        let passport = find(&tasks, "Renew passport");
+        assert!(passport.completed);
+        assert_eq!(passport.priority, Some(Priority::Critical));
+    }
+}
@@ -0,0 +1,74 @@
+//! The trash: tasks removed by `delete` and `clear`, kept until they are restored or the
+//! trash is emptied.
+//!
+//! Trashed tasks are kept in `<data file>.trash`, a JSON array, newest last, so they don't
+//! show up anywhere else. With `trash_days` set in the configuration, tasks older than that
+//! are dropped whenever more are trashed.
+
+use crate::{storage, Task};
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A task in the trash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trashed {
+    pub deleted_at: DateTime<Local>,
+    pub task: Task,
+}
+
+/// The trash kept next to `data_file`.
+pub fn path_for(data_file: &Path) -> PathBuf {
+    let mut name = data_file.file_name().unwrap_or_default().to_os_string();
+    name.push(".trash");
+    data_file.with_file_name(name)
+}
+
+/// The tasks in the trash at `path`, oldest first.
+pub fn load(path: &Path) -> Result<Vec<Trashed>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    Ok(serde_json::from_str(&storage::read_text(path)?)?)
+}
+
+pub fn save(path: &Path, trash: &[Trashed]) -> Result<(), Box<dyn std::error::Error>> {
+    if trash.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    fs::write(path, serde_json::to_string_pretty(trash)?)?;
+    Ok(())
+}
+
+/// Drops the tasks that `forget` matches from the trash at `path`, returning how many there
+/// were.
+pub fn forget(
+    path: &Path,
+    forget: impl Fn(&Task) -> bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut trash = load(path)?;
+    let before = trash.len();
+    trash.retain(|t| !forget(&t.task));
+    let removed = before - trash.len();
+    if removed > 0 {
+        save(path, &trash)?;
+    }
+    Ok(removed)
+}
+
+/// Drops the tasks trashed before `cutoff`, returning how many there were.
+pub fn purge_before(trash: &mut Vec<Trashed>, cutoff: DateTime<Local>) -> usize {
+    let before = trash.len();
+    trash.retain(|t| t.deleted_at >= cutoff);
+    before - trash.len()
+}
+
+/// The cutoff for the configured `trash_days`, if any.
+pub fn configured_cutoff(now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let days = crate::config::current().trash_days?;
+    Some(now - Duration::days(i64::from(days)))
+}
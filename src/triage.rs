@@ -0,0 +1,82 @@
+//! `triage`: walks the inbox one task at a time, filing each with tags, a priority or a due
+//! date, or completing, deleting or skipping it.
+
+use std::io::{BufRead, Write};
+use todo_core::filing::{self, Action};
+use todo_core::{dates, Completion, TodoList};
+
+const HELP: &str = "File each task with tags (#home), a priority (!high) and a due date (due:friday), in any order.
+Enter skips a task; `done` completes it, `delete` removes it and `quit` stops.";
+
+pub fn run(
+    list: &mut TodoList,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let inbox: Vec<usize> = list.inbox().map(|t| t.id).collect();
+    if inbox.is_empty() {
+        writeln!(out, "📥 The inbox is empty.")?;
+        return Ok(());
+    }
+    writeln!(out, "📥 {} task(s) to triage.\n{}\n", inbox.len(), HELP)?;
+    let (mut filed, mut skipped) = (0, 0);
+    'tasks: for (n, id) in inbox.iter().enumerate() {
+        let Some(task) = list.get(*id) else {
+            continue;
+        };
+        writeln!(
+            out,
+            "[{}/{}] {} - {}",
+            n + 1,
+            inbox.len(),
+            id,
+            task.description
+        )?;
+        loop {
+            write!(out, "> ")?;
+            out.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(out)?;
+                break 'tasks;
+            }
+            let filing = match filing::parse(&line, dates::now()) {
+                Ok(Action::File(filing)) => filing,
+                Ok(Action::Skip) => {
+                    skipped += 1;
+                    break;
+                }
+                Ok(Action::Done) => {
+                    if let Completion::Repeated { next, .. } = list.complete(*id)? {
+                        writeln!(out, "  ✓ Completed; next one is task {}.", next.id)?;
+                    } else {
+                        writeln!(out, "  ✓ Completed.")?;
+                    }
+                    filed += 1;
+                    break;
+                }
+                Ok(Action::Delete) => {
+                    list.delete(*id)?;
+                    writeln!(out, "  🗑 Deleted.")?;
+                    filed += 1;
+                    break;
+                }
+                Ok(Action::Quit) => break 'tasks,
+                Err(e) => {
+                    writeln!(out, "  {}", e)?;
+                    continue;
+                }
+            };
+            list.update(*id, |task| filing.apply(task))?;
+            filed += 1;
+            break;
+        }
+    }
+    let left = list.inbox().count();
+    writeln!(
+        out,
+        "\n✓ Triaged {} task(s), skipped {}; {} left in the inbox.",
+        filed, skipped, left
+    )?;
+    Ok(())
+}
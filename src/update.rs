@@ -0,0 +1,147 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info};
+
+const RELEASES_URL: &str =
+    "https://api.github.com/repos/davidagustin/rust-practice/releases/latest";
+const USER_AGENT: &str = concat!("rust-todo/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Name of the release asset built for the running platform, e.g. `rust-todo-x86_64-linux`.
+fn asset_name() -> String {
+    let suffix = if cfg!(windows) { ".exe" } else { "" };
+    format!(
+        "rust-todo-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        suffix
+    )
+}
+
+/// Parses `v1.2.3` / `1.2.3` into comparable numeric parts, ignoring pre-release suffixes.
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn fetch_latest() -> Result<Release, Box<dyn std::error::Error>> {
+    let release = ureq::get(RELEASES_URL)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/vnd.github+json")
+        .call()?
+        .body_mut()
+        .read_json::<Release>()?;
+    debug!(tag = %release.tag_name, assets = release.assets.len(), "fetched latest release");
+    Ok(release)
+}
+
+fn download(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let bytes = ureq::get(url)
+        .header("User-Agent", USER_AGENT)
+        .call()?
+        .body_mut()
+        .with_config()
+        .limit(200 * 1024 * 1024)
+        .read_to_vec()?;
+    Ok(bytes)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Replaces the running executable with `bytes`, keeping the old binary until the swap succeeds.
+fn replace_binary(exe: &Path, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let staged = exe.with_extension("new");
+    let backup = exe.with_extension("old");
+    fs::write(&staged, bytes)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(exe, &backup)?;
+    if let Err(e) = fs::rename(&staged, exe) {
+        fs::rename(&backup, exe)?;
+        return Err(e.into());
+    }
+    // Windows keeps the running image locked, so the backup may only be removable later.
+    let _ = fs::remove_file(&backup);
+    Ok(())
+}
+
+pub fn self_update(check_only: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release = fetch_latest()?;
+    let latest = release.tag_name.trim_start_matches('v');
+
+    if parse_version(latest) <= parse_version(current) {
+        println!("✓ rust-todo {} is up to date.", current);
+        return Ok(());
+    }
+    if check_only {
+        println!(
+            "A new version is available: {} (installed: {})",
+            latest, current
+        );
+        println!("Run `rust-todo self-update` to install it.");
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let checksum_name = format!("{}.sha256", name);
+    let find = |wanted: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == wanted)
+            .ok_or_else(|| format!("release {} has no asset named {}", release.tag_name, wanted))
+    };
+    let binary_asset = find(&name)?;
+    let checksum_asset = find(&checksum_name)?;
+
+    info!(asset = %binary_asset.name, "downloading release asset");
+    let bytes = download(&binary_asset.browser_download_url)?;
+    let checksum_file = String::from_utf8(download(&checksum_asset.browser_download_url)?)?;
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .ok_or("checksum file is empty")?
+        .to_lowercase();
+    let actual = sha256_hex(&bytes);
+    if actual != expected {
+        return Err(format!(
+            "checksum mismatch for {}: expected {}, got {}",
+            name, expected, actual
+        )
+        .into());
+    }
+    debug!(sha256 = %actual, "checksum verified");
+
+    let exe = std::env::current_exe()?;
+    replace_binary(&exe, &bytes)?;
+    println!("✓ Updated rust-todo {} → {}", current, latest);
+    Ok(())
+}